@@ -11,17 +11,18 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::net::SocketAddr;
+use std::{net::SocketAddr, time::Duration};
 
 use anyhow::{bail, Context};
-use clap::Args;
+use clap::{ArgMatches, Args};
+use ethers::types::Address;
 use rundler_builder::{
-    self, BloxrouteSenderArgs, BuilderEvent, BuilderEventKind, BuilderTask, BuilderTaskArgs,
-    EntryPointBuilderSettings, FlashbotsSenderArgs, LocalBuilderBuilder, RawSenderArgs,
-    TransactionSenderArgs, TransactionSenderKind,
+    self, BeneficiarySelection, BloxrouteSenderArgs, BuilderEvent, BuilderEventKind,
+    BuilderSettings, BuilderTask, BuilderTaskArgs, EntryPointBuilderSettings, FlashbotsSenderArgs,
+    LocalBuilderBuilder, RawSenderArgs, TransactionSenderArgs, TransactionSenderKind,
 };
 use rundler_pool::RemotePoolClient;
-use rundler_sim::{MempoolConfigs, PriorityFeeMode};
+use rundler_sim::{MempoolConfigs, PriorityFeeMode, SimulationSettings};
 use rundler_task::{
     server::{connect_with_retries_shutdown, format_socket_addr},
     spawn_tasks_with_shutdown,
@@ -30,7 +31,7 @@ use rundler_types::{chain::ChainSpec, EntryPointVersion};
 use rundler_utils::emit::{self, WithEntryPoint, EVENT_CHANNEL_CAPACITY};
 use tokio::sync::broadcast;
 
-use super::{json::get_json_config, CommonArgs};
+use super::{is_default, json::get_json_config, CommonArgs};
 
 const REQUEST_CHANNEL_CAPACITY: usize = 1024;
 
@@ -215,8 +216,18 @@ pub struct BuilderArgs {
     )]
     max_blocks_to_wait_for_mine: u64,
 
+    /// If set, the maximum time, in milliseconds, that a bundle transaction may stay
+    /// pending, across all of its fee increases, before it is cancelled to free its
+    /// nonce.
+    #[arg(
+        long = "builder.max_pending_time_millis",
+        name = "builder.max_pending_time_millis",
+        env = "BUILDER_MAX_PENDING_TIME_MILLIS"
+    )]
+    max_pending_time_millis: Option<u64>,
+
     /// Percentage amount to increase gas fees when retrying a transaction after
-    /// it failed to mine.
+    /// it failed to mine. Defaults to the chain's preset.
     #[arg(
         long = "builder.replacement_fee_percent_increase",
         name = "builder.replacement_fee_percent_increase",
@@ -225,6 +236,47 @@ pub struct BuilderArgs {
     )]
     replacement_fee_percent_increase: u64,
 
+    /// Factor to grow the fee increase percentage by on each successive
+    /// replacement of a transaction. A value of 1.0 increases fees by
+    /// `replacement_fee_percent_increase` on every replacement.
+    #[arg(
+        long = "builder.fee_increase_multiplier",
+        name = "builder.fee_increase_multiplier",
+        env = "BUILDER_FEE_INCREASE_MULTIPLIER",
+        default_value = "1.0"
+    )]
+    fee_increase_multiplier: f64,
+
+    /// Maximum percentage amount to increase gas fees by on a single replacement
+    /// when the fee increase grows. Defaults to the chain's preset.
+    #[arg(
+        long = "builder.max_fee_increase_percent",
+        name = "builder.max_fee_increase_percent",
+        env = "BUILDER_MAX_FEE_INCREASE_PERCENT",
+        default_value = "100"
+    )]
+    max_fee_increase_percent: u64,
+
+    /// Minimum max priority fee per gas of a replacement bundle transaction, in
+    /// wei. For chains enforcing a minimum priority fee, which a percentage
+    /// increase of a near zero priority fee may never reach.
+    #[arg(
+        long = "builder.min_replacement_priority_fee",
+        name = "builder.min_replacement_priority_fee",
+        env = "BUILDER_MIN_REPLACEMENT_PRIORITY_FEE",
+        default_value = "0"
+    )]
+    min_replacement_priority_fee: u128,
+
+    /// Minimum max fee per gas of a replacement bundle transaction, in wei.
+    #[arg(
+        long = "builder.min_replacement_max_fee",
+        name = "builder.min_replacement_max_fee",
+        env = "BUILDER_MIN_REPLACEMENT_MAX_FEE",
+        default_value = "0"
+    )]
+    min_replacement_max_fee: u128,
+
     /// Maximum number of times to increase gas fees when retrying a cancellation transaction
     /// before giving up.
     #[arg(
@@ -235,6 +287,17 @@ pub struct BuilderArgs {
     )]
     max_cancellation_fee_increases: u64,
 
+    /// Whether to cancel a pending bundle transaction that is abandoned after
+    /// fee increases because no operations pay the increased fees. If false, the
+    /// transaction is left pending until it mines or drops.
+    #[arg(
+        long = "builder.cancel_on_stall",
+        name = "builder.cancel_on_stall",
+        env = "BUILDER_CANCEL_ON_STALL",
+        default_value = "true"
+    )]
+    cancel_on_stall: bool,
+
     /// The maximum number of blocks to wait in a replacement underpriced state before issuing
     /// a cancellation transaction.
     #[arg(
@@ -245,6 +308,324 @@ pub struct BuilderArgs {
     )]
     max_replacement_underpriced_blocks: u64,
 
+    /// If set, the extra percentage to increase gas fees by, on top of
+    /// `replacement_fee_percent_increase`, after a replacement transaction is
+    /// rejected as underpriced. The bundle attempt is then retried right away
+    /// instead of on the next block.
+    #[arg(
+        long = "builder.replacement_underpriced_fee_bump_percent",
+        name = "builder.replacement_underpriced_fee_bump_percent",
+        env = "BUILDER_REPLACEMENT_UNDERPRICED_FEE_BUMP_PERCENT"
+    )]
+    replacement_underpriced_fee_bump_percent: Option<u64>,
+
+    /// The minimum time, in milliseconds, to wait between sending new bundles.
+    /// Operations continue to accumulate in the pool while waiting. Fee
+    /// increases on a pending bundle are not delayed.
+    #[arg(
+        long = "builder.min_bundle_interval_millis",
+        name = "builder.min_bundle_interval_millis",
+        env = "BUILDER_MIN_BUNDLE_INTERVAL_MILLIS",
+        default_value = "0"
+    )]
+    min_bundle_interval_millis: u64,
+
+    /// If set, the interval, in milliseconds, to poll for transaction updates while
+    /// a bundle transaction is pending, instead of only polling on each new block.
+    /// Bounded below by 100ms.
+    #[arg(
+        long = "builder.pending_poll_interval_millis",
+        name = "builder.pending_poll_interval_millis",
+        env = "BUILDER_PENDING_POLL_INTERVAL_MILLIS"
+    )]
+    pending_poll_interval_millis: Option<u64>,
+
+    /// The maximum number of operations from a throttled paymaster to include
+    /// in a single bundle. Operations from banned paymasters are never included.
+    #[arg(
+        long = "builder.throttled_paymaster_bundle_count",
+        name = "builder.throttled_paymaster_bundle_count",
+        env = "BUILDER_THROTTLED_PAYMASTER_BUNDLE_COUNT",
+        default_value = "4"
+    )]
+    throttled_paymaster_bundle_count: usize,
+
+    /// If true, each candidate operation is simulated against the state
+    /// produced by the operations already selected for the bundle, instead
+    /// of only against the latest block.
+    ///
+    /// Each candidate is simulated with an `eth_call` of all the operations
+    /// selected before it, so a bundle of n operations makes n sequential
+    /// calls executing O(n^2) operations.
+    #[arg(
+        long = "builder.simulate_against_bundle_state",
+        name = "builder.simulate_against_bundle_state",
+        env = "BUILDER_SIMULATE_AGAINST_BUNDLE_STATE",
+        default_value = "false"
+    )]
+    simulate_against_bundle_state: bool,
+
+    /// If set, the number of consecutive bundle proposals an operation can fail
+    /// simulation in with a transient error, such as an RPC error, before it is
+    /// removed from the pool.
+    #[arg(
+        long = "builder.max_transient_simulation_failures",
+        name = "builder.max_transient_simulation_failures",
+        env = "BUILDER_MAX_TRANSIENT_SIMULATION_FAILURES"
+    )]
+    max_transient_simulation_failures: Option<u64>,
+
+    /// Percentage to add to the gas estimate of a bundle transaction as a buffer
+    /// when setting its gas limit. Set to 0 to disable the buffer. Defaults to
+    /// the chain's preset.
+    #[arg(
+        long = "builder.gas_estimate_overhead_percent",
+        name = "builder.gas_estimate_overhead_percent",
+        env = "BUILDER_GAS_ESTIMATE_OVERHEAD_PERCENT",
+        default_value = "5"
+    )]
+    gas_estimate_overhead_percent: u64,
+
+    /// If set, the maximum number of operations from a single sender to include
+    /// in any `builder.sender_window_bundles` consecutive bundles. Excess
+    /// operations are deferred to later bundles.
+    #[arg(
+        long = "builder.max_ops_per_sender_in_window",
+        name = "builder.max_ops_per_sender_in_window",
+        env = "BUILDER_MAX_OPS_PER_SENDER_IN_WINDOW"
+    )]
+    max_ops_per_sender_in_window: Option<u64>,
+
+    /// The number of consecutive bundles that
+    /// `builder.max_ops_per_sender_in_window` applies to
+    #[arg(
+        long = "builder.sender_window_bundles",
+        name = "builder.sender_window_bundles",
+        env = "BUILDER_SENDER_WINDOW_BUNDLES",
+        default_value = "10"
+    )]
+    sender_window_bundles: u64,
+
+    /// If set, the number of consecutive mined bundles an operation can be
+    /// included in without being executed before it is removed from the pool.
+    #[arg(
+        long = "builder.max_bundle_reverts_per_op",
+        name = "builder.max_bundle_reverts_per_op",
+        env = "BUILDER_MAX_BUNDLE_REVERTS_PER_OP"
+    )]
+    max_bundle_reverts_per_op: Option<u64>,
+
+    /// Beneficiaries to select the beneficiary of each bundle from
+    /// (comma-separated). If empty, the builder's signer address is the only
+    /// beneficiary.
+    #[arg(
+        long = "builder.beneficiaries",
+        name = "builder.beneficiaries",
+        env = "BUILDER_BENEFICIARIES",
+        value_delimiter = ','
+    )]
+    beneficiaries: Vec<Address>,
+
+    /// How the beneficiary of each bundle is selected from the beneficiaries.
+    /// Options are `round_robin` and `random`.
+    #[arg(
+        long = "builder.beneficiary_selection",
+        name = "builder.beneficiary_selection",
+        env = "BUILDER_BENEFICIARY_SELECTION",
+        default_value = "round_robin"
+    )]
+    beneficiary_selection: BeneficiarySelection,
+
+    /// Beneficiaries to fall back to, in order, if the builder's beneficiary
+    /// fails to receive the bundle's fees (comma-separated).
+    #[arg(
+        long = "builder.fallback_beneficiaries",
+        name = "builder.fallback_beneficiaries",
+        env = "BUILDER_FALLBACK_BENEFICIARIES",
+        value_delimiter = ','
+    )]
+    fallback_beneficiaries: Vec<Address>,
+
+    /// In manual bundling mode, whether all queued send bundle requests share
+    /// the result of the next bundle
+    #[arg(
+        long = "builder.coalesce_manual_bundle_requests",
+        name = "builder.coalesce_manual_bundle_requests",
+        env = "BUILDER_COALESCE_MANUAL_BUNDLE_REQUESTS",
+        default_value = "false"
+    )]
+    coalesce_manual_bundle_requests: bool,
+
+    /// Whether to send bundles as legacy transactions with a single gas price,
+    /// for chains that don't support EIP-1559 transactions
+    #[arg(
+        long = "builder.use_legacy_gas",
+        name = "builder.use_legacy_gas",
+        env = "BUILDER_USE_LEGACY_GAS",
+        default_value = "false"
+    )]
+    use_legacy_gas: bool,
+
+    /// The maximum number of successful simulation results the builder caches for
+    /// the latest block, to avoid re-tracing unchanged operations. 0 disables the cache.
+    #[arg(
+        long = "builder.sim_cache_size",
+        name = "builder.sim_cache_size",
+        env = "BUILDER_SIM_CACHE_SIZE",
+        default_value = "0"
+    )]
+    sim_cache_size: u32,
+
+    /// The maximum number of rejected operations to remove from the pool in a
+    /// single request. Larger numbers of rejected operations are removed in
+    /// batches.
+    #[arg(
+        long = "builder.rejected_ops_removal_batch_size",
+        name = "builder.rejected_ops_removal_batch_size",
+        env = "BUILDER_REJECTED_OPS_REMOVAL_BATCH_SIZE",
+        default_value = "1000"
+    )]
+    rejected_ops_removal_batch_size: usize,
+
+    /// If set, the maximum gas limit of a bundle transaction, including the gas
+    /// estimate overhead. Bundles over this limit are not sent. Useful on chains
+    /// with a low block gas limit.
+    #[arg(
+        long = "builder.max_bundle_transaction_gas",
+        name = "builder.max_bundle_transaction_gas",
+        env = "BUILDER_MAX_BUNDLE_TRANSACTION_GAS"
+    )]
+    max_bundle_transaction_gas: Option<u64>,
+
+    /// Whether to simulate each bundle against the latest block right before
+    /// sending it. Ops that fail are removed from the pool and the bundle is
+    /// re-proposed, instead of reverting on chain.
+    #[arg(
+        long = "builder.validate_bundle_before_send",
+        name = "builder.validate_bundle_before_send",
+        env = "BUILDER_VALIDATE_BUNDLE_BEFORE_SEND",
+        default_value = "false"
+    )]
+    validate_bundle_before_send: bool,
+
+    /// If set, the maximum number of operations in a bundle transaction. Bundles
+    /// with more operations are truncated to the highest priority ones, and the
+    /// rest are left in the pool for a later bundle.
+    #[arg(
+        long = "builder.max_ops_per_bundle",
+        name = "builder.max_ops_per_bundle",
+        env = "BUILDER_MAX_OPS_PER_BUNDLE"
+    )]
+    max_ops_per_bundle: Option<usize>,
+
+    /// If set, the maximum number of operations sharing a paymaster or factory in
+    /// a bundle transaction. Operations over the limit are left in the pool for a
+    /// later bundle.
+    #[arg(
+        long = "builder.max_ops_per_entity_per_bundle",
+        name = "builder.max_ops_per_entity_per_bundle",
+        env = "BUILDER_MAX_OPS_PER_ENTITY_PER_BUNDLE"
+    )]
+    max_ops_per_entity_per_bundle: Option<usize>,
+
+    /// If set, the maximum size in bytes of the calldata of a bundle transaction.
+    /// The largest operations of a bundle over the limit are left in the pool for
+    /// a later bundle.
+    #[arg(
+        long = "builder.max_bundle_calldata_bytes",
+        name = "builder.max_bundle_calldata_bytes",
+        env = "BUILDER_MAX_BUNDLE_CALLDATA_BYTES"
+    )]
+    max_bundle_calldata_bytes: Option<usize>,
+
+    /// Minimum max priority fee per gas of an operation to bundle it, in wei.
+    /// Operations below it are left in the pool. 0 disables the minimum.
+    #[arg(
+        long = "builder.min_op_priority_fee",
+        name = "builder.min_op_priority_fee",
+        env = "BUILDER_MIN_OP_PRIORITY_FEE",
+        default_value = "0"
+    )]
+    min_op_priority_fee: u128,
+
+    /// If set, the address of a pause guardian contract implementing
+    /// `paused() returns (bool)`. No bundles are built while it reports the
+    /// entry point as paused.
+    #[arg(
+        long = "builder.pause_guardian",
+        name = "builder.pause_guardian",
+        env = "BUILDER_PAUSE_GUARDIAN"
+    )]
+    pause_guardian: Option<Address>,
+
+    /// The minimum interval between pause guardian checks, in milliseconds
+    #[arg(
+        long = "builder.pause_check_interval_millis",
+        name = "builder.pause_check_interval_millis",
+        env = "BUILDER_PAUSE_CHECK_INTERVAL_MILLIS",
+        default_value = "60000"
+    )]
+    pause_check_interval_millis: u64,
+
+    /// If set, the number of consecutive failed bundle attempts after which
+    /// bundle attempts are backed off exponentially until one succeeds
+    #[arg(
+        long = "builder.consecutive_failure_threshold",
+        name = "builder.consecutive_failure_threshold",
+        env = "BUILDER_CONSECUTIVE_FAILURE_THRESHOLD"
+    )]
+    consecutive_failure_threshold: Option<u64>,
+
+    /// The maximum backoff between bundle attempts after consecutive failures,
+    /// in milliseconds
+    #[arg(
+        long = "builder.max_failure_backoff_millis",
+        name = "builder.max_failure_backoff_millis",
+        env = "BUILDER_MAX_FAILURE_BACKOFF_MILLIS",
+        default_value = "300000"
+    )]
+    max_failure_backoff_millis: u64,
+
+    /// Whether to sort the ops of each bundle by sender then nonce instead of
+    /// keeping the proposer's order, for reproducible tests
+    #[arg(
+        long = "builder.deterministic_ordering",
+        name = "builder.deterministic_ordering",
+        env = "BUILDER_DETERMINISTIC_ORDERING",
+        default_value = "false"
+    )]
+    deterministic_ordering: bool,
+
+    /// The maximum backoff between automatic bundle attempts while the pool has
+    /// no operations, in milliseconds. Zero disables the backoff.
+    #[arg(
+        long = "builder.idle_backoff_max_millis",
+        name = "builder.idle_backoff_max_millis",
+        env = "BUILDER_IDLE_BACKOFF_MAX_MILLIS",
+        default_value = "0"
+    )]
+    idle_backoff_max_millis: u64,
+
+    /// Percentage to increase the gas limit of a bundle by on each replacement,
+    /// compounded and capped at the max bundle gas. Zero keeps the estimated gas limit.
+    #[arg(
+        long = "builder.gas_limit_increase_percent",
+        name = "builder.gas_limit_increase_percent",
+        env = "BUILDER_GAS_LIMIT_INCREASE_PERCENT",
+        default_value = "0"
+    )]
+    gas_limit_increase_percent: u64,
+
+    /// Maximum number of bundle transactions in flight at consecutive nonces. One
+    /// waits for each bundle to mine before sending the next.
+    #[arg(
+        long = "builder.max_inflight_bundles",
+        name = "builder.max_inflight_bundles",
+        env = "BUILDER_MAX_INFLIGHT_BUNDLES",
+        default_value = "1"
+    )]
+    max_inflight_bundles: usize,
+
     /// The index offset to apply to the builder index
     #[arg(
         long = "builder_index_offset",
@@ -256,6 +637,21 @@ pub struct BuilderArgs {
 }
 
 impl BuilderArgs {
+    /// Sets the flags left unset by the operator to the presets of the chain with ID
+    /// `chain_id`
+    pub(crate) fn apply_chain_presets(&mut self, chain_id: u64, matches: &ArgMatches) {
+        let preset = BuilderSettings::preset_for_chain(chain_id);
+        if is_default(matches, "builder.gas_estimate_overhead_percent") {
+            self.gas_estimate_overhead_percent = preset.gas_estimate_overhead_percent;
+        }
+        if is_default(matches, "builder.replacement_fee_percent_increase") {
+            self.replacement_fee_percent_increase = preset.replacement_fee_percent_increase;
+        }
+        if is_default(matches, "builder.max_fee_increase_percent") {
+            self.max_fee_increase_percent = preset.max_fee_increase_percent;
+        }
+    }
+
     /// Convert the CLI arguments into the arguments for the builder combining
     /// common and builder specific arguments.
     pub async fn to_args(
@@ -350,15 +746,57 @@ impl BuilderArgs {
             redis_uri: self.redis_uri.clone(),
             redis_lock_ttl_millis: self.redis_lock_ttl_millis,
             max_bundle_size: self.max_bundle_size,
-            max_bundle_gas: common.max_bundle_gas,
-            bundle_priority_fee_overhead_percent: common.bundle_priority_fee_overhead_percent,
+            settings: BuilderSettings {
+                max_bundle_gas: common.max_bundle_gas,
+                bundle_priority_fee_overhead_percent: common.bundle_priority_fee_overhead_percent,
+                gas_estimate_overhead_percent: self.gas_estimate_overhead_percent,
+                replacement_fee_percent_increase: self.replacement_fee_percent_increase,
+                max_fee_increase_percent: self.max_fee_increase_percent,
+            },
+            max_op_field_size: common.max_op_field_size,
             priority_fee_mode,
             sender_args,
-            sim_settings: common.try_into()?,
+            sim_settings: SimulationSettings {
+                cache_size: self.sim_cache_size,
+                ..SimulationSettings::try_from(common)?
+            },
             max_blocks_to_wait_for_mine: self.max_blocks_to_wait_for_mine,
-            replacement_fee_percent_increase: self.replacement_fee_percent_increase,
+            max_pending_time: self.max_pending_time_millis.map(Duration::from_millis),
+            fee_increase_multiplier: self.fee_increase_multiplier,
+            min_replacement_priority_fee: self.min_replacement_priority_fee,
+            min_replacement_max_fee: self.min_replacement_max_fee,
             max_cancellation_fee_increases: self.max_cancellation_fee_increases,
+            cancel_on_stall: self.cancel_on_stall,
             max_replacement_underpriced_blocks: self.max_replacement_underpriced_blocks,
+            replacement_underpriced_fee_bump_percent: self.replacement_underpriced_fee_bump_percent,
+            min_bundle_interval: Duration::from_millis(self.min_bundle_interval_millis),
+            pending_poll_interval: self.pending_poll_interval_millis.map(Duration::from_millis),
+            throttled_paymaster_bundle_count: self.throttled_paymaster_bundle_count,
+            simulate_against_bundle_state: self.simulate_against_bundle_state,
+            max_transient_simulation_failures: self.max_transient_simulation_failures,
+            max_ops_per_sender_in_window: self.max_ops_per_sender_in_window,
+            sender_window_bundles: self.sender_window_bundles,
+            max_bundle_reverts_per_op: self.max_bundle_reverts_per_op,
+            beneficiaries: self.beneficiaries.clone(),
+            beneficiary_selection: self.beneficiary_selection,
+            fallback_beneficiaries: self.fallback_beneficiaries.clone(),
+            coalesce_manual_bundle_requests: self.coalesce_manual_bundle_requests,
+            use_legacy_gas: self.use_legacy_gas,
+            rejected_ops_removal_batch_size: self.rejected_ops_removal_batch_size,
+            max_bundle_transaction_gas: self.max_bundle_transaction_gas,
+            validate_bundle_before_send: self.validate_bundle_before_send,
+            max_ops_per_bundle: self.max_ops_per_bundle,
+            max_ops_per_entity_per_bundle: self.max_ops_per_entity_per_bundle,
+            max_bundle_calldata_bytes: self.max_bundle_calldata_bytes,
+            min_op_priority_fee: self.min_op_priority_fee,
+            pause_guardian: self.pause_guardian,
+            pause_check_interval: Duration::from_millis(self.pause_check_interval_millis),
+            consecutive_failure_threshold: self.consecutive_failure_threshold,
+            max_failure_backoff: Duration::from_millis(self.max_failure_backoff_millis),
+            deterministic_ordering: self.deterministic_ordering,
+            idle_backoff_max: Duration::from_millis(self.idle_backoff_max_millis),
+            gas_limit_increase_percent: self.gas_limit_increase_percent,
+            max_inflight_bundles: self.max_inflight_bundles,
             remote_address,
         })
     }
@@ -414,7 +852,7 @@ impl BuilderArgs {
 #[derive(Args, Debug)]
 pub struct BuilderCliArgs {
     #[command(flatten)]
-    builder: BuilderArgs,
+    pub(crate) builder: BuilderArgs,
 
     #[arg(
         long = "builder.pool_url",
@@ -482,3 +920,60 @@ pub fn is_nonspammy_event(event: &WithEntryPoint<BuilderEvent>) -> bool {
     }
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, FromArgMatches};
+
+    use super::*;
+    use crate::cli::{Cli, Command};
+
+    fn parse(args: &[&str], chain_id: u64) -> BuilderArgs {
+        let matches = Cli::command().get_matches_from(args);
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        cli.apply_chain_presets(chain_id, &matches);
+        match cli.command {
+            Command::Builder(args) => args.builder,
+            Command::Node(args) => args.builder,
+            _ => panic!("should parse a builder command"),
+        }
+    }
+
+    #[test]
+    fn test_chain_presets() {
+        let args = parse(&["rundler", "builder"], 1);
+        assert_eq!(args.replacement_fee_percent_increase, 10);
+        assert_eq!(args.gas_estimate_overhead_percent, 5);
+
+        let args = parse(&["rundler", "builder"], 137);
+        assert_eq!(args.replacement_fee_percent_increase, 20);
+
+        let args = parse(&["rundler", "node"], 42161);
+        assert_eq!(args.gas_estimate_overhead_percent, 15);
+    }
+
+    #[test]
+    fn test_chain_presets_keep_flags() {
+        let args = parse(
+            &[
+                "rundler",
+                "builder",
+                "--builder.replacement_fee_percent_increase",
+                "15",
+            ],
+            137,
+        );
+        assert_eq!(args.replacement_fee_percent_increase, 15);
+
+        let args = parse(
+            &[
+                "rundler",
+                "node",
+                "--builder.gas_estimate_overhead_percent",
+                "0",
+            ],
+            42161,
+        );
+        assert_eq!(args.gas_estimate_overhead_percent, 0);
+    }
+}
@@ -11,14 +11,22 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
+use std::time::Duration;
+
 use anyhow::{bail, Context};
-use clap::{builder::PossibleValuesParser, Args, Parser, Subcommand};
+use clap::{
+    builder::PossibleValuesParser, parser::ValueSource, ArgMatches, Args, CommandFactory,
+    FromArgMatches, Parser, Subcommand,
+};
+use ethers::types::Address;
 
 mod builder;
 mod chain_spec;
 mod json;
 mod metrics;
 mod node;
+#[cfg(feature = "otel")]
+mod otel;
 mod pool;
 mod rpc;
 mod tracing;
@@ -27,6 +35,7 @@ use builder::BuilderCliArgs;
 use node::NodeCliArgs;
 use pool::PoolCliArgs;
 use rpc::RpcCliArgs;
+use rundler_builder::BuilderSettings;
 use rundler_rpc::{EthApiSettings, RundlerApiSettings};
 use rundler_sim::{
     EstimationSettings, PrecheckSettings, PriorityFeeMode, SimulationSettings, MIN_CALL_GAS_LIMIT,
@@ -37,8 +46,12 @@ use rundler_sim::{
 /// Parses the CLI arguments and runs the appropriate subcommand.
 /// Listens for a ctrl-c signal and shuts down all components when received.
 pub async fn run() -> anyhow::Result<()> {
-    let opt = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let mut opt = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
     let _guard = tracing::configure_logging(&opt.logs)?;
+
+    let cs = chain_spec::resolve_chain_spec(&opt.common.network, &opt.common.chain_spec);
+    opt.apply_chain_presets(cs.id, &matches);
     tracing::info!("Parsed CLI options: {:#?}", opt);
 
     let metrics_addr = format!("{}:{}", opt.metrics.host, opt.metrics.port).parse()?;
@@ -50,7 +63,6 @@ pub async fn run() -> anyhow::Result<()> {
     )
     .context("metrics server should start")?;
 
-    let cs = chain_spec::resolve_chain_spec(&opt.common.network, &opt.common.chain_spec);
     tracing::info!("Chain spec: {:#?}", cs);
 
     match opt.command {
@@ -61,6 +73,8 @@ pub async fn run() -> anyhow::Result<()> {
     }
 
     tracing::info!("Shutdown, goodbye");
+    #[cfg(feature = "otel")]
+    opentelemetry::global::shutdown_tracer_provider();
     Ok(())
 }
 
@@ -175,6 +189,43 @@ pub struct CommonArgs {
     )]
     tracer_timeout: String,
 
+    /// Storage accesses of trusted entities exempted from the storage access rules,
+    /// as `<entity>:<contract>[:<slot>]`. Without a slot, all slots of the contract are exempted.
+    #[arg(
+        long = "storage_allowlist",
+        name = "storage_allowlist",
+        env = "STORAGE_ALLOWLIST",
+        value_delimiter = ',',
+        global = true
+    )]
+    storage_allowlist: Vec<String>,
+
+    /// Flag for skipping the storage access rules during simulation, while still
+    /// enforcing the opcode rules
+    #[arg(long = "skip_storage_rules", env = "SKIP_STORAGE_RULES", global = true)]
+    skip_storage_rules: bool,
+
+    /// Aggregators that user operations may use (comma-separated). If unset, any
+    /// aggregator is supported
+    #[arg(
+        long = "supported_aggregators",
+        name = "supported_aggregators",
+        env = "SUPPORTED_AGGREGATORS",
+        value_delimiter = ',',
+        global = true
+    )]
+    supported_aggregators: Option<Vec<Address>>,
+
+    /// Buffer required between the verification gas limit of a user operation and
+    /// the verification gas it uses during simulation, for entry point v0.6
+    #[arg(
+        long = "verification_gas_limit_buffer",
+        name = "verification_gas_limit_buffer",
+        env = "VERIFICATION_GAS_LIMIT_BUFFER",
+        global = true
+    )]
+    verification_gas_limit_buffer: Option<u64>,
+
     /// Amount of blocks to search when calling eth_getUserOperationByHash.
     /// Defaults from 0 to latest block
     #[arg(
@@ -203,6 +254,89 @@ pub struct CommonArgs {
     )]
     verification_estimation_gas_fee: u64,
 
+    /// The multiple of the verification estimation gas fee the fee-payer of a user
+    /// operation with a paymaster must hold during estimation
+    #[arg(
+        long = "paymaster_fee_payer_multiplier",
+        name = "paymaster_fee_payer_multiplier",
+        env = "PAYMASTER_FEE_PAYER_MULTIPLIER",
+        default_value = "3",
+        global = true
+    )]
+    paymaster_fee_payer_multiplier: u64,
+
+    #[arg(
+        long = "call_gas_estimation_tolerance",
+        name = "call_gas_estimation_tolerance",
+        env = "CALL_GAS_ESTIMATION_TOLERANCE",
+        default_value = "4096",
+        global = true
+    )]
+    call_gas_estimation_tolerance: u64,
+
+    #[arg(
+        long = "max_estimation_attempts",
+        name = "max_estimation_attempts",
+        env = "MAX_ESTIMATION_ATTEMPTS",
+        default_value = "10",
+        global = true
+    )]
+    max_estimation_attempts: u64,
+
+    #[arg(
+        long = "max_op_field_size",
+        name = "max_op_field_size",
+        env = "MAX_OP_FIELD_SIZE",
+        global = true
+    )]
+    max_op_field_size: Option<usize>,
+
+    #[arg(
+        long = "estimation_timeout_millis",
+        name = "estimation_timeout_millis",
+        env = "ESTIMATION_TIMEOUT_MILLIS",
+        default_value = "10000",
+        global = true
+    )]
+    estimation_timeout_millis: u64,
+
+    #[arg(
+        long = "refresh_on_stale_revert",
+        name = "refresh_on_stale_revert",
+        env = "REFRESH_ON_STALE_REVERT",
+        default_value = "false",
+        global = true
+    )]
+    refresh_on_stale_revert: bool,
+
+    #[arg(
+        long = "verification_gas_margin_percent",
+        name = "verification_gas_margin_percent",
+        env = "VERIFICATION_GAS_MARGIN_PERCENT",
+        default_value = "0",
+        global = true
+    )]
+    verification_gas_margin_percent: u64,
+
+    #[arg(
+        long = "max_concurrent_estimations",
+        name = "max_concurrent_estimations",
+        env = "MAX_CONCURRENT_ESTIMATIONS",
+        default_value = "4",
+        global = true
+    )]
+    max_concurrent_estimations: usize,
+
+    #[arg(
+        long = "estimation_block_tag",
+        name = "estimation_block_tag",
+        env = "ESTIMATION_BLOCK_TAG",
+        default_value = "latest",
+        value_parser = PossibleValuesParser::new(["latest", "pending"]),
+        global = true
+    )]
+    estimation_block_tag: String,
+
     #[arg(
         long = "bundle_priority_fee_overhead_percent",
         name = "bundle_priority_fee_overhead_percent",
@@ -307,6 +441,33 @@ pub struct CommonArgs {
 
 const SIMULATION_GAS_OVERHEAD: u64 = 100_000;
 
+impl CommonArgs {
+    /// Sets the flags left unset by the operator to the presets of the chain with ID
+    /// `chain_id`
+    fn apply_chain_presets(&mut self, chain_id: u64, matches: &ArgMatches) {
+        let estimation = EstimationSettings::preset_for_chain(chain_id);
+        let builder = BuilderSettings::preset_for_chain(chain_id);
+        if is_default(matches, "max_verification_gas") {
+            self.max_verification_gas = estimation.max_verification_gas;
+        }
+        if is_default(matches, "max_simulate_handle_ops_gas") {
+            self.max_simulate_handle_ops_gas = estimation.max_simulate_handle_ops_gas;
+        }
+        if is_default(matches, "max_bundle_gas") {
+            self.max_bundle_gas = builder.max_bundle_gas;
+        }
+        if is_default(matches, "bundle_priority_fee_overhead_percent") {
+            self.bundle_priority_fee_overhead_percent =
+                builder.bundle_priority_fee_overhead_percent;
+        }
+    }
+}
+
+/// Returns whether the flag with ID `id` was left unset, taking its default value
+fn is_default(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::DefaultValue)
+}
+
 impl TryFrom<&CommonArgs> for EstimationSettings {
     type Error = anyhow::Error;
 
@@ -337,6 +498,15 @@ impl TryFrom<&CommonArgs> for EstimationSettings {
             max_total_execution_gas: value.max_bundle_gas,
             max_simulate_handle_ops_gas: value.max_simulate_handle_ops_gas,
             verification_estimation_gas_fee: value.verification_estimation_gas_fee,
+            paymaster_fee_payer_multiplier: value.paymaster_fee_payer_multiplier,
+            call_gas_estimation_tolerance: value.call_gas_estimation_tolerance,
+            max_estimation_attempts: value.max_estimation_attempts,
+            max_op_field_size: value.max_op_field_size,
+            estimation_timeout: Duration::from_millis(value.estimation_timeout_millis),
+            refresh_on_stale_revert: value.refresh_on_stale_revert,
+            verification_gas_margin_percent: value.verification_gas_margin_percent,
+            max_concurrent_estimations: value.max_concurrent_estimations,
+            block_tag: value.estimation_block_tag.parse()?,
         })
     }
 }
@@ -367,12 +537,26 @@ impl TryFrom<&CommonArgs> for SimulationSettings {
             bail!("Invalid value for tracer_timeout, must be parsable by the ParseDuration function. See docs https://pkg.go.dev/time#ParseDuration")
         }
 
+        let storage_allowlist = value
+            .storage_allowlist
+            .iter()
+            .map(|entry| entry.parse())
+            .collect::<Result<Vec<_>, _>>()
+            .context("Invalid value for storage_allowlist")?;
+
         Ok(Self::new(
             value.min_unstake_delay,
             value.min_stake_value,
             value.max_simulate_handle_ops_gas,
             value.max_verification_gas,
             value.tracer_timeout.clone(),
+            storage_allowlist,
+            value.skip_storage_rules,
+            value
+                .supported_aggregators
+                .as_ref()
+                .map(|aggregators| aggregators.iter().copied().collect()),
+            value.verification_gas_limit_buffer,
         ))
     }
 }
@@ -491,6 +675,20 @@ pub struct LogsArgs {
         global = true
     )]
     json: bool,
+
+    /// OTLP endpoint
+    ///
+    /// If set, trace spans will be exported to this OpenTelemetry collector
+    /// endpoint over gRPC. Requires the `otel` feature.
+    #[cfg(feature = "otel")]
+    #[arg(
+        long = "log.otlp_endpoint",
+        name = "log.otlp_endpoint",
+        env = "LOG_OTLP_ENDPOINT",
+        default_value = None,
+        global = true
+    )]
+    otlp_endpoint: Option<String>,
 }
 
 /// CLI options
@@ -508,3 +706,66 @@ pub struct Cli {
     #[clap(flatten)]
     logs: LogsArgs,
 }
+
+impl Cli {
+    /// Sets the flags left unset by the operator to the presets of the chain with ID
+    /// `chain_id`. The defaults of these flags are the mainnet presets.
+    fn apply_chain_presets(&mut self, chain_id: u64, matches: &ArgMatches) {
+        self.common.apply_chain_presets(chain_id, matches);
+        let Some((_, command_matches)) = matches.subcommand() else {
+            return;
+        };
+        match &mut self.command {
+            Command::Node(args) => args.builder.apply_chain_presets(chain_id, command_matches),
+            Command::Builder(args) => args.builder.apply_chain_presets(chain_id, command_matches),
+            Command::Pool(_) | Command::Rpc(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str], chain_id: u64) -> Cli {
+        let matches = Cli::command().get_matches_from(args);
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        cli.apply_chain_presets(chain_id, &matches);
+        cli
+    }
+
+    #[test]
+    fn test_chain_presets() {
+        let cli = parse(&["rundler", "pool"], 1);
+        assert_eq!(cli.common.max_bundle_gas, 25_000_000);
+        assert_eq!(cli.common.max_verification_gas, 5_000_000);
+
+        let cli = parse(&["rundler", "pool"], 43114);
+        assert_eq!(cli.common.max_bundle_gas, 15_000_000);
+        assert_eq!(cli.common.max_verification_gas, 3_000_000);
+        assert_eq!(cli.common.max_simulate_handle_ops_gas, 12_000_000);
+        assert!(EstimationSettings::try_from(&cli.common).is_ok());
+
+        let cli = parse(&["rundler", "pool"], 8453);
+        assert_eq!(cli.common.bundle_priority_fee_overhead_percent, 20);
+    }
+
+    #[test]
+    fn test_chain_presets_keep_flags() {
+        let cli = parse(
+            &[
+                "rundler",
+                "pool",
+                "--max_bundle_gas",
+                "20000000",
+                "--bundle_priority_fee_overhead_percent",
+                "0",
+            ],
+            8453,
+        );
+        assert_eq!(cli.common.max_bundle_gas, 20_000_000);
+        assert_eq!(cli.common.bundle_priority_fee_overhead_percent, 0);
+        // unset flags still take the preset
+        assert_eq!(cli.common.max_verification_gas, 5_000_000);
+    }
+}
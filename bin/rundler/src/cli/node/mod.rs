@@ -38,7 +38,7 @@ pub struct NodeCliArgs {
     pool: PoolArgs,
 
     #[command(flatten)]
-    builder: BuilderArgs,
+    pub(crate) builder: BuilderArgs,
 
     #[command(flatten)]
     rpc: RpcArgs,
@@ -61,14 +61,16 @@ pub async fn run(
     let builder_task_args = builder_args
         .to_args(chain_spec.clone(), &common_args, None)
         .await?;
-    let rpc_task_args = rpc_args.to_args(
-        chain_spec,
-        &common_args,
-        (&common_args).try_into()?,
-        (&common_args).into(),
-        (&common_args).try_into()?,
-        (&common_args).try_into()?,
-    )?;
+    let rpc_task_args = rpc_args
+        .to_args(
+            chain_spec,
+            &common_args,
+            (&common_args).try_into()?,
+            (&common_args).into(),
+            (&common_args).try_into()?,
+            (&common_args).try_into()?,
+        )
+        .await?;
 
     let (event_sender, event_rx) =
         broadcast::channel::<WithEntryPoint<Event>>(EVENT_CHANNEL_CAPACITY);
@@ -0,0 +1,161 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt;
+
+use opentelemetry::{baggage::BaggageExt, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    runtime,
+    trace::{self as sdktrace, Tracer},
+    Resource,
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Subscriber,
+};
+use tracing_opentelemetry::{OpenTelemetryLayer, OtelData};
+use tracing_subscriber::{
+    layer::{Context, Layered},
+    registry::LookupSpan,
+    Layer,
+};
+
+/// Span field holding the hash of the user operation a span is working on.
+///
+/// Spans recording this field attach the hash to their OpenTelemetry context as
+/// baggage, so that it is carried by all of their child spans.
+const OP_HASH_FIELD: &str = "op_hash";
+
+/// Creates a layer exporting spans to the OTLP collector at `endpoint`
+pub(crate) fn layer<S>(
+    endpoint: &str,
+) -> anyhow::Result<Layered<OpHashBaggageLayer, OpenTelemetryLayer<S, Tracer>, S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let tracer =
+        opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(endpoint),
+            )
+            .with_trace_config(sdktrace::config().with_resource(Resource::new(vec![
+                KeyValue::new("service.name", "rundler"),
+            ])))
+            .install_batch(runtime::Tokio)?;
+
+    Ok(tracing_opentelemetry::layer()
+        .with_tracer(tracer)
+        .and_then(OpHashBaggageLayer))
+}
+
+/// Adds the op hash of spans recording the `op_hash` field as baggage on their
+/// OpenTelemetry context.
+///
+/// Must be layered on top of the `OpenTelemetryLayer` so that the span's OpenTelemetry
+/// data exists when a span is created.
+pub(crate) struct OpHashBaggageLayer;
+
+impl<S> Layer<S> for OpHashBaggageLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut visitor = OpHashVisitor::default();
+        attrs.record(&mut visitor);
+        let Some(op_hash) = visitor.0 else {
+            return;
+        };
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut extensions = span.extensions_mut();
+        if let Some(otel_data) = extensions.get_mut::<OtelData>() {
+            otel_data.parent_cx = otel_data
+                .parent_cx
+                .with_baggage(vec![KeyValue::new(OP_HASH_FIELD, op_hash)]);
+        }
+    }
+}
+
+#[derive(Default)]
+struct OpHashVisitor(Option<String>);
+
+impl Visit for OpHashVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == OP_HASH_FIELD {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == OP_HASH_FIELD {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::{SpanId, TracerProvider as _};
+    use opentelemetry_sdk::{testing::trace::InMemorySpanExporter, trace::TracerProvider};
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+    use super::*;
+
+    #[test]
+    fn test_op_spans_parent_child() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = Registry::default().with(
+            tracing_opentelemetry::layer()
+                .with_tracer(provider.tracer("test"))
+                .and_then(OpHashBaggageLayer),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let bundle_span = tracing::info_span!("make_bundle");
+            let _bundle = bundle_span.enter();
+            let op_span = tracing::info_span!("simulate_op", op_hash = "0x01");
+            let _op = op_span.enter();
+            let sim_span = tracing::info_span!("simulate_validation");
+
+            // the op hash is carried as baggage by the child span
+            let cx = sim_span.context();
+            let op_hash = cx.baggage().get(OP_HASH_FIELD).map(|v| v.to_string());
+            assert_eq!(op_hash.as_deref(), Some("0x01"));
+        });
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let span_id = |name: &str| {
+            spans
+                .iter()
+                .find(|s| s.name == name)
+                .map(|s| (s.span_context.span_id(), s.parent_span_id))
+                .unwrap()
+        };
+        let (bundle_id, bundle_parent) = span_id("make_bundle");
+        let (op_id, op_parent) = span_id("simulate_op");
+        let (_, sim_parent) = span_id("simulate_validation");
+        assert_eq!(bundle_parent, SpanId::INVALID);
+        assert_eq!(op_parent, bundle_id);
+        assert_eq!(sim_parent, op_id);
+    }
+}
@@ -11,18 +11,19 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use anyhow::Context;
 use clap::Args;
+use ethers::{abi::Abi, types::Address};
 use rundler_builder::RemoteBuilderClient;
 use rundler_pool::RemotePoolClient;
 use rundler_rpc::{EthApiSettings, RpcTask, RpcTaskArgs, RundlerApiSettings};
-use rundler_sim::{EstimationSettings, PrecheckSettings};
+use rundler_sim::{EstimationSettings, PrecheckSettings, RevertDecoder};
 use rundler_task::{server::connect_with_retries_shutdown, spawn_tasks_with_shutdown};
 use rundler_types::chain::ChainSpec;
 
-use super::CommonArgs;
+use super::{json::get_json_config, CommonArgs};
 
 /// CLI options for the RPC server
 #[derive(Args, Debug)]
@@ -74,13 +75,44 @@ pub struct RpcArgs {
         default_value = "100"
     )]
     max_connections: u32,
+
+    /// Path to an ABI JSON file, or S3 object, declaring custom errors to decode
+    /// the revert data of user operation calls with during gas estimation
+    #[arg(
+        long = "rpc.custom_errors_abi_path",
+        name = "rpc.custom_errors_abi_path",
+        env = "RPC_CUSTOM_ERRORS_ABI_PATH"
+    )]
+    custom_errors_abi_path: Option<String>,
+
+    /// Other deployments of entry point v0.6, e.g. a staging deployment, that gas
+    /// estimation requests may target (comma-separated). Only estimation is
+    /// supported against them.
+    #[arg(
+        long = "rpc.estimation_entry_points_v0_6",
+        name = "rpc.estimation_entry_points_v0_6",
+        env = "RPC_ESTIMATION_ENTRY_POINTS_V0_6",
+        value_delimiter = ','
+    )]
+    estimation_entry_points_v0_6: Vec<Address>,
+
+    /// Other deployments of entry point v0.7, e.g. a staging deployment, that gas
+    /// estimation requests may target (comma-separated). Only estimation is
+    /// supported against them.
+    #[arg(
+        long = "rpc.estimation_entry_points_v0_7",
+        name = "rpc.estimation_entry_points_v0_7",
+        env = "RPC_ESTIMATION_ENTRY_POINTS_V0_7",
+        value_delimiter = ','
+    )]
+    estimation_entry_points_v0_7: Vec<Address>,
 }
 
 impl RpcArgs {
     /// Convert the CLI arguments into the arguments for the RPC server combining
     /// common and rpc specific arguments.
     #[allow(clippy::too_many_arguments)]
-    pub fn to_args(
+    pub async fn to_args(
         &self,
         chain_spec: ChainSpec,
         common: &CommonArgs,
@@ -95,6 +127,18 @@ impl RpcArgs {
             .map(|api| api.parse())
             .collect::<Result<Vec<_>, _>>()?;
 
+        let revert_decoder = match &self.custom_errors_abi_path {
+            Some(path) => {
+                let abi = get_json_config::<Abi>(path, &common.aws_region)
+                    .await
+                    .with_context(|| format!("should load custom errors ABI from {path}"))?;
+                let decoder = RevertDecoder::from_abi(&abi);
+                tracing::info!("Loaded {} custom errors from {path}", decoder.len());
+                decoder
+            }
+            None => RevertDecoder::default(),
+        };
+
         Ok(RpcTaskArgs {
             chain_spec,
             unsafe_mode: common.unsafe_mode,
@@ -109,6 +153,9 @@ impl RpcArgs {
             eth_api_settings,
             rundler_api_settings,
             estimation_settings,
+            revert_decoder: Arc::new(revert_decoder),
+            estimation_entry_points_v0_6: self.estimation_entry_points_v0_6.clone(),
+            estimation_entry_points_v0_7: self.estimation_entry_points_v0_7.clone(),
             rpc_timeout: Duration::from_secs(self.timeout_seconds.parse()?),
             max_connections: self.max_connections,
             entry_point_v0_6_enabled: !common.disable_entry_point_v0_6,
@@ -153,14 +200,16 @@ pub async fn run(
         builder_url,
     } = rpc_args;
 
-    let task_args = rpc_args.to_args(
-        chain_spec.clone(),
-        &common_args,
-        (&common_args).try_into()?,
-        (&common_args).into(),
-        (&common_args).try_into()?,
-        (&common_args).try_into()?,
-    )?;
+    let task_args = rpc_args
+        .to_args(
+            chain_spec.clone(),
+            &common_args,
+            (&common_args).try_into()?,
+            (&common_args).into(),
+            (&common_args).try_into()?,
+            (&common_args).try_into()?,
+        )
+        .await?;
 
     let pool = connect_with_retries_shutdown(
         "op pool from rpc",
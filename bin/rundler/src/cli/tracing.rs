@@ -17,7 +17,9 @@ pub use tracing::*;
 use tracing::{subscriber, subscriber::Interest, Metadata, Subscriber};
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_log::LogTracer;
-use tracing_subscriber::{layer::SubscriberExt, EnvFilter, FmtSubscriber, Layer};
+use tracing_subscriber::{
+    layer::SubscriberExt, registry::LookupSpan, EnvFilter, FmtSubscriber, Layer,
+};
 
 use super::LogsArgs;
 
@@ -32,19 +34,9 @@ pub fn configure_logging(config: &LogsArgs) -> anyhow::Result<WorkerGuard> {
         .with_env_filter(EnvFilter::from_default_env())
         .with_writer(appender);
     if config.json {
-        subscriber::set_global_default(
-            subscriber_builder
-                .json()
-                .finish()
-                .with(TargetBlacklistLayer),
-        )?;
+        set_global_default(subscriber_builder.json().finish(), config)?;
     } else {
-        subscriber::set_global_default(
-            subscriber_builder
-                .pretty()
-                .finish()
-                .with(TargetBlacklistLayer),
-        )?;
+        set_global_default(subscriber_builder.pretty().finish(), config)?;
     }
 
     // Redirect logs from external crates using `log` to the tracing subscriber
@@ -53,6 +45,24 @@ pub fn configure_logging(config: &LogsArgs) -> anyhow::Result<WorkerGuard> {
     Ok(guard)
 }
 
+#[cfg_attr(not(feature = "otel"), allow(unused_variables))]
+fn set_global_default<S>(subscriber: S, config: &LogsArgs) -> anyhow::Result<()>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync + 'static,
+{
+    let subscriber = subscriber.with(TargetBlacklistLayer);
+    #[cfg(feature = "otel")]
+    let subscriber = subscriber.with(
+        config
+            .otlp_endpoint
+            .as_deref()
+            .map(super::otel::layer)
+            .transpose()?,
+    );
+    subscriber::set_global_default(subscriber)?;
+    Ok(())
+}
+
 const BLACKLISTED_TARGETS: &[&str] = &["h2", "hyper", "tower::buffer"];
 
 struct TargetBlacklistLayer;
@@ -12,7 +12,7 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     future::Future,
     marker::PhantomData,
     mem,
@@ -28,6 +28,7 @@ use futures_util::TryFutureExt;
 use linked_hash_map::LinkedHashMap;
 #[cfg(test)]
 use mockall::automock;
+use rand::Rng;
 use rundler_provider::{
     BundleHandler, EntryPoint, HandleOpsOut, L1GasProvider, Provider, SignatureAggregator,
 };
@@ -38,27 +39,28 @@ use rundler_sim::{
 use rundler_types::{
     chain::ChainSpec,
     pool::{Pool, PoolOperation, SimulationViolation},
-    Entity, EntityInfo, EntityInfos, EntityType, EntityUpdate, EntityUpdateType, GasFees,
-    Timestamp, UserOperation, UserOperationVariant, UserOpsPerAggregator, BUNDLE_BYTE_OVERHEAD,
-    TIME_RANGE_BUFFER, USER_OP_OFFSET_WORD_SIZE,
+    Entity, EntityInfo, EntityInfos, EntityType, EntityUpdate, EntityUpdateReason,
+    EntityUpdateType, GasFees, Timestamp, UserOperation, UserOperationVariant,
+    UserOpsPerAggregator, BUNDLE_BYTE_OVERHEAD, TIME_RANGE_BUFFER, USER_OP_OFFSET_WORD_SIZE,
 };
 use rundler_utils::{emit::WithEntryPoint, math};
 use tokio::{sync::broadcast, try_join};
-use tracing::{error, info, warn};
+use tracing::{error, info, instrument, warn};
 
 use crate::emit::{BuilderEvent, ConditionNotMetReason, OpRejectionReason, SkipReason};
 
-/// Extra buffer percent to add on the bundle transaction gas estimate to be sure it will be enough
-const BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT: u64 = 5;
-
 #[derive(Debug)]
 pub(crate) struct Bundle<UO: UserOperation> {
     pub(crate) ops_per_aggregator: Vec<UserOpsPerAggregator<UO>>,
     pub(crate) gas_estimate: U256,
     pub(crate) gas_fees: GasFees,
     pub(crate) expected_storage: ExpectedStorage,
+    /// Expected storage of each op in the bundle by op hash, to rebuild
+    /// `expected_storage` if ops are removed from the bundle
+    pub(crate) expected_storage_by_op: HashMap<H256, ExpectedStorage>,
     pub(crate) rejected_ops: Vec<UO>,
     pub(crate) entity_updates: Vec<EntityUpdate>,
+    pub(crate) beneficiary: Address,
 }
 
 impl<UO: UserOperation> Default for Bundle<UO> {
@@ -68,8 +70,10 @@ impl<UO: UserOperation> Default for Bundle<UO> {
             gas_estimate: U256::zero(),
             gas_fees: GasFees::default(),
             expected_storage: ExpectedStorage::default(),
+            expected_storage_by_op: HashMap::new(),
             rejected_ops: Vec::new(),
             entity_updates: Vec::new(),
+            beneficiary: Address::zero(),
         }
     }
 }
@@ -91,6 +95,28 @@ impl<UO: UserOperation> Bundle<UO> {
     }
 }
 
+/// Returns the gas limit of a bundle transaction executing `ops`, increased by
+/// `overhead_percent`
+pub(crate) fn bundle_gas_limit<'a, UO: UserOperation>(
+    chain_spec: &ChainSpec,
+    ops: impl IntoIterator<Item = &'a UO>,
+    overhead_percent: u64,
+) -> U256 {
+    // TODO(danc): in the 0.7 entrypoint we could optimize this by removing the need for
+    // the 10K gas and 63/64 gas overheads for each op in the bundle and instead calculate exactly
+    // the limit needed to include that overhead for each op.
+    //
+    // In the 0.6 entrypoint we're assuming that we need 1 verification gas buffer for each op in the bundle
+    // regardless of if it uses a post op or not. We can optimize to calculate the exact gas overhead
+    // needed to have the buffer for each op.
+    let gas_limit = ops
+        .into_iter()
+        .map(|op| gas::user_operation_gas_limit(chain_spec, op, false))
+        .fold(U256::zero(), |acc, i| acc + i)
+        + chain_spec.transaction_intrinsic_gas;
+    math::increase_by_percent(gas_limit, overhead_percent)
+}
+
 #[async_trait]
 #[cfg_attr(test, automock(type UO = rundler_types::v0_6::UserOperation;))]
 pub(crate) trait BundleProposer: Send + Sync + 'static {
@@ -100,10 +126,15 @@ pub(crate) trait BundleProposer: Send + Sync + 'static {
     ///
     /// If `min_fees` is `Some`, the proposer will ensure the bundle has
     /// at least `min_fees`.
+    ///
+    /// `priority_ops` are placed ahead of the pool's operations. They skip the fee
+    /// and sender throttling checks, but are simulated and rejected like any other
+    /// operation.
     async fn make_bundle(
         &mut self,
         min_fees: Option<GasFees>,
         is_replacement: bool,
+        priority_ops: Vec<PoolOperation>,
     ) -> BundleProposerResult<Bundle<Self::UO>>;
 
     /// Gets the current gas fees
@@ -116,6 +147,13 @@ pub(crate) trait BundleProposer: Send + Sync + 'static {
 
     /// Notifies the proposer that a condition was not met during the last bundle proposal
     fn notify_condition_not_met(&mut self);
+
+    /// Returns the first slot of `expected_storage` whose latest value differs
+    /// from the expected one, if any
+    async fn find_unmet_condition(
+        &self,
+        expected_storage: &ExpectedStorage,
+    ) -> Option<ConditionNotMetReason>;
 }
 
 pub(crate) type BundleProposerResult<T> = std::result::Result<T, BundleProposerError>;
@@ -144,17 +182,49 @@ pub(crate) struct BundleProposerImpl<UO, S, E, P, M> {
     fee_estimator: FeeEstimator<P>,
     event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
     condition_not_met_notified: bool,
+    // index of the beneficiary in use, where 0 is the primary beneficiary and
+    // the rest index into the fallback beneficiaries
+    beneficiary_index: usize,
+    // beneficiary selected for the current bundle
+    primary_beneficiary: Address,
+    // index of the next beneficiary selected in round robin order
+    next_beneficiary: usize,
+    // consecutive bundle proposals in which each op failed simulation with a
+    // transient error, only kept if `max_transient_simulation_failures` is set
+    op_transient_failures: HashMap<H256, u64>,
+    // ops included per sender in recent bundles, for throttling senders
+    sender_inclusions: SenderInclusions,
     _uo_type: PhantomData<UO>,
 }
 
+/// How the beneficiary of each bundle is selected among the builder's beneficiaries
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum BeneficiarySelection {
+    /// Each beneficiary in turn
+    #[default]
+    RoundRobin,
+    /// A random beneficiary for each bundle
+    Random,
+}
+
 #[derive(Debug)]
 pub(crate) struct Settings {
     pub(crate) chain_spec: ChainSpec,
     pub(crate) max_bundle_size: u64,
     pub(crate) max_bundle_gas: u64,
-    pub(crate) beneficiary: Address,
+    /// Beneficiaries to select the beneficiary of each bundle from, must not be empty
+    pub(crate) beneficiaries: Vec<Address>,
+    pub(crate) beneficiary_selection: BeneficiarySelection,
+    pub(crate) fallback_beneficiaries: Vec<Address>,
     pub(crate) bundle_priority_fee_overhead_percent: u64,
     pub(crate) priority_fee_mode: PriorityFeeMode,
+    pub(crate) simulate_against_bundle_state: bool,
+    pub(crate) max_op_field_size: Option<usize>,
+    pub(crate) max_transient_simulation_failures: Option<u64>,
+    pub(crate) gas_estimate_overhead_percent: u64,
+    pub(crate) max_ops_per_sender_in_window: Option<u64>,
+    pub(crate) sender_window_bundles: u64,
 }
 
 #[async_trait]
@@ -183,10 +253,19 @@ where
         self.condition_not_met_notified = true;
     }
 
+    async fn find_unmet_condition(
+        &self,
+        expected_storage: &ExpectedStorage,
+    ) -> Option<ConditionNotMetReason> {
+        self.check_op_conditions_met(expected_storage).await
+    }
+
+    #[instrument(skip_all, fields(is_replacement = is_replacement))]
     async fn make_bundle(
         &mut self,
         required_fees: Option<GasFees>,
         is_replacement: bool,
+        priority_ops: Vec<PoolOperation>,
     ) -> BundleProposerResult<Bundle<UO>> {
         let (ops, (block_hash, _), (bundle_fees, base_fee)) = try_join!(
             self.get_ops_from_pool(),
@@ -195,12 +274,23 @@ where
                 .map_err(BundleProposerError::from),
             self.estimate_gas_fees(required_fees)
         )?;
-        if ops.is_empty() {
+        let ops = self.remove_priority_ops(ops, &priority_ops);
+        if ops.is_empty() && priority_ops.is_empty() {
             return Err(BundleProposerError::NoOperationsInitially);
         }
 
         tracing::debug!("Starting bundle proposal with {} ops", ops.len());
 
+        // a fallback beneficiary stays in use until it fails too
+        if self.beneficiary_index == 0 {
+            self.select_beneficiary();
+        }
+
+        // a replacement bundle takes the place of the latest bundle in the sender window
+        if is_replacement {
+            self.sender_inclusions.remove_latest();
+        }
+
         // (0) Determine fees required for ops to be included in a bundle
         // if replacing, just require bundle fees increase chances of unsticking
         let required_op_fees = if is_replacement {
@@ -208,8 +298,9 @@ where
         } else {
             self.fee_estimator.required_op_fees(bundle_fees)
         };
-        let all_paymaster_addresses = ops
+        let all_paymaster_addresses = priority_ops
             .iter()
+            .chain(&ops)
             .filter_map(|op| op.uo.paymaster())
             .collect::<Vec<Address>>();
 
@@ -225,11 +316,18 @@ where
             .collect::<Vec<_>>();
 
         tracing::debug!("Bundle proposal after fee limit had {} ops", ops.len());
-        if ops.is_empty() {
+        if ops.is_empty() && priority_ops.is_empty() {
             return Err(BundleProposerError::NoOperationsAfterFeeFilter);
         }
 
-        // (2) Limit the amount of operations for simulation
+        // (2) Defer ops from senders included too often in recent bundles, and place
+        // the priority ops first
+        let ops = priority_ops
+            .into_iter()
+            .chain(self.throttle_senders(ops))
+            .collect();
+
+        // (3) Limit the amount of operations for simulation
         let (ops, gas_limit) = self.limit_user_operations_for_simulation(ops);
 
         tracing::debug!(
@@ -238,7 +336,7 @@ where
             gas_limit
         );
 
-        // (3) simulate ops
+        // (4) simulate ops
         let simulation_futures = ops
             .into_iter()
             .map(|op| self.simulate_op(op, block_hash))
@@ -250,10 +348,7 @@ where
         let (ops_with_simulations, balances_by_paymaster) =
             tokio::join!(ops_with_simulations_future, balances_by_paymaster_future);
         let balances_by_paymaster = balances_by_paymaster?;
-        let ops_with_simulations = ops_with_simulations
-            .into_iter()
-            .flatten()
-            .collect::<Vec<_>>();
+        let ops_with_simulations = self.skip_transient_simulation_failures(ops_with_simulations);
         let mut context = self
             .assemble_context(ops_with_simulations, balances_by_paymaster)
             .await;
@@ -277,17 +372,24 @@ where
                 }
 
                 let mut expected_storage = ExpectedStorage::default();
+                let mut expected_storage_by_op = HashMap::new();
                 for op in context.iter_ops_with_simulations() {
                     expected_storage.merge(&op.simulation.expected_storage)?;
+                    expected_storage_by_op
+                        .insert(self.op_hash(&op.op), op.simulation.expected_storage.clone());
                 }
+                self.sender_inclusions
+                    .record(context.iter_ops().map(|op| op.sender()));
 
                 return Ok(Bundle {
                     ops_per_aggregator: context.to_ops_per_aggregator(),
                     gas_estimate,
                     gas_fees: bundle_fees,
                     expected_storage,
+                    expected_storage_by_op,
                     rejected_ops: context.rejected_ops.iter().map(|po| po.0.clone()).collect(),
                     entity_updates: context.entity_updates.into_values().collect(),
+                    beneficiary: self.beneficiary(),
                 });
             }
             info!("Bundle gas estimation failed. Retrying after removing rejected op(s).");
@@ -319,6 +421,7 @@ where
         settings: Settings,
         event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
     ) -> Self {
+        let primary_beneficiary = settings.beneficiaries[0];
         Self {
             builder_index,
             pool,
@@ -331,9 +434,14 @@ where
                 settings.priority_fee_mode,
                 settings.bundle_priority_fee_overhead_percent,
             ),
+            sender_inclusions: SenderInclusions::new(settings.sender_window_bundles),
             settings,
             event_sender,
             condition_not_met_notified: false,
+            beneficiary_index: 0,
+            primary_beneficiary,
+            next_beneficiary: 0,
+            op_transient_failures: HashMap::new(),
             _uo_type: PhantomData,
         }
     }
@@ -414,17 +522,13 @@ where
         Some(op)
     }
 
-    // Simulate a single op. Returns None if the op should be skipped.
-    //
-    // Filters on any errors
+    // Simulate a single op
+    #[instrument(skip_all, fields(op_hash = ?self.op_hash(&op.uo)))]
     async fn simulate_op(
         &self,
         op: PoolOperation,
         block_hash: H256,
-    ) -> Option<(PoolOperation, Result<SimulationResult, SimulationError>)> {
-        let op_hash = self.op_hash(&op.uo);
-
-        // Simulate
+    ) -> (PoolOperation, Result<SimulationResult, SimulationError>) {
         let result = self
             .simulator
             .simulate_validation(
@@ -433,30 +537,55 @@ where
                 Some(op.expected_code_hash),
             )
             .await;
-        let result = match result {
-            Ok(success) => (op, Ok(success)),
-            Err(error) => match error {
-                SimulationError {
-                    violation_error: ViolationError::Violations(_),
-                    entity_infos: _,
-                } => (op, Err(error)),
-                SimulationError {
+        (op, result)
+    }
+
+    // Skips ops whose simulation failed with an error other than a validation violation,
+    // such as an RPC error. These errors are not a deterministic rejection of the op, so
+    // it remains in the pool to be simulated again for a later bundle.
+    //
+    // If `max_transient_simulation_failures` is set, ops failing this way in that many
+    // consecutive bundle proposals are kept instead, to be rejected.
+    fn skip_transient_simulation_failures(
+        &mut self,
+        ops_with_simulations: Vec<(PoolOperation, Result<SimulationResult, SimulationError>)>,
+    ) -> Vec<(PoolOperation, Result<SimulationResult, SimulationError>)> {
+        let mut previous = mem::take(&mut self.op_transient_failures);
+        let mut kept = vec![];
+        for (op, result) in ops_with_simulations {
+            let error = match &result {
+                Err(SimulationError {
                     violation_error: ViolationError::Other(error),
-                    entity_infos: _,
-                } => {
-                    self.emit(BuilderEvent::skipped_op(
-                        self.builder_index,
-                        op_hash,
-                        SkipReason::Other {
-                            reason: Arc::new(format!("Failed to simulate op: {error:?}, skipping")),
-                        },
-                    ));
-                    return None;
+                    ..
+                }) => error,
+                _ => {
+                    kept.push((op, result));
+                    continue;
                 }
-            },
-        };
+            };
 
-        Some(result)
+            let op_hash = self.op_hash(&op.uo);
+            let count = previous.remove(&op_hash).unwrap_or(0) + 1;
+            match self.settings.max_transient_simulation_failures {
+                Some(max_failures) if count >= max_failures => {
+                    warn!("Rejecting op {op_hash:?} after it failed simulation in {count} consecutive bundle proposals: {error:?}");
+                    kept.push((op, result));
+                    continue;
+                }
+                Some(_) => {
+                    self.op_transient_failures.insert(op_hash, count);
+                }
+                None => {}
+            }
+            self.emit(BuilderEvent::skipped_op(
+                self.builder_index,
+                op_hash,
+                SkipReason::Other {
+                    reason: Arc::new(format!("Failed to simulate op: {error:?}, skipping")),
+                },
+            ));
+        }
+        kept
     }
 
     async fn assemble_context(
@@ -473,8 +602,27 @@ where
 
         let mut gas_spent = self.settings.chain_spec.transaction_intrinsic_gas;
         let mut constructed_bundle_size = BUNDLE_BYTE_OVERHEAD;
+        let mut bundle_state_ops = Vec::<UO>::new();
         for (po, simulation) in ops_with_simulations {
             let op = po.clone().uo;
+
+            // reject ops bloating the bundle calldata
+            if let Some(max_size) = self.settings.max_op_field_size {
+                if let Some((field, size)) = op.oversized_field(max_size) {
+                    self.emit(BuilderEvent::rejected_op(
+                        self.builder_index,
+                        self.op_hash(&op),
+                        OpRejectionReason::FieldTooLarge {
+                            field,
+                            size,
+                            max_size,
+                        },
+                    ));
+                    context.rejected_ops.push((op.into(), po.entity_infos));
+                    continue;
+                }
+            }
+
             let simulation = match simulation {
                 Ok(simulation) => simulation,
                 Err(error) => {
@@ -485,16 +633,23 @@ where
                             error: error.clone(),
                         },
                     ));
-                    if let SimulationError {
-                        violation_error: ViolationError::Violations(violations),
-                        entity_infos,
-                    } = error
-                    {
-                        // try to use EntityInfos from the latest simulation, but if it doesn't exist use the EntityInfos from the previous simulation
-                        let infos = entity_infos.map_or(po.entity_infos, |e| e);
-                        context.process_simulation_violations(violations, infos);
-                        context.rejected_ops.push((op.into(), po.entity_infos));
+                    match error {
+                        SimulationError {
+                            violation_error: ViolationError::Violations(violations),
+                            entity_infos,
+                        } => {
+                            // try to use EntityInfos from the latest simulation, but if it doesn't exist use the EntityInfos from the previous simulation
+                            let infos = entity_infos.map_or(po.entity_infos, |e| e);
+                            context.process_simulation_violations(violations, infos);
+                        }
+                        // only reached once the op has failed simulation with other errors
+                        // in `max_transient_simulation_failures` consecutive proposals
+                        SimulationError {
+                            violation_error: ViolationError::Other(_),
+                            ..
+                        } => {}
                     }
+                    context.rejected_ops.push((op.into(), po.entity_infos));
                     continue;
                 }
             };
@@ -547,6 +702,32 @@ where
                 ));
                 continue;
             }
+            // Simulate the op on top of the ops already selected. An op that is only
+            // invalid given their effects conflicts with them, and is left in the pool
+            // for a later bundle, like an op accessing another sender.
+            if self.settings.simulate_against_bundle_state
+                && simulation.aggregator.is_none()
+                && !bundle_state_ops.is_empty()
+            {
+                let gas = math::increase_by_percent(
+                    required_gas,
+                    self.settings.gas_estimate_overhead_percent,
+                );
+                if let Some(message) = self
+                    .check_against_bundle_state(&bundle_state_ops, &op, gas)
+                    .await
+                {
+                    info!("Excluding op from {:?} because it failed against the in-progress bundle state.", op.sender());
+                    self.emit(BuilderEvent::skipped_op(
+                        self.builder_index,
+                        self.op_hash(&op),
+                        SkipReason::ConflictsWithBundle {
+                            message: Arc::new(message),
+                        },
+                    ));
+                    continue;
+                }
+            }
             if let Some(paymaster) = op.paymaster() {
                 let Some(balance) = balances_by_paymaster.get_mut(&paymaster) else {
                     error!("Op had paymaster with unknown balance, but balances should have been loaded for all paymasters in bundle.");
@@ -569,6 +750,10 @@ where
             constructed_bundle_size =
                 constructed_bundle_size.saturating_add(op_size_with_offset_word);
 
+            if self.settings.simulate_against_bundle_state && simulation.aggregator.is_none() {
+                bundle_state_ops.push(op.clone());
+            }
+
             context
                 .groups_by_aggregator
                 .entry(simulation.aggregator_address())
@@ -581,12 +766,58 @@ where
         }
         for paymaster in paymasters_to_reject {
             // No need to update aggregator signatures because we haven't computed them yet.
-            let _ = context.reject_entity(paymaster.entity, paymaster.is_staked);
+            let _ = context.reject_entity(
+                paymaster.entity,
+                paymaster.is_staked,
+                EntityUpdateReason::InsufficientDeposit,
+            );
         }
         self.compute_all_aggregator_signatures(&mut context).await;
         context
     }
 
+    // Simulates an op appended to the unaggregated ops already selected for the bundle,
+    // so that its validation runs against the state produced by those earlier ops.
+    // Returns the failure message if the op fails in that state.
+    //
+    // Each call executes all the earlier ops again, so a bundle of n ops costs n
+    // sequential `eth_call`s executing O(n^2) ops in total.
+    //
+    // Any other outcome, including an RPC error, is left for the full bundle gas
+    // estimation to handle, so that a flaky node doesn't exclude valid ops.
+    async fn check_against_bundle_state(
+        &self,
+        bundle_state_ops: &[UO],
+        op: &UO,
+        gas: U256,
+    ) -> Option<String> {
+        let index = bundle_state_ops.len();
+        let mut user_ops = bundle_state_ops.to_vec();
+        user_ops.push(op.clone());
+        let ops_per_aggregator = vec![UserOpsPerAggregator {
+            user_ops,
+            aggregator: Address::zero(),
+            signature: Bytes::new(),
+        }];
+
+        match self
+            .entry_point
+            .call_handle_ops(ops_per_aggregator, self.beneficiary(), gas)
+            .await
+        {
+            Ok(HandleOpsOut::FailedOp(failed_index, message)) if failed_index == index => {
+                Some(message)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                warn!(
+                    "Failed to simulate op against in-progress bundle state, failing open: {e:?}"
+                );
+                None
+            }
+        }
+    }
+
     async fn check_conditions_met(&self, context: &mut ProposalContext<UO>) -> anyhow::Result<()> {
         let futs = context
             .iter_ops_with_simulations()
@@ -665,8 +896,9 @@ where
         context: &mut ProposalContext<UO>,
         entity: Entity,
         is_staked: bool,
+        reason: EntityUpdateReason,
     ) {
-        let changed_aggregators = context.reject_entity(entity, is_staked);
+        let changed_aggregators = context.reject_entity(entity, is_staked, reason);
         self.compute_aggregator_signatures(context, &changed_aggregators)
             .await;
     }
@@ -703,24 +935,21 @@ where
     /// amount of gas, but if not then mutates the context to remove whichever
     /// op(s) caused the failure.
     async fn estimate_gas_rejecting_failed_ops(
-        &self,
+        &mut self,
         context: &mut ProposalContext<UO>,
     ) -> BundleProposerResult<Option<U256>> {
         // sum up the gas needed for all the ops in the bundle
         // and apply an overhead multiplier
-        let gas = math::increase_by_percent(
-            context.get_bundle_gas_limit(&self.settings.chain_spec),
-            BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT,
+        let gas = bundle_gas_limit(
+            &self.settings.chain_spec,
+            context.iter_ops(),
+            self.settings.gas_estimate_overhead_percent,
         );
 
         // call handle ops with the bundle to filter any rejected ops before sending
         let handle_ops_out = self
             .entry_point
-            .call_handle_ops(
-                context.to_ops_per_aggregator(),
-                self.settings.beneficiary,
-                gas,
-            )
+            .call_handle_ops(context.to_ops_per_aggregator(), self.beneficiary(), gas)
             .await
             .context("should call handle ops with candidate bundle")?;
         match handle_ops_out {
@@ -738,8 +967,13 @@ where
             }
             HandleOpsOut::SignatureValidationFailed(aggregator) => {
                 info!("Rejected aggregator {aggregator:?} because its signature validation failed during gas estimation.");
-                self.reject_entity(context, Entity::aggregator(aggregator), false)
-                    .await;
+                self.reject_entity(
+                    context,
+                    Entity::aggregator(aggregator),
+                    false,
+                    EntityUpdateReason::SignatureValidationFailed,
+                )
+                .await;
                 Ok(None)
             }
             HandleOpsOut::PostOpRevert => {
@@ -747,9 +981,46 @@ where
                 self.process_post_op_revert(context, gas).await?;
                 Ok(None)
             }
+            HandleOpsOut::InvalidBeneficiary(message) => {
+                let failed_beneficiary = self.beneficiary();
+                self.rotate_beneficiary();
+                Err(anyhow::anyhow!(
+                    "beneficiary {failed_beneficiary:?} failed during gas estimation with {message}, using beneficiary {:?} for the next attempt",
+                    self.beneficiary()
+                )
+                .into())
+            }
+        }
+    }
+
+    // The beneficiary in use, either the primary beneficiary or one of the fallbacks
+    fn beneficiary(&self) -> Address {
+        match self.beneficiary_index {
+            0 => self.primary_beneficiary,
+            i => self.settings.fallback_beneficiaries[i - 1],
         }
     }
 
+    // Selects the primary beneficiary of the next bundle
+    fn select_beneficiary(&mut self) {
+        let beneficiaries = &self.settings.beneficiaries;
+        let index = match self.settings.beneficiary_selection {
+            BeneficiarySelection::RoundRobin => {
+                let index = self.next_beneficiary % beneficiaries.len();
+                self.next_beneficiary = index + 1;
+                index
+            }
+            BeneficiarySelection::Random => rand::thread_rng().gen_range(0..beneficiaries.len()),
+        };
+        self.primary_beneficiary = beneficiaries[index];
+    }
+
+    // Moves to the next fallback beneficiary, wrapping back around to the primary
+    fn rotate_beneficiary(&mut self) {
+        self.beneficiary_index =
+            (self.beneficiary_index + 1) % (self.settings.fallback_beneficiaries.len() + 1);
+    }
+
     async fn get_ops_from_pool(&self) -> BundleProposerResult<Vec<PoolOperation>> {
         // Use builder's index as the shard index to ensure that two builders don't
         // attempt to bundle the same operations.
@@ -826,6 +1097,7 @@ where
                         .entity_infos
                         .factory
                         .map_or(false, |f| f.is_staked),
+                    EntityUpdateReason::GasEstimationFailed,
                 )
                 .await;
             }
@@ -845,6 +1117,7 @@ where
                         .entity_infos
                         .paymaster
                         .map_or(false, |p| p.is_staked),
+                    EntityUpdateReason::GasEstimationFailed,
                 )
                 .await;
             }
@@ -932,7 +1205,7 @@ where
         }];
         let ret = self
             .entry_point
-            .call_handle_ops(bundle, self.settings.beneficiary, gas)
+            .call_handle_ops(bundle, self.beneficiary(), gas)
             .await;
         match ret {
             Ok(out) => {
@@ -965,7 +1238,7 @@ where
         let bundle = vec![group];
         let ret = self
             .entry_point
-            .call_handle_ops(bundle, self.settings.beneficiary, gas)
+            .call_handle_ops(bundle, self.beneficiary(), gas)
             .await;
         match ret {
             Ok(out) => {
@@ -987,6 +1260,51 @@ where
         }
     }
 
+    // Skips ops whose sender has reached the maximum number of ops included in the
+    // recent bundle window, including ops earlier in this bundle. Skipped ops remain
+    // in the pool for later bundles.
+    // Removes the pool's copies of the priority ops, so that they are not bundled twice
+    fn remove_priority_ops(
+        &self,
+        ops: Vec<PoolOperation>,
+        priority_ops: &[PoolOperation],
+    ) -> Vec<PoolOperation> {
+        if priority_ops.is_empty() {
+            return ops;
+        }
+        let priority_hashes: HashSet<_> =
+            priority_ops.iter().map(|op| self.op_hash(&op.uo)).collect();
+        ops.into_iter()
+            .filter(|op| !priority_hashes.contains(&self.op_hash(&op.uo)))
+            .collect()
+    }
+
+    fn throttle_senders(&self, ops: Vec<PoolOperation>) -> Vec<PoolOperation> {
+        let Some(max_ops) = self.settings.max_ops_per_sender_in_window else {
+            return ops;
+        };
+
+        let mut sender_counts = HashMap::new();
+        ops.into_iter()
+            .filter(|op| {
+                let sender = op.uo.sender();
+                let count = sender_counts
+                    .entry(sender)
+                    .or_insert_with(|| self.sender_inclusions.count(sender));
+                if *count >= max_ops {
+                    self.emit(BuilderEvent::skipped_op(
+                        self.builder_index,
+                        self.op_hash(&op.uo),
+                        SkipReason::SenderThrottled,
+                    ));
+                    return false;
+                }
+                *count += 1;
+                true
+            })
+            .collect()
+    }
+
     fn limit_user_operations_for_simulation(
         &self,
         ops: Vec<PoolOperation>,
@@ -1033,6 +1351,48 @@ where
     }
 }
 
+/// Number of ops included per sender in each of the bundles preceding the next
+/// bundle in a window of `window` consecutive bundles
+#[derive(Debug)]
+struct SenderInclusions {
+    max_bundles: usize,
+    bundles: VecDeque<HashMap<Address, u64>>,
+}
+
+impl SenderInclusions {
+    fn new(window: u64) -> Self {
+        Self {
+            max_bundles: (window as usize).saturating_sub(1),
+            bundles: VecDeque::new(),
+        }
+    }
+
+    fn count(&self, sender: Address) -> u64 {
+        self.bundles
+            .iter()
+            .filter_map(|bundle| bundle.get(&sender))
+            .sum()
+    }
+
+    fn record(&mut self, senders: impl IntoIterator<Item = Address>) {
+        if self.max_bundles == 0 {
+            return;
+        }
+        let mut bundle = HashMap::new();
+        for sender in senders {
+            *bundle.entry(sender).or_default() += 1;
+        }
+        if self.bundles.len() == self.max_bundles {
+            self.bundles.pop_front();
+        }
+        self.bundles.push_back(bundle);
+    }
+
+    fn remove_latest(&mut self) {
+        self.bundles.pop_back();
+    }
+}
+
 #[derive(Debug)]
 struct OpWithSimulation<UO> {
     op: UO,
@@ -1151,7 +1511,12 @@ impl<UO: UserOperation> ProposalContext<UO> {
 
     /// Returns the addresses of any aggregators whose signature may need to be recomputed.
     #[must_use = "rejected entity but did not update aggregator signatures"]
-    fn reject_entity(&mut self, entity: Entity, is_staked: bool) -> Vec<Address> {
+    fn reject_entity(
+        &mut self,
+        entity: Entity,
+        is_staked: bool,
+        reason: EntityUpdateReason,
+    ) -> Vec<Address> {
         let ret = match entity.kind {
             EntityType::Aggregator => {
                 self.reject_aggregator(entity.address);
@@ -1170,6 +1535,7 @@ impl<UO: UserOperation> ProposalContext<UO> {
                 } else {
                     EntityUpdateType::UnstakedInvalidation
                 },
+                reason,
             },
         );
         ret
@@ -1230,21 +1596,6 @@ impl<UO: UserOperation> ProposalContext<UO> {
             .collect()
     }
 
-    fn get_bundle_gas_limit(&self, chain_spec: &ChainSpec) -> U256 {
-        // TODO(danc): in the 0.7 entrypoint we could optimize this by removing the need for
-        // the 10K gas and 63/64 gas overheads for each op in the bundle and instead calculate exactly
-        // the limit needed to include that overhead for each op.
-        //
-        // In the 0.6 entrypoint we're assuming that we need 1 verification gas buffer for each op in the bundle
-        // regardless of if it uses a post op or not. We can optimize to calculate the exact gas overhead
-        // needed to have the buffer for each op.
-
-        self.iter_ops_with_simulations()
-            .map(|sim_op| gas::user_operation_gas_limit(chain_spec, &sim_op.op, false))
-            .fold(U256::zero(), |acc, i| acc + i)
-            + chain_spec.transaction_intrinsic_gas
-    }
-
     fn iter_ops_with_simulations(&self) -> impl Iterator<Item = &OpWithSimulation<UO>> + '_ {
         self.groups_by_aggregator
             .values()
@@ -1269,6 +1620,7 @@ impl<UO: UserOperation> ProposalContext<UO> {
                 EntityUpdate {
                     entity: factory.entity,
                     update_type: EntityUpdateType::StakedInvalidation,
+                    reason: EntityUpdateReason::SimulationFailed,
                 },
             );
             return;
@@ -1281,6 +1633,7 @@ impl<UO: UserOperation> ProposalContext<UO> {
                 EntityUpdate {
                     entity: entity_infos.sender.entity,
                     update_type: EntityUpdateType::StakedInvalidation,
+                    reason: EntityUpdateReason::SimulationFailed,
                 },
             );
             return;
@@ -1353,6 +1706,7 @@ impl<UO: UserOperation> ProposalContext<UO> {
         let entity_update = EntityUpdate {
             entity,
             update_type: ProposalContext::<UO>::get_entity_update_type(entity.kind, entity_infos),
+            reason: EntityUpdateReason::SimulationFailed,
         };
         self.entity_updates.insert(entity.address, entity_update);
     }
@@ -1428,6 +1782,8 @@ mod tests {
 
     use super::*;
 
+    const GAS_ESTIMATE_OVERHEAD_PERCENT: u64 = 5;
+
     #[tokio::test]
     async fn test_singleton_valid_bundle() {
         let op = UserOperation {
@@ -1451,7 +1807,7 @@ mod tests {
                 + op.call_gas_limit
                 + cs.transaction_intrinsic_gas
                 + ENTRY_POINT_INNER_GAS_OVERHEAD,
-            BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT,
+            GAS_ESTIMATE_OVERHEAD_PERCENT,
         );
 
         assert_eq!(
@@ -1874,10 +2230,12 @@ mod tests {
                 EntityUpdate {
                     entity: Entity::paymaster(address(1)),
                     update_type: EntityUpdateType::UnstakedInvalidation,
+                    reason: EntityUpdateReason::GasEstimationFailed,
                 },
                 EntityUpdate {
                     entity: Entity::factory(address(3)),
                     update_type: EntityUpdateType::UnstakedInvalidation,
+                    reason: EntityUpdateReason::GasEstimationFailed,
                 },
             ]
         );
@@ -1944,7 +2302,7 @@ mod tests {
             bundle.gas_estimate,
             U256::from(math::increase_by_percent(
                 9_000_000 + 2 * 5_000 + 21_000,
-                BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT
+                GAS_ESTIMATE_OVERHEAD_PERCENT
             ))
         );
     }
@@ -1993,7 +2351,10 @@ mod tests {
             + 5_000
             + 21_000;
 
-        assert_eq!(context.get_bundle_gas_limit(&cs), expected_gas_limit);
+        assert_eq!(
+            bundle_gas_limit(&cs, context.iter_ops(), 0),
+            expected_gas_limit
+        );
     }
 
     #[tokio::test]
@@ -2029,7 +2390,7 @@ mod tests {
             rejected_ops: vec![],
             entity_updates: BTreeMap::new(),
         };
-        let gas_limit = context.get_bundle_gas_limit(&cs);
+        let gas_limit = bundle_gas_limit(&cs, context.iter_ops(), 0);
 
         let expected_gas_limit = op1.pre_verification_gas
             + op1.verification_gas_limit * 3
@@ -2247,32 +2608,645 @@ mod tests {
         assert_eq!(bundle.rejected_ops, vec![op]);
     }
 
-    struct MockOp {
-        op: UserOperation,
-        simulation_result: Box<dyn Fn() -> Result<SimulationResult, SimulationError> + Send + Sync>,
-    }
-
-    struct MockAggregator {
-        address: Address,
-        signature: Box<dyn Fn() -> anyhow::Result<Option<Bytes>> + Send + Sync>,
-    }
-
-    async fn simple_make_bundle(mock_ops: Vec<MockOp>) -> Bundle<UserOperation> {
-        mock_make_bundle(
-            mock_ops,
+    #[tokio::test]
+    async fn test_skips_op_conflicting_with_bundle_state() {
+        // op2 is valid against the latest block, but fails once op1 is applied.
+        let op1 = op_with_sender(address(1));
+        let op2 = op_with_sender(address(2));
+        let bundle = mock_make_bundle_with_bundle_state(
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
             vec![],
-            vec![HandleOpsOut::Success],
+            vec![
+                HandleOpsOut::FailedOp(1, "AA25 invalid account nonce".to_string()),
+                HandleOpsOut::Success,
+            ],
             vec![],
             U256::zero(),
             U256::zero(),
             false,
             ExpectedStorage::default(),
+            true,
         )
-        .await
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op1],
+                ..Default::default()
+            }],
+        );
+        // op2 is left in the pool for a later bundle
+        assert!(bundle.rejected_ops.is_empty());
     }
 
-    #[allow(clippy::too_many_arguments)]
-    async fn mock_make_bundle(
+    #[tokio::test]
+    async fn test_falls_back_to_next_beneficiary() {
+        let entry_point_address = address(123);
+        let primary = address(124);
+        let fallback = address(125);
+        let current_block_hash = hash(125);
+        let op = op_with_sender(address(1));
+        let pool_op = PoolOperation {
+            uo: op.clone().into(),
+            expected_code_hash: hash(126),
+            entry_point: entry_point_address,
+            sim_block_hash: current_block_hash,
+            sim_block_number: 0,
+            account_is_staked: false,
+            valid_time_range: ValidTimeRange::default(),
+            entity_infos: EntityInfos::default(),
+            aggregator: None,
+        };
+
+        let mut pool_client = MockPool::new();
+        pool_client
+            .expect_get_ops()
+            .returning(move |_, _, _| Ok(vec![pool_op.clone()]));
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(|_, _, _| Ok(SimulationResult::default()));
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_address);
+        // the primary beneficiary reverts when receiving the bundle's fees
+        entry_point
+            .expect_call_handle_ops()
+            .withf(move |_, &b, _| b == primary)
+            .once()
+            .returning(|_, _, _| {
+                Ok(HandleOpsOut::InvalidBeneficiary(
+                    "AA91 failed send to beneficiary".to_string(),
+                ))
+            });
+        entry_point
+            .expect_call_handle_ops()
+            .withf(move |_, &b, _| b == fallback)
+            .once()
+            .returning(|_, _, _| Ok(HandleOpsOut::Success));
+
+        let mut provider = MockProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || Ok((current_block_hash, U64::zero())));
+        provider
+            .expect_get_base_fee()
+            .returning(|| Ok(U256::zero()));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::zero()));
+
+        let mut proposer = BundleProposerImpl::new(
+            0,
+            pool_client,
+            simulator,
+            entry_point,
+            Arc::new(provider),
+            Settings {
+                chain_spec: ChainSpec::default(),
+                max_bundle_size: 1,
+                max_bundle_gas: 10_000_000,
+                beneficiaries: vec![primary],
+                beneficiary_selection: BeneficiarySelection::RoundRobin,
+                fallback_beneficiaries: vec![fallback],
+                priority_fee_mode: PriorityFeeMode::PriorityFeeIncreasePercent(10),
+                bundle_priority_fee_overhead_percent: 0,
+                simulate_against_bundle_state: false,
+                max_op_field_size: None,
+                max_transient_simulation_failures: None,
+                gas_estimate_overhead_percent: GAS_ESTIMATE_OVERHEAD_PERCENT,
+                max_ops_per_sender_in_window: None,
+                sender_window_bundles: 0,
+            },
+            broadcast::channel(16).0,
+        );
+
+        // the first attempt fails with the primary beneficiary, the next uses the fallback
+        assert!(proposer.make_bundle(None, false, vec![]).await.is_err());
+        let bundle = proposer
+            .make_bundle(None, false, vec![])
+            .await
+            .expect("should make a bundle");
+        assert_eq!(bundle.beneficiary, fallback);
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op],
+                ..Default::default()
+            }],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_priority_ops_placed_first() {
+        let entry_point_address = address(123);
+        let current_block_hash = hash(125);
+        let pool_op = |op: UserOperation| PoolOperation {
+            uo: op.into(),
+            expected_code_hash: hash(126),
+            entry_point: entry_point_address,
+            sim_block_hash: current_block_hash,
+            sim_block_number: 0,
+            account_is_staked: false,
+            valid_time_range: ValidTimeRange::default(),
+            entity_infos: EntityInfos::default(),
+            aggregator: None,
+        };
+        let paying_op = UserOperation {
+            max_fee_per_gas: U256::from(10),
+            max_priority_fee_per_gas: U256::from(10),
+            ..op_with_sender(address(1))
+        };
+        // pays no fees, so is only bundled as a priority op
+        let priority_op = op_with_sender(address(2));
+
+        let mut pool_client = MockPool::new();
+        let pool_ops = vec![pool_op(paying_op.clone()), pool_op(priority_op.clone())];
+        pool_client
+            .expect_get_ops()
+            .returning(move |_, _, _| Ok(pool_ops.clone()));
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(|_, _, _| Ok(SimulationResult::default()));
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_address);
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _| Ok(HandleOpsOut::Success));
+
+        let mut provider = MockProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || Ok((current_block_hash, U64::zero())));
+        provider.expect_get_base_fee().returning(|| Ok(U256::one()));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::zero()));
+
+        let mut proposer = BundleProposerImpl::new(
+            0,
+            pool_client,
+            simulator,
+            entry_point,
+            Arc::new(provider),
+            Settings {
+                chain_spec: ChainSpec::default(),
+                max_bundle_size: 2,
+                max_bundle_gas: 10_000_000,
+                beneficiaries: vec![address(124)],
+                beneficiary_selection: BeneficiarySelection::RoundRobin,
+                fallback_beneficiaries: vec![],
+                priority_fee_mode: PriorityFeeMode::PriorityFeeIncreasePercent(10),
+                bundle_priority_fee_overhead_percent: 0,
+                simulate_against_bundle_state: false,
+                max_op_field_size: None,
+                max_transient_simulation_failures: None,
+                gas_estimate_overhead_percent: GAS_ESTIMATE_OVERHEAD_PERCENT,
+                max_ops_per_sender_in_window: None,
+                sender_window_bundles: 0,
+            },
+            broadcast::channel(16).0,
+        );
+
+        // the pool's copy of the priority op is not bundled twice
+        let bundle = proposer
+            .make_bundle(None, false, vec![pool_op(priority_op.clone())])
+            .await
+            .expect("should make a bundle");
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![priority_op, paying_op.clone()],
+                ..Default::default()
+            }],
+        );
+
+        // priority ops only apply to the bundle they are passed for
+        let bundle = proposer
+            .make_bundle(None, false, vec![])
+            .await
+            .expect("should make a bundle");
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![paying_op],
+                ..Default::default()
+            }],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_beneficiaries() {
+        let entry_point_address = address(123);
+        let beneficiaries = vec![address(124), address(125)];
+        let current_block_hash = hash(125);
+        let op = op_with_sender(address(1));
+        let pool_op = PoolOperation {
+            uo: op.clone().into(),
+            expected_code_hash: hash(126),
+            entry_point: entry_point_address,
+            sim_block_hash: current_block_hash,
+            sim_block_number: 0,
+            account_is_staked: false,
+            valid_time_range: ValidTimeRange::default(),
+            entity_infos: EntityInfos::default(),
+            aggregator: None,
+        };
+
+        let mut pool_client = MockPool::new();
+        pool_client
+            .expect_get_ops()
+            .returning(move |_, _, _| Ok(vec![pool_op.clone()]));
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(|_, _, _| Ok(SimulationResult::default()));
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_address);
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _| Ok(HandleOpsOut::Success));
+
+        let mut provider = MockProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || Ok((current_block_hash, U64::zero())));
+        provider
+            .expect_get_base_fee()
+            .returning(|| Ok(U256::zero()));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::zero()));
+
+        let mut proposer = BundleProposerImpl::new(
+            0,
+            pool_client,
+            simulator,
+            entry_point,
+            Arc::new(provider),
+            Settings {
+                chain_spec: ChainSpec::default(),
+                max_bundle_size: 1,
+                max_bundle_gas: 10_000_000,
+                beneficiaries: beneficiaries.clone(),
+                beneficiary_selection: BeneficiarySelection::RoundRobin,
+                fallback_beneficiaries: vec![],
+                priority_fee_mode: PriorityFeeMode::PriorityFeeIncreasePercent(10),
+                bundle_priority_fee_overhead_percent: 0,
+                simulate_against_bundle_state: false,
+                max_op_field_size: None,
+                max_transient_simulation_failures: None,
+                gas_estimate_overhead_percent: GAS_ESTIMATE_OVERHEAD_PERCENT,
+                max_ops_per_sender_in_window: None,
+                sender_window_bundles: 0,
+            },
+            broadcast::channel(16).0,
+        );
+
+        let mut selected = vec![];
+        for _ in 0..3 {
+            let bundle = proposer
+                .make_bundle(None, false, vec![])
+                .await
+                .expect("should make a bundle");
+            selected.push(bundle.beneficiary);
+        }
+        assert_eq!(
+            selected,
+            vec![beneficiaries[0], beneficiaries[1], beneficiaries[0]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_op_with_oversized_field() {
+        let entry_point_address = address(123);
+        let current_block_hash = hash(125);
+        let op = op_with_sender(address(1));
+        let oversized_op = UserOperation {
+            signature: vec![0; 101].into(),
+            ..op_with_sender(address(2))
+        };
+        let pool_ops = [&op, &oversized_op]
+            .into_iter()
+            .map(|op| PoolOperation {
+                uo: op.clone().into(),
+                expected_code_hash: hash(126),
+                entry_point: entry_point_address,
+                sim_block_hash: current_block_hash,
+                sim_block_number: 0,
+                account_is_staked: false,
+                valid_time_range: ValidTimeRange::default(),
+                entity_infos: EntityInfos::default(),
+                aggregator: None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut pool_client = MockPool::new();
+        pool_client
+            .expect_get_ops()
+            .returning(move |_, _, _| Ok(pool_ops.clone()));
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(|_, _, _| Ok(SimulationResult::default()));
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_address);
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _| Ok(HandleOpsOut::Success));
+
+        let mut provider = MockProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || Ok((current_block_hash, U64::zero())));
+        provider
+            .expect_get_base_fee()
+            .returning(|| Ok(U256::zero()));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::zero()));
+
+        let mut proposer = BundleProposerImpl::new(
+            0,
+            pool_client,
+            simulator,
+            entry_point,
+            Arc::new(provider),
+            Settings {
+                chain_spec: ChainSpec::default(),
+                max_bundle_size: 2,
+                max_bundle_gas: 10_000_000,
+                beneficiaries: vec![address(124)],
+                beneficiary_selection: BeneficiarySelection::RoundRobin,
+                fallback_beneficiaries: vec![],
+                priority_fee_mode: PriorityFeeMode::PriorityFeeIncreasePercent(10),
+                bundle_priority_fee_overhead_percent: 0,
+                simulate_against_bundle_state: false,
+                max_op_field_size: Some(100),
+                max_transient_simulation_failures: None,
+                gas_estimate_overhead_percent: GAS_ESTIMATE_OVERHEAD_PERCENT,
+                max_ops_per_sender_in_window: None,
+                sender_window_bundles: 0,
+            },
+            broadcast::channel(16).0,
+        );
+
+        let bundle = proposer
+            .make_bundle(None, false, vec![])
+            .await
+            .expect("should make a bundle");
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op],
+                ..Default::default()
+            }],
+        );
+        assert_eq!(bundle.rejected_ops, vec![oversized_op]);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_after_max_transient_simulation_failures() {
+        let entry_point_address = address(123);
+        let current_block_hash = hash(125);
+        let op = default_op();
+        let pool_op = PoolOperation {
+            uo: op.clone().into(),
+            expected_code_hash: hash(126),
+            entry_point: entry_point_address,
+            sim_block_hash: current_block_hash,
+            sim_block_number: 0,
+            account_is_staked: false,
+            valid_time_range: ValidTimeRange::default(),
+            entity_infos: EntityInfos::default(),
+            aggregator: None,
+        };
+
+        let mut pool_client = MockPool::new();
+        pool_client
+            .expect_get_ops()
+            .returning(move |_, _, _| Ok(vec![pool_op.clone()]));
+        let mut simulator = MockSimulator::new();
+        simulator.expect_simulate_validation().returning(|_, _, _| {
+            Err(SimulationError {
+                violation_error: ViolationError::Other(anyhow!("connection reset")),
+                entity_infos: None,
+            })
+        });
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_address);
+
+        let mut provider = MockProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || Ok((current_block_hash, U64::zero())));
+        provider
+            .expect_get_base_fee()
+            .returning(|| Ok(U256::zero()));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::zero()));
+
+        let mut proposer = BundleProposerImpl::new(
+            0,
+            pool_client,
+            simulator,
+            entry_point,
+            Arc::new(provider),
+            Settings {
+                chain_spec: ChainSpec::default(),
+                max_bundle_size: 1,
+                max_bundle_gas: 10_000_000,
+                beneficiaries: vec![address(124)],
+                beneficiary_selection: BeneficiarySelection::RoundRobin,
+                fallback_beneficiaries: vec![],
+                priority_fee_mode: PriorityFeeMode::PriorityFeeIncreasePercent(10),
+                bundle_priority_fee_overhead_percent: 0,
+                simulate_against_bundle_state: false,
+                max_op_field_size: None,
+                max_transient_simulation_failures: Some(2),
+                gas_estimate_overhead_percent: GAS_ESTIMATE_OVERHEAD_PERCENT,
+                max_ops_per_sender_in_window: None,
+                sender_window_bundles: 0,
+            },
+            broadcast::channel(16).0,
+        );
+
+        // the first transient failure leaves the op in the pool
+        let bundle = proposer.make_bundle(None, false, vec![]).await.unwrap();
+        assert!(bundle.is_empty());
+        assert!(bundle.rejected_ops.is_empty());
+
+        // the second consecutive failure rejects it
+        let bundle = proposer.make_bundle(None, false, vec![]).await.unwrap();
+        assert!(bundle.is_empty());
+        assert_eq!(bundle.rejected_ops, vec![op]);
+        assert!(proposer.op_transient_failures.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_throttles_sender_across_bundles() {
+        let entry_point_address = address(123);
+        let current_block_hash = hash(125);
+        let spammer_op = op_with_sender(address(1));
+        let other_op = op_with_sender(address(2));
+        let pool_ops: Vec<_> = [&spammer_op, &other_op]
+            .into_iter()
+            .map(|op| PoolOperation {
+                uo: op.clone().into(),
+                expected_code_hash: hash(126),
+                entry_point: entry_point_address,
+                sim_block_hash: current_block_hash,
+                sim_block_number: 0,
+                account_is_staked: false,
+                valid_time_range: ValidTimeRange::default(),
+                entity_infos: EntityInfos::default(),
+                aggregator: None,
+            })
+            .collect();
+
+        let mut pool_client = MockPool::new();
+        pool_client
+            .expect_get_ops()
+            .returning(move |_, _, _| Ok(pool_ops.clone()));
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(|_, _, _| Ok(SimulationResult::default()));
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_address);
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _| Ok(HandleOpsOut::Success));
+
+        let mut provider = MockProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || Ok((current_block_hash, U64::zero())));
+        provider
+            .expect_get_base_fee()
+            .returning(|| Ok(U256::zero()));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::zero()));
+
+        let mut proposer = BundleProposerImpl::new(
+            0,
+            pool_client,
+            simulator,
+            entry_point,
+            Arc::new(provider),
+            Settings {
+                chain_spec: ChainSpec::default(),
+                max_bundle_size: 2,
+                max_bundle_gas: 10_000_000,
+                beneficiaries: vec![address(124)],
+                beneficiary_selection: BeneficiarySelection::RoundRobin,
+                fallback_beneficiaries: vec![],
+                priority_fee_mode: PriorityFeeMode::PriorityFeeIncreasePercent(10),
+                bundle_priority_fee_overhead_percent: 0,
+                simulate_against_bundle_state: false,
+                max_op_field_size: None,
+                max_transient_simulation_failures: None,
+                gas_estimate_overhead_percent: GAS_ESTIMATE_OVERHEAD_PERCENT,
+                max_ops_per_sender_in_window: Some(2),
+                sender_window_bundles: 3,
+            },
+            broadcast::channel(16).0,
+        );
+
+        let both = vec![spammer_op.clone(), other_op.clone()];
+        let other_only = vec![other_op.clone()];
+
+        // the spammer is included in at most 2 of any 3 consecutive bundles, the
+        // other sender in all of them
+        for expected_ops in [&both, &both, &other_only, &both, &both, &other_only] {
+            let bundle = proposer.make_bundle(None, false, vec![]).await.unwrap();
+            assert_eq!(
+                bundle.iter_ops().cloned().collect::<Vec<_>>(),
+                *expected_ops
+            );
+        }
+
+        // a replacement takes the place of the latest bundle in the window, which
+        // did not include the spammer
+        let bundle = proposer.make_bundle(None, true, vec![]).await.unwrap();
+        assert_eq!(bundle.iter_ops().cloned().collect::<Vec<_>>(), both);
+    }
+
+    struct MockOp {
+        op: UserOperation,
+        simulation_result: Box<dyn Fn() -> Result<SimulationResult, SimulationError> + Send + Sync>,
+    }
+
+    struct MockAggregator {
+        address: Address,
+        signature: Box<dyn Fn() -> anyhow::Result<Option<Bytes>> + Send + Sync>,
+    }
+
+    async fn simple_make_bundle(mock_ops: Vec<MockOp>) -> Bundle<UserOperation> {
+        mock_make_bundle(
+            mock_ops,
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            U256::zero(),
+            U256::zero(),
+            false,
+            ExpectedStorage::default(),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn mock_make_bundle(
+        mock_ops: Vec<MockOp>,
+        mock_aggregators: Vec<MockAggregator>,
+        mock_handle_ops_call_results: Vec<HandleOpsOut>,
+        mock_paymaster_deposits: Vec<U256>,
+        base_fee: U256,
+        max_priority_fee_per_gas: U256,
+        notify_condition_not_met: bool,
+        actual_storage: ExpectedStorage,
+    ) -> Bundle<UserOperation> {
+        mock_make_bundle_with_bundle_state(
+            mock_ops,
+            mock_aggregators,
+            mock_handle_ops_call_results,
+            mock_paymaster_deposits,
+            base_fee,
+            max_priority_fee_per_gas,
+            notify_condition_not_met,
+            actual_storage,
+            false,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn mock_make_bundle_with_bundle_state(
         mock_ops: Vec<MockOp>,
         mock_aggregators: Vec<MockAggregator>,
         mock_handle_ops_call_results: Vec<HandleOpsOut>,
@@ -2281,6 +3255,7 @@ mod tests {
         max_priority_fee_per_gas: U256,
         notify_condition_not_met: bool,
         actual_storage: ExpectedStorage,
+        simulate_against_bundle_state: bool,
     ) -> Bundle<UserOperation> {
         let entry_point_address = address(123);
         let beneficiary = address(124);
@@ -2375,9 +3350,17 @@ mod tests {
                 chain_spec: ChainSpec::default(),
                 max_bundle_size,
                 max_bundle_gas: 10_000_000,
-                beneficiary,
+                beneficiaries: vec![beneficiary],
+                beneficiary_selection: BeneficiarySelection::RoundRobin,
+                fallback_beneficiaries: vec![],
                 priority_fee_mode: PriorityFeeMode::PriorityFeeIncreasePercent(10),
                 bundle_priority_fee_overhead_percent: 0,
+                simulate_against_bundle_state,
+                max_op_field_size: None,
+                max_transient_simulation_failures: None,
+                gas_estimate_overhead_percent: GAS_ESTIMATE_OVERHEAD_PERCENT,
+                max_ops_per_sender_in_window: None,
+                sender_window_bundles: 0,
             },
             event_sender,
         );
@@ -2387,7 +3370,7 @@ mod tests {
         }
 
         proposer
-            .make_bundle(None, false)
+            .make_bundle(None, false, vec![])
             .await
             .expect("should make a bundle")
     }
@@ -11,53 +11,102 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{marker::PhantomData, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    iter,
+    marker::PhantomData,
+    mem,
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::{bail, Context};
 use async_trait::async_trait;
-use ethers::types::{transaction::eip2718::TypedTransaction, Address, H256, U256};
-use futures_util::StreamExt;
+use ethers::types::{
+    transaction::eip2718::TypedTransaction, Address, Log, TransactionRequest, H256, U256,
+};
+use futures::future;
 #[cfg(test)]
 use mockall::automock;
-use rundler_provider::{BundleHandler, EntryPoint};
+use rundler_provider::{BundleHandler, EntryPoint, HandleOpsOut};
 use rundler_sim::ExpectedStorage;
 use rundler_types::{
     builder::BundlingMode,
     chain::ChainSpec,
-    pool::{NewHead, Pool},
-    EntityUpdate, UserOperation,
+    pool::{NewHead, Pool, PoolOperation, ReputationStatus},
+    Entity, EntityType, EntityUpdate, GasFees, UserOperation,
 };
-use rundler_utils::emit::WithEntryPoint;
+use rundler_utils::{emit::WithEntryPoint, math};
 use tokio::{
     join,
     sync::{broadcast, mpsc, mpsc::UnboundedReceiver, oneshot},
+    time::Instant,
 };
-use tracing::{debug, error, info, instrument, warn};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, field, info, info_span, instrument, warn, Span};
 
 use crate::{
-    bundle_proposer::{Bundle, BundleProposer, BundleProposerError},
-    emit::{BuilderEvent, BundleTxDetails},
+    bundle_proposer::{self, Bundle, BundleProposer, BundleProposerError},
+    emit::{BuilderEvent, BundleTxDetails, OpRejectionReason},
+    health::BuilderHealth,
     transaction_tracker::{TrackerUpdate, TransactionTracker, TransactionTrackerError},
 };
 
 #[async_trait]
 pub(crate) trait BundleSender: Send + Sync + 'static {
-    async fn send_bundles_in_loop(self) -> anyhow::Result<()>;
+    async fn send_bundles_in_loop(self, shutdown_token: CancellationToken) -> anyhow::Result<()>;
 }
 
 #[derive(Debug)]
 pub(crate) struct Settings {
     pub(crate) max_replacement_underpriced_blocks: u64,
+    pub(crate) retry_replacement_underpriced: bool,
     pub(crate) max_cancellation_fee_increases: u64,
+    pub(crate) cancel_on_stall: bool,
     pub(crate) max_blocks_to_wait_for_mine: u64,
+    pub(crate) max_pending_time: Option<Duration>,
+    pub(crate) min_bundle_interval: Duration,
+    pub(crate) pending_poll_interval: Option<Duration>,
+    pub(crate) throttled_paymaster_bundle_count: usize,
+    pub(crate) max_bundle_reverts_per_op: Option<u64>,
+    pub(crate) coalesce_manual_bundle_requests: bool,
+    pub(crate) use_legacy_gas: bool,
+    pub(crate) rejected_ops_removal_batch_size: usize,
+    pub(crate) max_bundle_gas: u64,
+    pub(crate) gas_estimate_overhead_percent: u64,
+    pub(crate) validate_bundle_before_send: bool,
+    pub(crate) max_ops_per_bundle: usize,
+    pub(crate) max_ops_per_entity_per_bundle: usize,
+    pub(crate) max_bundle_calldata_bytes: usize,
+    pub(crate) min_op_priority_fee: U256,
+    pub(crate) pause_guardian: Option<Address>,
+    pub(crate) pause_check_interval: Duration,
+    pub(crate) consecutive_failure_threshold: Option<u64>,
+    pub(crate) max_failure_backoff: Duration,
+    pub(crate) deterministic_ordering: bool,
+    pub(crate) idle_backoff_max: Duration,
+    pub(crate) gas_limit_increase_percent: u64,
+    pub(crate) max_inflight_bundles: usize,
 }
 
+/// Lower bound on the pending transaction poll interval to avoid excessive RPC calls
+const MIN_PENDING_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Minimum interval between heartbeat events while a bundle transaction is pending
+const PENDING_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+/// Backoff after the circuit breaker first opens, doubled on each failure while open
+const CIRCUIT_BREAKER_BASE_BACKOFF: Duration = Duration::from_secs(5);
+/// Number of consecutive bundle attempts finding no ops in the pool after which
+/// automatic attempts back off
+const IDLE_BACKOFF_THRESHOLD: u64 = 3;
+/// Backoff after the pool is first found idle, doubled on each further empty attempt
+const IDLE_BACKOFF_BASE: Duration = Duration::from_secs(1);
+
 #[derive(Debug)]
 pub(crate) struct BundleSenderImpl<UO, P, E, T, C> {
     builder_index: u64,
     bundle_action_receiver: Option<mpsc::Receiver<BundleSenderAction>>,
+    new_heads: Option<broadcast::Receiver<NewHead>>,
     chain_spec: ChainSpec,
-    beneficiary: Address,
     proposer: P,
     entry_point: E,
     transaction_tracker: Option<T>,
@@ -65,6 +114,32 @@ pub(crate) struct BundleSenderImpl<UO, P, E, T, C> {
     settings: Settings,
     event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
     metrics: BuilderMetrics,
+    last_bundle_sent: Option<Instant>,
+    // op hashes of each bundle transaction sent for the current nonce, by transaction hash
+    bundle_op_hashes: HashMap<H256, Vec<H256>>,
+    // transaction hash and op hashes of each bundle transaction sent ahead of the
+    // current nonce, in nonce order
+    pipelined_op_hashes: VecDeque<(H256, Vec<H256>)>,
+    // senders and paymasters of the ops of each in-flight bundle transaction, by
+    // transaction hash
+    inflight_entities: HashMap<H256, Vec<Address>>,
+    // number of consecutive mined bundles each op was included in but not executed, by op hash
+    op_bundle_reverts: HashMap<H256, u64>,
+    // when the last heartbeat event was emitted for a pending bundle transaction
+    last_pending_heartbeat: Option<Instant>,
+    // cancelled to stop the sender once it has no bundle transaction in flight
+    shutdown_token: CancellationToken,
+    // when the pause guardian was last checked, and whether it reported a pause
+    last_pause_check: Option<(Instant, bool)>,
+    // number of consecutive bundle attempts that failed with an error
+    consecutive_failures: u64,
+    // until when bundle attempts are skipped after too many consecutive failures
+    circuit_open_until: Option<Instant>,
+    // number of consecutive bundle attempts that found no ops in the pool
+    consecutive_empty_attempts: u64,
+    // until when automatic bundle attempts are skipped while the pool stays empty
+    idle_until: Option<Instant>,
+    health: Arc<BuilderHealth>,
     _uo_type: PhantomData<UO>,
 }
 
@@ -73,6 +148,9 @@ struct BundleTx {
     tx: TypedTransaction,
     expected_storage: ExpectedStorage,
     op_hashes: Vec<H256>,
+    // senders and paymasters of the bundled ops
+    entities: Vec<Address>,
+    beneficiary: Address,
 }
 
 pub enum BundleSenderAction {
@@ -82,21 +160,91 @@ pub enum BundleSenderAction {
 
 pub struct SendBundleRequest {
     pub responder: oneshot::Sender<SendBundleResult>,
+    /// Build the bundle transaction without sending it or removing any ops from
+    /// the pool, responding with `SendBundleResult::DryRun`
+    pub dry_run: bool,
+    /// Operations to include in the bundle ahead of the pool's operations,
+    /// regardless of their fees. They are still simulated and rejected like any
+    /// other operation, and are not bundled twice if also in the pool.
+    ///
+    /// They are proposed in each bundle, including fee increase replacements, until
+    /// a bundle containing them mines or they are rejected. Those of a dry run are
+    /// only proposed in the dry run.
+    ///
+    /// An injected operation that fails simulation or gas estimation counts against
+    /// the reputation of its entities like one from the pool, which may throttle or
+    /// ban them and remove their other operations from the pool. One that passes
+    /// simulation but reverts on chain costs the builder the gas of its execution.
+    pub priority_ops: Vec<PoolOperation>,
 }
 
 /// Response to a `SendBundleRequest` after
 /// going through a full cycle of bundling, sending,
 /// and waiting for the transaction to be mined.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum SendBundleResult {
     Success {
         block_number: u64,
         attempt_number: u64,
         tx_hash: H256,
+        /// Hash of each op in the mined bundle, and whether it executed
+        /// successfully. Ops that reverted, or that the entry point did not
+        /// execute, are unsuccessful. Empty if the receipt is unavailable.
+        op_results: Vec<(H256, bool)>,
+    },
+    /// No bundle was sent because there were no operations to bundle
+    NoOperationsInitially {
+        reason: EmptyReason,
     },
-    NoOperationsInitially,
     StalledAtMaxFeeIncreases,
-    Error(anyhow::Error),
+    /// The pending bundle transaction was replaced by a cancellation, which mined
+    /// at `nonce`
+    Cancelled {
+        nonce: U256,
+    },
+    /// A dry run built a bundle transaction, which was not sent
+    DryRun {
+        /// Hash of the unsigned bundle transaction
+        tx_hash_preview: H256,
+        op_hashes: Vec<H256>,
+        /// Gas limit of the bundle transaction
+        gas: U256,
+    },
+    /// No bundle was built because the pause guardian reported the entry point as
+    /// paused. A pending bundle transaction is not replaced while paused.
+    EntryPointPaused,
+    /// No bundle was built because the circuit breaker is open after too many
+    /// consecutive failed bundle attempts
+    CircuitOpen {
+        /// Time until the next bundle attempt
+        retry_in: Duration,
+    },
+    Error(Arc<anyhow::Error>),
+}
+
+/// Why a bundle attempt had no operations to bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, parse_display::Display)]
+pub enum EmptyReason {
+    /// The pool had no operations available to bundle
+    #[display("no operations in the pool")]
+    PoolEmpty,
+    /// No operation paid the fees required to be included in a bundle
+    #[display("no operations pay the required fees")]
+    BelowFeeThreshold,
+    /// All operations were rejected during bundle simulation, which removes them,
+    /// and the entities updated, from the pool
+    #[display("all operations rejected in simulation ({rejected_ops} rejected ops, {rejected_entities} rejected entities)")]
+    RejectedInSimulation {
+        rejected_ops: usize,
+        rejected_entities: usize,
+    },
+    /// All operations were deferred to later bundles, such as those of throttled
+    /// entities or senders, or those that failed simulation with a transient error
+    #[display("all operations deferred to later bundles")]
+    Deferred,
+    /// The bundle's gas estimate exceeds the maximum bundle gas
+    #[display("bundle gas estimate {gas_estimate} exceeds the max bundle gas")]
+    ExceedsMaxBundleGas { gas_estimate: U256 },
 }
 
 // Internal result of attempting to send a bundle.
@@ -108,11 +256,13 @@ enum SendBundleAttemptResult {
     // There were no operations after the fee was increased
     NoOperationsAfterFeeFilter,
     // There were no operations after the bundle was simulated
-    NoOperationsAfterSimulation,
+    NoOperationsAfterSimulation(EmptyReason),
     // Replacement Underpriced
     ReplacementUnderpriced,
     // Condition not met
     ConditionNotMet,
+    // The bundle failed simulation before it was sent
+    FailedValidation,
     // Nonce too low
     NonceTooLow,
 }
@@ -126,24 +276,43 @@ where
     T: TransactionTracker,
     C: Pool,
 {
-    /// Loops forever, attempting to form and send a bundle on each new block,
+    /// Loops until shutdown, attempting to form and send a bundle on each new block,
     /// then waiting for one bundle to be mined or dropped before forming the
     /// next one.
+    ///
+    /// On shutdown, no new bundles are formed. A bundle transaction in flight is
+    /// still tracked until it resolves, after which queued manual requests are
+    /// answered with an error and the loop returns.
     #[instrument(skip_all, fields(entry_point = self.entry_point.address().to_string(), builder_index = self.builder_index))]
-    async fn send_bundles_in_loop(mut self) -> anyhow::Result<()> {
+    async fn send_bundles_in_loop(
+        mut self,
+        shutdown_token: CancellationToken,
+    ) -> anyhow::Result<()> {
+        self.shutdown_token = shutdown_token;
+
         // trigger for sending bundles
         let sender_trigger = BundleSenderTrigger::new(
-            &self.pool,
+            self.new_heads.take().unwrap(),
+            Arc::clone(&self.health),
             self.bundle_action_receiver.take().unwrap(),
             Duration::from_millis(self.chain_spec.bundle_max_send_interval_millis),
-        )
-        .await?;
+            self.settings.pending_poll_interval,
+            self.settings.coalesce_manual_bundle_requests,
+        );
+
+        let mut transaction_tracker = self.transaction_tracker.take().unwrap();
+        self.warmup(&mut transaction_tracker).await;
 
         // initial state
-        let mut state =
-            SenderMachineState::new(sender_trigger, self.transaction_tracker.take().unwrap());
+        let mut state = SenderMachineState::new(sender_trigger, transaction_tracker);
 
         loop {
+            if self.shutdown_token.is_cancelled() && state.is_idle() {
+                info!("Shutting down bundle sender");
+                state.shutdown();
+                return Ok(());
+            }
+
             if let Err(e) = self.step_state(&mut state).await {
                 error!("Error in bundle sender loop: {e:#?}");
                 self.metrics.increment_state_machine_errors();
@@ -165,8 +334,8 @@ where
     pub(crate) fn new(
         builder_index: u64,
         bundle_action_receiver: mpsc::Receiver<BundleSenderAction>,
+        new_heads: broadcast::Receiver<NewHead>,
         chain_spec: ChainSpec,
-        beneficiary: Address,
         proposer: P,
         entry_point: E,
         transaction_tracker: T,
@@ -177,8 +346,8 @@ where
         Self {
             builder_index,
             bundle_action_receiver: Some(bundle_action_receiver),
+            new_heads: Some(new_heads),
             chain_spec,
-            beneficiary,
             proposer,
             transaction_tracker: Some(transaction_tracker),
             pool,
@@ -188,19 +357,82 @@ where
                 builder_index,
                 entry_point: entry_point.address(),
             },
+            health: Arc::new(BuilderHealth::new(entry_point.address(), builder_index)),
             entry_point,
+            last_bundle_sent: None,
+            bundle_op_hashes: HashMap::new(),
+            pipelined_op_hashes: VecDeque::new(),
+            inflight_entities: HashMap::new(),
+            op_bundle_reverts: HashMap::new(),
+            last_pending_heartbeat: None,
+            shutdown_token: CancellationToken::new(),
+            last_pause_check: None,
+            consecutive_failures: 0,
+            circuit_open_until: None,
+            consecutive_empty_attempts: 0,
+            idle_until: None,
             _uo_type: PhantomData,
         }
     }
 
+    /// Returns the health of the sender, updated as it runs
+    pub(crate) fn health(&self) -> Arc<BuilderHealth> {
+        Arc::clone(&self.health)
+    }
+
+    // Loads the signer's nonce into the transaction tracker before the loop starts,
+    // so that the first trigger can build a bundle right away. On failure the tracker
+    // keeps the nonce it was created with, and a wrong nonce is corrected by the
+    // first bundle attempt.
+    async fn warmup(&self, transaction_tracker: &mut T) {
+        match transaction_tracker.sync_nonce().await {
+            Ok(nonce) => info!("Bundle sender ready at nonce {nonce}"),
+            Err(error) => warn!("Failed to load nonce before the first bundle: {error}"),
+        }
+    }
+
     async fn step_state<TRIG: Trigger>(
         &mut self,
         state: &mut SenderMachineState<T, TRIG>,
     ) -> anyhow::Result<()> {
-        let tracker_update = state.wait_for_trigger().await?;
+        // only an idle sender stops waiting on shutdown, a sender with a transaction
+        // in flight keeps tracking it
+        let tracker_update = if state.is_idle() {
+            tokio::select! {
+                biased;
+                _ = self.shutdown_token.cancelled() => return Ok(()),
+                tracker_update = state.wait_for_trigger() => tracker_update?,
+            }
+        } else {
+            state.wait_for_trigger().await?
+        };
 
         match state.inner {
             InnerState::Building(building_state) => {
+                match tracker_update {
+                    Some(TrackerUpdate::Mined {
+                        tx_hash,
+                        nonce,
+                        block_number,
+                        ..
+                    }) => {
+                        // a previously abandoned transaction mined while building
+                        info!("Abandoned bundle transaction {tx_hash:?} mined while building");
+                        self.health.record_mined(block_number);
+                        self.emit(BuilderEvent::transaction_mined(
+                            self.builder_index,
+                            tx_hash,
+                            nonce.low_u64(),
+                            block_number,
+                            building_state.pending_since.map(|since| since.elapsed()),
+                        ));
+                    }
+                    Some(TrackerUpdate::Reorged { tx_hash, nonce }) => {
+                        // the tracker rewound its nonce, the next bundle reuses it
+                        self.record_reorg(tx_hash, nonce);
+                    }
+                    _ => {}
+                }
                 self.handle_building_state(state, building_state).await?;
             }
             InnerState::Pending(pending_state) => {
@@ -217,6 +449,10 @@ where
             }
         }
 
+        self.health.set_stalled(matches!(
+            state.inner,
+            InnerState::Cancelling(_) | InnerState::CancelPending(_)
+        ));
         Ok(())
     }
 
@@ -225,27 +461,91 @@ where
         state: &mut SenderMachineState<T, TRIG>,
         inner: BuildingState,
     ) -> anyhow::Result<()> {
+        if state.dry_run {
+            let result = match self.dry_run_bundle(state).await {
+                Ok(result) => result,
+                Err(error) => {
+                    error!("Dry run bundle error {error:?}");
+                    SendBundleResult::Error(Arc::new(error))
+                }
+            };
+            // a dry run leaves the bundle attempt it interrupted in place
+            state.dry_run = false;
+            state.respond(result);
+            state.update(InnerState::Building(inner.wait_for_trigger()));
+            return Ok(());
+        }
+
+        // after too many consecutive failures, only attempt a bundle once the backoff
+        // elapses instead of on every trigger
+        if let Some(retry_in) = self
+            .circuit_open_until
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+        {
+            debug!("Circuit breaker open, skipping bundle for another {retry_in:?}");
+            state.respond(SendBundleResult::CircuitOpen { retry_in });
+            state.update(InnerState::Building(inner.wait_for_trigger()));
+            return Ok(());
+        }
+
+        // while the pool stays empty, automatic attempts back off to save the RPC calls
+        // of building a bundle, until the backoff elapses or an op is added
+        if state.send_bundle_responses.is_empty() && self.is_idle_backoff_active().await {
+            state.update(InnerState::Building(inner.wait_for_trigger()));
+            return Ok(());
+        }
+
+        // bundles built while the entry point is paused would revert, skip building
+        // until it is unpaused, leaving any pending transaction in place
+        if self.is_entry_point_paused().await {
+            debug!("Entry point paused, skipping bundle");
+            self.metrics.increment_bundle_attempts_paused();
+            state.respond(SendBundleResult::EntryPointPaused);
+            state.update(InnerState::Building(inner.wait_for_trigger()));
+            return Ok(());
+        }
+
+        // space out new bundles, ops continue to accumulate in the pool while waiting.
+        // fee increases are not delayed as they replace an already pending bundle.
+        if inner.fee_increase_count == 0 {
+            self.wait_for_min_bundle_interval().await;
+        }
+
         // send bundle
         let block_number = state.block_number();
         debug!("Building bundle on block {}", block_number);
         let result = self.send_bundle(state, inner.fee_increase_count).await;
+        match result {
+            Ok(_) => self.close_circuit(),
+            Err(_) => self.record_bundle_failure(),
+        }
+        if matches!(result, Ok(SendBundleAttemptResult::NoOperationsInitially)) {
+            self.record_empty_attempt();
+        } else {
+            self.end_idle_backoff();
+        }
 
         // handle result
         match result {
             Ok(SendBundleAttemptResult::Success) => {
-                // sent the bundle
-                info!("Bundle sent successfully");
+                // sent the bundle, logged within the attempt's span
+                if inner.fee_increase_count == 0 {
+                    self.last_bundle_sent = Some(Instant::now());
+                }
                 state.update(InnerState::Pending(inner.to_pending(
                     block_number + self.settings.max_blocks_to_wait_for_mine,
+                    Instant::now(),
                 )));
             }
             Ok(SendBundleAttemptResult::NoOperationsInitially) => {
                 debug!("No operations available initially");
-                state.complete(Some(SendBundleResult::NoOperationsInitially));
+                state.complete(Some(SendBundleResult::NoOperationsInitially {
+                    reason: EmptyReason::PoolEmpty,
+                }));
             }
-            Ok(SendBundleAttemptResult::NoOperationsAfterSimulation) => {
-                debug!("No operations available after simulation");
-                state.complete(Some(SendBundleResult::NoOperationsInitially));
+            Ok(SendBundleAttemptResult::NoOperationsAfterSimulation(reason)) => {
+                debug!("No operations available after simulation: {reason}");
+                state.complete(Some(SendBundleResult::NoOperationsInitially { reason }));
             }
             Ok(SendBundleAttemptResult::NoOperationsAfterFeeFilter) => {
                 debug!("No operations to bundle after fee filtering");
@@ -262,20 +562,30 @@ where
                         state.update_and_abandon(InnerState::Building(inner.underpriced_round()));
                     }
                 } else if inner.fee_increase_count > 0 {
-                    warn!(
-                        "Abandoning bundle after {} fee increases, no operations available after fee increase",
-                        inner.fee_increase_count
-                    );
                     self.metrics.increment_bundle_txns_abandoned();
 
-                    // abandon the bundle by starting a new bundle process
-                    // If the node we are using still has the transaction in the mempool, its
-                    // possible we will get a `ReplacementUnderpriced` on the next iteration
-                    // and will start a cancellation.
-                    state.abandon();
+                    if self.settings.cancel_on_stall {
+                        warn!(
+                            "Abandoning bundle after {} fee increases, no operations available after fee increase, cancelling pending transaction",
+                            inner.fee_increase_count
+                        );
+                        // abandon the bundle by racing a cancellation against the pending transaction
+                        // at the same nonce. Exactly one of the two will mine, which deterministically
+                        // frees the nonce instead of hoping that the pending transaction drops.
+                        state.update(InnerState::Cancelling(inner.to_cancelling()));
+                    } else {
+                        warn!(
+                            "Abandoning bundle after {} fee increases, no operations available after fee increase, leaving pending transaction",
+                            inner.fee_increase_count
+                        );
+                        state.transaction_tracker.abandon();
+                        state.complete(Some(SendBundleResult::StalledAtMaxFeeIncreases));
+                    }
                 } else {
                     debug!("No operations available, waiting for next trigger");
-                    state.complete(Some(SendBundleResult::NoOperationsInitially));
+                    state.complete(Some(SendBundleResult::NoOperationsInitially {
+                        reason: EmptyReason::BelowFeeThreshold,
+                    }));
                 }
             }
             Ok(SendBundleAttemptResult::NonceTooLow) => {
@@ -285,20 +595,27 @@ where
             }
             Ok(SendBundleAttemptResult::ReplacementUnderpriced) => {
                 info!("Replacement transaction underpriced, marking as underpriced. Num fee increases {:?}", inner.fee_increase_count);
-                // unabandon to allow fee estimation to consider any submitted transactions, wait for next trigger
-                state.update_and_unabandon(InnerState::Building(
-                    inner.replacement_underpriced(block_number),
-                ));
+                // unabandon to allow fee estimation to consider any submitted transactions, wait for
+                // next trigger unless configured to retry with the bumped fees right away
+                let mut next = inner.replacement_underpriced(block_number);
+                if self.settings.retry_replacement_underpriced {
+                    next = next.retry();
+                }
+                state.update_and_unabandon(InnerState::Building(next));
             }
             Ok(SendBundleAttemptResult::ConditionNotMet) => {
                 info!("Condition not met, notifying proposer and starting new bundle attempt");
                 self.proposer.notify_condition_not_met();
                 state.update(InnerState::Building(inner.retry()));
             }
+            Ok(SendBundleAttemptResult::FailedValidation) => {
+                info!("Bundle failed validation before sending, starting new bundle attempt");
+                state.update(InnerState::Building(inner.retry()));
+            }
             Err(error) => {
                 error!("Bundle send error {error:?}");
                 self.metrics.increment_bundle_txns_failed();
-                let send_bundle_result = Some(SendBundleResult::Error(error));
+                let send_bundle_result = Some(SendBundleResult::Error(Arc::new(error)));
                 state.complete(send_bundle_result);
             }
         }
@@ -321,22 +638,43 @@ where
                     gas_used,
                     tx_hash,
                     nonce,
+                    logs,
                     ..
                 } => {
                     info!("Bundle transaction mined");
+                    self.health.record_mined(block_number);
+                    let time_to_mine = inner.pending_since.elapsed();
                     self.metrics.process_bundle_txn_success(gas_limit, gas_used);
+                    self.metrics.record_bundle_time_to_mine(time_to_mine);
+                    let op_results = self.reconcile_mined_ops(tx_hash, logs.as_deref()).await;
+                    let mined = op_results.iter().map(|(op_hash, _)| *op_hash).collect();
+                    self.drop_priority_ops(state, &mined);
                     self.emit(BuilderEvent::transaction_mined(
                         self.builder_index,
                         tx_hash,
                         nonce.low_u64(),
                         block_number,
+                        Some(time_to_mine),
                     ));
-                    let send_bundle_result = Some(SendBundleResult::Success {
+                    let send_bundle_result = SendBundleResult::Success {
                         block_number,
                         attempt_number,
                         tx_hash,
-                    });
-                    state.complete(send_bundle_result);
+                        op_results,
+                    };
+                    if self.promote_pipelined_bundle(state) {
+                        // the bundle at the next nonce is now the pending one
+                        let until =
+                            state.block_number() + self.settings.max_blocks_to_wait_for_mine;
+                        state.respond(send_bundle_result);
+                        state.update(InnerState::Pending(PendingState {
+                            until,
+                            fee_increase_count: 0,
+                            pending_since: Instant::now(),
+                        }));
+                    } else {
+                        state.complete(Some(send_bundle_result));
+                    }
                 }
                 TrackerUpdate::LatestTxDropped { nonce } => {
                     info!("Latest transaction dropped, starting new bundle attempt");
@@ -357,7 +695,26 @@ where
                     self.metrics.increment_bundle_txns_nonce_used();
                     state.reset();
                 }
+                TrackerUpdate::Reorged { tx_hash, nonce } => {
+                    info!("Mined bundle transaction reorged out, starting new bundle attempt");
+                    self.record_reorg(tx_hash, nonce);
+                    state.reset();
+                }
             }
+        } else if self
+            .settings
+            .max_pending_time
+            .is_some_and(|max_pending_time| inner.pending_since.elapsed() >= max_pending_time)
+        {
+            // the deadline applies regardless of the remaining fee increases, so that
+            // the nonce is freed within a bounded time
+            warn!(
+                "Bundle transaction not mined after {:?}, {} fee increases, cancelling pending transaction",
+                inner.pending_since.elapsed(),
+                inner.fee_increase_count
+            );
+            self.metrics.increment_bundle_txns_abandoned();
+            state.update(InnerState::Cancelling(inner.to_cancelling()));
         } else if state.block_number() >= inner.until {
             // start replacement, don't wait for trigger. Continue
             // to attempt until there are no longer any UOs priced high enough
@@ -369,11 +726,180 @@ where
             );
             self.metrics.increment_bundle_txn_fee_increases();
             state.update(InnerState::Building(inner.to_building()))
+        } else {
+            self.emit_pending_heartbeat(&inner, state.block_number());
+            // manual bundle requests wait on the pending bundle, only automatic
+            // bundles are pipelined
+            if self.settings.max_inflight_bundles > 1
+                && state.send_bundle_responses.is_empty()
+                && state.transaction_tracker.inflight_count() < self.settings.max_inflight_bundles
+            {
+                if let Err(error) = self.send_pipelined_bundle(state).await {
+                    warn!("Failed to send pipelined bundle: {error:?}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Drops the priority ops with hashes in `op_hashes` from the state, once they
+    // were rejected or a bundle containing them mined
+    fn drop_priority_ops<TRIG: Trigger>(
+        &self,
+        state: &mut SenderMachineState<T, TRIG>,
+        op_hashes: &HashSet<H256>,
+    ) {
+        state.priority_ops.retain(|op| {
+            !op_hashes.contains(&op.uo.hash(self.entry_point.address(), self.chain_spec.id))
+        });
+    }
+
+    // After a bundle transaction mines, moves the op hashes of the pipelined bundle
+    // the tracker promoted to the current nonce, if any, to the bundles of the
+    // current nonce. Returns whether a bundle was promoted.
+    fn promote_pipelined_bundle<TRIG: Trigger>(
+        &mut self,
+        state: &SenderMachineState<T, TRIG>,
+    ) -> bool {
+        if self.settings.max_inflight_bundles <= 1 {
+            return false;
+        }
+        if state.transaction_tracker.inflight_count() == 0 {
+            self.pipelined_op_hashes.clear();
+            self.inflight_entities.clear();
+            return false;
+        }
+
+        let pipelined = state.transaction_tracker.pipelined_transactions();
+        while self
+            .pipelined_op_hashes
+            .front()
+            .is_some_and(|(tx_hash, _)| !pipelined.contains(tx_hash))
+        {
+            let (tx_hash, op_hashes) = self.pipelined_op_hashes.pop_front().unwrap();
+            self.bundle_op_hashes.insert(tx_hash, op_hashes);
+        }
+        true
+    }
+
+    /// Sends a bundle at the nonce after the in-flight bundle transactions, without
+    /// waiting for them to mine. Ops already in flight are left out of the bundle.
+    ///
+    /// The bundle is simulated against the latest block, ignoring the effects of the
+    /// in-flight bundles, so ops sharing a sender or paymaster with an in-flight bundle
+    /// are left out too, and no ops are removed from the pool for failing simulation.
+    async fn send_pipelined_bundle<TRIG: Trigger>(
+        &mut self,
+        state: &mut SenderMachineState<T, TRIG>,
+    ) -> anyhow::Result<()> {
+        if self
+            .circuit_open_until
+            .is_some_and(|until| until > Instant::now())
+            || self
+                .last_bundle_sent
+                .is_some_and(|sent| sent.elapsed() < self.settings.min_bundle_interval)
+            || self.is_entry_point_paused().await
+        {
+            return Ok(());
+        }
+
+        let (nonce, _) = state.transaction_tracker.get_nonce_and_required_fees()?;
+        let nonce = nonce + state.transaction_tracker.inflight_count();
+        let mut bundle = match self.proposer.make_bundle(None, false, vec![]).await {
+            Ok(bundle) => bundle,
+            Err(
+                BundleProposerError::NoOperationsInitially
+                | BundleProposerError::NoOperationsAfterFeeFilter,
+            ) => return Ok(()),
+            Err(e) => bail!("Failed to make bundle: {e:?}"),
+        };
+        let inflight = self
+            .bundle_op_hashes
+            .values()
+            .chain(
+                self.pipelined_op_hashes
+                    .iter()
+                    .map(|(_, op_hashes)| op_hashes),
+            )
+            .flatten()
+            .copied()
+            .collect();
+        let inflight_entities = self.inflight_entities.values().flatten().copied().collect();
+        let removed = self.remove_inflight_ops(&mut bundle, &inflight)
+            + self.remove_inflight_entity_ops(&mut bundle, &inflight_entities);
+        if removed > 0 {
+            self.refresh_bundle(&mut bundle)?;
         }
+        // ops may have failed simulation only because of the in-flight bundles, leave
+        // them in the pool
+        bundle.rejected_ops.clear();
+        bundle.entity_updates.clear();
+
+        let Ok(BundleTx {
+            tx,
+            expected_storage,
+            op_hashes,
+            entities,
+            beneficiary,
+        }) = self
+            .get_bundle_tx(nonce, bundle, 0, state.block_number())
+            .await?
+        else {
+            return Ok(());
+        };
 
+        self.metrics.increment_bundle_txns_sent();
+        let tx_hash = state
+            .transaction_tracker
+            .send_pipelined_transaction(tx.clone(), &expected_storage)
+            .await?;
+        info!(
+            "Sent pipelined bundle transaction {tx_hash:?} with {} ops at nonce {nonce}",
+            op_hashes.len()
+        );
+        self.last_bundle_sent = Some(Instant::now());
+        self.pipelined_op_hashes
+            .push_back((tx_hash, op_hashes.clone()));
+        self.inflight_entities.insert(tx_hash, entities);
+        self.emit(BuilderEvent::formed_bundle(
+            self.builder_index,
+            Some(BundleTxDetails {
+                tx_hash,
+                tx,
+                op_hashes: Arc::new(op_hashes),
+                beneficiary,
+                expected_storage: Arc::new(expected_storage),
+            }),
+            nonce.low_u64(),
+            0,
+            None,
+        ));
         Ok(())
     }
 
+    // Emits a heartbeat event once a bundle transaction has been pending for
+    // `PENDING_HEARTBEAT_INTERVAL`, and at most once per interval after that
+    fn emit_pending_heartbeat(&mut self, inner: &PendingState, block_number: u64) {
+        let pending_for = inner.pending_since.elapsed();
+        if pending_for < PENDING_HEARTBEAT_INTERVAL
+            || self
+                .last_pending_heartbeat
+                .is_some_and(|last| last.elapsed() < PENDING_HEARTBEAT_INTERVAL)
+        {
+            return;
+        }
+
+        debug!("Bundle transaction still pending after {pending_for:?}");
+        self.last_pending_heartbeat = Some(Instant::now());
+        self.emit(BuilderEvent::still_pending_after_wait(
+            self.builder_index,
+            inner.fee_increase_count,
+            pending_for,
+            block_number,
+        ));
+    }
+
     async fn handle_cancelling_state<TRIG: Trigger>(
         &mut self,
         state: &mut SenderMachineState<T, TRIG>,
@@ -450,6 +976,42 @@ where
         if let Some(update) = tracker_update {
             match update {
                 TrackerUpdate::Mined {
+                    tx_hash,
+                    nonce,
+                    block_number,
+                    attempt_number,
+                    gas_limit,
+                    gas_used,
+                    is_cancellation: false,
+                    logs,
+                    ..
+                } => {
+                    // the original bundle transaction mined before the cancellation
+                    info!("Bundle transaction mined while cancelling");
+                    self.health.record_mined(block_number);
+                    let time_to_mine = inner.pending_since.map(|since| since.elapsed());
+                    self.metrics.process_bundle_txn_success(gas_limit, gas_used);
+                    if let Some(time_to_mine) = time_to_mine {
+                        self.metrics.record_bundle_time_to_mine(time_to_mine);
+                    }
+                    let op_results = self.reconcile_mined_ops(tx_hash, logs.as_deref()).await;
+                    self.emit(BuilderEvent::transaction_mined(
+                        self.builder_index,
+                        tx_hash,
+                        nonce.low_u64(),
+                        block_number,
+                        time_to_mine,
+                    ));
+                    state.complete(Some(SendBundleResult::Success {
+                        block_number,
+                        attempt_number,
+                        tx_hash,
+                        op_results,
+                    }));
+                    return Ok(());
+                }
+                TrackerUpdate::Mined {
+                    nonce,
                     gas_used,
                     gas_price,
                     ..
@@ -462,6 +1024,7 @@ where
                         self.metrics
                             .increment_cancellation_txns_total_fee(fee.as_u64());
                     };
+                    state.respond(SendBundleResult::Cancelled { nonce });
                 }
                 TrackerUpdate::LatestTxDropped { .. } => {
                     // If a cancellation gets dropped, move to bundling state as there is no
@@ -473,6 +1036,11 @@ where
                     // a pending transaction
                     info!("Nonce used externally while cancelling, starting new bundle attempt");
                 }
+                TrackerUpdate::Reorged { tx_hash, nonce } => {
+                    // An earlier transaction was reorged out, its nonce must be used again
+                    info!("Mined transaction reorged out while cancelling, starting new bundle attempt");
+                    self.record_reorg(tx_hash, nonce);
+                }
             }
             state.reset();
         } else if state.block_number() >= inner.until {
@@ -495,22 +1063,91 @@ where
         Ok(())
     }
 
+    // whether the configured pause guardian reports the entry point as paused. A
+    // check is reused for the pause check interval, and a failed check is treated as
+    // not paused so that an unreachable guardian doesn't stop bundling.
+    async fn is_entry_point_paused(&mut self) -> bool {
+        let Some(pause_guardian) = self.settings.pause_guardian else {
+            return false;
+        };
+        let was_paused = match self.last_pause_check {
+            Some((checked_at, paused))
+                if checked_at.elapsed() < self.settings.pause_check_interval =>
+            {
+                return paused;
+            }
+            Some((_, paused)) => paused,
+            None => false,
+        };
+
+        let paused = match self.entry_point.is_paused(pause_guardian).await {
+            Ok(paused) => paused,
+            Err(error) => {
+                warn!("Failed to check whether the entry point is paused: {error:?}");
+                false
+            }
+        };
+        if paused && !was_paused {
+            warn!("Entry point paused by pause guardian {pause_guardian:?}, not building bundles");
+        } else if !paused && was_paused {
+            info!("Entry point unpaused, resuming bundle building");
+        }
+        self.last_pause_check = Some((Instant::now(), paused));
+        paused
+    }
+
+    async fn wait_for_min_bundle_interval(&self) {
+        let Some(last_bundle_sent) = self.last_bundle_sent else {
+            return;
+        };
+
+        let next_bundle_at = last_bundle_sent + self.settings.min_bundle_interval;
+        if next_bundle_at > Instant::now() {
+            debug!(
+                "Waiting {:?} for minimum bundle interval",
+                next_bundle_at - Instant::now()
+            );
+            tokio::time::sleep_until(next_bundle_at).await;
+        }
+    }
+
+    // the span's fields tie the logs of a bundle attempt together, and are recorded
+    // as they become known. The builder index and entry point are carried by the
+    // parent span of the sender loop.
     /// Constructs a bundle and sends it to the entry point as a transaction.
     ///
     /// Returns empty if:
     ///  - There are no ops available to bundle initially.
     ///  - The gas fees are high enough that the bundle is empty because there
     ///    are no ops that meet the fee requirements.
+    #[instrument(
+        skip_all,
+        fields(
+            fee_increase_count = fee_increase_count,
+            block_number = state.block_number(),
+            nonce = field::Empty,
+            op_count = field::Empty,
+            op_hashes = field::Empty,
+            tx_hash = field::Empty,
+        )
+    )]
     async fn send_bundle<TRIG: Trigger>(
         &mut self,
         state: &mut SenderMachineState<T, TRIG>,
         fee_increase_count: u64,
     ) -> anyhow::Result<SendBundleAttemptResult> {
         let (nonce, required_fees) = state.transaction_tracker.get_nonce_and_required_fees()?;
+        let span = Span::current();
+        span.record("nonce", nonce.low_u64());
 
-        let bundle = match self
+        // priority ops are proposed again in replacements of the bundle
+        let mut bundle = match self
             .proposer
-            .make_bundle(required_fees, fee_increase_count > 0)
+            .make_bundle(
+                required_fees,
+                fee_increase_count > 0,
+                state.priority_ops.clone(),
+            )
             .await
         {
             Ok(bundle) => bundle,
@@ -522,22 +1159,59 @@ where
             }
             Err(e) => bail!("Failed to make bundle: {e:?}"),
         };
+        if !state.priority_ops.is_empty() && !bundle.rejected_ops.is_empty() {
+            let rejected = bundle
+                .rejected_ops
+                .iter()
+                .map(|op| self.op_hash(op))
+                .collect();
+            self.drop_priority_ops(state, &rejected);
+        }
 
-        let Some(bundle_tx) = self.get_bundle_tx(nonce, bundle).await? else {
-            self.emit(BuilderEvent::formed_bundle(
-                self.builder_index,
-                None,
-                nonce.low_u64(),
-                fee_increase_count,
-                required_fees,
-            ));
-            return Ok(SendBundleAttemptResult::NoOperationsAfterSimulation);
+        if !self.pipelined_op_hashes.is_empty() {
+            // replacing the bundle at the current nonce, leave out the ops of the
+            // bundles pipelined after it
+            let pipelined = self
+                .pipelined_op_hashes
+                .iter()
+                .flat_map(|(_, op_hashes)| op_hashes)
+                .copied()
+                .collect();
+            if self.remove_inflight_ops(&mut bundle, &pipelined) > 0 {
+                self.refresh_bundle(&mut bundle)?;
+            }
+        }
+
+        if self.settings.validate_bundle_before_send && !self.validate_bundle(&bundle).await? {
+            return Ok(SendBundleAttemptResult::FailedValidation);
+        }
+
+        let bundle_tx = match self
+            .get_bundle_tx(nonce, bundle, fee_increase_count, state.block_number())
+            .await?
+        {
+            Ok(bundle_tx) => bundle_tx,
+            Err(reason) => {
+                self.emit(BuilderEvent::formed_bundle(
+                    self.builder_index,
+                    None,
+                    nonce.low_u64(),
+                    fee_increase_count,
+                    required_fees,
+                ));
+                return Ok(SendBundleAttemptResult::NoOperationsAfterSimulation(reason));
+            }
         };
         let BundleTx {
             tx,
             expected_storage,
             op_hashes,
+            entities,
+            beneficiary,
         } = bundle_tx;
+        span.record("op_count", op_hashes.len());
+        span.record("op_hashes", field::debug(&op_hashes));
+        debug!("Sending bundle transaction");
 
         self.metrics.increment_bundle_txns_sent();
 
@@ -545,15 +1219,28 @@ where
             .transaction_tracker
             .send_transaction(tx.clone(), &expected_storage)
             .await;
+        let expected_storage = Arc::new(expected_storage);
 
         match send_result {
             Ok(tx_hash) => {
+                span.record("tx_hash", field::debug(tx_hash));
+                info!("Sent bundle transaction");
+                if fee_increase_count == 0 {
+                    self.bundle_op_hashes.clear();
+                    self.pipelined_op_hashes.clear();
+                    self.inflight_entities.clear();
+                }
+                self.bundle_op_hashes.insert(tx_hash, op_hashes.clone());
+                self.inflight_entities.insert(tx_hash, entities);
+
                 self.emit(BuilderEvent::formed_bundle(
                     self.builder_index,
                     Some(BundleTxDetails {
                         tx_hash,
                         tx,
                         op_hashes: Arc::new(op_hashes),
+                        beneficiary,
+                        expected_storage,
                     }),
                     nonce.low_u64(),
                     fee_increase_count,
@@ -574,7 +1261,15 @@ where
             }
             Err(TransactionTrackerError::ConditionNotMet) => {
                 self.metrics.increment_bundle_txn_condition_not_met();
-                warn!("Bundle attempt condition not met");
+                match self.proposer.find_unmet_condition(&expected_storage).await {
+                    Some(reason) => warn!(
+                        "Bundle attempt condition not met, address: {:?}, slot: {:?}, expected: {:?}, actual: {:?}",
+                        reason.address, reason.slot, reason.expected, reason.actual
+                    ),
+                    None => warn!(
+                        "Bundle attempt condition not met, but all expected storage values now match: {expected_storage:?}"
+                    ),
+                }
                 Ok(SendBundleAttemptResult::ConditionNotMet)
             }
             Err(e) => {
@@ -584,13 +1279,115 @@ where
         }
     }
 
+    /// Simulates `handleOps` with `bundle` against the latest block to catch ops that
+    /// became invalid since the bundle was proposed. An op that fails is removed from
+    /// the pool.
+    ///
+    /// Returns whether the bundle passed.
+    async fn validate_bundle(&self, bundle: &Bundle<UO>) -> anyhow::Result<bool> {
+        if bundle.is_empty() {
+            return Ok(true);
+        }
+
+        let handle_ops_out = self
+            .entry_point
+            .call_handle_ops(
+                bundle.ops_per_aggregator.clone(),
+                bundle.beneficiary,
+                bundle.gas_estimate,
+            )
+            .await
+            .context("should call handle ops with bundle before sending")?;
+        match handle_ops_out {
+            HandleOpsOut::Success => Ok(true),
+            HandleOpsOut::FailedOp(index, message) => {
+                let op = bundle
+                    .iter_ops()
+                    .nth(index)
+                    .context("failed op index should be in the bundle")?;
+                let op_hash = self.op_hash(op);
+                warn!("Op {op_hash:?} failed bundle validation before sending with {message}, removing it from the pool");
+                self.metrics.increment_bundle_failed_validation();
+                self.emit(BuilderEvent::rejected_op(
+                    self.builder_index,
+                    op_hash,
+                    OpRejectionReason::FailedInBundle {
+                        message: Arc::new(message),
+                    },
+                ));
+                self.remove_ops_from_pool(std::slice::from_ref(op)).await?;
+                Ok(false)
+            }
+            handle_ops_out => {
+                // the proposer handles these failures when forming the next bundle
+                warn!("Bundle failed validation before sending with {handle_ops_out:?}");
+                self.metrics.increment_bundle_failed_validation();
+                Ok(false)
+            }
+        }
+    }
+
+    /// Builds a bundle transaction without sending it. Rejected ops are not removed
+    /// from the pool, and no events are emitted.
+    async fn dry_run_bundle<TRIG: Trigger>(
+        &mut self,
+        state: &mut SenderMachineState<T, TRIG>,
+    ) -> anyhow::Result<SendBundleResult> {
+        let (nonce, required_fees) = state.transaction_tracker.get_nonce_and_required_fees()?;
+
+        // only the dry run's own priority ops, those of the bundle attempt it
+        // interrupted are kept for that attempt
+        let priority_ops = mem::take(&mut state.dry_run_priority_ops);
+        let mut bundle = match self
+            .proposer
+            .make_bundle(required_fees, false, priority_ops)
+            .await
+        {
+            Ok(bundle) => bundle,
+            Err(BundleProposerError::NoOperationsInitially) => {
+                return Ok(SendBundleResult::NoOperationsInitially {
+                    reason: EmptyReason::PoolEmpty,
+                });
+            }
+            Err(BundleProposerError::NoOperationsAfterFeeFilter) => {
+                return Ok(SendBundleResult::NoOperationsInitially {
+                    reason: EmptyReason::BelowFeeThreshold,
+                });
+            }
+            Err(e) => bail!("Failed to make bundle: {e:?}"),
+        };
+        // a dry run leaves the pool untouched, duplicates are only left out
+        let proposed_ops = bundle.len();
+        self.remove_duplicate_ops(&mut bundle);
+        self.limit_throttled_paymaster_ops(&mut bundle).await?;
+        self.limit_ops_per_entity(&mut bundle);
+        self.remove_ops_below_min_priority_fee(&mut bundle);
+        self.truncate_bundle(&mut bundle);
+        self.trim_bundle_to_max_calldata(&mut bundle);
+        if bundle.len() < proposed_ops {
+            self.refresh_bundle(&mut bundle)?;
+        }
+
+        Ok(match self.to_bundle_tx(nonce, bundle) {
+            Ok(BundleTx { tx, op_hashes, .. }) => SendBundleResult::DryRun {
+                tx_hash_preview: tx.sighash(),
+                op_hashes,
+                gas: tx.gas().copied().unwrap_or_default(),
+            },
+            Err(reason) => SendBundleResult::NoOperationsInitially { reason },
+        })
+    }
+
     /// Builds a bundle and returns some metadata and the transaction to send
-    /// it, or `None` if there are no valid operations available.
+    /// it, or the reason the bundle is empty if there are no valid operations
+    /// available.
     async fn get_bundle_tx(
         &mut self,
         nonce: U256,
-        bundle: Bundle<UO>,
-    ) -> anyhow::Result<Option<BundleTx>> {
+        mut bundle: Bundle<UO>,
+        fee_increase_count: u64,
+        block_number: u64,
+    ) -> anyhow::Result<Result<BundleTx, EmptyReason>> {
         let remove_ops_future = async {
             if bundle.rejected_ops.is_empty() {
                 return;
@@ -615,47 +1412,637 @@ where
 
         join!(remove_ops_future, update_entities_future);
 
-        if bundle.is_empty() {
-            if !bundle.rejected_ops.is_empty() || !bundle.entity_updates.is_empty() {
-                info!(
+        let proposed_ops = bundle.len();
+        // only one op per sender and nonce can be executed, the others would fail
+        // on chain
+        let duplicate_ops = self.remove_duplicate_ops(&mut bundle);
+        if !duplicate_ops.is_empty() {
+            self.metrics
+                .increment_bundle_duplicate_ops(duplicate_ops.len() as u64);
+            if let Err(error) = self.remove_ops_from_pool(&duplicate_ops).await {
+                error!("Failed to remove duplicate ops from pool: {error}");
+            }
+        }
+
+        self.limit_throttled_paymaster_ops(&mut bundle).await?;
+        let (over_entity_limit, capped_entities) = self.limit_ops_per_entity(&mut bundle);
+        if over_entity_limit > 0 {
+            self.metrics
+                .increment_bundle_ops_over_entity_limit(over_entity_limit as u64);
+        }
+        for entity in &capped_entities {
+            self.metrics.increment_bundle_entity_limit_reached(entity);
+        }
+        let below_min_priority_fee = self.remove_ops_below_min_priority_fee(&mut bundle);
+        if below_min_priority_fee > 0 {
+            self.metrics
+                .increment_bundle_ops_below_min_priority_fee(below_min_priority_fee as u64);
+        }
+        if self.truncate_bundle(&mut bundle) {
+            self.metrics.increment_bundle_truncated();
+        }
+        let over_max_calldata = self.trim_bundle_to_max_calldata(&mut bundle);
+        if over_max_calldata > 0 {
+            self.metrics
+                .increment_bundle_ops_over_max_calldata(over_max_calldata as u64);
+        }
+        // the proposer's gas estimate and expected storage cover the removed ops too
+        if bundle.len() < proposed_ops {
+            self.refresh_bundle(&mut bundle)?;
+        }
+        self.increase_bundle_gas_limit(&mut bundle, fee_increase_count);
+        self.metrics
+            .record_bundle_op_counts(bundle.len(), bundle.rejected_ops.len());
+
+        if bundle.is_empty()
+            && (!bundle.rejected_ops.is_empty() || !bundle.entity_updates.is_empty())
+        {
+            info!(
                 "Empty bundle with {} rejected ops and {} rejected entities. Removing them from pool.",
                 bundle.rejected_ops.len(),
                 bundle.entity_updates.len()
             );
+            self.emit(BuilderEvent::skipped_bundle(
+                self.builder_index,
+                bundle.rejected_ops.len(),
+                bundle.entity_updates.clone(),
+                block_number,
+            ));
+        }
+
+        let bundle_tx = self.to_bundle_tx(nonce, bundle);
+        if let Err(EmptyReason::ExceedsMaxBundleGas { .. }) = bundle_tx {
+            self.metrics.increment_bundle_over_gas_limit();
+        }
+        Ok(bundle_tx)
+    }
+
+    /// Recomputes the gas estimate and expected storage of `bundle` from the ops
+    /// left in it, after ops were removed from the proposed bundle
+    fn refresh_bundle(&self, bundle: &mut Bundle<UO>) -> anyhow::Result<()> {
+        let mut expected_storage = ExpectedStorage::default();
+        for op in bundle.iter_ops() {
+            if let Some(op_storage) = bundle.expected_storage_by_op.get(&self.op_hash(op)) {
+                expected_storage.merge(op_storage)?;
             }
-            return Ok(None);
         }
-        info!(
-            "Selected bundle with {} op(s), with {} rejected op(s) and {} updated entities",
-            bundle.len(),
-            bundle.rejected_ops.len(),
-            bundle.entity_updates.len()
+        bundle.expected_storage = expected_storage;
+        bundle.gas_estimate = bundle_proposer::bundle_gas_limit(
+            &self.chain_spec,
+            bundle.iter_ops(),
+            self.settings.gas_estimate_overhead_percent,
         );
-        let op_hashes: Vec<_> = bundle.iter_ops().map(|op| self.op_hash(op)).collect();
-        let mut tx = self.entry_point.get_send_bundle_transaction(
+        Ok(())
+    }
+
+    /// Increases the gas limit of a replacement bundle by `gas_limit_increase_percent`
+    /// compounded for each fee increase, as the previous transaction may not have
+    /// been mined because its ops used more gas than estimated. The increased limit
+    /// is capped at the max bundle gas.
+    fn increase_bundle_gas_limit(&self, bundle: &mut Bundle<UO>, fee_increase_count: u64) {
+        let percent = self.settings.gas_limit_increase_percent;
+        let max_bundle_gas = U256::from(self.settings.max_bundle_gas);
+        if percent == 0 || bundle.gas_estimate > max_bundle_gas {
+            return;
+        }
+        let gas_limit = (0..fee_increase_count).fold(bundle.gas_estimate, |gas_limit, _| {
+            math::increase_by_percent(gas_limit, percent)
+        });
+        bundle.gas_estimate = gas_limit.min(max_bundle_gas);
+    }
+
+    /// Returns the transaction to send `bundle` at `nonce`, or the reason the
+    /// bundle is empty
+    fn to_bundle_tx(&self, nonce: U256, mut bundle: Bundle<UO>) -> Result<BundleTx, EmptyReason> {
+        if bundle.is_empty() {
+            if bundle.rejected_ops.is_empty() && bundle.entity_updates.is_empty() {
+                return Err(EmptyReason::Deferred);
+            }
+            return Err(EmptyReason::RejectedInSimulation {
+                rejected_ops: bundle.rejected_ops.len(),
+                rejected_entities: bundle.entity_updates.len(),
+            });
+        }
+        if bundle.gas_estimate > U256::from(self.settings.max_bundle_gas) {
+            warn!(
+                "Bundle gas estimate {} exceeds the max bundle gas {}, not sending bundle with {} op(s)",
+                bundle.gas_estimate,
+                self.settings.max_bundle_gas,
+                bundle.len()
+            );
+            return Err(EmptyReason::ExceedsMaxBundleGas {
+                gas_estimate: bundle.gas_estimate,
+            });
+        }
+        info!(
+            "Selected bundle with {} op(s), with {} rejected op(s) and {} updated entities",
+            bundle.len(),
+            bundle.rejected_ops.len(),
+            bundle.entity_updates.len()
+        );
+        if self.settings.deterministic_ordering {
+            sort_bundle_ops(&mut bundle);
+        }
+        let op_hashes: Vec<_> = bundle.iter_ops().map(|op| self.op_hash(op)).collect();
+        let entities = bundle
+            .iter_ops()
+            .flat_map(|op| iter::once(op.sender()).chain(op.paymaster()))
+            .collect();
+        let mut tx = self.entry_point.get_send_bundle_transaction(
             bundle.ops_per_aggregator,
-            self.beneficiary,
+            bundle.beneficiary,
             bundle.gas_estimate,
             bundle.gas_fees,
         );
+        if self.settings.use_legacy_gas {
+            tx = to_legacy_tx(tx);
+        }
         tx.set_nonce(nonce);
-        Ok(Some(BundleTx {
+        Ok(BundleTx {
             tx,
             expected_storage: bundle.expected_storage,
             op_hashes,
+            entities,
+            beneficiary: bundle.beneficiary,
+        })
+    }
+
+    /// Limits the number of operations per throttled paymaster in the bundle and removes
+    /// the operations of banned paymasters, using reputation data from the pool.
+    ///
+    /// Removed operations remain in the pool and may be included in a later bundle.
+    /// Operations in aggregated groups are left untouched, as removing them would
+    /// invalidate the aggregated signature.
+    async fn limit_throttled_paymaster_ops(&self, bundle: &mut Bundle<UO>) -> anyhow::Result<()> {
+        let paymasters: HashSet<Address> = bundle
+            .ops_per_aggregator
+            .iter()
+            .filter(|group| group.aggregator.is_zero())
+            .flat_map(|group| group.user_ops.iter().filter_map(|op| op.paymaster()))
+            .collect();
+        if paymasters.is_empty() {
+            return Ok(());
+        }
+
+        let entry_point = self.entry_point.address();
+        let statuses = future::try_join_all(paymasters.into_iter().map(|paymaster| async move {
+            self.pool
+                .get_reputation_status(entry_point, paymaster)
+                .await
+                .map(|status| (paymaster, status))
         }))
+        .await
+        .context("builder should get paymaster reputation status from pool")?;
+
+        // remaining number of operations allowed per throttled or banned paymaster
+        let mut remaining: HashMap<Address, usize> = statuses
+            .into_iter()
+            .filter_map(|(paymaster, status)| match status {
+                ReputationStatus::Ok => None,
+                ReputationStatus::Throttled => {
+                    Some((paymaster, self.settings.throttled_paymaster_bundle_count))
+                }
+                ReputationStatus::Banned => Some((paymaster, 0)),
+            })
+            .collect();
+        if remaining.is_empty() {
+            return Ok(());
+        }
+
+        let len_before = bundle.len();
+        for group in bundle
+            .ops_per_aggregator
+            .iter_mut()
+            .filter(|group| group.aggregator.is_zero())
+        {
+            group.user_ops.retain(|op| {
+                let Some(count) = op.paymaster().and_then(|p| remaining.get_mut(&p)) else {
+                    return true;
+                };
+                if *count == 0 {
+                    return false;
+                }
+                *count -= 1;
+                true
+            });
+        }
+        bundle
+            .ops_per_aggregator
+            .retain(|group| !group.user_ops.is_empty());
+
+        let removed = len_before - bundle.len();
+        if removed > 0 {
+            info!("Removed {removed} op(s) from bundle due to throttled or banned paymasters");
+        }
+
+        Ok(())
     }
 
-    async fn remove_ops_from_pool(&self, ops: &[UO]) -> anyhow::Result<()> {
-        self.pool
-            .remove_ops(
-                self.entry_point.address(),
-                ops.iter()
-                    .map(|op| op.hash(self.entry_point.address(), self.chain_spec.id))
-                    .collect(),
+    // Limits the number of ops sharing a paymaster or factory to
+    // `max_ops_per_entity_per_bundle`, keeping the first ops of the bundle. Returns the
+    // number of ops removed and the entities that reached the limit. Removed ops
+    // remain in the pool. Ops of aggregated groups count towards the limit but are not
+    // removed, as removing them would invalidate the aggregated signature.
+    fn limit_ops_per_entity(&self, bundle: &mut Bundle<UO>) -> (usize, HashSet<Entity>) {
+        let max_ops = self.settings.max_ops_per_entity_per_bundle;
+        if max_ops == usize::MAX {
+            return (0, HashSet::new());
+        }
+
+        let mut counts: HashMap<Entity, usize> = HashMap::new();
+        let mut capped_entities = HashSet::new();
+        let len_before = bundle.len();
+        for group in &mut bundle.ops_per_aggregator {
+            let is_aggregated = !group.aggregator.is_zero();
+            group.user_ops.retain(|op| {
+                let entities: Vec<_> = op
+                    .entities()
+                    .into_iter()
+                    .filter(|entity| {
+                        matches!(entity.kind, EntityType::Paymaster | EntityType::Factory)
+                    })
+                    .collect();
+                if !is_aggregated {
+                    let capped = entities
+                        .iter()
+                        .find(|entity| counts.get(entity).is_some_and(|&count| count >= max_ops));
+                    if let Some(&capped) = capped {
+                        capped_entities.insert(capped);
+                        return false;
+                    }
+                }
+                for entity in entities {
+                    *counts.entry(entity).or_default() += 1;
+                }
+                true
+            });
+        }
+        bundle
+            .ops_per_aggregator
+            .retain(|group| !group.user_ops.is_empty());
+
+        let removed = len_before - bundle.len();
+        if removed > 0 {
+            info!(
+                "Removed {removed} op(s) from bundle over the limit of {max_ops} op(s) per entity, entities at the limit: {:?}",
+                capped_entities.iter().map(ToString::to_string).collect::<Vec<_>>()
+            );
+        }
+        (removed, capped_entities)
+    }
+
+    // Keeps a single op per sender and nonce in the bundle, the one paying the highest
+    // fees, and returns the others. An aggregated group holding a dropped op is
+    // removed whole, as its aggregated signature covers all of its ops, but only the
+    // dropped ops are returned.
+    fn remove_duplicate_ops(&self, bundle: &mut Bundle<UO>) -> Vec<UO> {
+        let fees = |op: &UO| (op.max_priority_fee_per_gas(), op.max_fee_per_gas());
+        // position of the op kept for each sender and nonce
+        let mut kept: HashMap<(Address, U256), (usize, usize)> = HashMap::new();
+        for (group_index, group) in bundle.ops_per_aggregator.iter().enumerate() {
+            for (op_index, op) in group.user_ops.iter().enumerate() {
+                kept.entry((op.sender(), op.nonce()))
+                    .and_modify(|(kept_group, kept_op)| {
+                        let kept_fees =
+                            fees(&bundle.ops_per_aggregator[*kept_group].user_ops[*kept_op]);
+                        if fees(op) > kept_fees {
+                            (*kept_group, *kept_op) = (group_index, op_index);
+                        }
+                    })
+                    .or_insert((group_index, op_index));
+            }
+        }
+        if kept.len() == bundle.len() {
+            return vec![];
+        }
+        let kept: HashSet<(usize, usize)> = kept.into_values().collect();
+
+        let mut duplicates = vec![];
+        for (group_index, group) in bundle.ops_per_aggregator.iter_mut().enumerate() {
+            let (kept_ops, dropped_ops): (Vec<_>, Vec<_>) = group
+                .user_ops
+                .drain(..)
+                .enumerate()
+                .partition(|(op_index, _)| kept.contains(&(group_index, *op_index)));
+            // ops of an aggregated group are kept or removed together
+            if group.aggregator.is_zero() || dropped_ops.is_empty() {
+                group.user_ops = kept_ops.into_iter().map(|(_, op)| op).collect();
+            }
+            duplicates.extend(dropped_ops.into_iter().map(|(_, op)| op));
+        }
+        bundle
+            .ops_per_aggregator
+            .retain(|group| !group.user_ops.is_empty());
+
+        for op in &duplicates {
+            warn!(
+                "Dropping op {:?} from bundle, another op of sender {:?} with nonce {} pays higher fees",
+                self.op_hash(op),
+                op.sender(),
+                op.nonce()
+            );
+        }
+        duplicates
+    }
+
+    // Removes the ops with hashes in `inflight` from the bundle, leaving them in the
+    // pool, and returns the number removed. An aggregated group is removed whole if
+    // any of its ops is in flight, as its aggregated signature covers all of its ops.
+    fn remove_inflight_ops(&self, bundle: &mut Bundle<UO>, inflight: &HashSet<H256>) -> usize {
+        if inflight.is_empty() {
+            return 0;
+        }
+
+        let len_before = bundle.len();
+        for group in &mut bundle.ops_per_aggregator {
+            if group.aggregator.is_zero() {
+                group
+                    .user_ops
+                    .retain(|op| !inflight.contains(&self.op_hash(op)));
+            } else if group
+                .user_ops
+                .iter()
+                .any(|op| inflight.contains(&self.op_hash(op)))
+            {
+                group.user_ops.clear();
+            }
+        }
+        bundle
+            .ops_per_aggregator
+            .retain(|group| !group.user_ops.is_empty());
+        len_before - bundle.len()
+    }
+
+    // Removes the ops whose sender or paymaster is in `entities` from the bundle,
+    // leaving them in the pool, and returns the number removed. An aggregated group is
+    // removed whole if any of its ops is removed.
+    fn remove_inflight_entity_ops(
+        &self,
+        bundle: &mut Bundle<UO>,
+        entities: &HashSet<Address>,
+    ) -> usize {
+        if entities.is_empty() {
+            return 0;
+        }
+
+        let is_inflight = |op: &UO| {
+            entities.contains(&op.sender()) || op.paymaster().is_some_and(|p| entities.contains(&p))
+        };
+        let len_before = bundle.len();
+        for group in &mut bundle.ops_per_aggregator {
+            if group.aggregator.is_zero() {
+                group.user_ops.retain(|op| !is_inflight(op));
+            } else if group.user_ops.iter().any(is_inflight) {
+                group.user_ops.clear();
+            }
+        }
+        bundle
+            .ops_per_aggregator
+            .retain(|group| !group.user_ops.is_empty());
+        len_before - bundle.len()
+    }
+
+    // Removes the ops paying a max priority fee below the minimum op priority fee
+    // from the bundle, leaving them in the pool, and returns the number removed. An
+    // aggregated group is removed whole if any of its ops is below the minimum, as
+    // its aggregated signature covers all of its ops.
+    fn remove_ops_below_min_priority_fee(&self, bundle: &mut Bundle<UO>) -> usize {
+        let min_priority_fee = self.settings.min_op_priority_fee;
+        if min_priority_fee.is_zero() {
+            return 0;
+        }
+
+        let len_before = bundle.len();
+        for group in &mut bundle.ops_per_aggregator {
+            if group.aggregator.is_zero() {
+                group
+                    .user_ops
+                    .retain(|op| op.max_priority_fee_per_gas() >= min_priority_fee);
+            } else if group
+                .user_ops
+                .iter()
+                .any(|op| op.max_priority_fee_per_gas() < min_priority_fee)
+            {
+                group.user_ops.clear();
+            }
+        }
+        bundle
+            .ops_per_aggregator
+            .retain(|group| !group.user_ops.is_empty());
+
+        let removed = len_before - bundle.len();
+        if removed > 0 {
+            info!(
+                "Removed {removed} op(s) from bundle paying a max priority fee below {min_priority_fee}"
+            );
+        }
+        removed
+    }
+
+    /// Truncates the bundle to at most `max_ops_per_bundle` operations, keeping the
+    /// first ones, which the pool orders by fee priority. Returns whether any operations
+    /// were removed.
+    ///
+    /// Removed operations remain in the pool and may be included in a later bundle.
+    /// Aggregated groups are kept or removed whole, as removing some of their operations
+    /// would invalidate the aggregated signature.
+    fn truncate_bundle(&self, bundle: &mut Bundle<UO>) -> bool {
+        let len_before = bundle.len();
+        if len_before <= self.settings.max_ops_per_bundle {
+            return false;
+        }
+
+        let mut remaining = self.settings.max_ops_per_bundle;
+        for group in &mut bundle.ops_per_aggregator {
+            if group.aggregator.is_zero() {
+                group.user_ops.truncate(remaining);
+            } else if group.user_ops.len() > remaining {
+                group.user_ops.clear();
+            }
+            remaining -= group.user_ops.len();
+        }
+        bundle
+            .ops_per_aggregator
+            .retain(|group| !group.user_ops.is_empty());
+
+        info!(
+            "Truncated bundle from {len_before} to {} op(s), the max ops per bundle",
+            bundle.len()
+        );
+        true
+    }
+
+    // Removes the largest ops from the bundle, leaving them in the pool, until the
+    // calldata of its transaction fits in `max_bundle_calldata_bytes`, and returns the
+    // number removed. An aggregated group holding the largest op is removed whole, as
+    // its aggregated signature covers all of its ops.
+    fn trim_bundle_to_max_calldata(&self, bundle: &mut Bundle<UO>) -> usize {
+        let max_bytes = self.settings.max_bundle_calldata_bytes;
+        if max_bytes == usize::MAX {
+            return 0;
+        }
+
+        let len_before = bundle.len();
+        while !bundle.is_empty() && self.bundle_calldata_bytes(bundle) > max_bytes {
+            let largest = bundle
+                .ops_per_aggregator
+                .iter()
+                .enumerate()
+                .flat_map(|(group_index, group)| {
+                    group
+                        .user_ops
+                        .iter()
+                        .enumerate()
+                        .map(move |(op_index, op)| (group_index, op_index, op.abi_encoded_size()))
+                })
+                .max_by_key(|(_, _, size)| *size);
+            let Some((group_index, op_index, _)) = largest else {
+                break;
+            };
+            let group = &mut bundle.ops_per_aggregator[group_index];
+            if group.aggregator.is_zero() {
+                group.user_ops.remove(op_index);
+            } else {
+                group.user_ops.clear();
+            }
+            bundle
+                .ops_per_aggregator
+                .retain(|group| !group.user_ops.is_empty());
+        }
+
+        let removed = len_before - bundle.len();
+        if removed > 0 {
+            info!(
+                "Removed {removed} op(s) from bundle for its calldata to fit in {max_bytes} bytes"
+            );
+        }
+        removed
+    }
+
+    // Length in bytes of the calldata of the transaction sending `bundle`
+    fn bundle_calldata_bytes(&self, bundle: &Bundle<UO>) -> usize {
+        self.entry_point
+            .get_send_bundle_transaction(
+                bundle.ops_per_aggregator.clone(),
+                bundle.beneficiary,
+                bundle.gas_estimate,
+                bundle.gas_fees,
             )
-            .await
-            .context("builder should remove rejected ops from pool")
+            .data()
+            .map_or(0, |data| data.len())
+    }
+
+    /// Reconciles which operations of a mined bundle transaction were executed, using the
+    /// `UserOperationEvent` logs in its receipt.
+    ///
+    /// Executed operations are removed from the pool. Operations that were bundled but
+    /// skipped by the entry point remain in the pool to be included in a later bundle,
+    /// unless they have been skipped in `max_bundle_reverts_per_op` consecutive bundles.
+    ///
+    /// Returns whether each bundled operation executed successfully.
+    #[instrument(skip_all, fields(tx_hash = ?tx_hash))]
+    async fn reconcile_mined_ops(
+        &mut self,
+        tx_hash: H256,
+        logs: Option<&[Log]>,
+    ) -> Vec<(H256, bool)> {
+        let bundled = self.bundle_op_hashes.remove(&tx_hash);
+        self.bundle_op_hashes.clear();
+        self.inflight_entities.retain(|tx_hash, _| {
+            self.pipelined_op_hashes
+                .iter()
+                .any(|(pipelined, _)| pipelined == tx_hash)
+        });
+        let Some(bundled) = bundled else {
+            return vec![];
+        };
+        let Some(logs) = logs else {
+            warn!("Receipt unavailable for mined bundle transaction {tx_hash:?}, skipping op reconciliation");
+            return vec![];
+        };
+
+        let outcomes = rundler_provider::user_operation_outcomes(self.entry_point.address(), logs);
+        let op_results = bundled
+            .iter()
+            .map(|hash| (*hash, outcomes.get(hash).copied().unwrap_or(false)))
+            .collect();
+        let (executed, skipped): (Vec<_>, Vec<_>) = bundled
+            .into_iter()
+            .partition(|hash| outcomes.contains_key(hash));
+
+        if !skipped.is_empty() {
+            warn!(
+                "{} of {} bundled op(s) were not executed by the entry point, leaving them in the pool: {skipped:?}",
+                skipped.len(),
+                skipped.len() + executed.len()
+            );
+            self.metrics
+                .increment_bundle_ops_skipped(skipped.len() as u64);
+        }
+        for op_hash in &executed {
+            info_span!("op_mined", op_hash = ?op_hash)
+                .in_scope(|| debug!("Op executed in mined bundle"));
+        }
+
+        let mut to_remove = executed;
+        for (op_hash, count) in self.record_op_bundle_reverts(&skipped) {
+            warn!("Removing op {op_hash:?} from the pool after it was not executed in {count} consecutive bundles");
+            self.emit(BuilderEvent::rejected_op(
+                self.builder_index,
+                op_hash,
+                OpRejectionReason::RepeatedlyFailedInBundle { count },
+            ));
+            to_remove.push(op_hash);
+        }
+        if !to_remove.is_empty() {
+            if let Err(error) = self
+                .pool
+                .remove_ops(self.entry_point.address(), to_remove)
+                .await
+            {
+                error!("Failed to remove executed ops from pool: {error}");
+            }
+        }
+
+        op_results
+    }
+
+    /// Records that the given ops were included in a mined bundle but not executed, returning
+    /// the ops that have now been skipped in `max_bundle_reverts_per_op` consecutive bundles
+    /// along with their count.
+    ///
+    /// Counts are only kept for ops skipped in the latest mined bundle.
+    fn record_op_bundle_reverts(&mut self, skipped: &[H256]) -> Vec<(H256, u64)> {
+        let Some(max_reverts) = self.settings.max_bundle_reverts_per_op else {
+            return vec![];
+        };
+
+        let mut previous = mem::take(&mut self.op_bundle_reverts);
+        let mut exceeded = vec![];
+        for &op_hash in skipped {
+            let count = previous.remove(&op_hash).unwrap_or(0) + 1;
+            if count >= max_reverts {
+                exceeded.push((op_hash, count));
+            } else {
+                self.op_bundle_reverts.insert(op_hash, count);
+            }
+        }
+        exceeded
+    }
+
+    /// Removes `ops` from the pool, in batches of at most `rejected_ops_removal_batch_size`
+    /// ops per request to bound the size of each request.
+    async fn remove_ops_from_pool(&self, ops: &[UO]) -> anyhow::Result<()> {
+        for batch in ops.chunks(self.settings.rejected_ops_removal_batch_size.max(1)) {
+            self.pool
+                .remove_ops(
+                    self.entry_point.address(),
+                    batch.iter().map(|op| self.op_hash(op)).collect(),
+                )
+                .await
+                .context("builder should remove rejected ops from pool")?;
+        }
+        Ok(())
     }
 
     async fn update_entities_in_pool(&self, entity_updates: &[EntityUpdate]) -> anyhow::Result<()> {
@@ -665,6 +2052,116 @@ where
             .context("builder should remove update entities in the pool")
     }
 
+    fn record_reorg(&self, tx_hash: H256, nonce: U256) {
+        warn!("Bundle transaction {tx_hash:?} with nonce {nonce:?} was reorged out");
+        self.metrics.increment_bundle_txns_reorged();
+        self.emit(BuilderEvent::transaction_reorged(
+            self.builder_index,
+            tx_hash,
+            nonce.low_u64(),
+        ));
+    }
+
+    // counts a failed bundle attempt, opening the circuit breaker once the consecutive
+    // failure threshold is reached. Each further failure while open doubles the
+    // backoff, up to `max_failure_backoff`.
+    fn record_bundle_failure(&mut self) {
+        self.consecutive_failures += 1;
+        let Some(threshold) = self.settings.consecutive_failure_threshold else {
+            return;
+        };
+        if self.consecutive_failures < threshold {
+            return;
+        }
+
+        let backoff = exponential_backoff(
+            CIRCUIT_BREAKER_BASE_BACKOFF,
+            self.consecutive_failures - threshold,
+            self.settings.max_failure_backoff,
+        );
+        warn!(
+            "Circuit breaker open after {} consecutive failed bundle attempts, next attempt in {backoff:?}",
+            self.consecutive_failures
+        );
+        self.circuit_open_until = Some(Instant::now() + backoff);
+        self.health.set_circuit_open_until(self.circuit_open_until);
+        self.metrics.increment_circuit_breaker_opened();
+        self.emit(BuilderEvent::circuit_open(
+            self.builder_index,
+            self.consecutive_failures,
+            backoff,
+        ));
+    }
+
+    // resets the circuit breaker after a bundle attempt completes without error
+    fn close_circuit(&mut self) {
+        if self.circuit_open_until.take().is_some() {
+            info!(
+                "Circuit breaker closed after {} consecutive failed bundle attempts",
+                self.consecutive_failures
+            );
+            self.health.set_circuit_open_until(None);
+        }
+        self.consecutive_failures = 0;
+    }
+
+    // counts a bundle attempt that found no ops in the pool, backing off automatic
+    // attempts once the idle threshold is reached. Each further empty attempt doubles
+    // the backoff, up to `idle_backoff_max`.
+    fn record_empty_attempt(&mut self) {
+        self.consecutive_empty_attempts += 1;
+        if self.settings.idle_backoff_max.is_zero()
+            || self.consecutive_empty_attempts < IDLE_BACKOFF_THRESHOLD
+        {
+            return;
+        }
+
+        let backoff = exponential_backoff(
+            IDLE_BACKOFF_BASE,
+            self.consecutive_empty_attempts - IDLE_BACKOFF_THRESHOLD,
+            self.settings.idle_backoff_max,
+        );
+        debug!(
+            "No operations in the pool for {} consecutive bundle attempts, next automatic attempt in {backoff:?}",
+            self.consecutive_empty_attempts
+        );
+        self.idle_until = Some(Instant::now() + backoff);
+    }
+
+    fn end_idle_backoff(&mut self) {
+        self.consecutive_empty_attempts = 0;
+        self.idle_until = None;
+    }
+
+    // returns whether automatic bundle attempts are backing off while the pool is idle.
+    // The pool is checked on each trigger, which is cheaper than building a bundle,
+    // so that the backoff ends as soon as an op is added.
+    async fn is_idle_backoff_active(&mut self) -> bool {
+        if self
+            .idle_until
+            .map_or(true, |until| until <= Instant::now())
+        {
+            return false;
+        }
+
+        match self
+            .pool
+            .get_ops(self.entry_point.address(), 1, self.builder_index)
+            .await
+        {
+            Ok(ops) if ops.is_empty() => true,
+            Ok(_) => {
+                debug!("Operation added to the pool, ending idle backoff");
+                self.end_idle_backoff();
+                false
+            }
+            Err(error) => {
+                warn!("Failed to check the pool for operations during idle backoff: {error}");
+                false
+            }
+        }
+    }
+
     fn emit(&self, event: BuilderEvent) {
         let _ = self.event_sender.send(WithEntryPoint {
             entry_point: self.entry_point.address(),
@@ -680,9 +2177,16 @@ where
 struct SenderMachineState<T, TRIG> {
     trigger: TRIG,
     transaction_tracker: T,
-    send_bundle_response: Option<oneshot::Sender<SendBundleResult>>,
+    send_bundle_responses: Vec<oneshot::Sender<SendBundleResult>>,
     inner: InnerState,
     requires_reset: bool,
+    // whether the current bundle attempt is a dry run, which is not sent
+    dry_run: bool,
+    // ops of the manual requests to place ahead of the pool's ops, proposed in each
+    // bundle until a bundle containing them mines or they are rejected
+    priority_ops: Vec<PoolOperation>,
+    // priority ops of a dry run request, only proposed in the dry run
+    dry_run_priority_ops: Vec<PoolOperation>,
 }
 
 impl<T: TransactionTracker, TRIG: Trigger> SenderMachineState<T, TRIG> {
@@ -690,9 +2194,12 @@ impl<T: TransactionTracker, TRIG: Trigger> SenderMachineState<T, TRIG> {
         Self {
             trigger,
             transaction_tracker,
-            send_bundle_response: None,
+            send_bundle_responses: vec![],
             inner: InnerState::new(),
             requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
         }
     }
 
@@ -707,6 +2214,7 @@ impl<T: TransactionTracker, TRIG: Trigger> SenderMachineState<T, TRIG> {
             wait_for_trigger: false,
             fee_increase_count: 0,
             underpriced_info: None,
+            pending_since: None,
         };
         self.inner = InnerState::Building(building_state);
     }
@@ -724,22 +2232,38 @@ impl<T: TransactionTracker, TRIG: Trigger> SenderMachineState<T, TRIG> {
         self.inner = next_state;
     }
 
-    fn abandon(&mut self) {
-        self.transaction_tracker.abandon();
-        self.inner = InnerState::new();
+    // sends the result to the manual callers waiting on the current bundle attempt
+    fn respond(&mut self, result: SendBundleResult) {
+        for r in self.send_bundle_responses.drain(..) {
+            if r.send(result.clone()).is_err() {
+                error!("Failed to send bundle result to manual caller");
+            }
+        }
     }
 
     fn complete(&mut self, result: Option<SendBundleResult>) {
         if let Some(result) = result {
-            if let Some(r) = self.send_bundle_response.take() {
-                if r.send(result).is_err() {
-                    error!("Failed to send bundle result to manual caller");
-                }
-            }
+            self.respond(result);
         }
         self.inner = InnerState::new();
     }
 
+    // whether there is no bundle transaction in flight, so that no new bundle is
+    // being built to replace one
+    fn is_idle(&self) -> bool {
+        matches!(&self.inner, InnerState::Building(s) if s.fee_increase_count == 0)
+    }
+
+    // answers the manual callers waiting on the current bundle attempt, and those
+    // of requests still queued, with an error
+    fn shutdown(&mut self) {
+        let responders = self.trigger.close();
+        self.send_bundle_responses.extend(responders);
+        self.respond(SendBundleResult::Error(Arc::new(anyhow::anyhow!(
+            "bundle sender shut down"
+        ))));
+    }
+
     async fn wait_for_trigger(&mut self) -> anyhow::Result<Option<TrackerUpdate>> {
         if self.requires_reset {
             self.transaction_tracker.reset().await;
@@ -752,7 +2276,14 @@ impl<T: TransactionTracker, TRIG: Trigger> SenderMachineState<T, TRIG> {
                     return Ok(None);
                 }
 
-                self.send_bundle_response = self.trigger.wait_for_trigger().await?;
+                let requests = self.trigger.wait_for_trigger().await?;
+                self.send_bundle_responses = requests.responders;
+                self.dry_run = requests.dry_run;
+                if requests.dry_run {
+                    self.dry_run_priority_ops = requests.priority_ops;
+                } else {
+                    self.priority_ops.extend(requests.priority_ops);
+                }
                 self.transaction_tracker
                     .check_for_update()
                     .await
@@ -792,6 +2323,7 @@ impl InnerState {
             wait_for_trigger: true,
             fee_increase_count: 0,
             underpriced_info: None,
+            pending_since: None,
         })
     }
 }
@@ -801,6 +2333,8 @@ struct BuildingState {
     wait_for_trigger: bool,
     fee_increase_count: u64,
     underpriced_info: Option<UnderpricedInfo>,
+    // when the first transaction at the current nonce was sent, if any
+    pending_since: Option<Instant>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -811,10 +2345,11 @@ struct UnderpricedInfo {
 
 impl BuildingState {
     // Transition to pending state
-    fn to_pending(self, until: u64) -> PendingState {
+    fn to_pending(self, until: u64, sent_at: Instant) -> PendingState {
         PendingState {
             until,
             fee_increase_count: self.fee_increase_count,
+            pending_since: self.pending_since.unwrap_or(sent_at),
         }
     }
 
@@ -822,6 +2357,7 @@ impl BuildingState {
     fn to_cancelling(self) -> CancellingState {
         CancellingState {
             fee_increase_count: 0,
+            pending_since: self.pending_since,
         }
     }
 
@@ -831,6 +2367,12 @@ impl BuildingState {
         self
     }
 
+    // Wait for the next trigger, keeping any fee increase state
+    fn wait_for_trigger(mut self) -> Self {
+        self.wait_for_trigger = true;
+        self
+    }
+
     // Mark a replacement as underpriced
     //
     // The next state will wait for a trigger to reduce bundle building loops
@@ -848,6 +2390,7 @@ impl BuildingState {
             wait_for_trigger: true,
             fee_increase_count: self.fee_increase_count + 1,
             underpriced_info: Some(ui),
+            pending_since: self.pending_since,
         }
     }
 
@@ -865,6 +2408,7 @@ impl BuildingState {
             wait_for_trigger: true,
             fee_increase_count: 0,
             underpriced_info: Some(underpriced_info),
+            pending_since: self.pending_since,
         }
     }
 }
@@ -873,6 +2417,8 @@ impl BuildingState {
 struct PendingState {
     until: u64,
     fee_increase_count: u64,
+    // when the first transaction at the current nonce was sent
+    pending_since: Instant,
 }
 
 impl PendingState {
@@ -881,6 +2427,15 @@ impl PendingState {
             wait_for_trigger: false,
             fee_increase_count: self.fee_increase_count + 1,
             underpriced_info: None,
+            pending_since: Some(self.pending_since),
+        }
+    }
+
+    // Transition to cancelling state
+    fn to_cancelling(self) -> CancellingState {
+        CancellingState {
+            fee_increase_count: 0,
+            pending_since: Some(self.pending_since),
         }
     }
 }
@@ -888,6 +2443,8 @@ impl PendingState {
 #[derive(Debug, Clone, Copy)]
 struct CancellingState {
     fee_increase_count: u64,
+    // when the first transaction of the bundle being cancelled was sent, if any
+    pending_since: Option<Instant>,
 }
 
 impl CancellingState {
@@ -900,6 +2457,7 @@ impl CancellingState {
         CancelPendingState {
             until,
             fee_increase_count: self.fee_increase_count,
+            pending_since: self.pending_since,
         }
     }
 }
@@ -908,26 +2466,82 @@ impl CancellingState {
 struct CancelPendingState {
     until: u64,
     fee_increase_count: u64,
+    // when the first transaction of the bundle being cancelled was sent, if any
+    pending_since: Option<Instant>,
 }
 
 impl CancelPendingState {
     fn to_cancelling(self) -> CancellingState {
         CancellingState {
             fee_increase_count: self.fee_increase_count + 1,
+            pending_since: self.pending_since,
+        }
+    }
+}
+
+// Converts a bundle transaction to a legacy transaction, paying its max fee per gas as
+// the gas price
+fn to_legacy_tx(tx: TypedTransaction) -> TypedTransaction {
+    TransactionRequest {
+        from: tx.from().copied(),
+        to: tx.to().cloned(),
+        gas: tx.gas().copied(),
+        gas_price: Some(GasFees::from(&tx).max_fee_per_gas),
+        value: tx.value().copied(),
+        data: tx.data().cloned(),
+        nonce: tx.nonce().copied(),
+        chain_id: tx.chain_id(),
+    }
+    .into()
+}
+
+// Sorts the ops of `bundle` into a stable order, independent of the proposer's:
+// aggregator groups by aggregator address, and the ops of the unaggregated group by
+// sender, then nonce. The ops of aggregated groups keep their order, as their
+// aggregated signature may depend on it.
+//
+// Ops are reordered after simulation, so an op whose validation depends on the ops
+// bundled before it may fail on chain. Intended for reproducible tests.
+fn sort_bundle_ops<UO: UserOperation>(bundle: &mut Bundle<UO>) {
+    bundle
+        .ops_per_aggregator
+        .sort_by_key(|group| group.aggregator);
+    for group in &mut bundle.ops_per_aggregator {
+        if group.aggregator.is_zero() {
+            group.user_ops.sort_by_key(|op| (op.sender(), op.nonce()));
         }
     }
 }
 
+// returns `base` doubled `exponent` times, up to `max_backoff`
+fn exponential_backoff(base: Duration, exponent: u64, max_backoff: Duration) -> Duration {
+    let multiplier = u32::try_from(exponent)
+        .ok()
+        .and_then(|exponent| 1_u32.checked_shl(exponent))
+        .unwrap_or(u32::MAX);
+    base.saturating_mul(multiplier).min(max_backoff)
+}
+
 #[async_trait]
 #[cfg_attr(test, automock)]
 trait Trigger {
-    async fn wait_for_trigger(
-        &mut self,
-    ) -> anyhow::Result<Option<oneshot::Sender<SendBundleResult>>>;
+    async fn wait_for_trigger(&mut self) -> anyhow::Result<ManualRequests>;
 
     async fn wait_for_block(&mut self) -> anyhow::Result<NewHead>;
 
     fn last_block(&self) -> &NewHead;
+
+    /// Stops receiving manual send bundle requests, returning the responders of
+    /// the requests already queued
+    fn close(&mut self) -> Vec<oneshot::Sender<SendBundleResult>>;
+}
+
+// Manual send bundle requests served by a triggered bundle attempt
+#[derive(Debug, Default)]
+struct ManualRequests {
+    responders: Vec<oneshot::Sender<SendBundleResult>>,
+    dry_run: bool,
+    priority_ops: Vec<PoolOperation>,
 }
 
 struct BundleSenderTrigger {
@@ -935,15 +2549,23 @@ struct BundleSenderTrigger {
     block_rx: UnboundedReceiver<NewHead>,
     bundle_action_receiver: mpsc::Receiver<BundleSenderAction>,
     timer: tokio::time::Interval,
+    pending_poll_interval: Option<Duration>,
+    coalesce_manual_requests: bool,
+    // dry run request received while coalescing manual requests, served by the
+    // next trigger
+    queued_request: Option<SendBundleRequest>,
     last_block: NewHead,
 }
 
 #[async_trait]
 impl Trigger for BundleSenderTrigger {
-    async fn wait_for_trigger(
-        &mut self,
-    ) -> anyhow::Result<Option<oneshot::Sender<SendBundleResult>>> {
-        let mut send_bundle_response: Option<oneshot::Sender<SendBundleResult>> = None;
+    async fn wait_for_trigger(&mut self) -> anyhow::Result<ManualRequests> {
+        if let Some(request) = self.queued_request.take() {
+            self.consume_blocks()?;
+            return Ok(self.manual_requests(request));
+        }
+
+        let mut requests = ManualRequests::default();
 
         loop {
             // 3 triggers for loop logic:
@@ -984,7 +2606,7 @@ impl Trigger for BundleSenderTrigger {
                         Some(BundleSenderAction::SendBundle(r)) => {
                             match self.bundling_mode {
                                 BundlingMode::Manual => {
-                                    send_bundle_response = Some(r.responder);
+                                    requests = self.manual_requests(r);
                                     break;
                                 },
                                 BundlingMode::Auto => {
@@ -1004,15 +2626,21 @@ impl Trigger for BundleSenderTrigger {
 
         self.consume_blocks()?;
 
-        Ok(send_bundle_response)
+        Ok(requests)
     }
 
     async fn wait_for_block(&mut self) -> anyhow::Result<NewHead> {
-        self.last_block = self
-            .block_rx
-            .recv()
-            .await
-            .ok_or_else(|| anyhow::anyhow!("Block stream closed"))?;
+        // A transaction is pending, if configured poll for updates more often than new blocks
+        // arrive to reduce the latency of detecting the transaction being mined.
+        let block = match self.pending_poll_interval {
+            Some(interval) => match tokio::time::timeout(interval, self.block_rx.recv()).await {
+                Ok(block) => block,
+                Err(_) => return Ok(self.last_block.clone()),
+            },
+            None => self.block_rx.recv().await,
+        };
+
+        self.last_block = block.ok_or_else(|| anyhow::anyhow!("Block stream closed"))?;
         self.consume_blocks()?;
         Ok(self.last_block.clone())
     }
@@ -1020,47 +2648,73 @@ impl Trigger for BundleSenderTrigger {
     fn last_block(&self) -> &NewHead {
         &self.last_block
     }
+
+    fn close(&mut self) -> Vec<oneshot::Sender<SendBundleResult>> {
+        self.bundle_action_receiver.close();
+        let mut responders: Vec<_> = self
+            .queued_request
+            .take()
+            .map(|r| r.responder)
+            .into_iter()
+            .collect();
+        while let Ok(action) = self.bundle_action_receiver.try_recv() {
+            if let BundleSenderAction::SendBundle(r) = action {
+                responders.push(r.responder);
+            }
+        }
+        responders
+    }
 }
 
 impl BundleSenderTrigger {
-    async fn new<P: Pool>(
-        pool_client: &P,
+    fn new(
+        new_heads: broadcast::Receiver<NewHead>,
+        health: Arc<BuilderHealth>,
         bundle_action_receiver: mpsc::Receiver<BundleSenderAction>,
         timer_interval: Duration,
-    ) -> anyhow::Result<Self> {
-        let block_rx = Self::start_block_stream(pool_client).await?;
+        pending_poll_interval: Option<Duration>,
+        coalesce_manual_requests: bool,
+    ) -> Self {
+        let block_rx = Self::start_block_stream(new_heads, health);
 
-        Ok(Self {
+        Self {
             bundling_mode: BundlingMode::Auto,
             block_rx,
             bundle_action_receiver,
             timer: tokio::time::interval(timer_interval),
+            pending_poll_interval: pending_poll_interval
+                .map(|interval| interval.max(MIN_PENDING_POLL_INTERVAL)),
+            coalesce_manual_requests,
+            queued_request: None,
             last_block: NewHead {
                 block_hash: H256::zero(),
                 block_number: 0,
             },
-        })
+        }
     }
 
-    async fn start_block_stream<P: Pool>(
-        pool_client: &P,
-    ) -> anyhow::Result<UnboundedReceiver<NewHead>> {
-        let Ok(mut new_heads) = pool_client.subscribe_new_heads().await else {
-            error!("Failed to subscribe to new blocks");
-            bail!("failed to subscribe to new blocks");
-        };
-
+    // Buffers new blocks for the trigger, recording them in the sender's health as
+    // soon as they arrive, even while the sender is busy
+    fn start_block_stream(
+        mut new_heads: broadcast::Receiver<NewHead>,
+        health: Arc<BuilderHealth>,
+    ) -> UnboundedReceiver<NewHead> {
         let (tx, rx) = mpsc::unbounded_channel();
         tokio::spawn(async move {
             loop {
-                match new_heads.next().await {
-                    Some(b) => {
+                match new_heads.recv().await {
+                    Ok(b) => {
+                        health.record_block(b.block_number);
                         if tx.send(b).is_err() {
                             error!("Failed to buffer new block for bundle sender");
                             return;
                         }
                     }
-                    None => {
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        // only the latest block is used, so skipped blocks can be ignored
+                        warn!("Bundle sender lagged behind the block stream, skipped {skipped} blocks");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
                         error!("Block stream ended");
                         return;
                     }
@@ -1068,7 +2722,7 @@ impl BundleSenderTrigger {
             }
         });
 
-        Ok(rx)
+        rx
     }
 
     fn consume_blocks(&mut self) -> anyhow::Result<()> {
@@ -1088,6 +2742,47 @@ impl BundleSenderTrigger {
             }
         }
     }
+
+    // Requests served by the bundle attempt triggered by the manual `request`
+    fn manual_requests(&mut self, request: SendBundleRequest) -> ManualRequests {
+        let mut requests = ManualRequests {
+            responders: vec![request.responder],
+            dry_run: request.dry_run,
+            priority_ops: request.priority_ops,
+        };
+        // a dry run doesn't send a bundle whose result could be shared
+        if self.coalesce_manual_requests && !requests.dry_run {
+            self.coalesce_pending_requests(&mut requests);
+        }
+        requests
+    }
+
+    // Collect the responders of any other manual send bundle requests already queued,
+    // so that they all receive the result of the next bundle. Stops at a dry run
+    // request, which is queued for the next trigger.
+    fn coalesce_pending_requests(&mut self, requests: &mut ManualRequests) {
+        while let Ok(action) = self.bundle_action_receiver.try_recv() {
+            match action {
+                BundleSenderAction::ChangeMode(mode) => {
+                    debug!("changing bundling mode to {mode:?}");
+                    self.bundling_mode = mode;
+                }
+                BundleSenderAction::SendBundle(r) => match self.bundling_mode {
+                    BundlingMode::Manual if r.dry_run => {
+                        self.queued_request = Some(r);
+                        return;
+                    }
+                    BundlingMode::Manual => {
+                        requests.responders.push(r.responder);
+                        requests.priority_ops.extend(r.priority_ops);
+                    }
+                    BundlingMode::Auto => {
+                        error!("Received bundle send action while in auto mode, ignoring");
+                    }
+                },
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1097,6 +2792,10 @@ struct BuilderMetrics {
 }
 
 impl BuilderMetrics {
+    fn increment_bundle_ops_skipped(&self, count: u64) {
+        metrics::counter!("builder_bundle_ops_skipped", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(count);
+    }
+
     fn increment_bundle_txns_sent(&self) {
         metrics::counter!("builder_bundle_txns_sent", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string())
             .increment(1);
@@ -1113,7 +2812,48 @@ impl BuilderMetrics {
         }
     }
 
-    fn increment_bundle_txns_dropped(&self) {
+    fn record_bundle_time_to_mine(&self, time_to_mine: Duration) {
+        metrics::histogram!("builder_bundle_time_to_mine_seconds", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).record(time_to_mine.as_secs_f64());
+    }
+
+    fn record_bundle_op_counts(&self, op_count: usize, rejected_op_count: usize) {
+        metrics::histogram!("builder_bundle_op_count", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).record(op_count as f64);
+        metrics::histogram!("builder_bundle_rejected_op_count", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).record(rejected_op_count as f64);
+    }
+
+    fn increment_bundle_over_gas_limit(&self) {
+        metrics::counter!("builder_bundle_over_gas_limit", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(1);
+    }
+
+    fn increment_bundle_ops_below_min_priority_fee(&self, count: u64) {
+        metrics::counter!("builder_bundle_ops_below_min_priority_fee", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(count);
+    }
+
+    fn increment_bundle_duplicate_ops(&self, count: u64) {
+        metrics::counter!("builder_bundle_duplicate_ops", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(count);
+    }
+
+    fn increment_bundle_ops_over_max_calldata(&self, count: u64) {
+        metrics::counter!("builder_bundle_ops_over_max_calldata", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(count);
+    }
+
+    fn increment_bundle_ops_over_entity_limit(&self, count: u64) {
+        metrics::counter!("builder_bundle_ops_over_entity_limit", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(count);
+    }
+
+    fn increment_bundle_entity_limit_reached(&self, entity: &Entity) {
+        metrics::counter!("builder_bundle_entity_limit_reached", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string(), "entity_type" => entity.kind.to_string(), "entity_address" => format!("{:?}", entity.address)).increment(1);
+    }
+
+    fn increment_bundle_truncated(&self) {
+        metrics::counter!("builder_bundle_truncated", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(1);
+    }
+
+    fn increment_bundle_attempts_paused(&self) {
+        metrics::counter!("builder_bundle_attempts_paused", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(1);
+    }
+
+    fn increment_bundle_txns_dropped(&self) {
         metrics::counter!("builder_bundle_txns_dropped", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(1);
     }
 
@@ -1131,6 +2871,14 @@ impl BuilderMetrics {
         metrics::counter!("builder_bundle_txns_nonce_used", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(1);
     }
 
+    fn increment_circuit_breaker_opened(&self) {
+        metrics::counter!("builder_circuit_breaker_opened", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(1);
+    }
+
+    fn increment_bundle_txns_reorged(&self) {
+        metrics::counter!("builder_bundle_txns_reorged", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(1);
+    }
+
     fn increment_bundle_txn_fee_increases(&self) {
         metrics::counter!("builder_bundle_fee_increases", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(1);
     }
@@ -1143,6 +2891,10 @@ impl BuilderMetrics {
         metrics::counter!("builder_bundle_nonce_too_low", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(1);
     }
 
+    fn increment_bundle_failed_validation(&self) {
+        metrics::counter!("builder_bundle_failed_validation", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(1);
+    }
+
     fn increment_bundle_txn_condition_not_met(&self) {
         metrics::counter!("builder_bundle_condition_not_met", "entry_point" => self.entry_point.to_string(), "builder_index" => self.builder_index.to_string()).increment(1);
     }
@@ -1178,11 +2930,19 @@ impl BuilderMetrics {
 
 #[cfg(test)]
 mod tests {
-    use ethers::types::Bytes;
+    use ethers::{
+        abi::{self, Token},
+        contract::EthEvent,
+        types::{Bytes, Eip1559TransactionRequest},
+    };
     use mockall::Sequence;
-    use rundler_provider::MockEntryPointV0_6;
+    use rundler_provider::{InMemoryEntryPoint, MockEntryPointV0_6};
     use rundler_types::{
-        chain::ChainSpec, pool::MockPool, v0_6::UserOperation, GasFees, UserOpsPerAggregator,
+        chain::ChainSpec,
+        contracts::v0_6::i_entry_point::UserOperationEventFilter,
+        pool::{MockPool, PoolOperation},
+        v0_6::UserOperation,
+        Entity, EntityUpdateReason, EntityUpdateType, GasFees, UserOpsPerAggregator,
     };
     use tokio::sync::{broadcast, mpsc};
 
@@ -1190,6 +2950,7 @@ mod tests {
     use crate::{
         bundle_proposer::{Bundle, MockBundleProposer},
         bundle_sender::{BundleSenderImpl, MockTrigger},
+        emit::{BuilderEventKind, ConditionNotMetReason},
         transaction_tracker::MockTransactionTracker,
     };
 
@@ -1202,13 +2963,16 @@ mod tests {
             mut mock_trigger,
         } = new_mocks();
 
-        // block 0
-        add_trigger_no_update_last_block(
+        // manual request on block 0
+        let mut receiver = add_manual_trigger_no_update(
             &mut mock_trigger,
             &mut mock_tracker,
             &mut Sequence::new(),
-            0,
         );
+        mock_trigger.expect_last_block().return_const(NewHead {
+            block_number: 0,
+            block_hash: H256::zero(),
+        });
 
         // zero nonce
         mock_tracker
@@ -1219,7 +2983,7 @@ mod tests {
         mock_proposer
             .expect_make_bundle()
             .times(1)
-            .returning(|_, _| Box::pin(async { Ok(Bundle::<UserOperation>::default()) }));
+            .returning(|_, _, _| Box::pin(async { Ok(Bundle::<UserOperation>::default()) }));
 
         let mut sender = new_sender(mock_proposer, mock_entry_point);
 
@@ -1236,6 +3000,60 @@ mod tests {
                 ..
             })
         ));
+        // no ops were rejected, so they were all deferred
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(SendBundleResult::NoOperationsInitially {
+                reason: EmptyReason::Deferred
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_empty_send_below_fee_threshold() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        // manual request on block 0
+        let mut receiver = add_manual_trigger_no_update(
+            &mut mock_trigger,
+            &mut mock_tracker,
+            &mut Sequence::new(),
+        );
+        mock_trigger.expect_last_block().return_const(NewHead {
+            block_number: 0,
+            block_hash: H256::zero(),
+        });
+
+        // zero nonce
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+
+        // no ops pay the required fees
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _, _| {
+                Box::pin(async { Err(BundleProposerError::NoOperationsAfterFeeFilter) })
+            });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+
+        sender.step_state(&mut state).await.unwrap();
+
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(SendBundleResult::NoOperationsInitially {
+                reason: EmptyReason::BelowFeeThreshold
+            })
+        ));
     }
 
     #[tokio::test]
@@ -1264,7 +3082,7 @@ mod tests {
         mock_proposer
             .expect_make_bundle()
             .times(1)
-            .returning(|_, _| Box::pin(async { Ok(bundle()) }));
+            .returning(|_, _, _| Box::pin(async { Ok(bundle()) }));
 
         // should create the bundle txn
         mock_entry_point
@@ -1294,356 +3112,3045 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_wait_for_mine_success() {
+    async fn test_failed_validation_before_send() {
         let Mocks {
-            mock_proposer,
-            mock_entry_point,
+            mut mock_proposer,
+            mut mock_entry_point,
             mut mock_tracker,
             mut mock_trigger,
         } = new_mocks();
 
-        let mut seq = Sequence::new();
-        add_trigger_wait_for_block_last_block(&mut mock_trigger, &mut seq, 1);
-        mock_trigger
-            .expect_wait_for_block()
-            .once()
-            .in_sequence(&mut seq)
-            .returning(|| {
-                Box::pin(async {
-                    Ok(NewHead {
-                        block_number: 2,
-                        block_hash: H256::zero(),
-                    })
-                })
-            });
-        // no call to last_block after mine
+        // block 0
+        add_trigger_no_update_last_block(
+            &mut mock_trigger,
+            &mut mock_tracker,
+            &mut Sequence::new(),
+            0,
+        );
 
-        let mut seq = Sequence::new();
-        mock_tracker
-            .expect_check_for_update()
-            .once()
-            .in_sequence(&mut seq)
-            .returning(|| Box::pin(async { Ok(None) }));
+        // zero nonce
         mock_tracker
-            .expect_check_for_update()
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+
+        // bundle with one op
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(bundle()) }));
+
+        // the op became invalid since the bundle was proposed
+        mock_entry_point
+            .expect_call_handle_ops()
             .once()
-            .in_sequence(&mut seq)
-            .returning(|| {
+            .returning(|_, _, _| {
                 Box::pin(async {
-                    Ok(Some(TrackerUpdate::Mined {
-                        block_number: 2,
-                        nonce: U256::zero(),
-                        gas_limit: None,
-                        gas_used: None,
-                        gas_price: None,
-                        tx_hash: H256::zero(),
-                        attempt_number: 0,
-                    }))
+                    Ok(HandleOpsOut::FailedOp(
+                        0,
+                        "AA25 invalid account nonce".into(),
+                    ))
                 })
             });
 
-        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        // the op is removed from the pool, and no transaction is sent
+        let mut mock_pool = MockPool::new();
+        mock_pool
+            .expect_remove_ops()
+            .withf(|_, ops| ops.len() == 1)
+            .once()
+            .returning(|_, _| Ok(()));
 
-        // start in pending state
-        let mut state = SenderMachineState {
-            trigger: mock_trigger,
-            transaction_tracker: mock_tracker,
-            send_bundle_response: None,
-            inner: InnerState::Pending(PendingState {
-                until: 3,
-                fee_increase_count: 0,
-            }),
-            requires_reset: false,
-        };
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        sender.settings.validate_bundle_before_send = true;
 
-        // first step has no update
-        sender.step_state(&mut state).await.unwrap();
-        assert!(matches!(
-            state.inner,
-            InnerState::Pending(PendingState { until: 3, .. })
-        ));
+        // start in building state
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
 
-        // second step is mined and moves back to building
         sender.step_state(&mut state).await.unwrap();
+
+        // the bundle is re-proposed without waiting for a trigger
         assert!(matches!(
             state.inner,
             InnerState::Building(BuildingState {
-                wait_for_trigger: true,
+                wait_for_trigger: false,
                 fee_increase_count: 0,
-                underpriced_info: None,
+                ..
             })
         ));
     }
 
     #[tokio::test]
-    async fn test_wait_for_mine_timed_out() {
+    async fn test_send_legacy_gas() {
         let Mocks {
-            mock_proposer,
-            mock_entry_point,
+            mut mock_proposer,
+            mut mock_entry_point,
             mut mock_tracker,
             mut mock_trigger,
         } = new_mocks();
 
-        let mut seq = Sequence::new();
-        for i in 1..=3 {
-            add_trigger_wait_for_block_last_block(&mut mock_trigger, &mut seq, i);
-        }
+        add_trigger_no_update_last_block(
+            &mut mock_trigger,
+            &mut mock_tracker,
+            &mut Sequence::new(),
+            0,
+        );
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(bundle()) }));
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|_, _, gas, _| {
+                Eip1559TransactionRequest::new()
+                    .gas(gas)
+                    .max_fee_per_gas(100)
+                    .max_priority_fee_per_gas(10)
+                    .into()
+            });
 
+        // should send a legacy transaction paying the max fee as its gas price
         mock_tracker
-            .expect_check_for_update()
-            .times(3)
-            .returning(|| Box::pin(async { Ok(None) }));
+            .expect_send_transaction()
+            .once()
+            .withf(|tx, _| {
+                matches!(tx, TypedTransaction::Legacy(_))
+                    && tx.gas_price() == Some(U256::from(100))
+                    && tx.gas() == Some(&U256::from(100_000))
+            })
+            .returning(|_, _| Box::pin(async { Ok(H256::zero()) }));
 
         let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.use_legacy_gas = true;
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
 
-        // start in pending state
-        let mut state = SenderMachineState {
-            trigger: mock_trigger,
-            transaction_tracker: mock_tracker,
-            send_bundle_response: None,
-            inner: InnerState::Pending(PendingState {
-                until: 3,
-                fee_increase_count: 0,
-            }),
-            requires_reset: false,
-        };
-
-        // first and second step has no update
-        for _ in 0..2 {
-            sender.step_state(&mut state).await.unwrap();
-            assert!(matches!(
-                state.inner,
-                InnerState::Pending(PendingState { until: 3, .. })
-            ));
-        }
-
-        // third step times out and moves back to building with a fee increase
         sender.step_state(&mut state).await.unwrap();
-        assert!(matches!(
-            state.inner,
-            InnerState::Building(BuildingState {
-                wait_for_trigger: false,
-                fee_increase_count: 1,
-                underpriced_info: None,
-            })
-        ));
+        assert!(matches!(state.inner, InnerState::Pending(..)));
     }
 
     #[tokio::test]
-    async fn test_transition_to_cancel() {
+    async fn test_min_bundle_interval() {
         let Mocks {
             mut mock_proposer,
-            mock_entry_point,
+            mut mock_entry_point,
             mut mock_tracker,
             mut mock_trigger,
         } = new_mocks();
 
+        // block 0 and block 1
         let mut seq = Sequence::new();
-        add_trigger_no_update_last_block(&mut mock_trigger, &mut mock_tracker, &mut seq, 3);
+        add_trigger_no_update_last_block(&mut mock_trigger, &mut mock_tracker, &mut seq, 0);
+        add_trigger_no_update_last_block(&mut mock_trigger, &mut mock_tracker, &mut seq, 1);
 
         // zero nonce
         mock_tracker
             .expect_get_nonce_and_required_fees()
             .returning(|| Ok((U256::zero(), None)));
 
-        // fee filter error
+        // bundle with one op
         mock_proposer
             .expect_make_bundle()
-            .times(1)
-            .returning(|_, _| {
-                Box::pin(async { Err(BundleProposerError::NoOperationsAfterFeeFilter) })
+            .times(2)
+            .returning(|_, _, _| Box::pin(async { Ok(bundle()) }));
+
+        // should create the bundle txn
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|_, _, _, _| TypedTransaction::default());
+
+        // should send the bundle txn, record the send times
+        let sent_at = Arc::new(std::sync::Mutex::new(vec![]));
+        let sent_at_clone = Arc::clone(&sent_at);
+        mock_tracker
+            .expect_send_transaction()
+            .times(2)
+            .returning(move |_, _| {
+                sent_at_clone.lock().unwrap().push(Instant::now());
+                Box::pin(async { Ok(H256::zero()) })
             });
 
+        let min_bundle_interval = Duration::from_millis(100);
         let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.min_bundle_interval = min_bundle_interval;
 
-        // start in underpriced meta-state
-        let mut state = SenderMachineState {
-            trigger: mock_trigger,
-            transaction_tracker: mock_tracker,
-            send_bundle_response: None,
-            inner: InnerState::Building(BuildingState {
-                wait_for_trigger: true,
-                fee_increase_count: 0,
-                underpriced_info: Some(UnderpricedInfo {
-                    since_block: 0,
-                    rounds: 1,
-                }),
-            }),
-            requires_reset: false,
-        };
+        // start in building state
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
 
-        // step state, block number should trigger move to cancellation
+        // first bundle is sent immediately
         sender.step_state(&mut state).await.unwrap();
-        assert!(matches!(
-            state.inner,
-            InnerState::Cancelling(CancellingState {
+        assert!(matches!(state.inner, InnerState::Pending(..)));
+
+        // bundle mined, build the next bundle on the next block
+        state.complete(None);
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(state.inner, InnerState::Pending(..)));
+
+        // bundles are spaced by at least the minimum interval
+        let sent_at = sent_at.lock().unwrap();
+        assert_eq!(sent_at.len(), 2);
+        assert!(sent_at[1] - sent_at[0] >= min_bundle_interval);
+    }
+
+    #[tokio::test]
+    async fn test_entry_point_paused() {
+        let Mocks {
+            mut mock_proposer,
+            mut mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        // two manual requests
+        let mut seq = Sequence::new();
+        let receivers = (0..2)
+            .map(|_| add_manual_trigger_no_update(&mut mock_trigger, &mut mock_tracker, &mut seq))
+            .collect::<Vec<_>>();
+
+        // checked once, the result is reused within the check interval
+        mock_entry_point
+            .expect_is_paused()
+            .times(1)
+            .returning(|_| Ok(true));
+
+        // no bundle is built while paused
+        mock_proposer.expect_make_bundle().times(0);
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.pause_guardian = Some(Address::random());
+        sender.settings.pause_check_interval = Duration::from_secs(3600);
+
+        // start in building state
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+        for mut receiver in receivers {
+            sender.step_state(&mut state).await.unwrap();
+
+            assert!(matches!(
+                state.inner,
+                InnerState::Building(BuildingState {
+                    wait_for_trigger: true,
+                    fee_increase_count: 0,
+                    ..
+                })
+            ));
+            assert!(matches!(
+                receiver.try_recv(),
+                Ok(SendBundleResult::EntryPointPaused)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        // three manual requests
+        let mut seq = Sequence::new();
+        let mut receivers = (0..3)
+            .map(|_| add_manual_trigger_no_update(&mut mock_trigger, &mut mock_tracker, &mut seq))
+            .collect::<Vec<_>>();
+        let mut open_receiver = receivers.pop().unwrap();
+        mock_trigger.expect_last_block().return_const(NewHead {
+            block_number: 0,
+            block_hash: H256::zero(),
+        });
+
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+
+        // no bundle is attempted once the circuit opens after the second failure
+        mock_proposer
+            .expect_make_bundle()
+            .times(2)
+            .returning(|_, _, _| {
+                Box::pin(async { Err(BundleProposerError::Other(anyhow::anyhow!("rpc down"))) })
+            });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.consecutive_failure_threshold = Some(2);
+        let mut events = sender.event_sender.subscribe();
+
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+        for mut receiver in receivers {
+            sender.step_state(&mut state).await.unwrap();
+            assert!(matches!(
+                receiver.try_recv(),
+                Ok(SendBundleResult::Error(_))
+            ));
+        }
+
+        let event = events.try_recv().unwrap().event;
+        assert!(matches!(
+            event.kind,
+            BuilderEventKind::CircuitOpen {
+                consecutive_failures: 2,
+                backoff: CIRCUIT_BREAKER_BASE_BACKOFF,
+            }
+        ));
+
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            open_receiver.try_recv(),
+            Ok(SendBundleResult::CircuitOpen { .. })
+        ));
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: true,
+                fee_increase_count: 0,
+                ..
+            })
+        ));
+
+        // the first attempt completing without error closes the circuit
+        sender.close_circuit();
+        assert_eq!(sender.consecutive_failures, 0);
+        assert!(sender.circuit_open_until.is_none());
+    }
+
+    #[test]
+    fn test_circuit_backoff() {
+        let max_backoff = Duration::from_secs(60);
+        let backoffs = (0..6)
+            .map(|failures| {
+                exponential_backoff(CIRCUIT_BREAKER_BASE_BACKOFF, failures, max_backoff).as_secs()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(backoffs, vec![5, 10, 20, 40, 60, 60]);
+        assert_eq!(
+            exponential_backoff(CIRCUIT_BREAKER_BASE_BACKOFF, u64::MAX, max_backoff),
+            max_backoff
+        );
+    }
+
+    #[tokio::test]
+    async fn test_idle_backoff() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        // automatic triggers on block 0
+        mock_trigger
+            .expect_wait_for_trigger()
+            .returning(|| Box::pin(async { Ok(ManualRequests::default()) }));
+        mock_trigger.expect_last_block().return_const(NewHead {
+            block_number: 0,
+            block_hash: H256::zero(),
+        });
+        mock_tracker
+            .expect_check_for_update()
+            .returning(|| Box::pin(async { Ok(None) }));
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+
+        // the pool is empty on each attempt, the fourth trigger is skipped
+        mock_proposer
+            .expect_make_bundle()
+            .times(4)
+            .returning(|_, _, _| {
+                Box::pin(async { Err(BundleProposerError::NoOperationsInitially) })
+            });
+
+        // checked while backing off, empty then with an op
+        let mut seq = Sequence::new();
+        let mut mock_pool = MockPool::new();
+        mock_pool
+            .expect_get_ops()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_, _, _| Ok(vec![]));
+        mock_pool
+            .expect_get_ops()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_, _, _| {
+                Ok(vec![PoolOperation {
+                    uo: UserOperation::default().into(),
+                    entry_point: Address::zero(),
+                    aggregator: None,
+                    valid_time_range: Default::default(),
+                    expected_code_hash: H256::zero(),
+                    sim_block_hash: H256::zero(),
+                    sim_block_number: 0,
+                    account_is_staked: false,
+                    entity_infos: Default::default(),
+                }])
+            });
+
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        sender.settings.idle_backoff_max = Duration::from_secs(60);
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+
+        for _ in 0..IDLE_BACKOFF_THRESHOLD {
+            assert!(sender.idle_until.is_none());
+            sender.step_state(&mut state).await.unwrap();
+        }
+        assert!(sender.idle_until.is_some());
+
+        // skipped while the pool stays empty
+        sender.step_state(&mut state).await.unwrap();
+        assert_eq!(sender.consecutive_empty_attempts, IDLE_BACKOFF_THRESHOLD);
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: true,
+                ..
+            })
+        ));
+
+        // an op in the pool ends the backoff right away
+        sender.step_state(&mut state).await.unwrap();
+        assert!(sender.idle_until.is_none());
+        assert_eq!(sender.consecutive_empty_attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_when_idle() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        // no bundle is triggered, the queued manual request is answered with an error
+        let (responder, response) = oneshot::channel();
+        mock_trigger
+            .expect_close()
+            .once()
+            .return_once(move || vec![responder]);
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.shutdown_token.cancel();
+
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+        sender.step_state(&mut state).await.unwrap();
+        assert!(state.is_idle());
+
+        state.shutdown();
+        assert!(matches!(
+            response.await.unwrap(),
+            SendBundleResult::Error(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_pending() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        mock_trigger.expect_wait_for_block().once().returning(|| {
+            Box::pin(async {
+                Ok(NewHead {
+                    block_number: 2,
+                    block_hash: H256::zero(),
+                })
+            })
+        });
+        mock_tracker.expect_check_for_update().once().returning(|| {
+            Box::pin(async {
+                Ok(Some(TrackerUpdate::Mined {
+                    block_number: 2,
+                    nonce: U256::zero(),
+                    gas_limit: None,
+                    gas_used: None,
+                    gas_price: None,
+                    tx_hash: H256::zero(),
+                    attempt_number: 0,
+                    is_cancellation: false,
+                    logs: None,
+                }))
+            })
+        });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.shutdown_token.cancel();
+
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::Pending(PendingState {
+                until: 3,
+                fee_increase_count: 0,
+                pending_since: Instant::now(),
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+        assert!(!state.is_idle());
+
+        // the pending transaction is still tracked until it mines
+        sender.step_state(&mut state).await.unwrap();
+        assert!(state.is_idle());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_mine_success() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        let mut seq = Sequence::new();
+        add_trigger_wait_for_block_last_block(&mut mock_trigger, &mut seq, 1);
+        mock_trigger
+            .expect_wait_for_block()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|| {
+                Box::pin(async {
+                    Ok(NewHead {
+                        block_number: 2,
+                        block_hash: H256::zero(),
+                    })
+                })
+            });
+        // no call to last_block after mine
+
+        let mut seq = Sequence::new();
+        mock_tracker
+            .expect_check_for_update()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|| Box::pin(async { Ok(None) }));
+        mock_tracker
+            .expect_check_for_update()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|| {
+                Box::pin(async {
+                    Ok(Some(TrackerUpdate::Mined {
+                        block_number: 2,
+                        nonce: U256::zero(),
+                        gas_limit: None,
+                        gas_used: None,
+                        gas_price: None,
+                        tx_hash: H256::zero(),
+                        attempt_number: 0,
+                        is_cancellation: false,
+                        logs: None,
+                    }))
+                })
+            });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+
+        // start in pending state
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::Pending(PendingState {
+                until: 3,
                 fee_increase_count: 0,
+                pending_since: Instant::now(),
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        // first step has no update
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Pending(PendingState { until: 3, .. })
+        ));
+
+        // second step is mined and moves back to building
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: true,
+                fee_increase_count: 0,
+                underpriced_info: None,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_bundle() {
+        let Mocks {
+            mut mock_proposer,
+            mut mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        mock_trigger.expect_wait_for_block().times(2).returning(|| {
+            Box::pin(async {
+                Ok(NewHead {
+                    block_number: 1,
+                    block_hash: H256::zero(),
+                })
+            })
+        });
+        mock_trigger.expect_last_block().return_const(NewHead {
+            block_number: 1,
+            block_hash: H256::zero(),
+        });
+
+        let head_tx_hash = H256::zero();
+        let pipelined_tx_hash = H256::from_low_u64_be(1);
+        let mut seq = Sequence::new();
+        mock_tracker
+            .expect_check_for_update()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|| Box::pin(async { Ok(None) }));
+        mock_tracker
+            .expect_check_for_update()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(move || {
+                Box::pin(async move {
+                    Ok(Some(TrackerUpdate::Mined {
+                        block_number: 1,
+                        nonce: U256::zero(),
+                        gas_limit: None,
+                        gas_used: None,
+                        gas_price: None,
+                        tx_hash: head_tx_hash,
+                        attempt_number: 0,
+                        is_cancellation: false,
+                        logs: None,
+                    }))
+                })
+            });
+        // the head bundle is in flight, then the pipelined bundle once promoted
+        mock_tracker.expect_inflight_count().return_const(1_usize);
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+        mock_tracker
+            .expect_pipelined_transactions()
+            .returning(Vec::new);
+
+        // bundle with the op in flight in the head bundle and a new op
+        let head_op = UserOperation::default();
+        let new_op = UserOperation {
+            sender: Address::from_low_u64_be(1),
+            ..Default::default()
+        };
+        let mut pipelined_bundle = bundle();
+        pipelined_bundle.ops_per_aggregator[0].user_ops = vec![head_op.clone(), new_op.clone()];
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .return_once(|_, _, _| Box::pin(async { Ok(pipelined_bundle) }));
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|_, _, _, _| TypedTransaction::default());
+
+        // sent at the nonce after the head bundle
+        mock_tracker
+            .expect_send_pipelined_transaction()
+            .times(1)
+            .returning(move |_, _| Box::pin(async move { Ok(pipelined_tx_hash) }));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.max_inflight_bundles = 2;
+        let head_op_hash = sender.op_hash(&head_op);
+        let new_op_hash = sender.op_hash(&new_op);
+        sender
+            .bundle_op_hashes
+            .insert(head_tx_hash, vec![head_op_hash]);
+
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::Pending(PendingState {
+                until: 3,
+                fee_increase_count: 1,
+                pending_since: Instant::now(),
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        // the op already in flight is left out of the pipelined bundle
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Pending(PendingState { until: 3, .. })
+        ));
+        assert_eq!(
+            sender.pipelined_op_hashes,
+            VecDeque::from([(pipelined_tx_hash, vec![new_op_hash])])
+        );
+
+        // the head bundle mines, the pipelined bundle becomes the pending one
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Pending(PendingState {
+                until: 4,
+                fee_increase_count: 0,
+                ..
+            })
+        ));
+        assert!(sender.pipelined_op_hashes.is_empty());
+        assert_eq!(
+            sender.bundle_op_hashes,
+            HashMap::from([(pipelined_tx_hash, vec![new_op_hash])])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_bundle_skips_inflight_entities() {
+        let Mocks {
+            mut mock_proposer,
+            mut mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        mock_trigger.expect_wait_for_block().once().returning(|| {
+            Box::pin(async {
+                Ok(NewHead {
+                    block_number: 1,
+                    block_hash: H256::zero(),
+                })
+            })
+        });
+        mock_trigger.expect_last_block().return_const(NewHead {
+            block_number: 1,
+            block_hash: H256::zero(),
+        });
+        mock_tracker
+            .expect_check_for_update()
+            .once()
+            .returning(|| Box::pin(async { Ok(None) }));
+        mock_tracker.expect_inflight_count().return_const(1_usize);
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+        mock_tracker
+            .expect_pipelined_transactions()
+            .returning(Vec::new);
+
+        // ops failing simulation against the latest block are left in the pool
+        let mut mock_pool = MockPool::new();
+        mock_pool.expect_remove_ops().never();
+        mock_pool.expect_update_entities().never();
+
+        let head_tx_hash = H256::zero();
+        let inflight_sender = Address::from_low_u64_be(1);
+        let inflight_paymaster = Address::from_low_u64_be(2);
+        let sender_op = UserOperation {
+            sender: inflight_sender,
+            nonce: U256::one(),
+            ..Default::default()
+        };
+        let paymaster_op = UserOperation {
+            sender: Address::from_low_u64_be(3),
+            paymaster_and_data: inflight_paymaster.as_bytes().to_vec().into(),
+            ..Default::default()
+        };
+        let new_op = UserOperation {
+            sender: Address::from_low_u64_be(4),
+            ..Default::default()
+        };
+        let rejected_op = UserOperation {
+            sender: inflight_sender,
+            nonce: U256::from(2),
+            ..Default::default()
+        };
+        let mut pipelined_bundle = bundle();
+        pipelined_bundle.ops_per_aggregator[0].user_ops =
+            vec![sender_op, paymaster_op, new_op.clone()];
+        pipelined_bundle.rejected_ops = vec![rejected_op];
+        pipelined_bundle.entity_updates = vec![EntityUpdate {
+            entity: Entity::paymaster(inflight_paymaster),
+            update_type: EntityUpdateType::UnstakedInvalidation,
+            reason: EntityUpdateReason::InsufficientDeposit,
+        }];
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .return_once(|_, _, _| Box::pin(async { Ok(pipelined_bundle) }));
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .once()
+            .returning(|ops_per_aggregator, _, _, _| {
+                assert_eq!(ops_per_aggregator[0].user_ops.len(), 1);
+                TypedTransaction::default()
+            });
+
+        let pipelined_tx_hash = H256::from_low_u64_be(1);
+        mock_tracker
+            .expect_send_pipelined_transaction()
+            .times(1)
+            .returning(move |_, _| Box::pin(async move { Ok(pipelined_tx_hash) }));
+
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        sender.settings.max_inflight_bundles = 2;
+        let new_op_hash = sender.op_hash(&new_op);
+        sender.bundle_op_hashes.insert(head_tx_hash, vec![]);
+        sender
+            .inflight_entities
+            .insert(head_tx_hash, vec![inflight_sender, inflight_paymaster]);
+
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::Pending(PendingState {
+                until: 3,
+                fee_increase_count: 0,
+                pending_since: Instant::now(),
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        // the ops sharing a sender or paymaster with the in-flight bundle are left out
+        sender.step_state(&mut state).await.unwrap();
+        assert_eq!(
+            sender.pipelined_op_hashes,
+            VecDeque::from([(pipelined_tx_hash, vec![new_op_hash])])
+        );
+        assert_eq!(
+            sender.inflight_entities[&pipelined_tx_hash],
+            [new_op.sender]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_mine_timed_out() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        let mut seq = Sequence::new();
+        for i in 1..=3 {
+            add_trigger_wait_for_block_last_block(&mut mock_trigger, &mut seq, i);
+        }
+
+        mock_tracker
+            .expect_check_for_update()
+            .times(3)
+            .returning(|| Box::pin(async { Ok(None) }));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+
+        // start in pending state
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::Pending(PendingState {
+                until: 3,
+                fee_increase_count: 0,
+                pending_since: Instant::now(),
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        // first and second step has no update
+        for _ in 0..2 {
+            sender.step_state(&mut state).await.unwrap();
+            assert!(matches!(
+                state.inner,
+                InnerState::Pending(PendingState { until: 3, .. })
+            ));
+        }
+
+        // third step times out and moves back to building with a fee increase
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: false,
+                fee_increase_count: 1,
+                underpriced_info: None,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_max_blocks_to_wait_for_mine() {
+        let Mocks {
+            mut mock_proposer,
+            mut mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        // bundle sent on block 10, then blocks 11 to 15 without a mined transaction
+        let mut seq = Sequence::new();
+        add_trigger_no_update_last_block(&mut mock_trigger, &mut mock_tracker, &mut seq, 10);
+        for i in 11..=15 {
+            add_trigger_wait_for_block_last_block(&mut mock_trigger, &mut seq, i);
+        }
+
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(bundle()) }));
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|_, _, _, _| TypedTransaction::default());
+        mock_tracker
+            .expect_send_transaction()
+            .times(1)
+            .returning(|_, _| Box::pin(async { Ok(H256::zero()) }));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.max_blocks_to_wait_for_mine = 5;
+
+        // start in building state
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+
+        // the sent bundle waits until block 15 to be mined
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Pending(PendingState {
+                until: 15,
+                fee_increase_count: 0,
+                ..
+            })
+        ));
+        for _ in 11..15 {
+            sender.step_state(&mut state).await.unwrap();
+            assert!(matches!(state.inner, InnerState::Pending(..)));
+        }
+
+        // not mined after 5 blocks, increases fees
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: false,
+                fee_increase_count: 1,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_max_pending_time_cancels() {
+        let Mocks {
+            mut mock_proposer,
+            mut mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        mock_trigger.expect_wait_for_block().times(2).returning(|| {
+            Box::pin(async {
+                Ok(NewHead {
+                    block_number: 1,
+                    block_hash: H256::zero(),
+                })
+            })
+        });
+        mock_trigger.expect_last_block().return_const(NewHead {
+            block_number: 1,
+            block_hash: H256::zero(),
+        });
+
+        // not mined before the deadline, then the cancellation mines
+        let mut seq = Sequence::new();
+        mock_tracker
+            .expect_check_for_update()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|| Box::pin(async { Ok(None) }));
+        mock_tracker
+            .expect_check_for_update()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|| {
+                Box::pin(async {
+                    Ok(Some(TrackerUpdate::Mined {
+                        block_number: 1,
+                        nonce: U256::zero(),
+                        gas_limit: None,
+                        gas_used: None,
+                        gas_price: None,
+                        tx_hash: H256::zero(),
+                        attempt_number: 0,
+                        is_cancellation: true,
+                        logs: None,
+                    }))
+                })
+            });
+        mock_proposer
+            .expect_estimate_gas_fees()
+            .once()
+            .returning(|_| Box::pin(async { Ok((GasFees::default(), U256::zero())) }));
+        mock_tracker
+            .expect_cancel_transaction()
+            .once()
+            .returning(|_, _| Box::pin(async { Ok(Some(H256::zero())) }));
+        mock_tracker
+            .expect_reset()
+            .once()
+            .returning(|| Box::pin(async {}));
+
+        // the fresh bundle is sent at the nonce freed by the cancellation
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .once()
+            .returning(|| Ok((U256::one(), None)));
+        mock_proposer
+            .expect_make_bundle()
+            .once()
+            .returning(|_, _, _| Box::pin(async { Ok(bundle()) }));
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|_, _, _, _| TypedTransaction::default());
+        mock_tracker
+            .expect_send_transaction()
+            .once()
+            .returning(|_, _| Box::pin(async { Ok(H256::zero()) }));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.max_pending_time = Some(Duration::ZERO);
+
+        // start in pending state, well before the block limit
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::Pending(PendingState {
+                until: 10,
+                fee_increase_count: 2,
+                pending_since: Instant::now(),
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        // the deadline is reached, cancel regardless of the fee increases
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Cancelling(CancellingState {
+                fee_increase_count: 0,
+                ..
+            })
+        ));
+
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(state.inner, InnerState::CancelPending(..)));
+
+        // the cancellation mines, freeing the nonce
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: false,
+                fee_increase_count: 0,
+                ..
+            })
+        ));
+
+        // a fresh bundle is sent
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Pending(PendingState {
+                until: 4,
+                fee_increase_count: 0,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_transition_to_cancel() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        let mut seq = Sequence::new();
+        add_trigger_no_update_last_block(&mut mock_trigger, &mut mock_tracker, &mut seq, 3);
+
+        // zero nonce
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+
+        // fee filter error
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _, _| {
+                Box::pin(async { Err(BundleProposerError::NoOperationsAfterFeeFilter) })
+            });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+
+        // start in underpriced meta-state
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::Building(BuildingState {
+                wait_for_trigger: true,
+                fee_increase_count: 0,
+                underpriced_info: Some(UnderpricedInfo {
+                    since_block: 0,
+                    rounds: 1,
+                }),
+                pending_since: None,
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        // step state, block number should trigger move to cancellation
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Cancelling(CancellingState {
+                fee_increase_count: 0,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_cancel() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        mock_proposer
+            .expect_estimate_gas_fees()
+            .once()
+            .returning(|_| Box::pin(async { Ok((GasFees::default(), U256::zero())) }));
+
+        mock_tracker
+            .expect_cancel_transaction()
+            .once()
+            .returning(|_, _| Box::pin(async { Ok(Some(H256::zero())) }));
+
+        mock_trigger.expect_last_block().return_const(NewHead {
+            block_number: 0,
+            block_hash: H256::zero(),
+        });
+
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::Cancelling(CancellingState {
+                fee_increase_count: 0,
+                pending_since: None,
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::CancelPending(CancelPendingState {
+                until: 3,
+                fee_increase_count: 0,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resubmit_cancel() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        let mut seq = Sequence::new();
+        for i in 1..=3 {
+            add_trigger_wait_for_block_last_block(&mut mock_trigger, &mut seq, i);
+        }
+
+        mock_tracker
+            .expect_check_for_update()
+            .times(3)
+            .returning(|| Box::pin(async { Ok(None) }));
+
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::CancelPending(CancelPendingState {
+                until: 3,
+                fee_increase_count: 0,
+                pending_since: None,
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+
+        for _ in 0..2 {
+            sender.step_state(&mut state).await.unwrap();
+            assert!(matches!(
+                state.inner,
+                InnerState::CancelPending(CancelPendingState {
+                    until: 3,
+                    fee_increase_count: 0,
+                    ..
+                })
+            ));
+        }
+
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Cancelling(CancellingState {
+                fee_increase_count: 1,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_condition_not_met() {
+        let Mocks {
+            mut mock_proposer,
+            mut mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        let mut seq = Sequence::new();
+        add_trigger_no_update_last_block(&mut mock_trigger, &mut mock_tracker, &mut seq, 1);
+
+        // zero nonce
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+
+        // bundle with one op
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(bundle()) }));
+
+        // should create the bundle txn
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|_, _, _, _| TypedTransaction::default());
+
+        // should send the bundle txn, returns condition not met
+        mock_tracker
+            .expect_send_transaction()
+            .returning(|_, _| Box::pin(async { Err(TransactionTrackerError::ConditionNotMet) }));
+
+        // should find the slot that differed
+        mock_proposer
+            .expect_find_unmet_condition()
+            .times(1)
+            .returning(|_| {
+                Box::pin(async {
+                    Some(ConditionNotMetReason {
+                        address: Address::random(),
+                        slot: H256::zero(),
+                        expected: H256::zero(),
+                        actual: H256::random(),
+                    })
+                })
+            });
+
+        // should notify proposer that condition was not met
+        mock_proposer
+            .expect_notify_condition_not_met()
+            .times(1)
+            .return_const(());
+
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::Building(BuildingState {
+                wait_for_trigger: true,
+                fee_increase_count: 0,
+                underpriced_info: None,
+                pending_since: None,
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+
+        sender.step_state(&mut state).await.unwrap();
+
+        // end back in the building state without waiting for trigger
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: false,
+                fee_increase_count: 0,
+                underpriced_info: None,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_abandon_sends_cancel() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        mock_trigger.expect_last_block().return_const(NewHead {
+            block_number: 4,
+            block_hash: H256::zero(),
+        });
+
+        // zero nonce
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+
+        // no ops left after the fee increase
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _, _| {
+                Box::pin(async { Err(BundleProposerError::NoOperationsAfterFeeFilter) })
+            });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+
+        // start in building state after a fee increase
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::Building(BuildingState {
+                wait_for_trigger: false,
+                fee_increase_count: 1,
+                underpriced_info: None,
+                pending_since: None,
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        // abandoning should race a cancellation against the pending transaction
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Cancelling(CancellingState {
+                fee_increase_count: 0,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_abandon_without_cancel_on_stall() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        mock_trigger.expect_last_block().return_const(NewHead {
+            block_number: 4,
+            block_hash: H256::zero(),
+        });
+
+        // zero nonce
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+
+        // the pending transaction is left in place
+        mock_tracker.expect_abandon().times(1).returning(|| ());
+
+        // no ops left after the fee increase
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _, _| {
+                Box::pin(async { Err(BundleProposerError::NoOperationsAfterFeeFilter) })
+            });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.cancel_on_stall = false;
+
+        // start in building state after a fee increase
+        let (responder, mut receiver) = oneshot::channel();
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![responder],
+            inner: InnerState::Building(BuildingState {
+                wait_for_trigger: false,
+                fee_increase_count: 1,
+                underpriced_info: None,
+                pending_since: None,
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: true,
+                fee_increase_count: 0,
+                ..
+            })
+        ));
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(SendBundleResult::StalledAtMaxFeeIncreases)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_race_bundle_mined() {
+        let (mut state, mut sender) = cancel_pending_with_mined(false);
+        let (responder, mut receiver) = oneshot::channel();
+        state.send_bundle_responses = vec![responder];
+
+        sender.step_state(&mut state).await.unwrap();
+
+        // the bundle won the race, the bundle attempt completes successfully
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: true,
+                fee_increase_count: 0,
+                underpriced_info: None,
+                ..
+            })
+        ));
+        assert!(!state.requires_reset);
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(SendBundleResult::Success { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_race_cancellation_mined() {
+        let (mut state, mut sender) = cancel_pending_with_mined(true);
+        let (responder, mut receiver) = oneshot::channel();
+        state.send_bundle_responses = vec![responder];
+
+        sender.step_state(&mut state).await.unwrap();
+
+        // the cancellation won the race, start over without waiting for a trigger
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: false,
+                fee_increase_count: 0,
+                underpriced_info: None,
+                ..
+            })
+        ));
+        assert!(state.requires_reset);
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(SendBundleResult::Cancelled { nonce }) if nonce == U256::zero()
+        ));
+    }
+
+    fn cancel_pending_with_mined(
+        is_cancellation: bool,
+    ) -> (
+        SenderMachineState<MockTransactionTracker, MockTrigger>,
+        BundleSenderImpl<
+            UserOperation,
+            MockBundleProposer,
+            MockEntryPointV0_6,
+            MockTransactionTracker,
+            MockPool,
+        >,
+    ) {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        mock_trigger.expect_wait_for_block().once().returning(|| {
+            Box::pin(async {
+                Ok(NewHead {
+                    block_number: 1,
+                    block_hash: H256::zero(),
+                })
+            })
+        });
+
+        mock_tracker
+            .expect_check_for_update()
+            .once()
+            .returning(move || {
+                Box::pin(async move {
+                    Ok(Some(TrackerUpdate::Mined {
+                        block_number: 1,
+                        nonce: U256::zero(),
+                        gas_limit: None,
+                        gas_used: None,
+                        gas_price: None,
+                        tx_hash: H256::zero(),
+                        attempt_number: 0,
+                        is_cancellation,
+                        logs: None,
+                    }))
+                })
+            });
+
+        let state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::CancelPending(CancelPendingState {
+                until: 3,
+                fee_increase_count: 0,
+                pending_since: None,
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        (state, new_sender(mock_proposer, mock_entry_point))
+    }
+
+    #[tokio::test]
+    async fn test_pending_poll_interval() {
+        let (_block_tx, block_rx) = mpsc::unbounded_channel();
+        let (_action_tx, action_rx) = mpsc::channel(1);
+        let mut trigger = BundleSenderTrigger {
+            bundling_mode: BundlingMode::Auto,
+            block_rx,
+            bundle_action_receiver: action_rx,
+            timer: tokio::time::interval(Duration::from_secs(60)),
+            pending_poll_interval: Some(MIN_PENDING_POLL_INTERVAL),
+            coalesce_manual_requests: false,
+            last_block: NewHead {
+                block_number: 1,
+                block_hash: H256::zero(),
+            },
+        };
+
+        // first timer tick is immediate
+        trigger.wait_for_trigger().await.unwrap();
+
+        // while a transaction is pending, polls without waiting for a new block
+        let block = tokio::time::timeout(Duration::from_secs(1), trigger.wait_for_block())
+            .await
+            .expect("should poll at the pending interval")
+            .unwrap();
+        assert_eq!(block.block_number, 1);
+
+        // while idle, waits for the next trigger at the normal interval
+        assert!(
+            tokio::time::timeout(Duration::from_secs(1), trigger.wait_for_trigger())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_manual_requests() {
+        let (_block_tx, block_rx) = mpsc::unbounded_channel();
+        let (action_tx, action_rx) = mpsc::channel(3);
+        let mut trigger = BundleSenderTrigger {
+            bundling_mode: BundlingMode::Manual,
+            block_rx,
+            bundle_action_receiver: action_rx,
+            timer: tokio::time::interval(Duration::from_secs(60)),
+            pending_poll_interval: None,
+            coalesce_manual_requests: true,
+            queued_request: None,
+            last_block: NewHead {
+                block_number: 1,
+                block_hash: H256::zero(),
+            },
+        };
+
+        let mut receivers = vec![];
+        for _ in 0..3 {
+            let (responder, receiver) = oneshot::channel();
+            action_tx
+                .send(BundleSenderAction::SendBundle(SendBundleRequest {
+                    responder,
+                    dry_run: false,
+                    priority_ops: vec![pool_op()],
+                }))
+                .await
+                .unwrap();
+            receivers.push(receiver);
+        }
+
+        // all three requests trigger a single bundle, with the priority ops of each
+        let requests = trigger.wait_for_trigger().await.unwrap();
+        assert_eq!(requests.responders.len(), 3);
+        assert_eq!(requests.priority_ops.len(), 3);
+        assert!(!requests.dry_run);
+
+        let mut state = SenderMachineState::new(MockTrigger::new(), MockTransactionTracker::new());
+        state.send_bundle_responses = requests.responders;
+        state.complete(Some(SendBundleResult::Success {
+            block_number: 2,
+            attempt_number: 0,
+            tx_hash: H256::zero(),
+            op_results: vec![],
+        }));
+
+        for mut receiver in receivers {
+            assert!(matches!(
+                receiver.try_recv(),
+                Ok(SendBundleResult::Success {
+                    block_number: 2,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_coalescing_stops_at_dry_run() {
+        let (_block_tx, block_rx) = mpsc::unbounded_channel();
+        let (action_tx, action_rx) = mpsc::channel(3);
+        let mut trigger = BundleSenderTrigger {
+            bundling_mode: BundlingMode::Manual,
+            block_rx,
+            bundle_action_receiver: action_rx,
+            timer: tokio::time::interval(Duration::from_secs(60)),
+            pending_poll_interval: None,
+            coalesce_manual_requests: true,
+            queued_request: None,
+            last_block: NewHead {
+                block_number: 1,
+                block_hash: H256::zero(),
+            },
+        };
+
+        for dry_run in [false, true, false] {
+            action_tx
+                .send(BundleSenderAction::SendBundle(SendBundleRequest {
+                    responder: oneshot::channel().0,
+                    dry_run,
+                    priority_ops: vec![],
+                }))
+                .await
+                .unwrap();
+        }
+
+        // the dry run is not coalesced with the request before it
+        let requests = trigger.wait_for_trigger().await.unwrap();
+        assert_eq!(requests.responders.len(), 1);
+        assert!(!requests.dry_run);
+
+        // nor with the request after it
+        let requests = trigger.wait_for_trigger().await.unwrap();
+        assert_eq!(requests.responders.len(), 1);
+        assert!(requests.dry_run);
+
+        let requests = trigger.wait_for_trigger().await.unwrap();
+        assert_eq!(requests.responders.len(), 1);
+        assert!(!requests.dry_run);
+    }
+
+    #[tokio::test]
+    async fn test_priority_ops_kept_for_replacement() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mock_trigger,
+        } = new_mocks();
+
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+        // the replacement is proposed with the priority ops of the bundle it replaces
+        mock_proposer
+            .expect_make_bundle()
+            .once()
+            .withf(|_, is_replacement, priority_ops| {
+                *is_replacement && *priority_ops == vec![pool_op()]
+            })
+            .returning(|_, _, _| {
+                Box::pin(async { Err(BundleProposerError::NoOperationsInitially) })
+            });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::Building(BuildingState {
+                wait_for_trigger: false,
+                fee_increase_count: 1,
+                underpriced_info: None,
+                pending_since: None,
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![pool_op()],
+            dry_run_priority_ops: vec![],
+        };
+
+        sender.step_state(&mut state).await.unwrap();
+        // kept until a bundle containing them mines or they are rejected
+        assert_eq!(state.priority_ops, vec![pool_op()]);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_leaves_priority_ops() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mock_trigger,
+        } = new_mocks();
+
+        let dry_run_op = PoolOperation {
+            uo: UserOperation {
+                nonce: U256::one(),
+                ..Default::default()
+            }
+            .into(),
+            ..pool_op()
+        };
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+        // the dry run is only proposed with its own priority ops
+        let expected_ops = vec![dry_run_op.clone()];
+        mock_proposer
+            .expect_make_bundle()
+            .once()
+            .withf(move |_, _, priority_ops| *priority_ops == expected_ops)
+            .returning(|_, _, _| {
+                Box::pin(async { Err(BundleProposerError::NoOperationsInitially) })
+            });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        let (responder, _receiver) = oneshot::channel();
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![responder],
+            inner: InnerState::Building(BuildingState {
+                wait_for_trigger: false,
+                fee_increase_count: 1,
+                underpriced_info: None,
+                pending_since: None,
+            }),
+            requires_reset: false,
+            dry_run: true,
+            priority_ops: vec![pool_op()],
+            dry_run_priority_ops: vec![dry_run_op],
+        };
+
+        sender.step_state(&mut state).await.unwrap();
+        // the priority ops of the interrupted replacement are left for it
+        assert_eq!(state.priority_ops, vec![pool_op()]);
+        assert!(state.dry_run_priority_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_not_sent() {
+        let Mocks {
+            mut mock_proposer,
+            mut mock_entry_point,
+            mut mock_tracker,
+            mock_trigger,
+        } = new_mocks();
+
+        // zero nonce
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+
+        // bundle with one op and a rejected op
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _, _| {
+                Box::pin(async {
+                    Ok(Bundle {
+                        rejected_ops: vec![UserOperation::default()],
+                        ..bundle()
+                    })
+                })
+            });
+
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|_, _, gas, _| Eip1559TransactionRequest::new().gas(gas).into());
+
+        // the rejected op is not removed from the pool, and no transaction is sent
+        let mut mock_pool = MockPool::new();
+        mock_pool
+            .expect_get_reputation_status()
+            .returning(|_, _| Ok(ReputationStatus::Ok));
+
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+
+        let (responder, mut receiver) = oneshot::channel();
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![responder],
+            inner: InnerState::Building(BuildingState {
+                wait_for_trigger: false,
+                fee_increase_count: 0,
+                underpriced_info: None,
+                pending_since: None,
+            }),
+            requires_reset: false,
+            dry_run: true,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        sender.step_state(&mut state).await.unwrap();
+
+        // back to waiting for a trigger
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: true,
+                ..
+            })
+        ));
+        let Ok(SendBundleResult::DryRun { op_hashes, gas, .. }) = receiver.try_recv() else {
+            panic!("expected a dry run result");
+        };
+        assert_eq!(op_hashes.len(), 1);
+        assert_eq!(gas, U256::from(100_000));
+    }
+
+    #[tokio::test]
+    async fn test_pending_heartbeat() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        mock_trigger.expect_wait_for_block().times(2).returning(|| {
+            Box::pin(async {
+                Ok(NewHead {
+                    block_number: 1,
+                    block_hash: H256::zero(),
+                })
+            })
+        });
+        mock_trigger.expect_last_block().return_const(NewHead {
+            block_number: 1,
+            block_hash: H256::zero(),
+        });
+        mock_tracker
+            .expect_check_for_update()
+            .times(2)
+            .returning(|| Box::pin(async { Ok(None) }));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        let mut events = sender.event_sender.subscribe();
+
+        // pending for longer than the heartbeat interval
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::Pending(PendingState {
+                until: 3,
+                fee_increase_count: 1,
+                pending_since: Instant::now()
+                    .checked_sub(PENDING_HEARTBEAT_INTERVAL * 2)
+                    .unwrap(),
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+
+        sender.step_state(&mut state).await.unwrap();
+        let event = events.try_recv().unwrap().event;
+        assert!(matches!(
+            event.kind,
+            BuilderEventKind::StillPendingAfterWait {
+                fee_increase_count: 1,
+                block_number: 1,
+                ..
+            }
+        ));
+
+        // no heartbeat on the next block
+        sender.step_state(&mut state).await.unwrap();
+        assert!(events.try_recv().is_err());
+        assert!(matches!(state.inner, InnerState::Pending(_)));
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_tx_mined_while_building_emits_event() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        mock_trigger
+            .expect_wait_for_trigger()
+            .once()
+            .returning(|| Box::pin(async { Ok(ManualRequests::default()) }));
+        mock_trigger.expect_last_block().return_const(NewHead {
+            block_number: 2,
+            block_hash: H256::zero(),
+        });
+        mock_tracker.expect_check_for_update().once().returning(|| {
+            Box::pin(async {
+                Ok(Some(TrackerUpdate::Mined {
+                    block_number: 2,
+                    nonce: U256::zero(),
+                    gas_limit: None,
+                    gas_used: None,
+                    gas_price: None,
+                    tx_hash: H256::from_low_u64_be(1),
+                    attempt_number: 0,
+                    is_cancellation: false,
+                    logs: None,
+                }))
+            })
+        });
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::one(), None)));
+        mock_proposer
+            .expect_make_bundle()
+            .once()
+            .returning(|_, _, _| {
+                Box::pin(async { Err(BundleProposerError::NoOperationsInitially) })
+            });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        let mut events = sender.event_sender.subscribe();
+
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+        sender.step_state(&mut state).await.unwrap();
+
+        let event = events.try_recv().unwrap().event;
+        assert!(matches!(
+            event.kind,
+            BuilderEventKind::TransactionMined {
+                nonce: 0,
+                block_number: 2,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_mined_event_time_to_mine() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        mock_trigger.expect_wait_for_block().once().returning(|| {
+            Box::pin(async {
+                Ok(NewHead {
+                    block_number: 2,
+                    block_hash: H256::zero(),
+                })
+            })
+        });
+        mock_tracker.expect_check_for_update().once().returning(|| {
+            Box::pin(async {
+                Ok(Some(TrackerUpdate::Mined {
+                    block_number: 2,
+                    nonce: U256::zero(),
+                    gas_limit: None,
+                    gas_used: None,
+                    gas_price: None,
+                    tx_hash: H256::zero(),
+                    attempt_number: 2,
+                    is_cancellation: false,
+                    logs: None,
+                }))
+            })
+        });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        let mut events = sender.event_sender.subscribe();
+
+        // the first transaction of the bundle was sent before 2 fee increases
+        let pending_since = Instant::now() - Duration::from_secs(30);
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_responses: vec![],
+            inner: InnerState::Pending(PendingState {
+                until: 3,
+                fee_increase_count: 2,
+                pending_since,
+            }),
+            requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
+        };
+        sender.step_state(&mut state).await.unwrap();
+
+        let event = events.try_recv().unwrap().event;
+        let BuilderEventKind::TransactionMined { time_to_mine, .. } = event.kind else {
+            panic!("expected a transaction mined event, got {event:?}");
+        };
+        assert!(time_to_mine.unwrap() >= Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_throttled_paymaster_ops_capped() {
+        let Mocks {
+            mock_proposer,
+            mut mock_entry_point,
+            ..
+        } = new_mocks();
+
+        let throttled = Address::from_low_u64_be(1);
+        let banned = Address::from_low_u64_be(2);
+        let mut mock_pool = MockPool::new();
+        mock_pool
+            .expect_get_reputation_status()
+            .returning(move |_, paymaster| {
+                let status = if paymaster == throttled {
+                    ReputationStatus::Throttled
+                } else {
+                    ReputationStatus::Banned
+                };
+                Ok(status)
+            });
+
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .once()
+            .returning(|ops_per_aggregator, _, _, _| {
+                // 4 throttled paymaster ops and the op without a paymaster
+                assert_eq!(ops_per_aggregator.len(), 1);
+                assert_eq!(ops_per_aggregator[0].user_ops.len(), 5);
+                TypedTransaction::default()
+            });
+
+        let op_with_paymaster = |paymaster: Address| UserOperation {
+            paymaster_and_data: paymaster.as_bytes().to_vec().into(),
+            ..Default::default()
+        };
+        let mut user_ops = vec![op_with_paymaster(throttled); 6];
+        user_ops.extend(vec![op_with_paymaster(banned); 2]);
+        user_ops.push(UserOperation::default());
+        let bundle = Bundle {
+            ops_per_aggregator: vec![UserOpsPerAggregator {
+                aggregator: Address::zero(),
+                signature: Bytes::new(),
+                user_ops,
+            }],
+            ..bundle()
+        };
+
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        let bundle_tx = sender
+            .get_bundle_tx(U256::zero(), bundle, 0, 0)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(bundle_tx.op_hashes.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_only_bundle_emits_skipped_bundle() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            ..
+        } = new_mocks();
+
+        let mut mock_pool = MockPool::new();
+        mock_pool.expect_remove_ops().returning(|_, _| Ok(()));
+        mock_pool.expect_update_entities().returning(|_, _| Ok(()));
+
+        let paymaster_update = EntityUpdate {
+            entity: Entity::paymaster(Address::random()),
+            update_type: EntityUpdateType::UnstakedInvalidation,
+            reason: EntityUpdateReason::InsufficientDeposit,
+        };
+        let bundle = Bundle {
+            ops_per_aggregator: vec![],
+            rejected_ops: vec![UserOperation::default(); 2],
+            entity_updates: vec![paymaster_update],
+            ..bundle()
+        };
+
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        let mut events = sender.event_sender.subscribe();
+        let bundle_tx = sender
+            .get_bundle_tx(U256::zero(), bundle, 0, 7)
+            .await
+            .unwrap();
+        assert!(matches!(
+            bundle_tx,
+            Err(EmptyReason::RejectedInSimulation {
+                rejected_ops: 2,
+                rejected_entities: 1
+            })
+        ));
+
+        let event = events.try_recv().unwrap().event;
+        let BuilderEventKind::SkippedBundle {
+            rejected_op_count,
+            rejected_entities,
+            block_number,
+        } = event.kind
+        else {
+            panic!("expected a skipped bundle event, got {event:?}");
+        };
+        assert_eq!(rejected_op_count, 2);
+        assert_eq!(rejected_entities, vec![paymaster_update]);
+        assert_eq!(block_number, 7);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_over_max_gas_not_sent() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            ..
+        } = new_mocks();
+
+        let mut mock_pool = MockPool::new();
+        mock_pool
+            .expect_get_reputation_status()
+            .returning(|_, _| Ok(ReputationStatus::Ok));
+
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        sender.settings.max_bundle_gas = 50_000;
+
+        // the bundle's gas estimate of 100,000 exceeds the max, no transaction is built
+        let bundle_tx = sender
+            .get_bundle_tx(U256::zero(), bundle(), 0, 0)
+            .await
+            .unwrap();
+        assert!(matches!(
+            bundle_tx,
+            Err(EmptyReason::ExceedsMaxBundleGas { gas_estimate }) if gas_estimate == U256::from(100_000)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_ops_below_min_priority_fee_removed() {
+        let Mocks {
+            mock_proposer,
+            mut mock_entry_point,
+            ..
+        } = new_mocks();
+
+        // removed ops are left in the pool
+        let mut mock_pool = MockPool::new();
+        mock_pool.expect_remove_ops().never();
+        mock_pool.expect_remove_op_by_id().never();
+
+        let op = |max_priority_fee_per_gas: u64| UserOperation {
+            max_priority_fee_per_gas: max_priority_fee_per_gas.into(),
+            max_fee_per_gas: 100.into(),
+            nonce: U256::from(max_priority_fee_per_gas),
+            ..Default::default()
+        };
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .once()
+            .returning(move |ops_per_aggregator, _, _, _| {
+                // the aggregated group has an op below the minimum and is removed whole
+                assert_eq!(ops_per_aggregator.len(), 1);
+                assert_eq!(ops_per_aggregator[0].user_ops, vec![op(10), op(20)]);
+                TypedTransaction::default()
+            });
+
+        let bundle = Bundle {
+            ops_per_aggregator: vec![
+                UserOpsPerAggregator {
+                    aggregator: Address::zero(),
+                    signature: Bytes::new(),
+                    user_ops: vec![op(10), op(9), op(20)],
+                },
+                UserOpsPerAggregator {
+                    aggregator: Address::from_low_u64_be(1),
+                    signature: Bytes::new(),
+                    user_ops: vec![op(30), op(1)],
+                },
+            ],
+            ..bundle()
+        };
+
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        sender.settings.min_op_priority_fee = U256::from(10);
+        let bundle_tx = sender
+            .get_bundle_tx(U256::zero(), bundle, 0, 0)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(bundle_tx.op_hashes.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_ops_removed() {
+        let Mocks {
+            mock_proposer,
+            mut mock_entry_point,
+            ..
+        } = new_mocks();
+
+        let op = |nonce: u64, max_priority_fee_per_gas: u64| UserOperation {
+            nonce: nonce.into(),
+            max_priority_fee_per_gas: max_priority_fee_per_gas.into(),
+            max_fee_per_gas: 100.into(),
+            ..Default::default()
+        };
+
+        // the lower fee op of the same sender and nonce is removed from the pool
+        let duplicate_hash = op(0, 10).hash(Address::default(), ChainSpec::default().id);
+        let mut mock_pool = MockPool::new();
+        mock_pool
+            .expect_remove_ops()
+            .withf(move |_, ops| ops == &vec![duplicate_hash])
+            .once()
+            .returning(|_, _| Ok(()));
+
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .once()
+            .returning(move |ops_per_aggregator, _, _, _| {
+                assert_eq!(ops_per_aggregator.len(), 1);
+                assert_eq!(ops_per_aggregator[0].user_ops, vec![op(0, 20), op(1, 10)]);
+                TypedTransaction::default()
+            });
+
+        let bundle = Bundle {
+            ops_per_aggregator: vec![UserOpsPerAggregator {
+                aggregator: Address::zero(),
+                signature: Bytes::new(),
+                user_ops: vec![op(0, 10), op(0, 20), op(1, 10)],
+            }],
+            ..bundle()
+        };
+
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        let bundle_tx = sender
+            .get_bundle_tx(U256::zero(), bundle, 0, 0)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(bundle_tx.op_hashes.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_warmup_failure_does_not_stop_sender() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            ..
+        } = new_mocks();
+
+        mock_tracker.expect_sync_nonce().once().returning(|| {
+            Box::pin(async { Err(TransactionTrackerError::Other(anyhow::anyhow!("boom"))) })
+        });
+
+        let sender = new_sender(mock_proposer, mock_entry_point);
+        sender.warmup(&mut mock_tracker).await;
+    }
+
+    #[tokio::test]
+    async fn test_deterministic_ordering() {
+        let Mocks {
+            mock_proposer,
+            mut mock_entry_point,
+            ..
+        } = new_mocks();
+
+        let op = |sender: u64, nonce: u64| UserOperation {
+            sender: Address::from_low_u64_be(sender),
+            nonce: nonce.into(),
+            ..Default::default()
+        };
+        let aggregator = Address::from_low_u64_be(1);
+
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .once()
+            .returning(move |ops_per_aggregator, _, _, _| {
+                assert_eq!(ops_per_aggregator.len(), 2);
+                // unaggregated ops sorted by sender then nonce
+                assert!(ops_per_aggregator[0].aggregator.is_zero());
+                assert_eq!(
+                    ops_per_aggregator[0].user_ops,
+                    vec![op(1, 0), op(1, 1), op(2, 0)]
+                );
+                // aggregated ops keep their order
+                assert_eq!(ops_per_aggregator[1].aggregator, aggregator);
+                assert_eq!(ops_per_aggregator[1].user_ops, vec![op(4, 0), op(3, 0)]);
+                TypedTransaction::default()
+            });
+
+        let bundle = Bundle {
+            ops_per_aggregator: vec![
+                UserOpsPerAggregator {
+                    aggregator,
+                    signature: Bytes::new(),
+                    user_ops: vec![op(4, 0), op(3, 0)],
+                },
+                UserOpsPerAggregator {
+                    aggregator: Address::zero(),
+                    signature: Bytes::new(),
+                    user_ops: vec![op(2, 0), op(1, 1), op(1, 0)],
+                },
+            ],
+            ..bundle()
+        };
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.deterministic_ordering = true;
+        let bundle_tx = sender
+            .get_bundle_tx(U256::zero(), bundle, 0, 0)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let chain_id = ChainSpec::default().id;
+        assert_eq!(
+            bundle_tx.op_hashes,
+            [op(1, 0), op(1, 1), op(2, 0), op(4, 0), op(3, 0)]
+                .iter()
+                .map(|op| op.hash(Address::default(), chain_id))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ops_limited_per_entity() {
+        let Mocks {
+            mock_proposer,
+            mut mock_entry_point,
+            ..
+        } = new_mocks();
+
+        let paymaster = Address::from_low_u64_be(1);
+        let factory = Address::from_low_u64_be(2);
+        let op =
+            |sender: u64, paymaster: Option<Address>, factory: Option<Address>| UserOperation {
+                sender: Address::from_low_u64_be(sender),
+                paymaster_and_data: paymaster
+                    .map(|p| Bytes::from(p.as_bytes().to_vec()))
+                    .unwrap_or_default(),
+                init_code: factory
+                    .map(|f| Bytes::from(f.as_bytes().to_vec()))
+                    .unwrap_or_default(),
+                ..Default::default()
+            };
+
+        // ops over the limit are left in the pool
+        let mut mock_pool = MockPool::new();
+        mock_pool.expect_remove_ops().never();
+
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .once()
+            .returning(move |ops_per_aggregator, _, _, _| {
+                assert_eq!(ops_per_aggregator.len(), 1);
+                assert_eq!(
+                    ops_per_aggregator[0].user_ops,
+                    vec![
+                        op(10, Some(paymaster), None),
+                        op(11, Some(paymaster), Some(factory)),
+                        op(13, None, Some(factory)),
+                        op(15, None, None),
+                    ]
+                );
+                TypedTransaction::default()
+            });
+
+        let bundle = Bundle {
+            ops_per_aggregator: vec![UserOpsPerAggregator {
+                aggregator: Address::zero(),
+                signature: Bytes::new(),
+                user_ops: vec![
+                    op(10, Some(paymaster), None),
+                    op(11, Some(paymaster), Some(factory)),
+                    // over the paymaster limit
+                    op(12, Some(paymaster), None),
+                    op(13, None, Some(factory)),
+                    // over the factory limit
+                    op(14, None, Some(factory)),
+                    op(15, None, None),
+                ],
+            }],
+            ..bundle()
+        };
+
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        sender.settings.max_ops_per_entity_per_bundle = 2;
+        let bundle_tx = sender
+            .get_bundle_tx(U256::zero(), bundle, 0, 0)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(bundle_tx.op_hashes.len(), 4);
+    }
+
+    #[test]
+    fn test_increase_bundle_gas_limit() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            ..
+        } = new_mocks();
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+
+        // disabled by default
+        let mut initial = bundle();
+        sender.increase_bundle_gas_limit(&mut initial, 2);
+        assert_eq!(initial.gas_estimate, U256::from(100_000));
+
+        sender.settings.gas_limit_increase_percent = 10;
+        let mut initial = bundle();
+        sender.increase_bundle_gas_limit(&mut initial, 0);
+        assert_eq!(initial.gas_estimate, U256::from(100_000));
+
+        // compounded for each fee increase
+        let mut replacement = bundle();
+        sender.increase_bundle_gas_limit(&mut replacement, 2);
+        assert_eq!(replacement.gas_estimate, U256::from(121_000));
+
+        // capped at the max bundle gas
+        sender.settings.max_bundle_gas = 115_000;
+        let mut replacement = bundle();
+        sender.increase_bundle_gas_limit(&mut replacement, 2);
+        assert_eq!(replacement.gas_estimate, U256::from(115_000));
+    }
+
+    #[tokio::test]
+    async fn test_bundle_truncated_to_max_ops() {
+        let Mocks {
+            mock_proposer,
+            mut mock_entry_point,
+            ..
+        } = new_mocks();
+
+        // truncated ops are left in the pool
+        let mut mock_pool = MockPool::new();
+        mock_pool.expect_remove_ops().never();
+        mock_pool.expect_remove_op_by_id().never();
+
+        let aggregator = Address::from_low_u64_be(1);
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .once()
+            .returning(|ops_per_aggregator, _, _, _| {
+                // the first 3 unaggregated ops, the aggregated group doesn't fit
+                assert_eq!(ops_per_aggregator.len(), 1);
+                assert!(ops_per_aggregator[0].aggregator.is_zero());
+                assert_eq!(ops_per_aggregator[0].user_ops.len(), 3);
+                TypedTransaction::default()
+            });
+
+        let bundle = Bundle {
+            ops_per_aggregator: vec![
+                UserOpsPerAggregator {
+                    aggregator: Address::zero(),
+                    signature: Bytes::new(),
+                    user_ops: vec![UserOperation::default(); 3],
+                },
+                UserOpsPerAggregator {
+                    aggregator,
+                    signature: Bytes::new(),
+                    user_ops: vec![UserOperation::default(); 2],
+                },
+            ],
+            ..bundle()
+        };
+
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        sender.settings.max_ops_per_bundle = 4;
+        let bundle_tx = sender
+            .get_bundle_tx(U256::zero(), bundle, 0, 0)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(bundle_tx.op_hashes.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_bundle_gas_limit_and_expected_storage() {
+        let Mocks {
+            mock_proposer,
+            mut mock_entry_point,
+            ..
+        } = new_mocks();
+
+        let chain_spec = ChainSpec::default();
+        let ops: Vec<_> = (0..3_u64)
+            .map(|nonce| UserOperation {
+                nonce: nonce.into(),
+                call_gas_limit: 100_000.into(),
+                ..Default::default()
+            })
+            .collect();
+        let proposed_gas = bundle_proposer::bundle_gas_limit(&chain_spec, &ops, 0);
+        let truncated_gas = bundle_proposer::bundle_gas_limit(&chain_spec, &ops[..2], 0);
+        assert!(truncated_gas < proposed_gas);
+
+        let mut expected_storage = ExpectedStorage::default();
+        let mut expected_storage_by_op = HashMap::new();
+        let mut truncated_storage = ExpectedStorage::default();
+        for (i, op) in ops.iter().enumerate() {
+            let mut op_storage = ExpectedStorage::default();
+            op_storage.insert(
+                Address::from_low_u64_be(i as u64 + 1),
+                U256::zero(),
+                U256::one(),
+            );
+            expected_storage.merge(&op_storage).unwrap();
+            if i < 2 {
+                truncated_storage.merge(&op_storage).unwrap();
+            }
+            expected_storage_by_op.insert(op.hash(Address::default(), chain_spec.id), op_storage);
+        }
+
+        // the transaction is sent with the gas limit of the truncated bundle
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .once()
+            .returning(move |ops_per_aggregator, _, gas, _| {
+                assert_eq!(ops_per_aggregator[0].user_ops.len(), 2);
+                assert_eq!(gas, truncated_gas);
+                TypedTransaction::default()
+            });
+
+        let bundle = Bundle {
+            ops_per_aggregator: vec![UserOpsPerAggregator {
+                aggregator: Address::zero(),
+                signature: Bytes::new(),
+                user_ops: ops,
+            }],
+            gas_estimate: proposed_gas,
+            expected_storage,
+            expected_storage_by_op,
+            ..bundle()
+        };
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.max_ops_per_bundle = 2;
+        let bundle_tx = sender
+            .get_bundle_tx(U256::zero(), bundle, 0, 0)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(bundle_tx.expected_storage.0, truncated_storage.0);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_trimmed_to_max_calldata() {
+        let Mocks {
+            mock_proposer,
+            mut mock_entry_point,
+            ..
+        } = new_mocks();
+
+        // trimmed ops are left in the pool
+        let mut mock_pool = MockPool::new();
+        mock_pool.expect_remove_ops().never();
+        mock_pool.expect_remove_op_by_id().never();
+
+        // calldata of 100 bytes per op
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|ops_per_aggregator, _, _, _| {
+                let op_count = ops_per_aggregator
+                    .iter()
+                    .map(|group| group.user_ops.len())
+                    .sum::<usize>();
+                Eip1559TransactionRequest::new()
+                    .data(vec![0; 100 * op_count])
+                    .into()
+            });
+
+        let small_op = UserOperation::default();
+        let large_op = UserOperation {
+            call_data: vec![1; 1000].into(),
+            ..Default::default()
+        };
+        let bundle = Bundle {
+            ops_per_aggregator: vec![UserOpsPerAggregator {
+                aggregator: Address::zero(),
+                signature: Bytes::new(),
+                user_ops: vec![small_op.clone(), large_op, small_op.clone()],
+            }],
+            ..bundle()
+        };
+
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        sender.settings.max_bundle_calldata_bytes = 250;
+        let bundle_tx = sender
+            .get_bundle_tx(U256::zero(), bundle, 0, 0)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // the largest op is removed
+        let small_op_hash = sender.op_hash(&small_op);
+        assert_eq!(bundle_tx.op_hashes, vec![small_op_hash, small_op_hash]);
+    }
+
+    #[tokio::test]
+    async fn test_replacement_underpriced_retried() {
+        let Mocks {
+            mut mock_proposer,
+            mut mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
+
+        let mut seq = Sequence::new();
+        add_trigger_no_update_last_block(&mut mock_trigger, &mut mock_tracker, &mut seq, 1);
+
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+        mock_proposer
+            .expect_make_bundle()
+            .once()
+            .returning(|_, _, _| Box::pin(async { Ok(bundle()) }));
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|_, _, _, _| TypedTransaction::default());
+        mock_tracker
+            .expect_send_transaction()
+            .once()
+            .returning(|_, _| {
+                Box::pin(async { Err(TransactionTrackerError::ReplacementUnderpriced) })
+            });
+        mock_tracker.expect_unabandon().once().return_const(());
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        sender.settings.retry_replacement_underpriced = true;
+
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+        sender.step_state(&mut state).await.unwrap();
+
+        // the attempt is retried with increased fees without waiting for a trigger
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: false,
+                fee_increase_count: 1,
+                underpriced_info: Some(UnderpricedInfo {
+                    since_block: 1,
+                    rounds: 1
+                }),
+                ..
             })
         ));
     }
 
     #[tokio::test]
-    async fn test_send_cancel() {
+    async fn test_send_in_memory_entry_point() {
         let Mocks {
             mut mock_proposer,
-            mock_entry_point,
             mut mock_tracker,
             mut mock_trigger,
+            ..
         } = new_mocks();
 
+        let entry_point_address = Address::from_low_u64_be(0x4337);
+        let beneficiary = Address::from_low_u64_be(1);
+        let entry_point = InMemoryEntryPoint::new(entry_point_address);
+        entry_point.set_send_bundle_transaction(
+            Eip1559TransactionRequest::new()
+                .to(entry_point_address)
+                .into(),
+        );
+
+        // block 0
+        add_trigger_no_update_last_block(
+            &mut mock_trigger,
+            &mut mock_tracker,
+            &mut Sequence::new(),
+            0,
+        );
+
+        // nonce 5
+        mock_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::from(5), None)));
+
+        // bundle with one op
         mock_proposer
-            .expect_estimate_gas_fees()
-            .once()
-            .returning(|_| Box::pin(async { Ok((GasFees::default(), U256::zero())) }));
+            .expect_make_bundle()
+            .times(1)
+            .returning(move |_, _, _| {
+                Box::pin(async move {
+                    Ok(Bundle {
+                        beneficiary,
+                        ..bundle()
+                    })
+                })
+            });
 
+        // should send the entry point's bundle txn with the tracker's nonce
         mock_tracker
-            .expect_cancel_transaction()
+            .expect_send_transaction()
+            .withf(move |tx, _| {
+                tx.to_addr() == Some(&entry_point_address) && tx.nonce() == Some(&U256::from(5))
+            })
             .once()
-            .returning(|_, _| Box::pin(async { Ok(Some(H256::zero())) }));
-
-        mock_trigger.expect_last_block().return_const(NewHead {
-            block_number: 0,
-            block_hash: H256::zero(),
-        });
+            .returning(|_, _| Box::pin(async { Ok(H256::zero()) }));
 
-        let mut state = SenderMachineState {
-            trigger: mock_trigger,
-            transaction_tracker: mock_tracker,
-            send_bundle_response: None,
-            inner: InnerState::Cancelling(CancellingState {
-                fee_increase_count: 0,
-            }),
-            requires_reset: false,
-        };
+        let mut sender = BundleSenderImpl::new(
+            0,
+            mpsc::channel(1000).1,
+            broadcast::channel(1).1,
+            ChainSpec::default(),
+            mock_proposer,
+            entry_point.clone(),
+            MockTransactionTracker::new(),
+            MockPool::new(),
+            test_settings(),
+            broadcast::channel(1000).0,
+        );
 
-        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        // start in building state
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
 
         sender.step_state(&mut state).await.unwrap();
-        assert!(matches!(
-            state.inner,
-            InnerState::CancelPending(CancelPendingState {
-                until: 3,
-                fee_increase_count: 0,
-            })
-        ));
+
+        // end in the pending state
+        assert!(matches!(state.inner, InnerState::Pending(..)));
+
+        // the bundle was built with the proposed ops, gas and beneficiary
+        let calls = entry_point.send_bundle_transaction_calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].ops_per_aggregator, bundle().ops_per_aggregator);
+        assert_eq!(calls[0].beneficiary, beneficiary);
+        assert_eq!(calls[0].gas, bundle().gas_estimate);
+        assert_eq!(calls[0].gas_fees, bundle().gas_fees);
     }
 
     #[tokio::test]
-    async fn test_resubmit_cancel() {
+    async fn test_empty_send_in_memory_entry_point() {
         let Mocks {
-            mock_proposer,
-            mock_entry_point,
+            mut mock_proposer,
             mut mock_tracker,
             mut mock_trigger,
+            ..
         } = new_mocks();
 
-        let mut seq = Sequence::new();
-        for i in 1..=3 {
-            add_trigger_wait_for_block_last_block(&mut mock_trigger, &mut seq, i);
-        }
+        let entry_point = InMemoryEntryPoint::new(Address::from_low_u64_be(0x4337));
+
+        // block 0
+        add_trigger_no_update_last_block(
+            &mut mock_trigger,
+            &mut mock_tracker,
+            &mut Sequence::new(),
+            0,
+        );
 
+        // zero nonce
         mock_tracker
-            .expect_check_for_update()
-            .times(3)
-            .returning(|| Box::pin(async { Ok(None) }));
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
 
-        let mut state = SenderMachineState {
-            trigger: mock_trigger,
-            transaction_tracker: mock_tracker,
-            send_bundle_response: None,
-            inner: InnerState::CancelPending(CancelPendingState {
-                until: 3,
-                fee_increase_count: 0,
-            }),
-            requires_reset: false,
-        };
+        // empty bundle
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(Bundle::<UserOperation>::default()) }));
 
-        let mut sender = new_sender(mock_proposer, mock_entry_point);
+        let mut sender = BundleSenderImpl::new(
+            0,
+            mpsc::channel(1000).1,
+            broadcast::channel(1).1,
+            ChainSpec::default(),
+            mock_proposer,
+            entry_point.clone(),
+            MockTransactionTracker::new(),
+            MockPool::new(),
+            test_settings(),
+            broadcast::channel(1000).0,
+        );
 
-        for _ in 0..2 {
-            sender.step_state(&mut state).await.unwrap();
-            assert!(matches!(
-                state.inner,
-                InnerState::CancelPending(CancelPendingState {
-                    until: 3,
-                    fee_increase_count: 0,
-                })
-            ));
-        }
+        // start in building state
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
 
         sender.step_state(&mut state).await.unwrap();
+
+        // no bundle transaction is built, back to waiting for a trigger
         assert!(matches!(
             state.inner,
-            InnerState::Cancelling(CancellingState {
-                fee_increase_count: 1,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: true,
+                ..
             })
         ));
+        assert!(entry_point.send_bundle_transaction_calls().is_empty());
     }
 
     #[tokio::test]
-    async fn test_condition_not_met() {
+    async fn test_remove_rejected_ops_in_batches() {
         let Mocks {
-            mut mock_proposer,
-            mut mock_entry_point,
-            mut mock_tracker,
-            mut mock_trigger,
+            mock_proposer,
+            mock_entry_point,
+            ..
         } = new_mocks();
 
-        let mut seq = Sequence::new();
-        add_trigger_no_update_last_block(&mut mock_trigger, &mut mock_tracker, &mut seq, 1);
+        let mut mock_pool = MockPool::new();
+        mock_pool
+            .expect_remove_ops()
+            .withf(|_, ops| ops.len() == 1000)
+            .times(10)
+            .returning(|_, _| Ok(()));
 
-        // zero nonce
-        mock_tracker
-            .expect_get_nonce_and_required_fees()
-            .returning(|| Ok((U256::zero(), None)));
+        let sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        let rejected_ops = vec![UserOperation::default(); 10_000];
+        sender.remove_ops_from_pool(&rejected_ops).await.unwrap();
+    }
 
-        // bundle with one op
-        mock_proposer
-            .expect_make_bundle()
-            .times(1)
-            .returning(|_, _| Box::pin(async { Ok(bundle()) }));
+    #[tokio::test]
+    async fn test_mined_partial_success_reconciles_ops() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+        } = new_mocks();
 
-        // should create the bundle txn
-        mock_entry_point
-            .expect_get_send_bundle_transaction()
-            .returning(|_, _, _, _| TypedTransaction::default());
+        let tx_hash = H256::from_low_u64_be(1);
+        let op_hashes: Vec<_> = (10..13).map(H256::from_low_u64_be).collect();
 
-        // should send the bundle txn, returns condition not met
+        mock_trigger.expect_wait_for_block().once().returning(|| {
+            Box::pin(async {
+                Ok(NewHead {
+                    block_number: 1,
+                    block_hash: H256::zero(),
+                })
+            })
+        });
+
+        // only the first and last ops emitted events, and the last one reverted
+        let executed = vec![op_hashes[0], op_hashes[2]];
+        let logs = vec![
+            user_op_event_log(op_hashes[0], true),
+            user_op_event_log(op_hashes[2], false),
+        ];
         mock_tracker
-            .expect_send_transaction()
-            .returning(|_, _| Box::pin(async { Err(TransactionTrackerError::ConditionNotMet) }));
+            .expect_check_for_update()
+            .once()
+            .returning(move || {
+                let logs = logs.clone();
+                Box::pin(async move {
+                    Ok(Some(TrackerUpdate::Mined {
+                        block_number: 1,
+                        nonce: U256::zero(),
+                        gas_limit: None,
+                        gas_used: None,
+                        gas_price: None,
+                        tx_hash,
+                        attempt_number: 0,
+                        is_cancellation: false,
+                        logs: Some(logs),
+                    }))
+                })
+            });
 
-        // should notify proposer that condition was not met
-        mock_proposer
-            .expect_notify_condition_not_met()
-            .times(1)
-            .return_const(());
+        // only the executed ops are removed from the pool
+        let mut mock_pool = MockPool::new();
+        mock_pool
+            .expect_remove_ops()
+            .withf(move |_, ops| ops == &executed)
+            .once()
+            .returning(|_, _| Ok(()));
 
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        sender.bundle_op_hashes.insert(tx_hash, op_hashes.clone());
+
+        // start in pending state
+        let (responder, mut receiver) = oneshot::channel();
         let mut state = SenderMachineState {
             trigger: mock_trigger,
             transaction_tracker: mock_tracker,
-            send_bundle_response: None,
-            inner: InnerState::Building(BuildingState {
-                wait_for_trigger: true,
+            send_bundle_responses: vec![responder],
+            inner: InnerState::Pending(PendingState {
+                until: 3,
                 fee_increase_count: 0,
-                underpriced_info: None,
+                pending_since: Instant::now(),
             }),
             requires_reset: false,
+            dry_run: false,
+            priority_ops: vec![],
+            dry_run_priority_ops: vec![],
         };
 
-        let mut sender = new_sender(mock_proposer, mock_entry_point);
-
         sender.step_state(&mut state).await.unwrap();
-
-        // end back in the building state without waiting for trigger
         assert!(matches!(
             state.inner,
             InnerState::Building(BuildingState {
-                wait_for_trigger: false,
-                fee_increase_count: 0,
-                underpriced_info: None,
+                wait_for_trigger: true,
+                ..
             })
         ));
+        assert!(sender.bundle_op_hashes.is_empty());
+
+        // only the first op succeeded
+        let Ok(SendBundleResult::Success { op_results, .. }) = receiver.try_recv() else {
+            panic!("expected a success result");
+        };
+        assert_eq!(
+            op_results,
+            vec![
+                (op_hashes[0], true),
+                (op_hashes[1], false),
+                (op_hashes[2], false)
+            ]
+        );
+    }
+
+    // a `UserOperationEvent` log of the op with hash `op_hash`
+    fn user_op_event_log(op_hash: H256, success: bool) -> Log {
+        Log {
+            address: Address::default(),
+            topics: vec![
+                UserOperationEventFilter::signature(),
+                op_hash,
+                H256::zero(),
+                H256::zero(),
+            ],
+            data: abi::encode(&[
+                Token::Uint(U256::zero()),
+                Token::Bool(success),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::zero()),
+            ])
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_op_repeatedly_failing_in_bundle_removed() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            ..
+        } = new_mocks();
+
+        let op_hash = H256::from_low_u64_be(10);
+
+        // the op is only removed from the pool after the third consecutive failure
+        let mut mock_pool = MockPool::new();
+        mock_pool
+            .expect_remove_ops()
+            .withf(move |_, ops| ops == &vec![op_hash])
+            .once()
+            .returning(|_, _| Ok(()));
+
+        let mut sender = new_sender_with_pool(mock_proposer, mock_entry_point, mock_pool);
+        sender.settings.max_bundle_reverts_per_op = Some(3);
+
+        for i in 0..3 {
+            let tx_hash = H256::from_low_u64_be(i);
+            sender.bundle_op_hashes.insert(tx_hash, vec![op_hash]);
+            // bundle mined without executing the op
+            sender.reconcile_mined_ops(tx_hash, Some(&[])).await;
+        }
+        assert!(sender.op_bundle_reverts.is_empty());
     }
 
     struct Mocks {
@@ -1676,25 +6183,94 @@ mod tests {
         MockEntryPointV0_6,
         MockTransactionTracker,
         MockPool,
+    > {
+        new_sender_with_pool(mock_proposer, mock_entry_point, MockPool::new())
+    }
+
+    fn new_sender_with_pool(
+        mock_proposer: MockBundleProposer,
+        mock_entry_point: MockEntryPointV0_6,
+        mock_pool: MockPool,
+    ) -> BundleSenderImpl<
+        UserOperation,
+        MockBundleProposer,
+        MockEntryPointV0_6,
+        MockTransactionTracker,
+        MockPool,
     > {
         BundleSenderImpl::new(
             0,
             mpsc::channel(1000).1,
+            broadcast::channel(1).1,
             ChainSpec::default(),
-            Address::default(),
             mock_proposer,
             mock_entry_point,
             MockTransactionTracker::new(),
-            MockPool::new(),
-            Settings {
-                max_cancellation_fee_increases: 3,
-                max_blocks_to_wait_for_mine: 3,
-                max_replacement_underpriced_blocks: 3,
-            },
+            mock_pool,
+            test_settings(),
             broadcast::channel(1000).0,
         )
     }
 
+    fn test_settings() -> Settings {
+        Settings {
+            max_cancellation_fee_increases: 3,
+            cancel_on_stall: true,
+            max_blocks_to_wait_for_mine: 3,
+            max_pending_time: None,
+            max_replacement_underpriced_blocks: 3,
+            retry_replacement_underpriced: false,
+            min_bundle_interval: Duration::ZERO,
+            pending_poll_interval: None,
+            throttled_paymaster_bundle_count: 4,
+            max_bundle_reverts_per_op: None,
+            coalesce_manual_bundle_requests: false,
+            use_legacy_gas: false,
+            rejected_ops_removal_batch_size: 1000,
+            max_bundle_gas: u64::MAX,
+            gas_estimate_overhead_percent: 0,
+            validate_bundle_before_send: false,
+            max_ops_per_bundle: usize::MAX,
+            max_ops_per_entity_per_bundle: usize::MAX,
+            max_bundle_calldata_bytes: usize::MAX,
+            min_op_priority_fee: U256::zero(),
+            pause_guardian: None,
+            pause_check_interval: Duration::ZERO,
+            consecutive_failure_threshold: None,
+            max_failure_backoff: Duration::from_secs(300),
+            deterministic_ordering: false,
+            idle_backoff_max: Duration::ZERO,
+            gas_limit_increase_percent: 0,
+            max_inflight_bundles: 1,
+        }
+    }
+
+    // a manual bundle request trigger, returning the receiver of its response
+    fn add_manual_trigger_no_update(
+        mock_trigger: &mut MockTrigger,
+        mock_tracker: &mut MockTransactionTracker,
+        seq: &mut Sequence,
+    ) -> oneshot::Receiver<SendBundleResult> {
+        let (responder, receiver) = oneshot::channel();
+        mock_trigger
+            .expect_wait_for_trigger()
+            .once()
+            .in_sequence(seq)
+            .return_once(move || {
+                Box::pin(async move {
+                    Ok(ManualRequests {
+                        responders: vec![responder],
+                        dry_run: false,
+                        priority_ops: vec![],
+                    })
+                })
+            });
+        mock_tracker
+            .expect_check_for_update()
+            .returning(|| Box::pin(async { Ok(None) }));
+        receiver
+    }
+
     fn add_trigger_no_update_last_block(
         mock_trigger: &mut MockTrigger,
         mock_tracker: &mut MockTransactionTracker,
@@ -1705,7 +6281,7 @@ mod tests {
             .expect_wait_for_trigger()
             .once()
             .in_sequence(seq)
-            .returning(move || Box::pin(async move { Ok(None) }));
+            .returning(move || Box::pin(async move { Ok(ManualRequests::default()) }));
         mock_tracker
             .expect_check_for_update()
             .returning(|| Box::pin(async { Ok(None) }));
@@ -1746,13 +6322,29 @@ mod tests {
             });
     }
 
+    fn pool_op() -> PoolOperation {
+        PoolOperation {
+            uo: UserOperation::default().into(),
+            entry_point: Address::zero(),
+            aggregator: None,
+            valid_time_range: Default::default(),
+            expected_code_hash: H256::zero(),
+            sim_block_hash: H256::zero(),
+            sim_block_number: 0,
+            account_is_staked: false,
+            entity_infos: Default::default(),
+        }
+    }
+
     fn bundle() -> Bundle<UserOperation> {
         Bundle {
             gas_estimate: U256::from(100_000),
             gas_fees: GasFees::default(),
             expected_storage: Default::default(),
+            expected_storage_by_op: HashMap::new(),
             rejected_ops: vec![],
             entity_updates: vec![],
+            beneficiary: Address::zero(),
             ops_per_aggregator: vec![UserOpsPerAggregator {
                 aggregator: Address::zero(),
                 signature: Bytes::new(),
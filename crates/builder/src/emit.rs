@@ -11,11 +11,11 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{fmt::Display, sync::Arc};
+use std::{fmt::Display, sync::Arc, time::Duration};
 
 use ethers::types::{transaction::eip2718::TypedTransaction, Address, H256, U256};
-use rundler_sim::SimulationError;
-use rundler_types::{GasFees, ValidTimeRange};
+use rundler_sim::{ExpectedStorage, SimulationError};
+use rundler_types::{EntityUpdate, GasFees, ValidTimeRange};
 use rundler_utils::strs;
 
 /// Builder event
@@ -53,11 +53,28 @@ impl BuilderEvent {
         )
     }
 
+    pub(crate) fn skipped_bundle(
+        builder_index: u64,
+        rejected_op_count: usize,
+        rejected_entities: Vec<EntityUpdate>,
+        block_number: u64,
+    ) -> Self {
+        Self::new(
+            builder_index,
+            BuilderEventKind::SkippedBundle {
+                rejected_op_count,
+                rejected_entities,
+                block_number,
+            },
+        )
+    }
+
     pub(crate) fn transaction_mined(
         builder_index: u64,
         tx_hash: H256,
         nonce: u64,
         block_number: u64,
+        time_to_mine: Option<Duration>,
     ) -> Self {
         Self::new(
             builder_index,
@@ -65,6 +82,44 @@ impl BuilderEvent {
                 tx_hash,
                 nonce,
                 block_number,
+                time_to_mine,
+            },
+        )
+    }
+
+    pub(crate) fn transaction_reorged(builder_index: u64, tx_hash: H256, nonce: u64) -> Self {
+        Self::new(
+            builder_index,
+            BuilderEventKind::TransactionReorged { tx_hash, nonce },
+        )
+    }
+
+    pub(crate) fn circuit_open(
+        builder_index: u64,
+        consecutive_failures: u64,
+        backoff: Duration,
+    ) -> Self {
+        Self::new(
+            builder_index,
+            BuilderEventKind::CircuitOpen {
+                consecutive_failures,
+                backoff,
+            },
+        )
+    }
+
+    pub(crate) fn still_pending_after_wait(
+        builder_index: u64,
+        fee_increase_count: u64,
+        pending_for: Duration,
+        block_number: u64,
+    ) -> Self {
+        Self::new(
+            builder_index,
+            BuilderEventKind::StillPendingAfterWait {
+                fee_increase_count,
+                pending_for,
+                block_number,
             },
         )
     }
@@ -118,6 +173,15 @@ pub enum BuilderEventKind {
         /// Required fees for the transaction that was sent
         required_fees: Option<GasFees>,
     },
+    /// No bundle was sent because all of its operations or entities were rejected
+    SkippedBundle {
+        /// Number of operations rejected from the bundle
+        rejected_op_count: usize,
+        /// Entities rejected from the bundle, with the reason for each rejection
+        rejected_entities: Vec<EntityUpdate>,
+        /// Block number the bundle was built on
+        block_number: u64,
+    },
     /// A bundle transaction was mined
     TransactionMined {
         /// Transaction hash
@@ -126,6 +190,32 @@ pub enum BuilderEventKind {
         nonce: u64,
         /// Block number containing the transaction
         block_number: u64,
+        /// Time since the first transaction of the bundle was sent, spanning all
+        /// fee increases. None if unknown.
+        time_to_mine: Option<Duration>,
+    },
+    /// A mined bundle transaction was reorged out of the canonical chain
+    TransactionReorged {
+        /// Transaction hash
+        tx_hash: H256,
+        /// Transaction nonce, to be used again
+        nonce: u64,
+    },
+    /// Bundle attempts are paused after too many consecutive failures
+    CircuitOpen {
+        /// Number of consecutive failed bundle attempts
+        consecutive_failures: u64,
+        /// Time until the next bundle attempt
+        backoff: Duration,
+    },
+    /// Heartbeat emitted periodically while waiting for a bundle transaction to mine
+    StillPendingAfterWait {
+        /// Number of times fees were increased
+        fee_increase_count: u64,
+        /// Time since the first transaction at the pending nonce was sent
+        pending_for: Duration,
+        /// Latest block number
+        block_number: u64,
     },
     /// The latest transaction was dropped
     LatestTransactionDropped {
@@ -162,6 +252,11 @@ pub struct BundleTxDetails {
     pub tx: TypedTransaction,
     /// Operation hashes included in the bundle
     pub op_hashes: Arc<Vec<H256>>,
+    /// Beneficiary receiving the bundle's fees
+    pub beneficiary: Address,
+    /// Storage values the bundle's ops read during validation, by contract and
+    /// slot, which the transaction is conditioned on when sent conditionally
+    pub expected_storage: Arc<ExpectedStorage>,
 }
 
 /// Reason for skipping an operation in a bundle
@@ -185,6 +280,10 @@ pub enum SkipReason {
     },
     /// Bundle ran out of space by gas limit to include the operation
     GasLimit,
+    /// Operation's sender reached its limit of operations included in recent bundles
+    SenderThrottled,
+    /// Operation reverted when simulated after the operations included earlier in the bundle
+    ConflictsWithBundle { message: Arc<String> },
     /// Other reason, typically internal errors
     Other { reason: Arc<String> },
 }
@@ -196,6 +295,14 @@ pub enum OpRejectionReason {
     FailedRevalidation { error: SimulationError },
     /// Operation reverted during bundle formation simulation with message
     FailedInBundle { message: Arc<String> },
+    /// Operation was bundled but not executed in too many consecutive mined bundles
+    RepeatedlyFailedInBundle { count: u64 },
+    /// Operation's `paymasterAndData` or `signature` field exceeds the maximum size
+    FieldTooLarge {
+        field: &'static str,
+        size: usize,
+        max_size: usize,
+    },
     /// Operation's storage slot condition was not met
     ConditionNotMet(ConditionNotMetReason),
 }
@@ -243,6 +350,7 @@ impl Display for BuilderEvent {
                                 "    Required maxFeePerGas: {}",
                                 "    Required maxPriorityFeePerGas: {}",
                                 "    Op hashes: {}",
+                                "    Beneficiary: {:?}",
                             ),
                             self.builder_index,
                             tx_details.tx_hash,
@@ -251,6 +359,7 @@ impl Display for BuilderEvent {
                             required_max_fee_per_gas,
                             required_max_priority_fee_per_gas,
                             op_hashes,
+                            tx_details.beneficiary,
                         )
                     }
                     None => write!(
@@ -271,10 +380,33 @@ impl Display for BuilderEvent {
                     ),
                 }
             }
+            BuilderEventKind::SkippedBundle {
+                rejected_op_count,
+                rejected_entities,
+                block_number,
+            } => {
+                let rejected_entities = rejected_entities
+                    .iter()
+                    .map(|update| format!("{} ({})", update.entity, update.reason))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(
+                    f,
+                    concat!(
+                        "Bundle skipped, all operations were rejected.",
+                        "    Builder index: {:?}",
+                        "    Rejected ops: {}",
+                        "    Rejected entities: {}",
+                        "    Block number: {}",
+                    ),
+                    self.builder_index, rejected_op_count, rejected_entities, block_number,
+                )
+            }
             BuilderEventKind::TransactionMined {
                 tx_hash,
                 nonce,
                 block_number,
+                time_to_mine,
             } => write!(
                 f,
                 concat!(
@@ -283,8 +415,47 @@ impl Display for BuilderEvent {
                     "    Transaction hash: {:?}",
                     "    Nonce: {}",
                     "    Block number: {}",
+                    "    Time to mine: {:?}",
+                ),
+                self.builder_index, tx_hash, nonce, block_number, time_to_mine,
+            ),
+            BuilderEventKind::TransactionReorged { tx_hash, nonce } => write!(
+                f,
+                concat!(
+                    "Transaction reorged out!",
+                    "    Builder index: {:?}",
+                    "    Transaction hash: {:?}",
+                    "    Nonce: {}",
+                ),
+                self.builder_index, tx_hash, nonce,
+            ),
+            BuilderEventKind::CircuitOpen {
+                consecutive_failures,
+                backoff,
+            } => write!(
+                f,
+                concat!(
+                    "Circuit breaker open!",
+                    "    Builder index: {:?}",
+                    "    Consecutive failures: {}",
+                    "    Next attempt in: {:?}",
+                ),
+                self.builder_index, consecutive_failures, backoff,
+            ),
+            BuilderEventKind::StillPendingAfterWait {
+                fee_increase_count,
+                pending_for,
+                block_number,
+            } => write!(
+                f,
+                concat!(
+                    "Bundle transaction still pending.",
+                    "    Builder index: {:?}",
+                    "    Fee increases: {}",
+                    "    Pending for: {:?}",
+                    "    Block number: {}",
                 ),
-                self.builder_index, tx_hash, nonce, block_number,
+                self.builder_index, fee_increase_count, pending_for, block_number,
             ),
             BuilderEventKind::LatestTransactionDropped { nonce } => {
                 write!(
@@ -0,0 +1,169 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{sync::RwLock, time::Duration};
+
+use ethers::types::Address;
+use tokio::time::Instant;
+
+/// Liveness of a bundle sender, updated by the sender as it runs and read by
+/// health checks
+#[derive(Debug)]
+pub(crate) struct BuilderHealth {
+    entry_point: Address,
+    builder_index: u64,
+    state: RwLock<HealthState>,
+}
+
+#[derive(Debug, Default)]
+struct HealthState {
+    // number of the first block the sender saw
+    first_block: Option<u64>,
+    // number of the latest block the sender saw, and when it saw it
+    last_block: Option<(u64, Instant)>,
+    // number of the block the last bundle was mined in, and when the sender saw it mined
+    last_mine: Option<(u64, Instant)>,
+    circuit_open_until: Option<Instant>,
+    stalled: bool,
+}
+
+/// Snapshot of the health of a bundle sender
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuilderHealthStatus {
+    /// Entry point the sender bundles for
+    pub entry_point: Address,
+    /// Index of the sender
+    pub builder_index: u64,
+    /// Number of the latest block the sender saw, if any
+    pub last_block_number: Option<u64>,
+    /// Time since the sender saw a new block, if any
+    pub time_since_last_block: Option<Duration>,
+    /// Number of the block the sender's last bundle was mined in, if any
+    pub last_mined_block_number: Option<u64>,
+    /// Time since the sender saw its last bundle mined, if any
+    pub time_since_last_mine: Option<Duration>,
+    /// Number of blocks since the sender's last bundle was mined, or since it saw
+    /// its first block if none was mined yet. A steadily growing count while ops
+    /// are pending indicates a stuck sender.
+    pub blocks_since_last_mine: Option<u64>,
+    /// Whether bundle attempts are skipped after too many consecutive failures
+    pub circuit_open: bool,
+    /// Whether the sender is cancelling a bundle transaction that was not mined
+    pub stalled: bool,
+}
+
+impl BuilderHealth {
+    pub(crate) fn new(entry_point: Address, builder_index: u64) -> Self {
+        Self {
+            entry_point,
+            builder_index,
+            state: RwLock::default(),
+        }
+    }
+
+    /// Records the latest block seen by the sender, only updating when the block
+    /// was last seen if it is new
+    pub(crate) fn record_block(&self, block_number: u64) {
+        let mut state = self.state.write().unwrap();
+        state.first_block.get_or_insert(block_number);
+        if state
+            .last_block
+            .map_or(true, |(last, _)| block_number > last)
+        {
+            state.last_block = Some((block_number, Instant::now()));
+        }
+    }
+
+    /// Records a bundle of the sender mined in block `block_number`
+    pub(crate) fn record_mined(&self, block_number: u64) {
+        self.state.write().unwrap().last_mine = Some((block_number, Instant::now()));
+    }
+
+    pub(crate) fn set_circuit_open_until(&self, until: Option<Instant>) {
+        self.state.write().unwrap().circuit_open_until = until;
+    }
+
+    pub(crate) fn set_stalled(&self, stalled: bool) {
+        self.state.write().unwrap().stalled = stalled;
+    }
+
+    /// Returns a snapshot of the sender's health
+    pub(crate) fn status(&self) -> BuilderHealthStatus {
+        let state = self.state.read().unwrap();
+        let last_block_number = state.last_block.map(|(number, _)| number);
+        let blocks_since_last_mine = last_block_number.and_then(|last| {
+            state
+                .last_mine
+                .map(|(mined, _)| mined)
+                .or(state.first_block)
+                .map(|since| last.saturating_sub(since))
+        });
+
+        BuilderHealthStatus {
+            entry_point: self.entry_point,
+            builder_index: self.builder_index,
+            last_block_number,
+            time_since_last_block: state.last_block.map(|(_, at)| at.elapsed()),
+            last_mined_block_number: state.last_mine.map(|(number, _)| number),
+            time_since_last_mine: state.last_mine.map(|(_, at)| at.elapsed()),
+            blocks_since_last_mine,
+            circuit_open: state
+                .circuit_open_until
+                .is_some_and(|until| until > Instant::now()),
+            stalled: state.stalled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        let health = BuilderHealth::new(Address::random(), 0);
+        let status = health.status();
+        assert_eq!(status.last_block_number, None);
+        assert_eq!(status.time_since_last_block, None);
+        assert_eq!(status.blocks_since_last_mine, None);
+
+        // counts from the first block until a bundle is mined
+        health.record_block(10);
+        health.record_block(15);
+        assert_eq!(health.status().blocks_since_last_mine, Some(5));
+
+        health.record_mined(14);
+        health.record_block(17);
+        // an old block doesn't replace the latest one
+        health.record_block(16);
+
+        let status = health.status();
+        assert_eq!(status.last_block_number, Some(17));
+        assert!(status.time_since_last_block.is_some());
+        assert_eq!(status.last_mined_block_number, Some(14));
+        assert!(status.time_since_last_mine.is_some());
+        assert_eq!(status.blocks_since_last_mine, Some(3));
+        assert!(!status.circuit_open);
+        assert!(!status.stalled);
+    }
+
+    #[test]
+    fn test_circuit_open_expires() {
+        let health = BuilderHealth::new(Address::random(), 0);
+        health.set_circuit_open_until(Some(Instant::now() + Duration::from_secs(60)));
+        assert!(health.status().circuit_open);
+
+        health.set_circuit_open_until(Some(Instant::now()));
+        assert!(!health.status().circuit_open);
+    }
+}
@@ -20,23 +20,31 @@
 //! Bundle builder implementation for the Rundler.
 
 mod bundle_proposer;
+pub use bundle_proposer::BeneficiarySelection;
 mod bundle_sender;
 
 mod emit;
 pub use emit::{BuilderEvent, BuilderEventKind};
 
+mod health;
+pub use health::BuilderHealthStatus;
+
 mod sender;
 pub use sender::{
     BloxrouteSenderArgs, FlashbotsSenderArgs, RawSenderArgs, TransactionSenderArgs,
     TransactionSenderKind,
 };
 
+mod replacement;
+
 mod server;
 pub use server::{LocalBuilderBuilder, LocalBuilderHandle, RemoteBuilderClient};
 
 mod signer;
 
 mod task;
-pub use task::{Args as BuilderTaskArgs, BuilderTask, EntryPointBuilderSettings};
+pub use task::{
+    Args as BuilderTaskArgs, BuilderTask, EntryPointBuilderSettings, Settings as BuilderSettings,
+};
 
 mod transaction_tracker;
@@ -0,0 +1,278 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt::Debug;
+
+use ethers::types::transaction::eip2718::TypedTransaction;
+use rand::Rng;
+use rundler_types::GasFees;
+use rundler_utils::math;
+
+/// Maximum jitter added to a growing fee increase, as a percentage of the increase
+const FEE_INCREASE_JITTER_PERCENT: u64 = 10;
+
+/// Rules that the fees of a transaction must satisfy for nodes to accept it as a
+/// replacement for a pending transaction with the same nonce
+pub(crate) trait ReplacementPolicy: Debug + Send + Sync {
+    /// Returns the minimum fees of a transaction replacing a pending transaction
+    /// sent with `fees`
+    fn replacement_fees(&self, fees: GasFees) -> GasFees;
+}
+
+/// Replacement rules for EIP-1559 transactions.
+///
+/// Nodes require both the max fee and the max priority fee to be increased. Each
+/// fee is also raised to at least its `floor`, for chains enforcing a minimum fee
+/// that a percent increase of a near zero fee would never reach.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Eip1559ReplacementPolicy {
+    pub(crate) percent_increase: u64,
+    pub(crate) floor: GasFees,
+}
+
+impl ReplacementPolicy for Eip1559ReplacementPolicy {
+    fn replacement_fees(&self, fees: GasFees) -> GasFees {
+        fees.increase_by_percent_with_floor(
+            self.percent_increase,
+            self.floor.max_priority_fee_per_gas,
+            self.floor.max_fee_per_gas,
+        )
+    }
+}
+
+/// Replacement rules for legacy transactions.
+///
+/// A legacy transaction has a single gas price, serving as both its max fee and its
+/// priority fee, so that is the fee increased. The replacement's priority fee is set
+/// to the same value, so that an EIP-1559 replacement, such as a cancellation, is
+/// also accepted. The gas price is raised to at least both fees of the `floor`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LegacyReplacementPolicy {
+    pub(crate) percent_increase: u64,
+    pub(crate) floor: GasFees,
+}
+
+impl ReplacementPolicy for LegacyReplacementPolicy {
+    fn replacement_fees(&self, fees: GasFees) -> GasFees {
+        let gas_price = math::increase_by_percent_ceil(fees.max_fee_per_gas, self.percent_increase)
+            .max(self.floor.max_fee_per_gas)
+            .max(self.floor.max_priority_fee_per_gas);
+        GasFees {
+            max_fee_per_gas: gas_price,
+            max_priority_fee_per_gas: gas_price,
+        }
+    }
+}
+
+/// Percentage fee increases of the successive replacements of a transaction.
+///
+/// The increase of the replacement of attempt `n` is `base_percent * multiplier^n`,
+/// capped at `max_percent`. It is never lower than `base_percent`, the increase nodes
+/// require to accept a replacement. A multiplier of 1 keeps the increase flat.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct FeeIncreaseSchedule {
+    pub(crate) base_percent: u64,
+    pub(crate) multiplier: f64,
+    pub(crate) max_percent: u64,
+}
+
+impl FeeIncreaseSchedule {
+    /// Returns the percent increase of the replacement of the transaction sent on
+    /// attempt `attempt`, without jitter
+    pub(crate) fn percent_increase(&self, attempt: u64) -> u64 {
+        let exponent = i32::try_from(attempt).unwrap_or(i32::MAX);
+        // float to int casts saturate
+        let percent = (self.base_percent as f64 * self.multiplier.powi(exponent)).round() as u64;
+        percent.min(self.max_percent).max(self.base_percent)
+    }
+
+    /// Returns the percent increase of the replacement of the transaction sent on
+    /// attempt `attempt`. Growing increases are raised by a random jitter of up to
+    /// `FEE_INCREASE_JITTER_PERCENT` percent, so that builders don't all bump fees in
+    /// lockstep.
+    pub(crate) fn jittered_percent_increase(&self, attempt: u64) -> u64 {
+        let percent = self.percent_increase(attempt);
+        if self.multiplier <= 1.0 {
+            return percent;
+        }
+        let jitter =
+            rand::thread_rng().gen_range(0..=math::percent(percent, FEE_INCREASE_JITTER_PERCENT));
+        (percent + jitter)
+            .min(self.max_percent)
+            .max(self.base_percent)
+    }
+}
+
+/// Returns the replacement policy for transactions of the same type as `tx`
+pub(crate) fn policy_for(
+    tx: &TypedTransaction,
+    percent_increase: u64,
+    floor: GasFees,
+) -> Box<dyn ReplacementPolicy> {
+    match tx {
+        TypedTransaction::Legacy(_) | TypedTransaction::Eip2930(_) => {
+            Box::new(LegacyReplacementPolicy {
+                percent_increase,
+                floor,
+            })
+        }
+        TypedTransaction::Eip1559(_) => Box::new(Eip1559ReplacementPolicy {
+            percent_increase,
+            floor,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Eip1559TransactionRequest, TransactionRequest, U256};
+
+    use super::*;
+
+    // whether a node bumping fees by `percent` accepts `new` as a replacement for `old`
+    fn is_accepted(old: GasFees, new: GasFees, percent: u64) -> bool {
+        let bumped = |fee: U256| fee * (100 + percent) / 100;
+        new.max_fee_per_gas >= bumped(old.max_fee_per_gas)
+            && new.max_priority_fee_per_gas >= bumped(old.max_priority_fee_per_gas)
+    }
+
+    #[test]
+    fn test_eip1559_replacement_fees() {
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .max_fee_per_gas(U256::from(101))
+            .max_priority_fee_per_gas(U256::from(7))
+            .into();
+        let fees = GasFees::from(&tx);
+
+        let replacement = policy_for(&tx, 10, GasFees::default()).replacement_fees(fees);
+
+        assert!(is_accepted(fees, replacement, 10));
+        assert_eq!(
+            replacement,
+            GasFees {
+                max_fee_per_gas: U256::from(112),
+                max_priority_fee_per_gas: U256::from(8),
+            }
+        );
+    }
+
+    #[test]
+    fn test_flat_fee_increases() {
+        let schedule = FeeIncreaseSchedule {
+            base_percent: 10,
+            multiplier: 1.0,
+            max_percent: 100,
+        };
+
+        for attempt in 0..5 {
+            assert_eq!(schedule.percent_increase(attempt), 10);
+            assert_eq!(schedule.jittered_percent_increase(attempt), 10);
+        }
+    }
+
+    #[test]
+    fn test_growing_fee_increases() {
+        let schedule = FeeIncreaseSchedule {
+            base_percent: 10,
+            multiplier: 2.0,
+            max_percent: 100,
+        };
+
+        let percents = (0..6)
+            .map(|attempt| schedule.percent_increase(attempt))
+            .collect::<Vec<_>>();
+        assert_eq!(percents, vec![10, 20, 40, 80, 100, 100]);
+
+        // jitter only increases the fee, by at most 10%, up to the cap
+        for (attempt, percent) in percents.into_iter().enumerate() {
+            let jittered = schedule.jittered_percent_increase(attempt as u64);
+            assert!(jittered >= percent && jittered <= (percent + percent / 10).min(100));
+        }
+
+        // the fees of successive replacements
+        let mut fees = GasFees {
+            max_fee_per_gas: U256::from(1000),
+            max_priority_fee_per_gas: U256::from(100),
+        };
+        let mut max_fees = vec![];
+        for attempt in 0..4 {
+            let policy = Eip1559ReplacementPolicy {
+                percent_increase: schedule.percent_increase(attempt),
+                floor: GasFees::default(),
+            };
+            fees = policy.replacement_fees(fees);
+            max_fees.push(fees.max_fee_per_gas.as_u64());
+        }
+        assert_eq!(max_fees, vec![1100, 1320, 1848, 3327]);
+    }
+
+    #[test]
+    fn test_fee_increases_never_below_base() {
+        // nodes require at least the base increase to accept a replacement
+        let schedule = FeeIncreaseSchedule {
+            base_percent: 10,
+            multiplier: 0.5,
+            max_percent: 5,
+        };
+
+        for attempt in 0..5 {
+            assert_eq!(schedule.percent_increase(attempt), 10);
+            assert_eq!(schedule.jittered_percent_increase(attempt), 10);
+        }
+    }
+
+    #[test]
+    fn test_replacement_fees_floor() {
+        let fees = GasFees {
+            max_fee_per_gas: U256::from(100),
+            max_priority_fee_per_gas: U256::from(1),
+        };
+        let floor = GasFees {
+            max_fee_per_gas: U256::from(120),
+            max_priority_fee_per_gas: U256::from(20),
+        };
+
+        // a 10% increase alone is below the floor, the floor is used
+        let tx: TypedTransaction = Eip1559TransactionRequest::new().into();
+        let replacement = policy_for(&tx, 10, floor).replacement_fees(fees);
+        assert!(is_accepted(fees, replacement, 10));
+        assert_eq!(replacement, floor);
+
+        let tx: TypedTransaction = TransactionRequest::new().into();
+        let replacement = policy_for(&tx, 10, floor).replacement_fees(fees);
+        assert_eq!(
+            replacement,
+            GasFees {
+                max_fee_per_gas: U256::from(120),
+                max_priority_fee_per_gas: U256::from(120),
+            }
+        );
+    }
+
+    #[test]
+    fn test_legacy_replacement_fees() {
+        let tx: TypedTransaction = TransactionRequest::new().gas_price(U256::from(101)).into();
+        let fees = GasFees::from(&tx);
+
+        let replacement = policy_for(&tx, 10, GasFees::default()).replacement_fees(fees);
+
+        assert!(is_accepted(fees, replacement, 10));
+        assert_eq!(
+            replacement,
+            GasFees {
+                max_fee_per_gas: U256::from(112),
+                max_priority_fee_per_gas: U256::from(112),
+            }
+        );
+    }
+}
@@ -11,6 +11,8 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
+use std::sync::{Arc, RwLock};
+
 use async_trait::async_trait;
 use ethers::types::{Address, H256};
 use rundler_task::server::{HealthCheck, ServerStatus};
@@ -21,13 +23,17 @@ use tokio::{
 };
 use tokio_util::sync::CancellationToken;
 
-use crate::bundle_sender::{BundleSenderAction, SendBundleRequest, SendBundleResult};
+use crate::{
+    bundle_sender::{BundleSenderAction, SendBundleRequest, SendBundleResult},
+    health::{BuilderHealth, BuilderHealthStatus},
+};
 
 /// Local builder server builder
 #[derive(Debug)]
 pub struct LocalBuilderBuilder {
     req_sender: mpsc::Sender<ServerRequest>,
     req_receiver: mpsc::Receiver<ServerRequest>,
+    bundle_sender_health: Arc<RwLock<Vec<Arc<BuilderHealth>>>>,
 }
 
 impl LocalBuilderBuilder {
@@ -37,6 +43,7 @@ impl LocalBuilderBuilder {
         Self {
             req_sender,
             req_receiver,
+            bundle_sender_health: Arc::default(),
         }
     }
 
@@ -44,9 +51,15 @@ impl LocalBuilderBuilder {
     pub fn get_handle(&self) -> LocalBuilderHandle {
         LocalBuilderHandle {
             req_sender: self.req_sender.clone(),
+            bundle_sender_health: Arc::clone(&self.bundle_sender_health),
         }
     }
 
+    /// Registers the health of a bundle sender, reported by the handles
+    pub(crate) fn add_bundle_sender_health(&self, health: Arc<BuilderHealth>) {
+        self.bundle_sender_health.write().unwrap().push(health);
+    }
+
     /// Run the local builder server, consuming the builder
     pub fn run(
         self,
@@ -64,6 +77,7 @@ impl LocalBuilderBuilder {
 #[derive(Debug, Clone)]
 pub struct LocalBuilderHandle {
     req_sender: mpsc::Sender<ServerRequest>,
+    bundle_sender_health: Arc<RwLock<Vec<Arc<BuilderHealth>>>>,
 }
 
 struct LocalBuilderServerRunner {
@@ -73,6 +87,17 @@ struct LocalBuilderServerRunner {
 }
 
 impl LocalBuilderHandle {
+    /// Returns the health of each bundle sender, for liveness and readiness probes.
+    /// Empty until the builder task has started its senders.
+    pub fn bundle_sender_health(&self) -> Vec<BuilderHealthStatus> {
+        self.bundle_sender_health
+            .read()
+            .unwrap()
+            .iter()
+            .map(|health| health.status())
+            .collect()
+    }
+
     async fn send(&self, request: ServerRequestKind) -> BuilderResult<ServerResponse> {
         let (response_sender, response_receiver) = oneshot::channel();
         let request = ServerRequest {
@@ -168,7 +193,9 @@ impl LocalBuilderServerRunner {
 
                                 let (tx, rx) = oneshot::channel();
                                 match self.bundle_sender_actions[0].send(BundleSenderAction::SendBundle(SendBundleRequest{
-                                    responder: tx
+                                    responder: tx,
+                                    dry_run: false,
+                                    priority_ops: vec![],
                                 })).await {
                                     Ok(()) => {},
                                     Err(e) => break 'a Err(anyhow::anyhow!("failed to send send bundle request: {}", e.to_string()).into())
@@ -183,10 +210,14 @@ impl LocalBuilderServerRunner {
                                     SendBundleResult::Success { tx_hash, block_number, .. } => {
                                         Ok(ServerResponse::DebugSendBundleNow { hash: tx_hash, block_number })
                                     },
-                                    SendBundleResult::NoOperationsInitially => {
-                                        Err(anyhow::anyhow!("no ops to send").into())
+                                    SendBundleResult::NoOperationsInitially { reason } => {
+                                        Err(anyhow::anyhow!("no ops to send: {reason}").into())
                                     },
                                     SendBundleResult::StalledAtMaxFeeIncreases => Err(anyhow::anyhow!("stalled at max fee increases").into()),
+                                    SendBundleResult::Cancelled { nonce } => Err(anyhow::anyhow!("bundle transaction cancelled at nonce {nonce}").into()),
+                                    SendBundleResult::DryRun { .. } => Err(anyhow::anyhow!("unexpected dry run result").into()),
+                                    SendBundleResult::EntryPointPaused => Err(anyhow::anyhow!("entry point paused").into()),
+                                    SendBundleResult::CircuitOpen { retry_in } => Err(anyhow::anyhow!("circuit breaker open, retrying in {retry_in:?}").into()),
                                     SendBundleResult::Error(e) => Err(anyhow::anyhow!("send bundle error: {e:?}").into()),
                                 }
                             },
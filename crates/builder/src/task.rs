@@ -21,7 +21,7 @@ use ethers::{
 };
 use ethers_signers::Signer;
 use futures::future;
-use futures_util::TryFutureExt;
+use futures_util::{FutureExt, StreamExt, TryFutureExt};
 use rundler_provider::{EntryPointProvider, EthersEntryPointV0_6, EthersEntryPointV0_7};
 use rundler_sim::{
     simulation::{self, UnsafeSimulator},
@@ -29,8 +29,11 @@ use rundler_sim::{
 };
 use rundler_task::Task;
 use rundler_types::{
-    chain::ChainSpec, pool::Pool, v0_6, v0_7, EntryPointVersion, UserOperation,
-    UserOperationVariant,
+    chain::{
+        ChainSpec, ARBITRUM_CHAIN_IDS, AVALANCHE_CHAIN_IDS, BASE_CHAIN_IDS, POLYGON_CHAIN_IDS,
+    },
+    pool::{NewHead, Pool},
+    v0_6, v0_7, EntryPointVersion, GasFees, UserOperation, UserOperationVariant,
 };
 use rundler_utils::{emit::WithEntryPoint, handle};
 use rusoto_core::Region;
@@ -43,7 +46,7 @@ use tokio_util::sync::CancellationToken;
 use tracing::info;
 
 use crate::{
-    bundle_proposer::{self, BundleProposerImpl},
+    bundle_proposer::{self, BeneficiarySelection, BundleProposerImpl},
     bundle_sender::{self, BundleSender, BundleSenderAction, BundleSenderImpl},
     emit::BuilderEvent,
     sender::TransactionSenderArgs,
@@ -52,6 +55,10 @@ use crate::{
     transaction_tracker::{self, TransactionTrackerImpl},
 };
 
+/// Capacity of the channel forwarding new blocks to the bundle senders. Senders only
+/// use the latest block, so a sender lagging behind skips to it.
+const NEW_HEADS_CHANNEL_CAPACITY: usize = 1024;
+
 /// Builder task arguments
 #[derive(Debug)]
 pub struct Args {
@@ -75,10 +82,10 @@ pub struct Args {
     pub redis_lock_ttl_millis: u64,
     /// Maximum bundle size in number of operations
     pub max_bundle_size: u64,
-    /// Maximum bundle size in gas limit
-    pub max_bundle_gas: u64,
-    /// Percentage to add to the network priority fee for the bundle priority fee
-    pub bundle_priority_fee_overhead_percent: u64,
+    /// Gas and fee settings, defaulting to the chain's preset
+    pub settings: Settings,
+    /// Maximum size in bytes of an operation's `paymasterAndData` and `signature` fields
+    pub max_op_field_size: Option<usize>,
     /// Priority fee mode to use for operation priority fee minimums
     pub priority_fee_mode: PriorityFeeMode,
     /// Sender to be used by the builder
@@ -87,12 +94,110 @@ pub struct Args {
     pub sim_settings: SimulationSettings,
     /// Maximum number of blocks to wait for a transaction to be mined
     pub max_blocks_to_wait_for_mine: u64,
-    /// Percentage to increase the fees by when replacing a bundle transaction
-    pub replacement_fee_percent_increase: u64,
+    /// Maximum time a bundle transaction may stay pending, across fee increases, before
+    /// it is cancelled, if any
+    pub max_pending_time: Option<Duration>,
+    /// Factor the fee increase percentage grows by on each replacement. 1.0 keeps the
+    /// increase flat at `settings.replacement_fee_percent_increase`.
+    pub fee_increase_multiplier: f64,
+    /// Minimum max priority fee per gas of a replacement bundle transaction, in wei
+    pub min_replacement_priority_fee: u128,
+    /// Minimum max fee per gas of a replacement bundle transaction, in wei
+    pub min_replacement_max_fee: u128,
     /// Maximum number of times to increase the fee when cancelling a transaction
     pub max_cancellation_fee_increases: u64,
+    /// Whether to cancel the pending bundle transaction when it is abandoned after fee
+    /// increases. If false, the transaction is left pending until it mines or drops.
+    pub cancel_on_stall: bool,
     /// Maximum amount of blocks to spend in a replacement underpriced state before moving to cancel
     pub max_replacement_underpriced_blocks: u64,
+    /// If set, the extra percentage to increase fees by after a replacement is
+    /// rejected as underpriced, retrying the bundle attempt right away instead of
+    /// on the next trigger
+    pub replacement_underpriced_fee_bump_percent: Option<u64>,
+    /// Minimum time to wait between sending new bundles
+    pub min_bundle_interval: Duration,
+    /// Interval to poll for transaction updates while a bundle is pending, if none,
+    /// poll on each new block
+    pub pending_poll_interval: Option<Duration>,
+    /// Maximum number of operations from a throttled paymaster to include in a bundle
+    pub throttled_paymaster_bundle_count: usize,
+    /// Number of consecutive mined bundles an operation can be included in without being
+    /// executed before it is removed from the pool. If none, operations are never removed
+    /// for this reason.
+    pub max_bundle_reverts_per_op: Option<u64>,
+    /// In manual bundling mode, whether all queued send bundle requests share the
+    /// result of the next bundle, instead of being handled one bundle at a time
+    pub coalesce_manual_bundle_requests: bool,
+    /// Whether to send bundles as legacy transactions with a single gas price
+    pub use_legacy_gas: bool,
+    /// Maximum number of rejected operations to remove from the pool in a single request
+    pub rejected_ops_removal_batch_size: usize,
+    /// Maximum gas limit of a bundle transaction, including the gas estimate overhead.
+    /// If none, bundle transactions are not limited beyond `settings.max_bundle_gas`.
+    pub max_bundle_transaction_gas: Option<u64>,
+    /// Whether to simulate each bundle against the latest block right before
+    /// sending it, removing ops that fail from the pool and re-proposing the bundle
+    pub validate_bundle_before_send: bool,
+    /// Maximum number of operations in a bundle. Operations over the limit are left
+    /// in the pool for a later bundle. If none, bundles are not limited beyond
+    /// `max_bundle_size`.
+    pub max_ops_per_bundle: Option<usize>,
+    /// Maximum number of operations sharing a paymaster or factory in a bundle.
+    /// Operations over the limit are left in the pool for a later bundle. If none,
+    /// operations are not limited per entity.
+    pub max_ops_per_entity_per_bundle: Option<usize>,
+    /// Maximum size in bytes of the calldata of a bundle transaction. The largest
+    /// operations of a bundle over the limit are left in the pool for a later bundle.
+    /// If none, the calldata is not limited.
+    pub max_bundle_calldata_bytes: Option<usize>,
+    /// Minimum max priority fee per gas of an operation to bundle it, in wei.
+    /// Operations below it are left in the pool. Zero disables the minimum.
+    pub min_op_priority_fee: u128,
+    /// Pause guardian contract, implementing `paused() returns (bool)`, to check
+    /// before building bundles. If none, bundling is never paused.
+    pub pause_guardian: Option<Address>,
+    /// Minimum interval between pause guardian checks
+    pub pause_check_interval: Duration,
+    /// Number of consecutive failed bundle attempts after which bundle attempts are
+    /// backed off exponentially until one succeeds. If none, attempts are never
+    /// backed off.
+    pub consecutive_failure_threshold: Option<u64>,
+    /// Maximum backoff between bundle attempts after consecutive failures
+    pub max_failure_backoff: Duration,
+    /// Whether to sort the ops of each bundle into a stable order by sender and
+    /// nonce, instead of keeping the proposer's order. For reproducible tests.
+    pub deterministic_ordering: bool,
+    /// Maximum backoff between automatic bundle attempts while the pool has no
+    /// operations. Zero disables the backoff.
+    pub idle_backoff_max: Duration,
+    /// Percentage to increase the gas limit of a bundle by on each replacement,
+    /// compounded. Zero keeps the estimated gas limit.
+    pub gas_limit_increase_percent: u64,
+    /// Maximum number of bundle transactions in flight at consecutive nonces. One
+    /// waits for each bundle to mine before sending the next.
+    pub max_inflight_bundles: usize,
+    /// Beneficiaries to select the beneficiary of each bundle from. If empty, the
+    /// signer's address is the only beneficiary.
+    pub beneficiaries: Vec<Address>,
+    /// How the beneficiary of each bundle is selected from `beneficiaries`
+    pub beneficiary_selection: BeneficiarySelection,
+    /// Beneficiaries to fall back to, in order, if the bundle beneficiary fails to
+    /// receive the bundle's fees
+    pub fallback_beneficiaries: Vec<Address>,
+    /// Whether to simulate each candidate op against the state of the ops
+    /// already selected for the bundle, with one `eth_call` per candidate
+    /// executing all the ops selected before it
+    pub simulate_against_bundle_state: bool,
+    /// Number of consecutive bundle proposals an operation can fail simulation in with
+    /// a transient error, such as an RPC error, before it is removed from the pool. If
+    /// none, operations are never removed for this reason.
+    pub max_transient_simulation_failures: Option<u64>,
+    /// Maximum number of operations from a single sender to include in any
+    /// `sender_window_bundles` consecutive bundles. If none, senders are not throttled.
+    pub max_ops_per_sender_in_window: Option<u64>,
+    /// Number of consecutive bundles that `max_ops_per_sender_in_window` applies to
+    pub sender_window_bundles: u64,
     /// Address to bind the remote builder server to, if any. If none, no server is starter.
     pub remote_address: Option<SocketAddr>,
     /// Entry points to start builders for
@@ -114,6 +219,69 @@ pub struct EntryPointBuilderSettings {
     pub mempool_configs: HashMap<H256, MempoolConfig>,
 }
 
+/// Gas and fee settings of the builder
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Settings {
+    /// Maximum bundle size in gas limit
+    pub max_bundle_gas: u64,
+    /// Percentage to add to the network priority fee for the bundle priority fee
+    pub bundle_priority_fee_overhead_percent: u64,
+    /// Percentage to add to the bundle transaction gas estimate as a buffer. Zero
+    /// disables the buffer.
+    pub gas_estimate_overhead_percent: u64,
+    /// Percentage to increase the fees by when replacing a bundle transaction
+    pub replacement_fee_percent_increase: u64,
+    /// Maximum percentage to increase the fees by when replacing a bundle transaction
+    pub max_fee_increase_percent: u64,
+}
+
+impl Settings {
+    /// Returns default settings for the chain with ID `chain_id`, to be overridden
+    /// as needed.
+    ///
+    /// These are the mainnet settings, except:
+    /// - On the Avalanche C-Chain, blocks are limited to 15M gas, so bundles are too.
+    /// - On Arbitrum, gas limits include the L1 data cost, which moves with the L1
+    ///   fees between estimation and inclusion, so the gas estimate buffer is larger.
+    /// - On Base, the sequencer orders transactions by priority fee and most pay the
+    ///   chain's minimum, so bundles pay more than the network priority fee to be
+    ///   included ahead of them.
+    /// - On Polygon PoS, fees spike quickly, so replacements increase fees by more
+    ///   to replace a stuck bundle in fewer attempts.
+    ///
+    /// The CLI defaults its flags for these settings to the preset of the configured
+    /// chain.
+    pub fn preset_for_chain(chain_id: u64) -> Self {
+        let defaults = Self {
+            max_bundle_gas: 25_000_000,
+            bundle_priority_fee_overhead_percent: 0,
+            gas_estimate_overhead_percent: 5,
+            replacement_fee_percent_increase: 10,
+            max_fee_increase_percent: 100,
+        };
+
+        match chain_id {
+            id if AVALANCHE_CHAIN_IDS.contains(&id) => Self {
+                max_bundle_gas: 15_000_000,
+                ..defaults
+            },
+            id if ARBITRUM_CHAIN_IDS.contains(&id) => Self {
+                gas_estimate_overhead_percent: 15,
+                ..defaults
+            },
+            id if BASE_CHAIN_IDS.contains(&id) => Self {
+                bundle_priority_fee_overhead_percent: 20,
+                ..defaults
+            },
+            id if POLYGON_CHAIN_IDS.contains(&id) => Self {
+                replacement_fee_percent_increase: 20,
+                ..defaults
+            },
+            _ => defaults,
+        }
+    }
+}
+
 /// Builder task
 #[derive(Debug)]
 pub struct BuilderTask<P> {
@@ -152,6 +320,8 @@ where
         let mut sender_handles = vec![];
         let mut bundle_sender_actions = vec![];
         let mut pk_iter = self.args.private_keys.clone().into_iter();
+        // senders of all entry points share a single subscription to new blocks
+        let (new_heads_sender, _) = broadcast::channel(NEW_HEADS_CHANNEL_CAPACITY);
 
         for ep in &self.args.entry_points {
             match ep.version {
@@ -163,6 +333,8 @@ where
                             submit_provider.clone(),
                             ep_v0_6.clone(),
                             &mut pk_iter,
+                            &new_heads_sender,
+                            &shutdown_token,
                         )
                         .await?;
                     sender_handles.extend(handles);
@@ -176,6 +348,8 @@ where
                             submit_provider.clone(),
                             ep_v0_7.clone(),
                             &mut pk_iter,
+                            &new_heads_sender,
+                            &shutdown_token,
                         )
                         .await?;
                     sender_handles.extend(handles);
@@ -187,17 +361,25 @@ where
             }
         }
 
+        // start forwarding blocks once all senders are subscribed. On shutdown, blocks
+        // are forwarded until all senders have resolved their in-flight transactions.
+        let senders_done = CancellationToken::new();
+        let new_heads_handle = self
+            .forward_new_heads(new_heads_sender, senders_done.clone())
+            .await?;
+
         // flatten the senders handles to one handle, short-circuit on errors
         let sender_handle = tokio::spawn(
             future::try_join_all(sender_handles)
                 .map_ok(|_| ())
-                .map_err(|e| anyhow::anyhow!(e)),
+                .map_err(|e| anyhow::anyhow!(e))
+                .inspect(move |_| senders_done.cancel()),
         );
 
         let builder_handle = self.builder_builder.get_handle();
         let builder_runnder_handle = self.builder_builder.run(
             bundle_sender_actions,
-            vec![self.args.chain_spec.entry_point_address_v0_6],
+            self.args.entry_points.iter().map(|ep| ep.address).collect(),
             shutdown_token.clone(),
         );
 
@@ -218,6 +400,7 @@ where
 
         match try_join!(
             handle::flatten_handle(sender_handle),
+            handle::flatten_handle(new_heads_handle),
             handle::flatten_handle(builder_runnder_handle),
             handle::flatten_handle(remote_handle),
         ) {
@@ -264,6 +447,8 @@ where
         submit_provider: Option<Arc<EthersProvider<C>>>,
         ep_v0_6: E,
         pk_iter: &mut I,
+        new_heads_sender: &broadcast::Sender<NewHead>,
+        shutdown_token: &CancellationToken,
     ) -> anyhow::Result<(
         Vec<JoinHandle<anyhow::Result<()>>>,
         Vec<mpsc::Sender<BundleSenderAction>>,
@@ -289,6 +474,8 @@ where
                         self.args.sim_settings.clone(),
                     ),
                     pk_iter,
+                    new_heads_sender,
+                    shutdown_token,
                 )
                 .await?
             } else {
@@ -304,6 +491,8 @@ where
                         ep.mempool_configs.clone(),
                     ),
                     pk_iter,
+                    new_heads_sender,
+                    shutdown_token,
                 )
                 .await?
             };
@@ -320,6 +509,8 @@ where
         submit_provider: Option<Arc<EthersProvider<C>>>,
         ep_v0_7: E,
         pk_iter: &mut I,
+        new_heads_sender: &broadcast::Sender<NewHead>,
+        shutdown_token: &CancellationToken,
     ) -> anyhow::Result<(
         Vec<JoinHandle<anyhow::Result<()>>>,
         Vec<mpsc::Sender<BundleSenderAction>>,
@@ -345,6 +536,8 @@ where
                         self.args.sim_settings.clone(),
                     ),
                     pk_iter,
+                    new_heads_sender,
+                    shutdown_token,
                 )
                 .await?
             } else {
@@ -360,6 +553,8 @@ where
                         ep.mempool_configs.clone(),
                     ),
                     pk_iter,
+                    new_heads_sender,
+                    shutdown_token,
                 )
                 .await?
             };
@@ -377,6 +572,8 @@ where
         entry_point: E,
         simulator: S,
         pk_iter: &mut I,
+        new_heads_sender: &broadcast::Sender<NewHead>,
+        shutdown_token: &CancellationToken,
     ) -> anyhow::Result<(
         JoinHandle<anyhow::Result<()>>,
         mpsc::Sender<BundleSenderAction>,
@@ -425,14 +622,29 @@ where
             info!("Created AWS KMS signer");
             ret
         };
-        let beneficiary = signer.address();
+        let beneficiaries = if self.args.beneficiaries.is_empty() {
+            vec![signer.address()]
+        } else {
+            self.args.beneficiaries.clone()
+        };
         let proposer_settings = bundle_proposer::Settings {
             chain_spec: self.args.chain_spec.clone(),
             max_bundle_size: self.args.max_bundle_size,
-            max_bundle_gas: self.args.max_bundle_gas,
-            beneficiary,
+            max_bundle_gas: self.args.settings.max_bundle_gas,
+            beneficiaries,
+            beneficiary_selection: self.args.beneficiary_selection,
+            fallback_beneficiaries: self.args.fallback_beneficiaries.clone(),
             priority_fee_mode: self.args.priority_fee_mode,
-            bundle_priority_fee_overhead_percent: self.args.bundle_priority_fee_overhead_percent,
+            bundle_priority_fee_overhead_percent: self
+                .args
+                .settings
+                .bundle_priority_fee_overhead_percent,
+            simulate_against_bundle_state: self.args.simulate_against_bundle_state,
+            max_op_field_size: self.args.max_op_field_size,
+            max_transient_simulation_failures: self.args.max_transient_simulation_failures,
+            gas_estimate_overhead_percent: self.args.settings.gas_estimate_overhead_percent,
+            max_ops_per_sender_in_window: self.args.max_ops_per_sender_in_window,
+            sender_window_bundles: self.args.sender_window_bundles,
         };
 
         let transaction_sender = self.args.sender_args.clone().into_sender(
@@ -442,7 +654,17 @@ where
         )?;
 
         let tracker_settings = transaction_tracker::Settings {
-            replacement_fee_percent_increase: self.args.replacement_fee_percent_increase,
+            replacement_fee_percent_increase: self.args.settings.replacement_fee_percent_increase,
+            fee_increase_multiplier: self.args.fee_increase_multiplier,
+            max_fee_increase_percent: self.args.settings.max_fee_increase_percent,
+            min_replacement_fees: GasFees {
+                max_fee_per_gas: self.args.min_replacement_max_fee.into(),
+                max_priority_fee_per_gas: self.args.min_replacement_priority_fee.into(),
+            },
+            replacement_underpriced_fee_bump_percent: self
+                .args
+                .replacement_underpriced_fee_bump_percent
+                .unwrap_or(0),
         };
 
         let transaction_tracker = TransactionTrackerImpl::new(
@@ -455,8 +677,39 @@ where
 
         let builder_settings = bundle_sender::Settings {
             max_replacement_underpriced_blocks: self.args.max_replacement_underpriced_blocks,
+            retry_replacement_underpriced: self
+                .args
+                .replacement_underpriced_fee_bump_percent
+                .is_some(),
             max_cancellation_fee_increases: self.args.max_cancellation_fee_increases,
+            cancel_on_stall: self.args.cancel_on_stall,
             max_blocks_to_wait_for_mine: self.args.max_blocks_to_wait_for_mine,
+            max_pending_time: self.args.max_pending_time,
+            min_bundle_interval: self.args.min_bundle_interval,
+            pending_poll_interval: self.args.pending_poll_interval,
+            throttled_paymaster_bundle_count: self.args.throttled_paymaster_bundle_count,
+            max_bundle_reverts_per_op: self.args.max_bundle_reverts_per_op,
+            coalesce_manual_bundle_requests: self.args.coalesce_manual_bundle_requests,
+            use_legacy_gas: self.args.use_legacy_gas,
+            rejected_ops_removal_batch_size: self.args.rejected_ops_removal_batch_size,
+            max_bundle_gas: self.args.max_bundle_transaction_gas.unwrap_or(u64::MAX),
+            gas_estimate_overhead_percent: self.args.settings.gas_estimate_overhead_percent,
+            validate_bundle_before_send: self.args.validate_bundle_before_send,
+            max_ops_per_bundle: self.args.max_ops_per_bundle.unwrap_or(usize::MAX),
+            max_ops_per_entity_per_bundle: self
+                .args
+                .max_ops_per_entity_per_bundle
+                .unwrap_or(usize::MAX),
+            max_bundle_calldata_bytes: self.args.max_bundle_calldata_bytes.unwrap_or(usize::MAX),
+            min_op_priority_fee: self.args.min_op_priority_fee.into(),
+            pause_guardian: self.args.pause_guardian,
+            pause_check_interval: self.args.pause_check_interval,
+            consecutive_failure_threshold: self.args.consecutive_failure_threshold,
+            max_failure_backoff: self.args.max_failure_backoff,
+            deterministic_ordering: self.args.deterministic_ordering,
+            idle_backoff_max: self.args.idle_backoff_max,
+            gas_limit_increase_percent: self.args.gas_limit_increase_percent,
+            max_inflight_bundles: self.args.max_inflight_bundles,
         };
 
         let proposer = BundleProposerImpl::new(
@@ -471,8 +724,8 @@ where
         let builder = BundleSenderImpl::new(
             index,
             send_bundle_rx,
+            new_heads_sender.subscribe(),
             self.args.chain_spec.clone(),
-            beneficiary,
             proposer,
             entry_point,
             transaction_tracker,
@@ -480,8 +733,109 @@ where
             builder_settings,
             self.event_sender.clone(),
         );
+        self.builder_builder
+            .add_bundle_sender_health(builder.health());
 
         // Spawn each sender as its own independent task
-        Ok((tokio::spawn(builder.send_bundles_in_loop()), send_bundle_tx))
+        Ok((
+            tokio::spawn(builder.send_bundles_in_loop(shutdown_token.clone())),
+            send_bundle_tx,
+        ))
+    }
+
+    // Forwards new blocks from the pool to the bundle senders, so that all senders
+    // react to the same blocks
+    async fn forward_new_heads(
+        &self,
+        new_heads_sender: broadcast::Sender<NewHead>,
+        senders_done: CancellationToken,
+    ) -> anyhow::Result<JoinHandle<anyhow::Result<()>>> {
+        let mut new_heads = self
+            .pool
+            .subscribe_new_heads()
+            .await
+            .context("should subscribe to new blocks")?;
+
+        Ok(tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = senders_done.cancelled() => return Ok(()),
+                    new_head = new_heads.next() => {
+                        let Some(new_head) = new_head else {
+                            bail!("block stream ended")
+                        };
+                        // only errors if there are no senders left to receive the block
+                        let _ = new_heads_sender.send(new_head);
+                    }
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rundler_sim::EstimationSettings;
+
+    use super::*;
+
+    #[test]
+    fn test_preset_for_chain() {
+        let mainnet = Settings::preset_for_chain(1);
+        assert_eq!(mainnet.max_bundle_gas, 25_000_000);
+        assert_eq!(mainnet.gas_estimate_overhead_percent, 5);
+        assert_eq!(mainnet.replacement_fee_percent_increase, 10);
+
+        // limited to the Avalanche block gas limit
+        for chain_id in AVALANCHE_CHAIN_IDS {
+            assert_eq!(
+                Settings::preset_for_chain(chain_id),
+                Settings {
+                    max_bundle_gas: 15_000_000,
+                    ..mainnet
+                }
+            );
+        }
+        // larger buffer for the L1 data cost in the gas limit
+        for chain_id in ARBITRUM_CHAIN_IDS {
+            assert_eq!(
+                Settings::preset_for_chain(chain_id),
+                Settings {
+                    gas_estimate_overhead_percent: 15,
+                    ..mainnet
+                }
+            );
+        }
+        // priority fee above the network's to be ordered ahead
+        for chain_id in BASE_CHAIN_IDS {
+            assert_eq!(
+                Settings::preset_for_chain(chain_id),
+                Settings {
+                    bundle_priority_fee_overhead_percent: 20,
+                    ..mainnet
+                }
+            );
+        }
+        // larger replacement fee increases
+        for chain_id in POLYGON_CHAIN_IDS {
+            assert_eq!(
+                Settings::preset_for_chain(chain_id),
+                Settings {
+                    replacement_fee_percent_increase: 20,
+                    ..mainnet
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_preset_bundle_gas_matches_estimation() {
+        // the CLI sets both from the same flag
+        for chain_id in [1, 43114, 42161, 8453, 137] {
+            assert_eq!(
+                Settings::preset_for_chain(chain_id).max_bundle_gas,
+                EstimationSettings::preset_for_chain(chain_id).max_total_execution_gas
+            );
+        }
     }
 }
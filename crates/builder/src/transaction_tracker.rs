@@ -11,11 +11,11 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::sync::Arc;
+use std::{collections::VecDeque, mem, sync::Arc};
 
 use anyhow::{bail, Context};
 use async_trait::async_trait;
-use ethers::types::{transaction::eip2718::TypedTransaction, Address, H256, U256};
+use ethers::types::{transaction::eip2718::TypedTransaction, Address, Log, H256, U256};
 #[cfg(test)]
 use mockall::automock;
 use rundler_provider::Provider;
@@ -23,7 +23,10 @@ use rundler_sim::ExpectedStorage;
 use rundler_types::GasFees;
 use tracing::{debug, info, warn};
 
-use crate::sender::{TransactionSender, TxSenderError, TxStatus};
+use crate::{
+    replacement::{self, Eip1559ReplacementPolicy, FeeIncreaseSchedule, ReplacementPolicy},
+    sender::{TransactionSender, TxSenderError, TxStatus},
+};
 
 /// Keeps track of pending transactions in order to suggest nonces and
 /// replacement fees and ensure that transactions do not get stalled. All sent
@@ -72,12 +75,32 @@ pub(crate) trait TransactionTracker: Send + Sync + 'static {
     /// Resets the tracker to its initial state
     async fn reset(&mut self);
 
+    /// Loads the current nonce from the provider, unless a transaction is being
+    /// tracked. Returns the tracker's nonce.
+    async fn sync_nonce(&mut self) -> TransactionTrackerResult<U256>;
+
     /// Abandons the current transaction.
     /// The current transaction will still be tracked, but will no longer be considered during fee estimation
     fn abandon(&mut self);
 
     /// Un-abandons the current transaction
     fn unabandon(&mut self);
+
+    /// Returns the number of nonces with a transaction in flight: zero if no
+    /// transaction is pending, otherwise one plus the number of pipelined transactions
+    fn inflight_count(&self) -> usize;
+
+    /// Sends a transaction at the nonce after those of the transactions in flight,
+    /// without waiting for them to mine. Pipelined transactions are never replaced,
+    /// each becomes the current transaction once the transactions before it mine.
+    async fn send_pipelined_transaction(
+        &mut self,
+        tx: TypedTransaction,
+        expected_storage: &ExpectedStorage,
+    ) -> TransactionTrackerResult<H256>;
+
+    /// Returns the hashes of the pipelined transactions, in nonce order
+    fn pipelined_transactions(&self) -> Vec<H256>;
 }
 
 /// Errors that can occur while using a `TransactionTracker`.
@@ -107,6 +130,9 @@ pub(crate) enum TrackerUpdate {
         gas_limit: Option<U256>,
         gas_used: Option<U256>,
         gas_price: Option<U256>,
+        is_cancellation: bool,
+        /// Receipt logs of the mined transaction, `None` if the receipt is unavailable
+        logs: Option<Vec<Log>>,
     },
     LatestTxDropped {
         nonce: U256,
@@ -114,6 +140,12 @@ pub(crate) enum TrackerUpdate {
     NonceUsedForOtherTx {
         nonce: U256,
     },
+    /// A transaction previously reported as mined is no longer in the canonical
+    /// chain. The tracker's nonce is rewound to the sender's nonce on chain.
+    Reorged {
+        tx_hash: H256,
+        nonce: U256,
+    },
 }
 
 #[derive(Debug)]
@@ -130,18 +162,41 @@ where
     transactions: Vec<PendingTransaction>,
     has_abandoned: bool,
     attempt_count: u64,
+    // last transaction reported as mined, checked for being reorged out
+    last_mined: Option<MinedTransaction>,
+    // transactions sent at the nonces following `nonce`, in nonce order
+    pipelined: VecDeque<PipelinedTransaction>,
 }
 
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct Settings {
     pub(crate) replacement_fee_percent_increase: u64,
+    pub(crate) fee_increase_multiplier: f64,
+    pub(crate) max_fee_increase_percent: u64,
+    pub(crate) min_replacement_fees: GasFees,
+    pub(crate) replacement_underpriced_fee_bump_percent: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct MinedTransaction {
+    tx_hash: H256,
+    nonce: U256,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PipelinedTransaction {
+    nonce: U256,
+    tx: PendingTransaction,
 }
 
 #[derive(Clone, Copy, Debug)]
 struct PendingTransaction {
     tx_hash: H256,
     gas_fees: GasFees,
+    // minimum fees of a transaction replacing this one
+    replacement_fees: GasFees,
     attempt_number: u64,
+    is_cancellation: bool,
 }
 
 impl<P, T> TransactionTrackerImpl<P, T>
@@ -155,10 +210,13 @@ where
         settings: Settings,
         builder_index: u64,
     ) -> anyhow::Result<Self> {
-        let nonce = provider
-            .get_transaction_count(sender.address())
-            .await
-            .unwrap_or(U256::zero());
+        let nonce = match provider.get_transaction_count(sender.address()).await {
+            Ok(nonce) => nonce,
+            Err(error) => {
+                warn!("Failed to load nonce of builder {builder_index}, starting at 0: {error}");
+                U256::zero()
+            }
+        };
         Ok(Self {
             provider,
             sender,
@@ -168,17 +226,48 @@ where
             transactions: vec![],
             has_abandoned: false,
             attempt_count: 0,
+            last_mined: None,
+            pipelined: VecDeque::new(),
         })
     }
 
+    // percent increase required of the replacement of the next transaction sent
+    fn replacement_percent_increase(&self) -> u64 {
+        FeeIncreaseSchedule {
+            base_percent: self.settings.replacement_fee_percent_increase,
+            multiplier: self.settings.fee_increase_multiplier,
+            max_percent: self.settings.max_fee_increase_percent,
+        }
+        .jittered_percent_increase(self.attempt_count)
+    }
+
     fn set_nonce_and_clear_state(&mut self, nonce: U256) {
         self.nonce = nonce;
         self.transactions.clear();
+        self.pipelined.clear();
         self.attempt_count = 0;
         self.has_abandoned = false;
         self.update_metrics();
     }
 
+    // Moves on to the nonce after the current transaction mined. The pipelined
+    // transaction at that nonce, if any, becomes the current transaction.
+    fn advance_nonce(&mut self) {
+        let next_nonce = self.nonce + 1;
+        let next_tx = self
+            .pipelined
+            .pop_front()
+            .filter(|pipelined| pipelined.nonce == next_nonce);
+        let pipelined = mem::take(&mut self.pipelined);
+        self.set_nonce_and_clear_state(next_nonce);
+        if let Some(next_tx) = next_tx {
+            self.transactions.push(next_tx.tx);
+            self.attempt_count = 1;
+            self.pipelined = pipelined;
+            self.update_metrics();
+        }
+    }
+
     async fn get_external_nonce(&self) -> anyhow::Result<U256> {
         self.provider
             .get_transaction_count(self.sender.address())
@@ -205,10 +294,25 @@ where
         Ok(())
     }
 
+    fn validate_pipelined_transaction(&self, tx: &TypedTransaction) -> anyhow::Result<U256> {
+        let Some(&nonce) = tx.nonce() else {
+            bail!("transaction given to tracker should have nonce set");
+        };
+        let inflight_count = self.inflight_count();
+        if inflight_count == 0 {
+            bail!("tried to pipeline a transaction without a transaction in flight");
+        }
+        let required_nonce = self.nonce + inflight_count;
+        if nonce != required_nonce {
+            bail!("tried to pipeline transaction with nonce {nonce}, but should match the next nonce of {required_nonce}");
+        }
+        Ok(nonce)
+    }
+
     fn update_metrics(&self) {
         TransactionTrackerMetrics::set_num_pending_transactions(
             self.builder_index,
-            self.transactions.len(),
+            self.transactions.len() + self.pipelined.len(),
         );
         TransactionTrackerMetrics::set_nonce(self.builder_index, self.nonce);
         TransactionTrackerMetrics::set_attempt_count(self.builder_index, self.attempt_count);
@@ -219,10 +323,45 @@ where
         }
     }
 
+    // If the sender's nonce on chain went below the nonce of the last mined
+    // transaction, checks whether that transaction is still mined. If not, it was
+    // reorged out and its nonce must be used again.
+    async fn check_for_reorg(
+        &mut self,
+        external_nonce: U256,
+    ) -> anyhow::Result<Option<TrackerUpdate>> {
+        let Some(mined) = self.last_mined else {
+            return Ok(None);
+        };
+        if external_nonce > mined.nonce {
+            return Ok(None);
+        }
+        let status = self
+            .sender
+            .get_transaction_status(mined.tx_hash)
+            .await
+            .context("tracker should check status of mined transaction when the nonce decreases")?;
+        if matches!(status, TxStatus::Mined { .. }) {
+            // the provider is behind the block the transaction mined in
+            return Ok(None);
+        }
+
+        warn!(
+            "Mined transaction {:?} with nonce {:?} was reorged out, status: {status:?}",
+            mined.tx_hash, mined.nonce
+        );
+        self.last_mined = None;
+        self.set_nonce_and_clear_state(external_nonce);
+        Ok(Some(TrackerUpdate::Reorged {
+            tx_hash: mined.tx_hash,
+            nonce: mined.nonce,
+        }))
+    }
+
     async fn get_mined_tx_gas_info(
         &self,
         tx_hash: H256,
-    ) -> anyhow::Result<(Option<U256>, Option<U256>, Option<U256>)> {
+    ) -> anyhow::Result<(Option<U256>, Option<U256>, Option<U256>, Option<Vec<Log>>)> {
         let (tx, tx_receipt) = tokio::try_join!(
             self.provider.get_transaction(tx_hash),
             self.provider.get_transaction_receipt(tx_hash),
@@ -231,14 +370,14 @@ where
             warn!("failed to fetch transaction data for tx: {}", tx_hash);
             None
         });
-        let (gas_used, gas_price) = match tx_receipt {
-            Some(r) => (r.gas_used, r.effective_gas_price),
+        let (gas_used, gas_price, logs) = match tx_receipt {
+            Some(r) => (r.gas_used, r.effective_gas_price, Some(r.logs)),
             None => {
                 warn!("failed to fetch transaction receipt for tx: {}", tx_hash);
-                (None, None)
+                (None, None, None)
             }
         };
-        Ok((gas_limit, gas_used, gas_price))
+        Ok((gas_limit, gas_used, gas_price, logs))
     }
 }
 
@@ -252,10 +391,7 @@ where
         let gas_fees = if self.has_abandoned {
             None
         } else {
-            self.transactions.last().map(|tx| {
-                tx.gas_fees
-                    .increase_by_percent(self.settings.replacement_fee_percent_increase)
-            })
+            self.transactions.last().map(|tx| tx.replacement_fees)
         };
         Ok((self.nonce, gas_fees))
     }
@@ -267,6 +403,12 @@ where
     ) -> TransactionTrackerResult<H256> {
         self.validate_transaction(&tx)?;
         let gas_fees = GasFees::from(&tx);
+        let replacement_fees = replacement::policy_for(
+            &tx,
+            self.replacement_percent_increase(),
+            self.settings.min_replacement_fees,
+        )
+        .replacement_fees(gas_fees);
         info!(
             "Sending transaction with nonce: {:?} gas fees: {:?} gas limit: {:?}",
             self.nonce,
@@ -284,7 +426,9 @@ where
                 self.transactions.push(PendingTransaction {
                     tx_hash: sent_tx.tx_hash,
                     gas_fees,
+                    replacement_fees,
                     attempt_number: self.attempt_count,
+                    is_cancellation: false,
                 });
                 self.has_abandoned = false;
                 self.attempt_count += 1;
@@ -305,9 +449,18 @@ where
                     self.transactions.push(PendingTransaction {
                         tx_hash: H256::zero(),
                         gas_fees,
+                        replacement_fees,
                         attempt_number: self.attempt_count,
+                        is_cancellation: false,
                     });
                 };
+                // the pending transaction pays more than expected, bump the fees of the
+                // next attempt beyond the usual replacement increase
+                if let Some(last) = self.transactions.last_mut() {
+                    last.replacement_fees = last.replacement_fees.increase_by_percent(
+                        self.settings.replacement_underpriced_fee_bump_percent,
+                    );
+                }
 
                 self.has_abandoned = false;
                 self.attempt_count += 1;
@@ -325,9 +478,7 @@ where
     ) -> TransactionTrackerResult<Option<H256>> {
         let (tx_hash, gas_fees) = match self.transactions.last() {
             Some(tx) => {
-                let increased_fees = tx
-                    .gas_fees
-                    .increase_by_percent(self.settings.replacement_fee_percent_increase);
+                let increased_fees = tx.replacement_fees;
                 let gas_fees = GasFees {
                     max_fee_per_gas: increased_fees
                         .max_fee_per_gas
@@ -357,10 +508,18 @@ where
             cancel_info.tx_hash, gas_fees
         );
 
+        // cancellations are always EIP-1559 transactions
+        let replacement_fees = Eip1559ReplacementPolicy {
+            percent_increase: self.replacement_percent_increase(),
+            floor: self.settings.min_replacement_fees,
+        }
+        .replacement_fees(gas_fees);
         self.transactions.push(PendingTransaction {
             tx_hash: cancel_info.tx_hash,
             gas_fees,
+            replacement_fees,
             attempt_number: self.attempt_count,
+            is_cancellation: true,
         });
 
         self.attempt_count += 1;
@@ -370,6 +529,12 @@ where
 
     async fn check_for_update(&mut self) -> TransactionTrackerResult<Option<TrackerUpdate>> {
         let external_nonce = self.get_external_nonce().await?;
+        TransactionTrackerMetrics::set_nonce_gap(self.builder_index, self.nonce, external_nonce);
+        if external_nonce < self.nonce {
+            if let Some(update) = self.check_for_reorg(external_nonce).await? {
+                return Ok(Some(update));
+            }
+        }
         if self.nonce < external_nonce {
             // The nonce has changed. Check to see which of our transactions has
             // mined, if any.
@@ -387,7 +552,7 @@ where
                     .context("tracker should check transaction status when the nonce changes")?;
                 info!("Status of tx {:?}: {:?}", tx.tx_hash, status);
                 if let TxStatus::Mined { block_number } = status {
-                    let (gas_limit, gas_used, gas_price) =
+                    let (gas_limit, gas_used, gas_price, logs) =
                         self.get_mined_tx_gas_info(tx.tx_hash).await?;
                     out = TrackerUpdate::Mined {
                         tx_hash: tx.tx_hash,
@@ -397,11 +562,23 @@ where
                         gas_limit,
                         gas_used,
                         gas_price,
+                        is_cancellation: tx.is_cancellation,
+                        logs,
                     };
+                    self.last_mined = Some(MinedTransaction {
+                        tx_hash: tx.tx_hash,
+                        nonce: self.nonce,
+                    });
                     break;
                 }
             }
-            self.set_nonce_and_clear_state(external_nonce);
+            if matches!(out, TrackerUpdate::Mined { .. }) && !self.pipelined.is_empty() {
+                // the pipelined transactions that mined too are reported by the
+                // following checks
+                self.advance_nonce();
+            } else {
+                self.set_nonce_and_clear_state(external_nonce);
+            }
             return Ok(Some(out));
         }
 
@@ -425,8 +602,12 @@ where
             TxStatus::Pending => None,
             TxStatus::Mined { block_number } => {
                 let nonce = self.nonce;
-                self.set_nonce_and_clear_state(nonce + 1);
-                let (gas_limit, gas_used, gas_price) =
+                self.advance_nonce();
+                self.last_mined = Some(MinedTransaction {
+                    tx_hash: last_tx.tx_hash,
+                    nonce,
+                });
+                let (gas_limit, gas_used, gas_price, logs) =
                     self.get_mined_tx_gas_info(last_tx.tx_hash).await?;
                 Some(TrackerUpdate::Mined {
                     tx_hash: last_tx.tx_hash,
@@ -436,6 +617,8 @@ where
                     gas_limit,
                     gas_used,
                     gas_price,
+                    is_cancellation: last_tx.is_cancellation,
+                    logs,
                 })
             }
             TxStatus::Dropped => Some(TrackerUpdate::LatestTxDropped { nonce: self.nonce }),
@@ -447,6 +630,14 @@ where
         self.set_nonce_and_clear_state(nonce);
     }
 
+    async fn sync_nonce(&mut self) -> TransactionTrackerResult<U256> {
+        if self.transactions.is_empty() {
+            let nonce = self.get_external_nonce().await?;
+            self.set_nonce_and_clear_state(nonce);
+        }
+        Ok(self.nonce)
+    }
+
     fn abandon(&mut self) {
         self.has_abandoned = true;
         self.attempt_count = 0;
@@ -456,6 +647,54 @@ where
     fn unabandon(&mut self) {
         self.has_abandoned = false;
     }
+
+    fn inflight_count(&self) -> usize {
+        if self.transactions.is_empty() {
+            0
+        } else {
+            1 + self.pipelined.len()
+        }
+    }
+
+    async fn send_pipelined_transaction(
+        &mut self,
+        tx: TypedTransaction,
+        expected_storage: &ExpectedStorage,
+    ) -> TransactionTrackerResult<H256> {
+        let nonce = self.validate_pipelined_transaction(&tx)?;
+        let gas_fees = GasFees::from(&tx);
+        let replacement_fees = replacement::policy_for(
+            &tx,
+            self.replacement_percent_increase(),
+            self.settings.min_replacement_fees,
+        )
+        .replacement_fees(gas_fees);
+        info!("Sending pipelined transaction with nonce: {nonce:?} gas fees: {gas_fees:?}");
+        let sent_tx = self.sender.send_transaction(tx, expected_storage).await?;
+        info!(
+            "Sent pipelined transaction {:?} nonce: {:?}",
+            sent_tx.tx_hash, sent_tx.nonce
+        );
+        self.pipelined.push_back(PipelinedTransaction {
+            nonce,
+            tx: PendingTransaction {
+                tx_hash: sent_tx.tx_hash,
+                gas_fees,
+                replacement_fees,
+                attempt_number: 0,
+                is_cancellation: false,
+            },
+        });
+        self.update_metrics();
+        Ok(sent_tx.tx_hash)
+    }
+
+    fn pipelined_transactions(&self) -> Vec<H256> {
+        self.pipelined
+            .iter()
+            .map(|pipelined| pipelined.tx.tx_hash)
+            .collect()
+    }
 }
 
 impl From<TxSenderError> for TransactionTrackerError {
@@ -487,6 +726,14 @@ impl TransactionTrackerMetrics {
             .set(nonce.as_u64() as f64);
     }
 
+    // The tracker's nonce minus the sender's nonce on chain. Briefly negative after a
+    // transaction mines, until the tracker catches up. A persistent gap means the
+    // tracker's view of the nonce drifted from the chain's.
+    fn set_nonce_gap(builder_index: u64, nonce: U256, external_nonce: U256) {
+        metrics::gauge!("builder_nonce_gap", "builder_index" => builder_index.to_string())
+            .set(nonce.as_u64() as f64 - external_nonce.as_u64() as f64);
+    }
+
     fn set_attempt_count(builder_index: u64, attempt_count: u64) {
         metrics::gauge!("builder_tracker_attempt_count", "builder_index" => builder_index.to_string()).set(attempt_count as f64);
     }
@@ -507,10 +754,10 @@ mod tests {
 
     use ethers::types::{Address, Eip1559TransactionRequest, Transaction, TransactionReceipt};
     use mockall::Sequence;
-    use rundler_provider::MockProvider;
+    use rundler_provider::{MockProvider, ProviderError};
 
     use super::*;
-    use crate::sender::{MockTransactionSender, SentTxInfo};
+    use crate::sender::{CancelTxInfo, MockTransactionSender, SentTxInfo};
 
     fn create_base_config() -> (MockTransactionSender, MockProvider) {
         let sender = MockTransactionSender::new();
@@ -525,6 +772,10 @@ mod tests {
     ) -> TransactionTrackerImpl<MockProvider, MockTransactionSender> {
         let settings = Settings {
             replacement_fee_percent_increase: 5,
+            fee_increase_multiplier: 1.0,
+            max_fee_increase_percent: 5,
+            min_replacement_fees: GasFees::default(),
+            replacement_underpriced_fee_bump_percent: 20,
         };
 
         let tracker: TransactionTrackerImpl<MockProvider, MockTransactionSender> =
@@ -576,6 +827,47 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_replacement_underpriced_fee_bump() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::zero());
+        sender.expect_send_transaction().returning(move |_a, _b| {
+            Box::pin(async { Err(TxSenderError::ReplacementUnderpriced) })
+        });
+
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(U256::from(0)));
+
+        let mut tracker = create_tracker(sender, provider).await;
+
+        let tx = Eip1559TransactionRequest::new()
+            .nonce(0)
+            .gas(10000)
+            .max_fee_per_gas(10000)
+            .max_priority_fee_per_gas(1000);
+        let exp = ExpectedStorage::default();
+
+        let sent = tracker.send_transaction(tx.into(), &exp).await;
+        assert!(matches!(
+            sent,
+            Err(TransactionTrackerError::ReplacementUnderpriced)
+        ));
+
+        // the 5% replacement increase, bumped by another 20%
+        let nonce_and_fees = tracker.get_nonce_and_required_fees().unwrap();
+        assert_eq!(
+            (
+                U256::from(0),
+                Some(GasFees {
+                    max_fee_per_gas: U256::from(12600),
+                    max_priority_fee_per_gas: U256::from(1260),
+                })
+            ),
+            nonce_and_fees
+        );
+    }
+
     #[tokio::test]
     async fn test_nonce_and_fees_abandoned() {
         let (mut sender, mut provider) = create_base_config();
@@ -617,6 +909,51 @@ mod tests {
         assert_eq!((U256::from(0), None), nonce_and_fees);
     }
 
+    #[tokio::test]
+    async fn test_sync_nonce() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::zero());
+        sender.expect_send_transaction().returning(move |_a, _b| {
+            Box::pin(async {
+                Ok(SentTxInfo {
+                    nonce: U256::from(5),
+                    tx_hash: H256::zero(),
+                })
+            })
+        });
+
+        // the nonce fails to load on creation, and is loaded by the sync
+        let mut seq = Sequence::new();
+        provider
+            .expect_get_transaction_count()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_a| Err(ProviderError::Other(anyhow::anyhow!("boom"))));
+        provider
+            .expect_get_transaction_count()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_a| Ok(U256::from(5)));
+
+        let mut tracker = create_tracker(sender, provider).await;
+        assert_eq!(
+            tracker.get_nonce_and_required_fees().unwrap().0,
+            U256::zero()
+        );
+        assert_eq!(tracker.sync_nonce().await.unwrap(), U256::from(5));
+
+        // a tracked transaction keeps its nonce, without loading it again
+        let tx = Eip1559TransactionRequest::new()
+            .nonce(5)
+            .gas(10000)
+            .max_fee_per_gas(10000);
+        tracker
+            .send_transaction(tx.into(), &ExpectedStorage::default())
+            .await
+            .unwrap();
+        assert_eq!(tracker.sync_nonce().await.unwrap(), U256::from(5));
+    }
+
     #[tokio::test]
     async fn test_send_transaction_without_nonce() {
         let (mut sender, mut provider) = create_base_config();
@@ -804,4 +1141,281 @@ mod tests {
 
         assert!(matches!(tracker_update, TrackerUpdate::Mined { .. }));
     }
+
+    #[tokio::test]
+    async fn test_check_for_update_reorged() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::zero());
+        let tx_hash = H256::random();
+        sender.expect_send_transaction().returning(move |_a, _b| {
+            Box::pin(async move {
+                Ok(SentTxInfo {
+                    nonce: U256::from(0),
+                    tx_hash,
+                })
+            })
+        });
+
+        // mined, then back in the mempool after a reorg
+        let mut sender_seq = Sequence::new();
+        sender
+            .expect_get_transaction_status()
+            .returning(move |_a| Box::pin(async { Ok(TxStatus::Mined { block_number: 1 }) }))
+            .times(1)
+            .in_sequence(&mut sender_seq);
+        sender
+            .expect_get_transaction_status()
+            .returning(move |_a| Box::pin(async { Ok(TxStatus::Pending) }))
+            .times(1)
+            .in_sequence(&mut sender_seq);
+
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(U256::from(0)));
+        add_mined_tx_gas_info(&mut provider);
+
+        let mut tracker = create_tracker(sender, provider).await;
+
+        let tx = Eip1559TransactionRequest::new().nonce(0);
+        tracker
+            .send_transaction(tx.into(), &ExpectedStorage::default())
+            .await
+            .unwrap();
+        let tracker_update = tracker.check_for_update().await.unwrap().unwrap();
+        assert!(matches!(tracker_update, TrackerUpdate::Mined { .. }));
+        assert_eq!(
+            tracker.get_nonce_and_required_fees().unwrap(),
+            (U256::from(1), None)
+        );
+
+        // the sender's nonce on chain is back to 0
+        let tracker_update = tracker.check_for_update().await.unwrap().unwrap();
+        assert!(matches!(
+            tracker_update,
+            TrackerUpdate::Reorged { tx_hash: hash, nonce } if hash == tx_hash && nonce == U256::zero()
+        ));
+        // the nonce is used again
+        assert_eq!(
+            tracker.get_nonce_and_required_fees().unwrap(),
+            (U256::from(0), None)
+        );
+    }
+
+    fn add_mined_tx_gas_info(provider: &mut MockProvider) {
+        provider.expect_get_transaction().returning(|_: H256| {
+            Ok(Some(Transaction {
+                gas: U256::from(0),
+                ..Default::default()
+            }))
+        });
+
+        provider
+            .expect_get_transaction_receipt()
+            .returning(|_: H256| {
+                Ok(Some(TransactionReceipt {
+                    gas_used: Some(U256::from(0)),
+                    ..Default::default()
+                }))
+            });
+    }
+
+    async fn send_and_cancel(
+        mut sender: MockTransactionSender,
+        mut provider: MockProvider,
+        bundle_tx_hash: H256,
+        cancel_tx_hash: H256,
+    ) -> TransactionTrackerImpl<MockProvider, MockTransactionSender> {
+        sender.expect_address().return_const(Address::zero());
+        sender.expect_send_transaction().returning(move |_a, _b| {
+            Box::pin(async move {
+                Ok(SentTxInfo {
+                    nonce: U256::from(0),
+                    tx_hash: bundle_tx_hash,
+                })
+            })
+        });
+        sender
+            .expect_cancel_transaction()
+            .returning(move |_a, _b, _c, _d| {
+                Box::pin(async move {
+                    Ok(CancelTxInfo {
+                        tx_hash: cancel_tx_hash,
+                        soft_cancelled: false,
+                    })
+                })
+            });
+
+        // the nonce is used by the time the tracker checks for an update
+        let mut provider_seq = Sequence::new();
+        for transaction_count in 0..=1 {
+            provider
+                .expect_get_transaction_count()
+                .returning(move |_a| Ok(U256::from(transaction_count)))
+                .times(1)
+                .in_sequence(&mut provider_seq);
+        }
+        add_mined_tx_gas_info(&mut provider);
+
+        let mut tracker = create_tracker(sender, provider).await;
+
+        let tx = Eip1559TransactionRequest::new()
+            .nonce(0)
+            .gas(10000)
+            .max_fee_per_gas(10000);
+        let exp = ExpectedStorage::default();
+        tracker.send_transaction(tx.into(), &exp).await.unwrap();
+        tracker.abandon();
+        let cancel = tracker
+            .cancel_transaction(Address::zero(), GasFees::default())
+            .await
+            .unwrap();
+        assert_eq!(cancel, Some(cancel_tx_hash));
+
+        tracker
+    }
+
+    #[tokio::test]
+    async fn test_cancel_race_cancellation_mined() {
+        let (mut sender, provider) = create_base_config();
+        let bundle_tx_hash = H256::random();
+        let cancel_tx_hash = H256::random();
+
+        sender
+            .expect_get_transaction_status()
+            .returning(move |hash| {
+                Box::pin(async move {
+                    if hash == cancel_tx_hash {
+                        Ok(TxStatus::Mined { block_number: 1 })
+                    } else {
+                        Ok(TxStatus::Dropped)
+                    }
+                })
+            });
+
+        let mut tracker = send_and_cancel(sender, provider, bundle_tx_hash, cancel_tx_hash).await;
+        let tracker_update = tracker.check_for_update().await.unwrap().unwrap();
+
+        assert!(matches!(
+            tracker_update,
+            TrackerUpdate::Mined {
+                tx_hash,
+                is_cancellation: true,
+                ..
+            } if tx_hash == cancel_tx_hash
+        ));
+        // nonce is resolved and the tracker moves on
+        assert_eq!(
+            tracker.get_nonce_and_required_fees().unwrap(),
+            (U256::from(1), None)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_race_original_mined() {
+        let (mut sender, provider) = create_base_config();
+        let bundle_tx_hash = H256::random();
+        let cancel_tx_hash = H256::random();
+
+        sender
+            .expect_get_transaction_status()
+            .returning(move |hash| {
+                Box::pin(async move {
+                    if hash == bundle_tx_hash {
+                        Ok(TxStatus::Mined { block_number: 1 })
+                    } else {
+                        Ok(TxStatus::Dropped)
+                    }
+                })
+            });
+
+        let mut tracker = send_and_cancel(sender, provider, bundle_tx_hash, cancel_tx_hash).await;
+        let tracker_update = tracker.check_for_update().await.unwrap().unwrap();
+
+        assert!(matches!(
+            tracker_update,
+            TrackerUpdate::Mined {
+                tx_hash,
+                is_cancellation: false,
+                ..
+            } if tx_hash == bundle_tx_hash
+        ));
+        assert_eq!(
+            tracker.get_nonce_and_required_fees().unwrap(),
+            (U256::from(1), None)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_transactions() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::zero());
+        let tx_hashes = [H256::random(), H256::random()];
+        sender.expect_send_transaction().returning(move |tx, _| {
+            let nonce = *tx.nonce().unwrap();
+            let tx_hash = tx_hashes[nonce.as_usize()];
+            Box::pin(async move { Ok(SentTxInfo { nonce, tx_hash }) })
+        });
+        sender
+            .expect_get_transaction_status()
+            .returning(|_| Box::pin(async { Ok(TxStatus::Mined { block_number: 1 }) }));
+
+        // both transactions mine in the same block
+        let mut provider_seq = Sequence::new();
+        provider
+            .expect_get_transaction_count()
+            .times(1)
+            .in_sequence(&mut provider_seq)
+            .returning(|_| Ok(U256::zero()));
+        provider
+            .expect_get_transaction_count()
+            .in_sequence(&mut provider_seq)
+            .returning(|_| Ok(U256::from(2)));
+        add_mined_tx_gas_info(&mut provider);
+
+        let mut tracker = create_tracker(sender, provider).await;
+        let exp = ExpectedStorage::default();
+
+        // nothing in flight to pipeline behind
+        let tx = Eip1559TransactionRequest::new().nonce(1);
+        assert!(tracker
+            .send_pipelined_transaction(tx.into(), &exp)
+            .await
+            .is_err());
+
+        let tx = Eip1559TransactionRequest::new().nonce(0);
+        tracker.send_transaction(tx.into(), &exp).await.unwrap();
+        // only the next nonce can be pipelined
+        let tx = Eip1559TransactionRequest::new().nonce(2);
+        assert!(tracker
+            .send_pipelined_transaction(tx.into(), &exp)
+            .await
+            .is_err());
+        let tx = Eip1559TransactionRequest::new().nonce(1);
+        tracker
+            .send_pipelined_transaction(tx.into(), &exp)
+            .await
+            .unwrap();
+        assert_eq!(tracker.inflight_count(), 2);
+        assert_eq!(tracker.pipelined_transactions(), vec![tx_hashes[1]]);
+
+        // each transaction is reported mined in turn
+        let tracker_update = tracker.check_for_update().await.unwrap().unwrap();
+        assert!(matches!(
+            tracker_update,
+            TrackerUpdate::Mined { tx_hash, nonce, .. } if tx_hash == tx_hashes[0] && nonce == U256::zero()
+        ));
+        assert_eq!(tracker.inflight_count(), 1);
+        assert!(tracker.pipelined_transactions().is_empty());
+
+        let tracker_update = tracker.check_for_update().await.unwrap().unwrap();
+        assert!(matches!(
+            tracker_update,
+            TrackerUpdate::Mined { tx_hash, nonce, .. } if tx_hash == tx_hashes[1] && nonce == U256::one()
+        ));
+        assert_eq!(tracker.inflight_count(), 0);
+        assert_eq!(
+            tracker.get_nonce_and_required_fees().unwrap(),
+            (U256::from(2), None)
+        );
+    }
 }
@@ -31,7 +31,7 @@ use rundler_types::{
 use rundler_utils::emit::WithEntryPoint;
 use tokio::sync::broadcast;
 use tonic::async_trait;
-use tracing::info;
+use tracing::{info, instrument};
 
 use super::{
     paymaster::PaymasterTracker, pool::PoolInner, reputation::AddressReputation, Mempool,
@@ -380,6 +380,7 @@ where
         self.paymaster.get_stake_status(address).await
     }
 
+    #[instrument(skip_all, fields(op_hash = ?op.hash(self.config.entry_point, self.config.chain_id)))]
     async fn add_operation(
         &self,
         origin: OperationOrigin,
@@ -604,6 +605,10 @@ where
 
     fn update_entity(&self, update: EntityUpdate) {
         let entity = update.entity;
+        info!(
+            "Applying {:?} to {entity}, rejected from a bundle: {}",
+            update.update_type, update.reason
+        );
         match update.update_type {
             EntityUpdateType::UnstakedInvalidation => {
                 self.reputation.handle_urep_030_penalty(entity.address);
@@ -36,9 +36,10 @@ use super::protos::{
     ReplacementUnderpricedError, SenderAddressUsedAsAlternateEntity, SenderFundsTooLow,
     SenderIsNotContractAndNoInitCode, SimulationViolationError as ProtoSimulationViolationError,
     TotalGasLimitTooHigh, UnintendedRevert, UnintendedRevertWithMessage, UnknownEntryPointError,
-    UnknownRevert, UnstakedAggregator, UnstakedPaymasterContext, UnsupportedAggregatorError,
-    UsedForbiddenOpcode, UsedForbiddenPrecompile, ValidationRevert as ProtoValidationRevert,
-    VerificationGasLimitBufferTooLow, VerificationGasLimitTooHigh, WrongNumberOfPhases,
+    UnknownRevert, UnstakedAggregator, UnstakedPaymasterContext, UnsupportedAggregator,
+    UnsupportedAggregatorError, UsedForbiddenOpcode, UsedForbiddenPrecompile,
+    ValidationRevert as ProtoValidationRevert, VerificationGasLimitBufferTooLow,
+    VerificationGasLimitTooHigh, WrongNumberOfPhases,
 };
 
 impl TryFrom<ProtoMempoolError> for PoolError {
@@ -652,6 +653,15 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     ),
                 }
             }
+            SimulationViolation::UnsupportedAggregator(address) => ProtoSimulationViolationError {
+                violation: Some(
+                    simulation_violation_error::Violation::UnsupportedAggregator(
+                        UnsupportedAggregator {
+                            aggregator_address: address.to_proto_bytes(),
+                        },
+                    ),
+                ),
+            },
         }
     }
 }
@@ -818,6 +828,9 @@ impl TryFrom<ProtoSimulationViolationError> for SimulationViolation {
                     from_bytes(&e.contract_address)?,
                 )
             }
+            Some(simulation_violation_error::Violation::UnsupportedAggregator(e)) => {
+                SimulationViolation::UnsupportedAggregator(from_bytes(&e.aggregator_address)?)
+            }
             None => {
                 bail!("unknown proto mempool simulation violation")
             }
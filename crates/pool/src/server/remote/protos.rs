@@ -22,8 +22,9 @@ use rundler_types::{
         StakeStatus as RundlerStakeStatus,
     },
     v0_6, v0_7, Entity as RundlerEntity, EntityInfos, EntityType as RundlerEntityType,
-    EntityUpdate as RundlerEntityUpdate, EntityUpdateType as RundlerEntityUpdateType,
-    StakeInfo as RundlerStakeInfo, UserOperationVariant, ValidTimeRange,
+    EntityUpdate as RundlerEntityUpdate, EntityUpdateReason as RundlerEntityUpdateReason,
+    EntityUpdateType as RundlerEntityUpdateType, StakeInfo as RundlerStakeInfo,
+    UserOperationVariant, ValidTimeRange,
 };
 
 tonic::include_proto!("op_pool");
@@ -194,11 +195,16 @@ impl TryFrom<&EntityUpdate> for RundlerEntityUpdate {
             .clone()
             .context(MISSING_ENTITY_ERR_STR)?))
             .try_into()?;
-        let update_type = RundlerEntityUpdateType::try_from(entity_update.update_type)
-            .map_err(|_| ConversionError::InvalidEnumValue(entity_update.update_type))?;
+        let update_type = EntityUpdateType::try_from(entity_update.update_type)
+            .map_err(|_| ConversionError::InvalidEnumValue(entity_update.update_type))?
+            .try_into()?;
+        let reason = EntityUpdateReason::try_from(entity_update.reason)
+            .map_err(|_| ConversionError::InvalidEnumValue(entity_update.reason))?
+            .try_into()?;
         Ok(RundlerEntityUpdate {
             entity,
             update_type,
+            reason,
         })
     }
 }
@@ -260,11 +266,67 @@ impl From<RundlerEntityUpdateType> for EntityUpdateType {
     }
 }
 
+impl TryFrom<EntityUpdateType> for RundlerEntityUpdateType {
+    type Error = ConversionError;
+
+    fn try_from(update_type: EntityUpdateType) -> Result<Self, Self::Error> {
+        match update_type {
+            EntityUpdateType::Unspecified => Err(ConversionError::InvalidEnumValue(
+                EntityUpdateType::Unspecified as i32,
+            )),
+            EntityUpdateType::UnstakedInvalidation => {
+                Ok(RundlerEntityUpdateType::UnstakedInvalidation)
+            }
+            EntityUpdateType::StakedInvalidation => Ok(RundlerEntityUpdateType::StakedInvalidation),
+        }
+    }
+}
+
+impl From<RundlerEntityUpdateReason> for EntityUpdateReason {
+    fn from(reason: RundlerEntityUpdateReason) -> Self {
+        match reason {
+            RundlerEntityUpdateReason::SimulationFailed => EntityUpdateReason::SimulationFailed,
+            RundlerEntityUpdateReason::GasEstimationFailed => {
+                EntityUpdateReason::GasEstimationFailed
+            }
+            RundlerEntityUpdateReason::SignatureValidationFailed => {
+                EntityUpdateReason::SignatureValidationFailed
+            }
+            RundlerEntityUpdateReason::InsufficientDeposit => {
+                EntityUpdateReason::InsufficientDeposit
+            }
+        }
+    }
+}
+
+impl TryFrom<EntityUpdateReason> for RundlerEntityUpdateReason {
+    type Error = ConversionError;
+
+    fn try_from(reason: EntityUpdateReason) -> Result<Self, Self::Error> {
+        match reason {
+            EntityUpdateReason::Unspecified => Err(ConversionError::InvalidEnumValue(
+                EntityUpdateReason::Unspecified as i32,
+            )),
+            EntityUpdateReason::SimulationFailed => Ok(RundlerEntityUpdateReason::SimulationFailed),
+            EntityUpdateReason::GasEstimationFailed => {
+                Ok(RundlerEntityUpdateReason::GasEstimationFailed)
+            }
+            EntityUpdateReason::SignatureValidationFailed => {
+                Ok(RundlerEntityUpdateReason::SignatureValidationFailed)
+            }
+            EntityUpdateReason::InsufficientDeposit => {
+                Ok(RundlerEntityUpdateReason::InsufficientDeposit)
+            }
+        }
+    }
+}
+
 impl From<&RundlerEntityUpdate> for EntityUpdate {
     fn from(entity_update: &RundlerEntityUpdate) -> Self {
         EntityUpdate {
             entity: Some(Entity::from(&entity_update.entity)),
             update_type: EntityUpdateType::from(entity_update.update_type).into(),
+            reason: EntityUpdateReason::from(entity_update.reason).into(),
         }
     }
 }
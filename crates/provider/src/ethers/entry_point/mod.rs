@@ -13,9 +13,14 @@
 
 use std::sync::Arc;
 
+use anyhow::Context;
 use ethers::{
+    abi::AbiDecode,
     providers::Middleware,
-    types::{Address, Bytes, Eip1559TransactionRequest, U256, U64},
+    types::{
+        spoof, transaction::eip2718::TypedTransaction, Address, Bytes, Eip1559TransactionRequest,
+        U256, U64,
+    },
 };
 use rundler_types::{
     chain::{ChainSpec, L1GasOracleContractType},
@@ -24,9 +29,14 @@ use rundler_types::{
     },
 };
 
+use crate::Provider;
+
 pub(crate) mod v0_6;
 pub(crate) mod v0_7;
 
+/// Selector of `paused() returns (bool)`
+const PAUSED_SELECTOR: [u8; 4] = [0x5c, 0x97, 0x5a, 0xbb];
+
 #[derive(Debug, Default)]
 pub(crate) enum L1GasOracle<P> {
     ArbitrumNitro(NodeInterface<P>),
@@ -56,13 +66,14 @@ where
         address: Address,
         data: Bytes,
         gas_price: U256,
+        state_override: &spoof::State,
     ) -> anyhow::Result<U256> {
         match self {
             L1GasOracle::ArbitrumNitro(arb_node) => {
-                estimate_arbitrum_l1_gas(arb_node, address, data).await
+                estimate_arbitrum_l1_gas(arb_node, address, data, state_override).await
             }
             L1GasOracle::OptimismBedrock(opt_oracle) => {
-                estimate_optimism_l1_gas(opt_oracle, address, data, gas_price).await
+                estimate_optimism_l1_gas(opt_oracle, address, data, gas_price, state_override).await
             }
             L1GasOracle::None => Ok(U256::zero()),
         }
@@ -83,10 +94,12 @@ async fn estimate_arbitrum_l1_gas<P: Middleware + 'static>(
     arb_node: &NodeInterface<P>,
     address: Address,
     data: Bytes,
+    state_override: &spoof::State,
 ) -> anyhow::Result<U256> {
     let gas = arb_node
         .gas_estimate_l1_component(address, false, data)
-        .call()
+        .call_raw()
+        .state(state_override)
         .await?;
     Ok(U256::from(gas.0))
 }
@@ -96,6 +109,7 @@ async fn estimate_optimism_l1_gas<P: Middleware + 'static>(
     address: Address,
     data: Bytes,
     gas_price: U256,
+    state_override: &spoof::State,
 ) -> anyhow::Result<U256> {
     // construct an unsigned transaction with default values just for L1 gas estimation
     let tx = Eip1559TransactionRequest::new()
@@ -110,6 +124,24 @@ async fn estimate_optimism_l1_gas<P: Middleware + 'static>(
         .chain_id(U64::from(100_000))
         .rlp();
 
-    let l1_fee = opt_oracle.get_l1_fee(tx).call().await?;
+    // the override can change the oracle's L1 fee parameters, e.g. to preview
+    // estimates under different L1 conditions
+    let l1_fee = opt_oracle
+        .get_l1_fee(tx)
+        .call_raw()
+        .state(state_override)
+        .await?;
     Ok(l1_fee.checked_div(gas_price).unwrap_or(U256::MAX))
 }
+
+async fn is_paused<P: Provider>(provider: &P, pause_guardian: Address) -> anyhow::Result<bool> {
+    let tx: TypedTransaction = Eip1559TransactionRequest::new()
+        .to(pause_guardian)
+        .data(Bytes::from(PAUSED_SELECTOR.to_vec()))
+        .into();
+    let result = provider
+        .call(&tx, None, &spoof::state())
+        .await
+        .context("pause guardian should return whether bundling is paused")?;
+    bool::decode(result).context("pause guardian should return a bool")
+}
@@ -134,6 +134,10 @@ where
             .context("should compute balances")?;
         Ok(out.balances)
     }
+
+    async fn is_paused(&self, pause_guardian: Address) -> anyhow::Result<bool> {
+        super::is_paused(&*self.provider, pause_guardian).await
+    }
 }
 
 #[async_trait::async_trait]
@@ -216,6 +220,12 @@ where
             if let Ok(failure) = SignatureValidationFailed::decode(revert_data) {
                 return Ok(HandleOpsOut::SignatureValidationFailed(failure.aggregator));
             }
+            if let Ok(ContractRevertError { reason }) = ContractRevertError::decode(revert_data) {
+                // AA90 invalid beneficiary, AA91 failed send to beneficiary
+                if reason.starts_with("AA90") || reason.starts_with("AA91") {
+                    return Ok(HandleOpsOut::InvalidBeneficiary(reason));
+                }
+            }
             // Special handling for a bug in the 0.6 entry point contract to detect the bug where
             // the `returndatacopy` opcode reverts due to a postOp revert and the revert data is too short.
             // See https://github.com/eth-infinitism/account-abstraction/pull/325 for more details.
@@ -256,6 +266,7 @@ where
         entry_point_address: Address,
         user_op: UserOperation,
         gas_price: U256,
+        state_override: &spoof::State,
     ) -> anyhow::Result<U256> {
         let data = self
             .i_entry_point
@@ -264,7 +275,7 @@ where
             .context("should get calldata for entry point handle ops")?;
 
         self.l1_gas_oracle
-            .estimate_l1_gas(entry_point_address, data, gas_price)
+            .estimate_l1_gas(entry_point_address, data, gas_price, state_override)
             .await
     }
 }
@@ -358,14 +369,14 @@ where
         user_op: UserOperation,
         target: Address,
         target_call_data: Bytes,
-        block_hash: H256,
+        block_id: BlockId,
         gas: U256,
         spoofed_state: &spoof::State,
     ) -> anyhow::Result<Result<ExecutionResult, ValidationRevert>> {
         let contract_error = self
             .i_entry_point
             .simulate_handle_op(user_op, target, target_call_data)
-            .block(block_hash)
+            .block(block_id)
             .gas(gas)
             .call_raw()
             .state(spoofed_state)
@@ -137,6 +137,10 @@ where
             .context("should compute balances")?;
         Ok(out.balances)
     }
+
+    async fn is_paused(&self, pause_guardian: Address) -> anyhow::Result<bool> {
+        super::is_paused(&*self.provider, pause_guardian).await
+    }
 }
 
 #[async_trait::async_trait]
@@ -224,6 +228,12 @@ where
             if let Ok(failure) = SignatureValidationFailed::decode(revert_data) {
                 return Ok(HandleOpsOut::SignatureValidationFailed(failure.aggregator));
             }
+            if let Ok(ContractRevertError { reason }) = ContractRevertError::decode(revert_data) {
+                // AA90 invalid beneficiary, AA91 failed send to beneficiary
+                if reason.starts_with("AA90") || reason.starts_with("AA91") {
+                    return Ok(HandleOpsOut::InvalidBeneficiary(reason));
+                }
+            }
         }
         Err(error)?
     }
@@ -257,6 +267,7 @@ where
         entry_point_address: Address,
         user_op: UserOperation,
         gas_price: U256,
+        state_override: &spoof::State,
     ) -> anyhow::Result<U256> {
         let data = self
             .i_entry_point
@@ -265,7 +276,7 @@ where
             .context("should get calldata for entry point handle ops")?;
 
         self.l1_gas_oracle
-            .estimate_l1_gas(entry_point_address, data, gas_price)
+            .estimate_l1_gas(entry_point_address, data, gas_price, state_override)
             .await
     }
 }
@@ -359,7 +370,7 @@ where
         user_op: UserOperation,
         target: Address,
         target_call_data: Bytes,
-        block_hash: H256,
+        block_id: BlockId,
         gas: U256,
         spoofed_state: &spoof::State,
     ) -> anyhow::Result<Result<ExecutionResult, ValidationRevert>> {
@@ -369,7 +380,7 @@ where
 
         let contract_error = ep_simulations
             .simulate_handle_op(user_op.pack(), target, target_call_data)
-            .block(block_hash)
+            .block(block_id)
             .gas(gas)
             .call_raw()
             .state(spoofed_state)
@@ -27,6 +27,11 @@ pub use ethers::{
     EntryPointV0_7 as EthersEntryPointV0_7,
 };
 
+#[cfg(feature = "test-utils")]
+mod pinned;
+#[cfg(feature = "test-utils")]
+pub use pinned::PinnedProvider;
+
 mod traits;
 #[cfg(any(test, feature = "test-utils"))]
 pub use traits::test_utils::*;
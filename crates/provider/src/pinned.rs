@@ -0,0 +1,230 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Provider pinned to a historical block, for testing against fork state.
+
+use std::{fmt::Debug, sync::Arc};
+
+use anyhow::Context;
+use ethers::{
+    abi::{AbiDecode, AbiEncode},
+    types::{
+        spoof, transaction::eip2718::TypedTransaction, Address, Block, BlockId, BlockNumber, Bytes,
+        FeeHistory, Filter, GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, Log,
+        Transaction, TransactionReceipt, TxHash, H256, U256, U64,
+    },
+};
+use rundler_types::contracts::utils::get_gas_used::GasUsedResult;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Provider, ProviderError, ProviderResult};
+
+/// A provider that treats a historical block as the latest block.
+///
+/// Calls that would otherwise read the latest state are made against the pinned
+/// block instead, so the inner provider must be backed by an archive node for
+/// blocks older than its pruning window.
+///
+/// Calls that are made directly against the inner provider, such as those of an
+/// entry point contract binding, are not pinned. Gas estimation passes the block
+/// hash returned by `get_latest_block_hash_and_number` to its simulations, so those
+/// are pinned as well.
+#[derive(Debug)]
+pub struct PinnedProvider<P> {
+    inner: Arc<P>,
+    block_hash: H256,
+    block_number: U64,
+    base_fee: U256,
+}
+
+impl<P: Provider> PinnedProvider<P> {
+    /// Pins `inner` to the block with number `block_number`
+    pub async fn new(inner: Arc<P>, block_number: u64) -> anyhow::Result<Self> {
+        let block = inner
+            .get_block(block_number)
+            .await?
+            .with_context(|| format!("block {block_number} should exist"))?;
+
+        Ok(Self {
+            block_hash: block.hash.context("block should have a hash")?,
+            block_number: block.number.context("block should have a number")?,
+            base_fee: block.base_fee_per_gas.unwrap_or_default(),
+            inner,
+        })
+    }
+
+    /// The hash of the pinned block
+    pub fn block_hash(&self) -> H256 {
+        self.block_hash
+    }
+
+    /// The number of the pinned block
+    pub fn block_number(&self) -> U64 {
+        self.block_number
+    }
+
+    fn pin(&self, block: Option<BlockId>) -> Option<BlockId> {
+        block.or(Some(self.block_hash.into()))
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: Provider> Provider for PinnedProvider<P> {
+    async fn request<T, R>(&self, method: &str, params: T) -> ProviderResult<R>
+    where
+        T: Debug + Serialize + Send + Sync + 'static,
+        R: Serialize + DeserializeOwned + Debug + Send + 'static,
+    {
+        self.inner.request(method, params).await
+    }
+
+    async fn fee_history<T: Into<U256> + Serialize + Send + Sync + 'static>(
+        &self,
+        t: T,
+        block_number: BlockNumber,
+        reward_percentiles: &[f64],
+    ) -> Result<FeeHistory, ProviderError> {
+        let block_number = match block_number {
+            BlockNumber::Latest | BlockNumber::Pending => BlockNumber::Number(self.block_number),
+            block_number => block_number,
+        };
+        self.inner
+            .fee_history(t, block_number, reward_percentiles)
+            .await
+    }
+
+    async fn call(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+        state_overrides: &spoof::State,
+    ) -> ProviderResult<Bytes> {
+        self.inner.call(tx, self.pin(block), state_overrides).await
+    }
+
+    async fn call_constructor<A, R>(
+        &self,
+        bytecode: &Bytes,
+        args: A,
+        block_id: Option<BlockId>,
+        state_overrides: &spoof::State,
+    ) -> anyhow::Result<R>
+    where
+        A: AbiEncode + Send + Sync + 'static,
+        R: AbiDecode + Send + Sync + 'static,
+    {
+        self.inner
+            .call_constructor(bytecode, args, self.pin(block_id), state_overrides)
+            .await
+    }
+
+    async fn get_block_number(&self) -> ProviderResult<u64> {
+        Ok(self.block_number.as_u64())
+    }
+
+    async fn get_block<T: Into<BlockId> + Send + Sync + 'static>(
+        &self,
+        block_hash_or_number: T,
+    ) -> ProviderResult<Option<Block<H256>>> {
+        let block_id = match block_hash_or_number.into() {
+            BlockId::Number(BlockNumber::Latest | BlockNumber::Pending) => self.block_hash.into(),
+            block_id => block_id,
+        };
+        self.inner.get_block(block_id).await
+    }
+
+    async fn get_balance(&self, address: Address, block: Option<BlockId>) -> ProviderResult<U256> {
+        self.inner.get_balance(address, self.pin(block)).await
+    }
+
+    async fn get_transaction<T: Send + Sync + Into<TxHash> + 'static>(
+        &self,
+        tx: T,
+    ) -> ProviderResult<Option<Transaction>> {
+        self.inner.get_transaction(tx).await
+    }
+
+    async fn get_transaction_receipt<T: Send + Sync + Into<TxHash> + 'static>(
+        &self,
+        transaction_hash: T,
+    ) -> ProviderResult<Option<TransactionReceipt>> {
+        self.inner.get_transaction_receipt(transaction_hash).await
+    }
+
+    async fn debug_trace_transaction(
+        &self,
+        tx_hash: TxHash,
+        trace_options: GethDebugTracingOptions,
+    ) -> ProviderResult<GethTrace> {
+        self.inner
+            .debug_trace_transaction(tx_hash, trace_options)
+            .await
+    }
+
+    async fn debug_trace_call(
+        &self,
+        tx: TypedTransaction,
+        block_id: Option<BlockId>,
+        trace_options: GethDebugTracingCallOptions,
+    ) -> ProviderResult<GethTrace> {
+        self.inner
+            .debug_trace_call(tx, self.pin(block_id), trace_options)
+            .await
+    }
+
+    async fn get_latest_block_hash_and_number(&self) -> ProviderResult<(H256, U64)> {
+        Ok((self.block_hash, self.block_number))
+    }
+
+    async fn get_base_fee(&self) -> ProviderResult<U256> {
+        Ok(self.base_fee)
+    }
+
+    async fn get_max_priority_fee(&self) -> ProviderResult<U256> {
+        self.inner.get_max_priority_fee().await
+    }
+
+    async fn get_code(&self, address: Address, block_hash: Option<H256>) -> ProviderResult<Bytes> {
+        self.inner
+            .get_code(address, block_hash.or(Some(self.block_hash)))
+            .await
+    }
+
+    async fn get_transaction_count(&self, address: Address) -> ProviderResult<U256> {
+        self.inner.get_transaction_count(address).await
+    }
+
+    async fn get_logs(&self, filter: &Filter) -> ProviderResult<Vec<Log>> {
+        self.inner.get_logs(filter).await
+    }
+
+    async fn get_gas_used(
+        self: &Arc<Self>,
+        target: Address,
+        value: U256,
+        data: Bytes,
+        state_overrides: spoof::State,
+    ) -> ProviderResult<GasUsedResult> {
+        self.inner
+            .get_gas_used(target, value, data, state_overrides)
+            .await
+    }
+
+    async fn batch_get_storage_at(
+        &self,
+        address: Address,
+        slots: Vec<H256>,
+    ) -> ProviderResult<Vec<H256>> {
+        self.inner.batch_get_storage_at(address, slots).await
+    }
+}
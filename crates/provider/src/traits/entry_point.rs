@@ -11,13 +11,19 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use ethers::types::{
-    spoof, transaction::eip2718::TypedTransaction, Address, BlockId, Bytes, H256, U256,
+use std::collections::HashMap;
+
+use ethers::{
+    contract::EthEvent,
+    types::{
+        spoof, transaction::eip2718::TypedTransaction, Address, BlockId, Bytes, Log, H256, U256,
+    },
 };
 use rundler_types::{
-    GasFees, Timestamp, UserOperation, UserOpsPerAggregator, ValidationError, ValidationOutput,
-    ValidationRevert,
+    contracts::v0_6::i_entry_point::UserOperationEventFilter, GasFees, Timestamp, UserOperation,
+    UserOpsPerAggregator, ValidationError, ValidationOutput, ValidationRevert,
 };
+use rundler_utils::eth;
 
 /// Output of a successful signature aggregator simulation call
 #[derive(Clone, Debug, Default)]
@@ -48,6 +54,8 @@ pub enum HandleOpsOut {
     FailedOp(usize, String),
     /// Call failed due to a signature validation failure
     SignatureValidationFailed(Address),
+    /// Call failed because the beneficiary is invalid or could not receive the bundle's fees
+    InvalidBeneficiary(String),
     /// Call failed due to a bug in the 0.6 entry point contract https://github.com/eth-infinitism/account-abstraction/pull/325.
     /// Special handling is required to remove the offending operation from the bundle.
     PostOpRevert,
@@ -92,7 +100,8 @@ pub trait EntryPoint: Send + Sync + 'static {
     /// Get the address of the entry point contract
     fn address(&self) -> Address;
 
-    /// Get the balance of an address
+    /// Get the entry point deposit of an address, such as a paymaster or an
+    /// account, using the entry point's `balanceOf` view
     async fn balance_of(&self, address: Address, block_id: Option<BlockId>)
         -> anyhow::Result<U256>;
 
@@ -101,6 +110,10 @@ pub trait EntryPoint: Send + Sync + 'static {
 
     /// Get the balances of a list of addresses in order
     async fn get_balances(&self, addresses: Vec<Address>) -> anyhow::Result<Vec<U256>>;
+
+    /// Whether bundling to the entry point is paused by the pause guardian contract
+    /// at `pause_guardian`, which must implement `paused() returns (bool)`
+    async fn is_paused(&self, pause_guardian: Address) -> anyhow::Result<bool>;
 }
 
 /// Trait for handling signature aggregators
@@ -162,12 +175,16 @@ pub trait L1GasProvider: Send + Sync + 'static {
 
     /// Calculate the L1 portion of the gas for a user operation
     ///
-    /// Returns zero for operations that do not require L1 gas
+    /// Returns zero for operations that do not require L1 gas.
+    ///
+    /// The state override is applied to the call to the L1 gas oracle, allowing
+    /// callers to override the oracle's L1 fee parameters.
     async fn calc_l1_gas(
         &self,
         entry_point_address: Address,
         op: Self::UO,
         gas_price: U256,
+        state_override: &spoof::State,
     ) -> anyhow::Result<U256>;
 }
 
@@ -219,7 +236,7 @@ pub trait SimulationProvider: Send + Sync + 'static {
         op: Self::UO,
         target: Address,
         target_call_data: Bytes,
-        block_hash: H256,
+        block_id: BlockId,
         gas: U256,
         spoofed_state: &spoof::State,
     ) -> anyhow::Result<Result<ExecutionResult, ValidationRevert>>;
@@ -244,3 +261,19 @@ pub trait EntryPointProvider<UO>:
     + L1GasProvider<UO = UO>
 {
 }
+
+/// Returns whether each user operation executed by the entry point at `entry_point`
+/// succeeded, by user operation hash, from the `UserOperationEvent` logs of a
+/// transaction receipt.
+///
+/// Operations included in a bundle but not executed emit no event, so are absent.
+pub fn user_operation_outcomes(entry_point: Address, logs: &[Log]) -> HashMap<H256, bool> {
+    // the event is the same for the v0.6 and v0.7 entry points
+    logs.iter()
+        .filter(|log| log.address == entry_point)
+        .filter_map(|log| {
+            UserOperationEventFilter::decode_log(&eth::log_to_raw_log(log.clone())).ok()
+        })
+        .map(|event| (H256::from(event.user_op_hash), event.success))
+        .collect()
+}
@@ -11,11 +11,17 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
 use ethers::types::{
     spoof, transaction::eip2718::TypedTransaction, Address, BlockId, Bytes, H256, U256,
 };
 use rundler_types::{
-    v0_6, v0_7, GasFees, UserOpsPerAggregator, ValidationError, ValidationOutput, ValidationRevert,
+    v0_6, v0_7, GasFees, UserOperation, UserOpsPerAggregator, ValidationError, ValidationOutput,
+    ValidationRevert,
 };
 
 use crate::{
@@ -33,6 +39,7 @@ mockall::mock! {
             -> anyhow::Result<U256>;
         async fn get_deposit_info(&self, address: Address) -> anyhow::Result<DepositInfo>;
         async fn get_balances(&self, addresses: Vec<Address>) -> anyhow::Result<Vec<U256>>;
+        async fn is_paused(&self, pause_guardian: Address) -> anyhow::Result<bool>;
     }
 
     #[async_trait::async_trait]
@@ -75,7 +82,7 @@ mockall::mock! {
             op: v0_6::UserOperation,
             target: Address,
             target_call_data: Bytes,
-            block_hash: H256,
+            block_id: BlockId,
             gas: U256,
             spoofed_state: &spoof::State,
         ) -> anyhow::Result<Result<ExecutionResult, ValidationRevert>>;
@@ -94,6 +101,7 @@ mockall::mock! {
             entry_point_address: Address,
             op: v0_6::UserOperation,
             gas_price: U256,
+            state_override: &spoof::State,
         ) -> anyhow::Result<U256>;
     }
 
@@ -126,6 +134,7 @@ mockall::mock! {
             -> anyhow::Result<U256>;
         async fn get_deposit_info(&self, address: Address) -> anyhow::Result<DepositInfo>;
         async fn get_balances(&self, addresses: Vec<Address>) -> anyhow::Result<Vec<U256>>;
+        async fn is_paused(&self, pause_guardian: Address) -> anyhow::Result<bool>;
     }
 
     #[async_trait::async_trait]
@@ -168,7 +177,7 @@ mockall::mock! {
             op: v0_7::UserOperation,
             target: Address,
             target_call_data: Bytes,
-            block_hash: H256,
+            block_id: BlockId,
             gas: U256,
             spoofed_state: &spoof::State,
         ) -> anyhow::Result<Result<ExecutionResult, ValidationRevert>>;
@@ -187,6 +196,7 @@ mockall::mock! {
             entry_point_address: Address,
             op: v0_7::UserOperation,
             gas_price: U256,
+            state_override: &spoof::State,
         ) -> anyhow::Result<U256>;
     }
 
@@ -208,3 +218,173 @@ mockall::mock! {
         ) -> TypedTransaction;
     }
 }
+
+/// Arguments of a recorded `get_send_bundle_transaction` call
+#[derive(Clone, Debug)]
+pub struct SendBundleTransactionCall<UO: UserOperation> {
+    /// Operations per aggregator in the bundle
+    pub ops_per_aggregator: Vec<UserOpsPerAggregator<UO>>,
+    /// Beneficiary of the bundle
+    pub beneficiary: Address,
+    /// Gas limit of the bundle
+    pub gas: U256,
+    /// Gas fees of the bundle
+    pub gas_fees: GasFees,
+}
+
+/// In-memory entry point for deterministic tests without a chain.
+///
+/// Records calls to `get_send_bundle_transaction` and returns the transactions,
+/// balances, deposits and `handleOps` results configured by the test. Clones
+/// share state, so a test can keep a handle while the code under test owns another.
+#[derive(Clone, Debug)]
+pub struct InMemoryEntryPoint<UO: UserOperation> {
+    address: Address,
+    state: Arc<Mutex<InMemoryEntryPointState<UO>>>,
+}
+
+#[derive(Debug)]
+struct InMemoryEntryPointState<UO: UserOperation> {
+    balances: HashMap<Address, U256>,
+    deposits: HashMap<Address, DepositInfo>,
+    handle_ops_out: HandleOpsOut,
+    send_bundle_transaction: TypedTransaction,
+    send_bundle_transaction_calls: Vec<SendBundleTransactionCall<UO>>,
+    paused: bool,
+}
+
+impl<UO: UserOperation> InMemoryEntryPoint<UO> {
+    /// Create a new in-memory entry point at `address`
+    ///
+    /// By default `handleOps` succeeds, all balances and deposits are zero, and the
+    /// entry point is not paused.
+    pub fn new(address: Address) -> Self {
+        Self {
+            address,
+            state: Arc::new(Mutex::new(InMemoryEntryPointState {
+                balances: HashMap::new(),
+                deposits: HashMap::new(),
+                handle_ops_out: HandleOpsOut::Success,
+                send_bundle_transaction: TypedTransaction::default(),
+                send_bundle_transaction_calls: vec![],
+                paused: false,
+            })),
+        }
+    }
+
+    /// Set the balance returned for `address`
+    pub fn set_balance(&self, address: Address, balance: U256) {
+        self.state.lock().unwrap().balances.insert(address, balance);
+    }
+
+    /// Set the deposit info returned for `address`
+    pub fn set_deposit_info(&self, address: Address, deposit_info: DepositInfo) {
+        self.state
+            .lock()
+            .unwrap()
+            .deposits
+            .insert(address, deposit_info);
+    }
+
+    /// Set the result returned by `call_handle_ops`
+    pub fn set_handle_ops_out(&self, handle_ops_out: HandleOpsOut) {
+        self.state.lock().unwrap().handle_ops_out = handle_ops_out;
+    }
+
+    /// Set whether `is_paused` reports the entry point as paused, for any guardian
+    pub fn set_paused(&self, paused: bool) {
+        self.state.lock().unwrap().paused = paused;
+    }
+
+    /// Set the transaction returned by `get_send_bundle_transaction`
+    pub fn set_send_bundle_transaction(&self, tx: TypedTransaction) {
+        self.state.lock().unwrap().send_bundle_transaction = tx;
+    }
+
+    /// Returns the recorded `get_send_bundle_transaction` calls, in call order
+    pub fn send_bundle_transaction_calls(&self) -> Vec<SendBundleTransactionCall<UO>> {
+        self.state
+            .lock()
+            .unwrap()
+            .send_bundle_transaction_calls
+            .clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl<UO: UserOperation> EntryPoint for InMemoryEntryPoint<UO> {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn balance_of(
+        &self,
+        address: Address,
+        _block_id: Option<BlockId>,
+    ) -> anyhow::Result<U256> {
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .balances
+            .get(&address)
+            .copied()
+            .unwrap_or_default())
+    }
+
+    async fn get_deposit_info(&self, address: Address) -> anyhow::Result<DepositInfo> {
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .deposits
+            .get(&address)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn get_balances(&self, addresses: Vec<Address>) -> anyhow::Result<Vec<U256>> {
+        let state = self.state.lock().unwrap();
+        Ok(addresses
+            .iter()
+            .map(|address| state.balances.get(address).copied().unwrap_or_default())
+            .collect())
+    }
+
+    async fn is_paused(&self, _pause_guardian: Address) -> anyhow::Result<bool> {
+        Ok(self.state.lock().unwrap().paused)
+    }
+}
+
+#[async_trait::async_trait]
+impl<UO: UserOperation> BundleHandler for InMemoryEntryPoint<UO> {
+    type UO = UO;
+
+    async fn call_handle_ops(
+        &self,
+        _ops_per_aggregator: Vec<UserOpsPerAggregator<UO>>,
+        _beneficiary: Address,
+        _gas: U256,
+    ) -> anyhow::Result<HandleOpsOut> {
+        Ok(self.state.lock().unwrap().handle_ops_out.clone())
+    }
+
+    fn get_send_bundle_transaction(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator<UO>>,
+        beneficiary: Address,
+        gas: U256,
+        gas_fees: GasFees,
+    ) -> TypedTransaction {
+        let mut state = self.state.lock().unwrap();
+        state
+            .send_bundle_transaction_calls
+            .push(SendBundleTransactionCall {
+                ops_per_aggregator,
+                beneficiary,
+                gas,
+                gas_fees,
+            });
+        state.send_bundle_transaction.clone()
+    }
+}
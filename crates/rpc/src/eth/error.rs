@@ -11,40 +11,25 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::fmt::Display;
-
-use ethers::types::{Address, Bytes, U256};
-use jsonrpsee::types::{
-    error::{CALL_EXECUTION_FAILED_CODE, INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE},
-    ErrorObjectOwned,
-};
+use ethers::types::{Address, U256};
+use jsonrpsee::types::{error::CALL_EXECUTION_FAILED_CODE, ErrorObjectOwned};
 use rundler_provider::ProviderError;
 use rundler_sim::GasEstimationError;
 use rundler_types::{
     pool::{MempoolError, PoolError, PrecheckViolation, SimulationViolation},
-    Entity, EntityType, Opcode, Timestamp, ValidationRevert,
+    rpc::{
+        ExecutionRevertedWithBytesData, ValidationRevertData, ENTRYPOINT_VALIDATION_REJECTED_CODE,
+        EXECUTION_REVERTED_CODE, INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE, OPCODE_VIOLATION_CODE,
+        OUT_OF_TIME_RANGE_CODE, PAYMASTER_DEPOSIT_TOO_LOW_CODE, PAYMASTER_VALIDATION_REJECTED_CODE,
+        SIGNATURE_CHECK_FAILED_CODE, STAKE_TOO_LOW_CODE, THROTTLED_OR_BANNED_CODE,
+        UNSUPPORTED_AGGREGATOR_CODE,
+    },
+    Entity, EntityType, Opcode, Timestamp,
 };
 use serde::Serialize;
 
 use crate::error::{rpc_err, rpc_err_with_data};
 
-// Error codes borrowed from jsonrpsee
-// INVALID_REQUEST_CODE = -32600
-// INVALID_PARAMS_CODE = -32602
-// INTERNAL_ERROR_CODE = -32603
-
-// Custom ERC-4337 error codes
-const ENTRYPOINT_VALIDATION_REJECTED_CODE: i32 = -32500;
-const PAYMASTER_VALIDATION_REJECTED_CODE: i32 = -32501;
-const OPCODE_VIOLATION_CODE: i32 = -32502;
-const OUT_OF_TIME_RANGE_CODE: i32 = -32503;
-const THROTTLED_OR_BANNED_CODE: i32 = -32504;
-const STAKE_TOO_LOW_CODE: i32 = -32505;
-const UNSUPORTED_AGGREGATOR_CODE: i32 = -32506;
-const SIGNATURE_CHECK_FAILED_CODE: i32 = -32507;
-const PAYMASTER_DEPOSIT_TOO_LOW: i32 = -32508;
-const EXECUTION_REVERTED: i32 = -32521;
-
 pub(crate) type EthResult<T> = Result<T, EthRpcError>;
 
 /// Error returned by the RPC server eth namespace
@@ -87,8 +72,8 @@ pub enum EthRpcError {
     #[error("Sender storage at (address: {1:?} slot: {2:#032x}) accessed during deployment. Factory (or {0:?}) must be staked")]
     AssociatedStorageDuringDeploy(Option<EntityType>, Address, U256),
     /// Invalid storage access, maps to Opcode Violation
-    #[error("{0} accesses inaccessible storage at address: {1:?} slot: {2:#032x}")]
-    InvalidStorageAccess(EntityType, Address, U256),
+    #[error("{0} {1:?} accesses inaccessible storage at address: {2:?} slot: {3:#032x}")]
+    InvalidStorageAccess(EntityType, Address, Address, U256),
     /// Operation is out of time range
     #[error("operation is out of time range")]
     OutOfTimeRange(OutOfTimeRangeData),
@@ -133,6 +118,9 @@ pub enum EthRpcError {
     ExecutionReverted(String),
     #[error("execution reverted")]
     ExecutionRevertedWithBytes(ExecutionRevertedWithBytesData),
+    /// Gas estimation failed, with the error code and data of the estimation error
+    #[error("{}", gas_estimation_message(.0))]
+    GasEstimation(GasEstimationError),
     #[error("operation rejected by mempool: {0}")]
     OperationRejected(String),
 }
@@ -186,52 +174,6 @@ impl StakeTooLowData {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ValidationRevertData {
-    reason: Option<String>,
-    inner_reason: Option<String>,
-    revert_data: Option<Bytes>,
-}
-
-impl Display for ValidationRevertData {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(reason) = &self.reason {
-            write!(f, "[reason]: {}", reason)?;
-        }
-        if let Some(inner_reason) = &self.inner_reason {
-            write!(f, " | [inner reason]: {}", inner_reason)?;
-        }
-        Ok(())
-    }
-}
-
-impl From<ValidationRevert> for ValidationRevertData {
-    fn from(value: ValidationRevert) -> Self {
-        match value {
-            ValidationRevert::EntryPoint(reason) => Self {
-                reason: Some(reason),
-                inner_reason: None,
-                revert_data: None,
-            },
-            ValidationRevert::Operation {
-                entry_point_reason,
-                inner_revert_data,
-                inner_revert_reason,
-            } => Self {
-                reason: Some(entry_point_reason),
-                inner_reason: inner_revert_reason,
-                revert_data: Some(inner_revert_data),
-            },
-            ValidationRevert::Unknown(data) => Self {
-                reason: None,
-                inner_reason: None,
-                revert_data: Some(data),
-            },
-        }
-    }
-}
-
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ReplacementUnderpricedData {
@@ -253,12 +195,6 @@ pub struct UnsupportedAggregatorData {
     pub aggregator: Address,
 }
 
-#[derive(Debug, Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ExecutionRevertedWithBytesData {
-    pub revert_data: Bytes,
-}
-
 impl From<PoolError> for EthRpcError {
     fn from(value: PoolError) -> Self {
         match value {
@@ -351,7 +287,7 @@ impl From<SimulationViolation> for EthRpcError {
                 Self::AssociatedStorageDuringDeploy(e.map(|e| e.kind), s.address, s.slot)
             }
             SimulationViolation::InvalidStorageAccess(entity, slot) => {
-                Self::InvalidStorageAccess(entity.kind, slot.address, slot.slot)
+                Self::InvalidStorageAccess(entity.kind, entity.address, slot.address, slot.slot)
             }
             SimulationViolation::NotStaked(stake_data) => {
                 Self::StakeTooLow(Box::new(StakeTooLowData::new(
@@ -365,6 +301,9 @@ impl From<SimulationViolation> for EthRpcError {
                 )))
             }
             SimulationViolation::AggregatorValidationFailed => Self::SignatureCheckFailed,
+            SimulationViolation::UnsupportedAggregator(aggregator) => {
+                Self::UnsupportedAggregator(UnsupportedAggregatorData { aggregator })
+            }
             SimulationViolation::OutOfGas(entity) => Self::OutOfGas(entity),
             SimulationViolation::ValidationRevert(revert) => Self::ValidationRevert(revert.into()),
             _ => Self::SimulationFailed(value),
@@ -385,7 +324,9 @@ impl From<EthRpcError> for ErrorObjectOwned {
             EthRpcError::PaymasterValidationRejected(data) => {
                 rpc_err_with_data(PAYMASTER_VALIDATION_REJECTED_CODE, msg, data)
             }
-            EthRpcError::PaymasterBalanceTooLow(_, _) => rpc_err(PAYMASTER_DEPOSIT_TOO_LOW, msg),
+            EthRpcError::PaymasterBalanceTooLow(_, _) => {
+                rpc_err(PAYMASTER_DEPOSIT_TOO_LOW_CODE, msg)
+            }
             EthRpcError::OpcodeViolation(_, _)
             | EthRpcError::OpcodeViolationMap(_)
             | EthRpcError::OutOfGas(_)
@@ -395,7 +336,7 @@ impl From<EthRpcError> for ErrorObjectOwned {
             | EthRpcError::SenderAddressUsedAsAlternateEntity(_)
             | EthRpcError::AssociatedStorageIsAlternateSender
             | EthRpcError::AssociatedStorageDuringDeploy(_, _, _)
-            | EthRpcError::InvalidStorageAccess(_, _, _) => rpc_err(OPCODE_VIOLATION_CODE, msg),
+            | EthRpcError::InvalidStorageAccess(_, _, _, _) => rpc_err(OPCODE_VIOLATION_CODE, msg),
             EthRpcError::OutOfTimeRange(data) => {
                 rpc_err_with_data(OUT_OF_TIME_RANGE_CODE, msg, data)
             }
@@ -404,7 +345,7 @@ impl From<EthRpcError> for ErrorObjectOwned {
             }
             EthRpcError::StakeTooLow(data) => rpc_err_with_data(OPCODE_VIOLATION_CODE, msg, data),
             EthRpcError::UnsupportedAggregator(data) => {
-                rpc_err_with_data(UNSUPORTED_AGGREGATOR_CODE, msg, data)
+                rpc_err_with_data(UNSUPPORTED_AGGREGATOR_CODE, msg, data)
             }
             EthRpcError::ReplacementUnderpriced(data) => {
                 rpc_err_with_data(INVALID_PARAMS_CODE, msg, data)
@@ -417,14 +358,21 @@ impl From<EthRpcError> for ErrorObjectOwned {
                 rpc_err(SIGNATURE_CHECK_FAILED_CODE, msg)
             }
             EthRpcError::PrecheckFailed(_) => rpc_err(CALL_EXECUTION_FAILED_CODE, msg),
-            EthRpcError::ExecutionReverted(_) => rpc_err(EXECUTION_REVERTED, msg),
+            EthRpcError::ExecutionReverted(_) => rpc_err(EXECUTION_REVERTED_CODE, msg),
             EthRpcError::ExecutionRevertedWithBytes(data) => {
-                rpc_err_with_data(EXECUTION_REVERTED, msg, data)
+                rpc_err_with_data(EXECUTION_REVERTED_CODE, msg, data)
             }
             EthRpcError::ValidationRevert(data) => {
                 rpc_err_with_data(ENTRYPOINT_VALIDATION_REJECTED_CODE, msg, data)
             }
             EthRpcError::OperationRejected(_) => rpc_err(INVALID_PARAMS_CODE, msg),
+            EthRpcError::GasEstimation(error) => {
+                let code = error.rpc_error_code();
+                match error.rpc_data() {
+                    Some(data) => rpc_err_with_data(code, msg, data),
+                    None => rpc_err(code, msg),
+                }
+            }
         }
     }
 }
@@ -447,24 +395,20 @@ impl From<ProviderError> for EthRpcError {
 
 impl From<GasEstimationError> for EthRpcError {
     fn from(e: GasEstimationError) -> Self {
-        match e {
-            GasEstimationError::RevertInValidation(revert) => Self::ValidationRevert(revert.into()),
-            GasEstimationError::RevertInCallWithMessage(message) => {
-                Self::ExecutionReverted(message)
-            }
-            GasEstimationError::RevertInCallWithBytes(b) => {
-                Self::ExecutionRevertedWithBytes(ExecutionRevertedWithBytesData { revert_data: b })
-            }
-            error @ GasEstimationError::GasUsedTooLarge => {
-                Self::EntryPointValidationRejected(error.to_string())
-            }
-            error @ GasEstimationError::GasTotalTooLarge(_, _) => {
-                Self::InvalidParams(error.to_string())
-            }
-            error @ GasEstimationError::GasFieldTooLarge(_, _) => {
-                Self::InvalidParams(error.to_string())
-            }
-            GasEstimationError::Other(error) => Self::Internal(error),
+        Self::GasEstimation(e)
+    }
+}
+
+fn gas_estimation_message(error: &GasEstimationError) -> String {
+    match error {
+        GasEstimationError::RevertInValidation(revert) => {
+            format!(
+                "validation reverted: {}",
+                ValidationRevertData::from(revert.clone())
+            )
         }
+        GasEstimationError::RevertInCallWithMessage(message) => message.clone(),
+        GasEstimationError::RevertInCallWithBytes(_) => "execution reverted".to_string(),
+        error => error.to_string(),
     }
 }
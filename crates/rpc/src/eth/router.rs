@@ -15,7 +15,7 @@ use std::{fmt::Debug, marker::PhantomData, sync::Arc};
 
 use ethers::types::{spoof, Address, H256};
 use rundler_provider::{EntryPoint, SimulationProvider};
-use rundler_sim::{GasEstimationError, GasEstimator};
+use rundler_sim::{EstimationOptions, GasEstimationError, GasEstimator};
 use rundler_types::{
     EntryPointVersion, GasEstimate, UserOperation, UserOperationOptionalGas, UserOperationVariant,
 };
@@ -140,13 +140,30 @@ impl EntryPointRouter {
             .map_err(Into::into)
     }
 
+    /// Estimates `uo` against `entry_point`. An entry point other than the routed
+    /// ones is passed as an override to the estimator of the op's version, which
+    /// rejects it unless it is configured for estimation.
     pub(crate) async fn estimate_gas(
         &self,
         entry_point: &Address,
         uo: UserOperationOptionalGas,
         state_override: Option<spoof::State>,
     ) -> EthResult<RpcGasEstimate> {
-        match self.get_ep_version(entry_point)? {
+        let (version, entry_point_override) = match self.get_ep_version(entry_point) {
+            Ok(version) => (version, None),
+            Err(error) => {
+                let version = match uo {
+                    UserOperationOptionalGas::V0_6(_) => EntryPointVersion::V0_6,
+                    UserOperationOptionalGas::V0_7(_) => EntryPointVersion::V0_7,
+                };
+                if self.route_for_version(version).is_none() {
+                    return Err(error);
+                }
+                (version, Some(*entry_point))
+            }
+        };
+
+        match version {
             EntryPointVersion::V0_6 => {
                 if !matches!(uo, UserOperationOptionalGas::V0_6(_)) {
                     return Err(EthRpcError::InvalidParams(format!(
@@ -160,7 +177,7 @@ impl EntryPointRouter {
                     .as_ref()
                     .unwrap()
                     .1
-                    .estimate_gas(uo, state_override)
+                    .estimate_gas(uo, state_override, entry_point_override)
                     .await?;
 
                 Ok(RpcGasEstimateV0_6::from(e).into())
@@ -178,7 +195,7 @@ impl EntryPointRouter {
                     .as_ref()
                     .unwrap()
                     .1
-                    .estimate_gas(uo, state_override)
+                    .estimate_gas(uo, state_override, entry_point_override)
                     .await?;
 
                 Ok(RpcGasEstimateV0_7::from(e).into())
@@ -217,6 +234,14 @@ impl EntryPointRouter {
         )))
     }
 
+    fn route_for_version(&self, version: EntryPointVersion) -> Option<&Arc<dyn EntryPointRoute>> {
+        match version {
+            EntryPointVersion::V0_6 => self.v0_6.as_ref().map(|(_, route)| route),
+            EntryPointVersion::V0_7 => self.v0_7.as_ref().map(|(_, route)| route),
+            EntryPointVersion::Unspecified => None,
+        }
+    }
+
     fn get_route(&self, entry_point: &Address) -> EthResult<&Arc<dyn EntryPointRoute>> {
         let ep = self.get_ep_version(entry_point)?;
 
@@ -243,6 +268,7 @@ pub(crate) trait EntryPointRoute: Send + Sync + 'static {
         &self,
         uo: UserOperationOptionalGas,
         state_override: Option<spoof::State>,
+        entry_point_override: Option<Address>,
     ) -> Result<GasEstimate, GasEstimationError>;
 
     async fn check_signature(
@@ -292,9 +318,14 @@ where
         &self,
         uo: UserOperationOptionalGas,
         state_override: Option<spoof::State>,
+        entry_point_override: Option<Address>,
     ) -> Result<GasEstimate, GasEstimationError> {
+        let options = EstimationOptions {
+            entry_point_override,
+            ..Default::default()
+        };
         self.gas_estimator
-            .estimate_op_gas(uo.into(), state_override.unwrap_or_default())
+            .estimate_op_gas(uo.into(), state_override.unwrap_or_default(), options)
             .await
     }
 
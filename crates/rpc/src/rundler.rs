@@ -15,15 +15,19 @@ use std::sync::Arc;
 
 use anyhow::Context;
 use async_trait::async_trait;
-use ethers::types::{Address, H256, U256};
+use ethers::types::{spoof, Address, H256, U256};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
 use rundler_provider::Provider;
-use rundler_sim::{gas, FeeEstimator};
-use rundler_types::{chain::ChainSpec, pool::Pool, UserOperation, UserOperationVariant};
+use rundler_sim::{apply_prior_nonces_state_override, gas, FeeEstimator};
+use rundler_types::{
+    chain::ChainSpec, pool::Pool, UserOperation, UserOperationOptionalGas, UserOperationVariant,
+};
 
 use crate::{
     eth::{EntryPointRouter, EthResult, EthRpcError},
-    types::{FromRpc, RpcUserOperation},
+    types::{
+        FromRpc, RpcGasEstimate, RpcPriorNonce, RpcUserOperation, RpcUserOperationOptionalGas,
+    },
     utils,
 };
 
@@ -59,6 +63,20 @@ pub trait RundlerApi {
         uo: RpcUserOperation,
         entry_point: Address,
     ) -> RpcResult<Option<H256>>;
+
+    /// Estimates the gas fields for a user operation, as `eth_estimateUserOperationGas`,
+    /// as if operations using `prior_nonces` had already executed.
+    ///
+    /// Only the senders' entry point nonces are advanced, past the highest prior nonce
+    /// of each nonce key. Other state changes of the prior operations are not applied.
+    #[method(name = "estimateUserOperationGas")]
+    async fn estimate_user_operation_gas(
+        &self,
+        op: RpcUserOperationOptionalGas,
+        entry_point: Address,
+        state_override: Option<spoof::State>,
+        prior_nonces: Vec<RpcPriorNonce>,
+    ) -> RpcResult<RpcGasEstimate>;
 }
 
 pub(crate) struct RundlerApi<P, PL> {
@@ -94,6 +112,26 @@ where
         )
         .await
     }
+
+    async fn estimate_user_operation_gas(
+        &self,
+        op: RpcUserOperationOptionalGas,
+        entry_point: Address,
+        state_override: Option<spoof::State>,
+        prior_nonces: Vec<RpcPriorNonce>,
+    ) -> RpcResult<RpcGasEstimate> {
+        utils::safe_call_rpc_handler(
+            "rundler_estimateUserOperationGas",
+            RundlerApi::estimate_user_operation_gas(
+                self,
+                op.into(),
+                entry_point,
+                state_override,
+                prior_nonces,
+            ),
+        )
+        .await
+    }
 }
 
 impl<P, PL> RundlerApi<P, PL>
@@ -172,4 +210,32 @@ where
 
         Ok(ret)
     }
+    async fn estimate_user_operation_gas(
+        &self,
+        op: UserOperationOptionalGas,
+        entry_point: Address,
+        state_override: Option<spoof::State>,
+        prior_nonces: Vec<RpcPriorNonce>,
+    ) -> EthResult<RpcGasEstimate> {
+        let bundle_size = op.single_uo_bundle_size_bytes();
+        if bundle_size > self.chain_spec.max_transaction_size_bytes {
+            return Err(EthRpcError::InvalidParams(format!(
+                "User operation in bundle size {} exceeds max transaction size {}",
+                bundle_size, self.chain_spec.max_transaction_size_bytes
+            )));
+        }
+
+        let mut state_override = state_override.unwrap_or_default();
+        apply_prior_nonces_state_override(
+            &mut state_override,
+            entry_point,
+            prior_nonces
+                .into_iter()
+                .map(|prior| (prior.sender, prior.nonce)),
+        );
+
+        self.entry_point_router
+            .estimate_gas(&entry_point, op, Some(state_override))
+            .await
+    }
 }
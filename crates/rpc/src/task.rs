@@ -15,7 +15,10 @@ use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use anyhow::bail;
 use async_trait::async_trait;
-use ethers::providers::{JsonRpcClient, Provider};
+use ethers::{
+    providers::{JsonRpcClient, Provider},
+    types::Address,
+};
 use jsonrpsee::{
     server::{middleware::ProxyGetRequestLayer, ServerBuilder},
     RpcModule,
@@ -23,6 +26,7 @@ use jsonrpsee::{
 use rundler_provider::{EthersEntryPointV0_6, EthersEntryPointV0_7};
 use rundler_sim::{
     EstimationSettings, FeeEstimator, GasEstimatorV0_6, GasEstimatorV0_7, PrecheckSettings,
+    RevertDecoder,
 };
 use rundler_task::{
     server::{format_socket_addr, HealthCheck},
@@ -68,6 +72,12 @@ pub struct Args {
     pub rundler_api_settings: RundlerApiSettings,
     /// Estimation settings.
     pub estimation_settings: EstimationSettings,
+    /// Decoder of the custom errors user operation calls revert with during estimation.
+    pub revert_decoder: Arc<RevertDecoder>,
+    /// Other deployments of entry point v0.6 that gas estimation requests may target.
+    pub estimation_entry_points_v0_6: Vec<Address>,
+    /// Other deployments of entry point v0.7 that gas estimation requests may target.
+    pub estimation_entry_points_v0_7: Vec<Address>,
     /// RPC timeout.
     pub rpc_timeout: Duration,
     /// Max number of connections.
@@ -110,24 +120,58 @@ where
             provider.clone(),
         );
 
+        let estimation_settings = self
+            .args
+            .estimation_settings
+            .for_chain(&self.args.chain_spec);
+
+        let new_fee_estimator = || {
+            FeeEstimator::new(
+                &self.args.chain_spec,
+                Arc::clone(&provider),
+                self.args.precheck_settings.priority_fee_mode,
+                self.args
+                    .precheck_settings
+                    .bundle_priority_fee_overhead_percent,
+            )
+        };
+        let new_estimator_v0_6 = |entry_point| {
+            GasEstimatorV0_6::new(
+                self.args.chain_spec.clone(),
+                provider.clone(),
+                entry_point,
+                estimation_settings,
+                new_fee_estimator(),
+            )
+            .with_revert_decoder(Arc::clone(&self.args.revert_decoder))
+        };
+        let new_estimator_v0_7 = |entry_point| {
+            GasEstimatorV0_7::new(
+                self.args.chain_spec.clone(),
+                Arc::clone(&provider),
+                entry_point,
+                estimation_settings,
+                new_fee_estimator(),
+            )
+            .with_revert_decoder(Arc::clone(&self.args.revert_decoder))
+        };
+
         let mut router_builder = EntryPointRouterBuilder::default();
         if self.args.entry_point_v0_6_enabled {
+            let mut gas_estimator = new_estimator_v0_6(ep_v0_6.clone());
+            for &address in &self.args.estimation_entry_points_v0_6 {
+                let entry_point = EthersEntryPointV0_6::new(
+                    address,
+                    &self.args.chain_spec,
+                    self.args.estimation_settings.max_simulate_handle_ops_gas,
+                    provider.clone(),
+                );
+                gas_estimator = gas_estimator
+                    .with_entry_point_override(address, new_estimator_v0_6(entry_point));
+            }
             router_builder = router_builder.v0_6(EntryPointRouteImpl::new(
                 ep_v0_6.clone(),
-                GasEstimatorV0_6::new(
-                    self.args.chain_spec.clone(),
-                    provider.clone(),
-                    ep_v0_6.clone(),
-                    self.args.estimation_settings,
-                    FeeEstimator::new(
-                        &self.args.chain_spec,
-                        Arc::clone(&provider),
-                        self.args.precheck_settings.priority_fee_mode,
-                        self.args
-                            .precheck_settings
-                            .bundle_priority_fee_overhead_percent,
-                    ),
-                ),
+                gas_estimator,
                 UserOperationEventProviderV0_6::new(
                     self.args.chain_spec.clone(),
                     provider.clone(),
@@ -139,22 +183,20 @@ where
         }
 
         if self.args.entry_point_v0_7_enabled {
+            let mut gas_estimator = new_estimator_v0_7(ep_v0_7.clone());
+            for &address in &self.args.estimation_entry_points_v0_7 {
+                let entry_point = EthersEntryPointV0_7::new(
+                    address,
+                    &self.args.chain_spec,
+                    self.args.estimation_settings.max_simulate_handle_ops_gas,
+                    provider.clone(),
+                );
+                gas_estimator = gas_estimator
+                    .with_entry_point_override(address, new_estimator_v0_7(entry_point));
+            }
             router_builder = router_builder.v0_7(EntryPointRouteImpl::new(
                 ep_v0_7.clone(),
-                GasEstimatorV0_7::new(
-                    self.args.chain_spec.clone(),
-                    Arc::clone(&provider),
-                    ep_v0_7.clone(),
-                    self.args.estimation_settings,
-                    FeeEstimator::new(
-                        &self.args.chain_spec,
-                        Arc::clone(&provider),
-                        self.args.precheck_settings.priority_fee_mode,
-                        self.args
-                            .precheck_settings
-                            .bundle_priority_fee_overhead_percent,
-                    ),
-                ),
+                gas_estimator,
                 UserOperationEventProviderV0_7::new(
                     self.args.chain_spec.clone(),
                     provider.clone(),
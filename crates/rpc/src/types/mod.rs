@@ -161,6 +161,16 @@ impl From<RpcUserOperationOptionalGas> for UserOperationOptionalGas {
     }
 }
 
+/// A sender nonce to advance past before estimating an operation
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RpcPriorNonce {
+    /// The sender of the prior operation
+    pub(crate) sender: Address,
+    /// The nonce of the prior operation
+    pub(crate) nonce: U256,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(untagged)]
 pub(crate) enum RpcGasEstimate {
@@ -15,7 +15,7 @@ use ethers::types::{Address, Bytes, U256};
 use rundler_types::{
     chain::ChainSpec,
     v0_6::{UserOperation, UserOperationOptionalGas},
-    GasEstimate,
+    Eip7702Auth, GasEstimate,
 };
 use serde::{Deserialize, Serialize};
 
@@ -88,6 +88,7 @@ pub(crate) struct RpcUserOperationOptionalGas {
     max_priority_fee_per_gas: Option<U256>,
     paymaster_and_data: Bytes,
     signature: Bytes,
+    eip7702_auth: Option<Eip7702Auth>,
 }
 
 impl From<RpcUserOperationOptionalGas> for UserOperationOptionalGas {
@@ -104,6 +105,7 @@ impl From<RpcUserOperationOptionalGas> for UserOperationOptionalGas {
             max_priority_fee_per_gas: def.max_priority_fee_per_gas,
             paymaster_and_data: def.paymaster_and_data,
             signature: def.signature,
+            eip7702_auth: def.eip7702_auth,
         }
     }
 }
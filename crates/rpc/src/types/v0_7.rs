@@ -17,7 +17,7 @@ use rundler_types::{
     v0_7::{
         UserOperation, UserOperationBuilder, UserOperationOptionalGas, UserOperationRequiredFields,
     },
-    GasEstimate,
+    Eip7702Auth, GasEstimate,
 };
 use serde::{Deserialize, Serialize};
 
@@ -149,6 +149,7 @@ pub(crate) struct RpcUserOperationOptionalGas {
     paymaster_post_op_gas_limit: Option<U128>,
     paymaster_data: Option<Bytes>,
     signature: Bytes,
+    eip7702_auth: Option<Eip7702Auth>,
 }
 
 impl From<RpcUserOperationOptionalGas> for UserOperationOptionalGas {
@@ -169,6 +170,7 @@ impl From<RpcUserOperationOptionalGas> for UserOperationOptionalGas {
             paymaster_post_op_gas_limit: def.paymaster_post_op_gas_limit,
             paymaster_data: def.paymaster_data.unwrap_or_default(),
             signature: def.signature,
+            eip7702_auth: def.eip7702_auth,
         }
     }
 }
@@ -180,6 +182,7 @@ pub(crate) struct RpcGasEstimate {
     call_gas_limit: U256,
     verification_gas_limit: U256,
     paymaster_verification_gas_limit: Option<U256>,
+    paymaster_post_op_gas_limit: Option<U256>,
 }
 
 impl From<GasEstimate> for RpcGasEstimate {
@@ -189,6 +192,7 @@ impl From<GasEstimate> for RpcGasEstimate {
             call_gas_limit: estimate.call_gas_limit,
             verification_gas_limit: estimate.verification_gas_limit,
             paymaster_verification_gas_limit: estimate.paymaster_verification_gas_limit,
+            paymaster_post_op_gas_limit: estimate.paymaster_post_op_gas_limit,
         }
     }
 }
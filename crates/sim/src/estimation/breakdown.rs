@@ -0,0 +1,124 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{cell::RefCell, future::Future};
+
+use ethers::types::{spoof, U256};
+use serde::{Deserialize, Serialize};
+
+tokio::task_local! {
+    static BREAKDOWN: RefCell<GasEstimateBreakdown>;
+}
+
+/// Breakdown of the work done to produce a gas estimate, for debugging estimates
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasEstimateBreakdown {
+    /// Number of `simulateHandleOp` calls made
+    pub simulate_handle_op_calls: u64,
+    /// Gas used by the `simulateHandleOp` call made at the maximum verification
+    /// gas, the largest if several verification gas limits were estimated
+    pub simulate_handle_ops_gas_used: Option<U256>,
+    /// Revert reasons encountered during simulation, including the out of gas
+    /// reverts expected during the verification gas search
+    pub revert_reasons: Vec<String>,
+    /// Bounds of the verification gas binary search at each step. For entry point
+    /// v0.7 this also includes the steps of the paymaster verification gas search.
+    pub verification_gas_search: Vec<SearchBounds>,
+    /// Bounds of the call gas binary search at each `simulateHandleOp` call. Each
+    /// call performs several steps of the search on chain.
+    pub call_gas_search: Vec<SearchBounds>,
+    /// State overrides applied to the simulations, in addition to the call gas
+    /// estimation proxy
+    pub state_override: spoof::State,
+}
+
+/// Low and high bounds of a binary search step
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchBounds {
+    /// Lower bound
+    pub low: U256,
+    /// Upper bound
+    pub high: U256,
+}
+
+/// Runs `estimation`, recording a breakdown of the work done within it.
+///
+/// Recording is task local, so `estimation` must not spawn the work to record.
+pub(crate) async fn with_breakdown<F: Future>(
+    state_override: spoof::State,
+    estimation: F,
+) -> (F::Output, GasEstimateBreakdown) {
+    let breakdown = GasEstimateBreakdown {
+        state_override,
+        ..Default::default()
+    };
+    BREAKDOWN
+        .scope(RefCell::new(breakdown), async move {
+            let output = estimation.await;
+            (output, BREAKDOWN.with(|breakdown| breakdown.take()))
+        })
+        .await
+}
+
+/// Records into the breakdown of the current estimation. A no-op unless called
+/// within `with_breakdown`.
+pub(crate) fn record(f: impl FnOnce(&mut GasEstimateBreakdown)) {
+    let _ = BREAKDOWN.try_with(|breakdown| f(&mut breakdown.borrow_mut()));
+}
+
+/// Records a `simulateHandleOp` call, and its revert reason if any
+pub(crate) fn record_simulation(revert_reason: Option<String>) {
+    record(|breakdown| {
+        breakdown.simulate_handle_op_calls += 1;
+        breakdown.revert_reasons.extend(revert_reason);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_records_within_scope() {
+        let (output, breakdown) = with_breakdown(spoof::State::default(), async {
+            record_simulation(None);
+            record_simulation(Some("AA13 initCode failed or OOG".to_string()));
+            record(|b| {
+                b.call_gas_search.push(SearchBounds {
+                    low: U256::zero(),
+                    high: U256::from(100),
+                })
+            });
+            5
+        })
+        .await;
+
+        assert_eq!(output, 5);
+        assert_eq!(breakdown.simulate_handle_op_calls, 2);
+        assert_eq!(
+            breakdown.revert_reasons,
+            vec!["AA13 initCode failed or OOG".to_string()]
+        );
+        assert_eq!(breakdown.call_gas_search.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_recording_outside_scope() {
+        // does not panic
+        record_simulation(None);
+
+        let (_, breakdown) = with_breakdown(spoof::State::default(), async {}).await;
+        assert_eq!(breakdown, GasEstimateBreakdown::default());
+    }
+}
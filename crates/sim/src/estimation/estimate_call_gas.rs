@@ -1,8 +1,10 @@
+use std::sync::Arc;
+
 use anyhow::{anyhow, Context};
 use async_trait::async_trait;
 use ethers::{
     abi::AbiDecode,
-    types::{spoof, Address, Bytes, H256, U128, U256},
+    types::{spoof, Address, BlockId, Bytes, U128, U256},
 };
 use rundler_provider::{EntryPoint, SimulationProvider};
 use rundler_types::{
@@ -15,17 +17,13 @@ use rundler_types::{
     },
     UserOperation,
 };
-use rundler_utils::eth;
 
-use super::Settings;
+use super::{
+    breakdown::{self, SearchBounds},
+    RevertDecoder, Settings,
+};
 use crate::GasEstimationError;
 
-/// Gas estimates will be rounded up to the next multiple of this. Increasing
-/// this value reduces the number of rounds of `eth_call` needed in binary
-/// search, e.g. a value of 1024 means ten fewer `eth_call`s needed for each of
-/// verification gas and call gas.
-const GAS_ROUNDING: u64 = 4096;
-
 /// Must match the constant in `CallGasEstimationProxyTypes.sol`.
 #[allow(dead_code)]
 pub(crate) const PROXY_IMPLEMENTATION_ADDRESS_MARKER: &str =
@@ -42,7 +40,7 @@ pub trait CallGasEstimator: Send + Sync + 'static {
     async fn estimate_call_gas(
         &self,
         op: Self::UO,
-        block_hash: H256,
+        block_id: BlockId,
         state_override: spoof::State,
     ) -> Result<U128, GasEstimationError>;
 
@@ -51,7 +49,7 @@ pub trait CallGasEstimator: Send + Sync + 'static {
     async fn simulate_handle_op_with_result(
         &self,
         op: Self::UO,
-        block_hash: H256,
+        block_id: BlockId,
         state_override: spoof::State,
     ) -> Result<(), GasEstimationError>;
 }
@@ -63,6 +61,7 @@ pub struct CallGasEstimatorImpl<E, S> {
     entry_point: E,
     settings: Settings,
     specialization: S,
+    revert_decoder: Arc<RevertDecoder>,
 }
 
 /// Functions associated with a particular user operation version that
@@ -73,7 +72,8 @@ pub trait CallGasEstimatorSpecialization: Send + Sync + 'static {
     /// The user operation type estimated by this specialization
     type UO: UserOperation;
 
-    /// Add the required CallGasEstimation proxy to the overrides at the given entrypoint address
+    /// Add the required CallGasEstimation proxy to the overrides at the given entrypoint address,
+    /// merging it with the existing overrides
     fn add_proxy_to_overrides(&self, ep_to_override: Address, state_override: &mut spoof::State);
 
     /// Returns the input user operation, modified to have limits but zero for the call gas limits.
@@ -106,7 +106,7 @@ where
     async fn estimate_call_gas(
         &self,
         op: Self::UO,
-        block_hash: H256,
+        block_id: BlockId,
         mut state_override: spoof::State,
     ) -> Result<U128, GasEstimationError> {
         let timer = std::time::Instant::now();
@@ -119,12 +119,18 @@ where
         let mut max_gas = U256::from(self.settings.max_call_gas);
         let mut is_continuation = false;
         let mut num_rounds = U256::zero();
-        loop {
+        for _ in 0..self.settings.max_estimation_attempts {
+            breakdown::record(|b| {
+                b.call_gas_search.push(SearchBounds {
+                    low: min_gas,
+                    high: max_gas,
+                })
+            });
             let target_call_data = self.specialization.get_estimate_call_gas_calldata(
                 callless_op.clone(),
                 min_gas,
                 max_gas,
-                GAS_ROUNDING.into(),
+                self.settings.call_gas_estimation_tolerance.into(),
                 is_continuation,
             );
             let target_revert_data = self
@@ -133,13 +139,14 @@ where
                     callless_op.clone(),
                     self.entry_point.address(),
                     target_call_data,
-                    block_hash,
+                    block_id,
                     self.settings.max_simulate_handle_ops_gas.into(),
                     &state_override,
                 )
                 .await?
                 .map_err(GasEstimationError::RevertInValidation)?
                 .target_result;
+            breakdown::record_simulation(None);
             if let Ok(result) = EstimateCallGasResult::decode(&target_revert_data) {
                 num_rounds += result.num_rounds;
                 tracing::debug!(
@@ -152,11 +159,9 @@ where
                     .ok()
                     .context("gas estimate should fit in a 128-bit int")?);
             } else if let Ok(revert) = EstimateCallGasRevertAtMax::decode(&target_revert_data) {
-                let error = if let Some(message) = eth::parse_revert_message(&revert.revert_data) {
-                    GasEstimationError::RevertInCallWithMessage(message)
-                } else {
-                    GasEstimationError::RevertInCallWithBytes(revert.revert_data)
-                };
+                let error =
+                    GasEstimationError::call_reverted(revert.revert_data, &self.revert_decoder);
+                breakdown::record(|b| b.revert_reasons.push(error.to_string()));
                 return Err(error);
             } else if let Ok(continuation) =
                 EstimateCallGasContinuation::decode(&target_revert_data)
@@ -181,12 +186,23 @@ where
                 ))?;
             }
         }
+
+        // Out of attempts, fall back to the upper bound of the search window, which
+        // is always enough gas for the call
+        tracing::warn!(
+            "binary search for call gas did not converge after {} attempts, using upper bound {max_gas}",
+            self.settings.max_estimation_attempts
+        );
+        Ok(max_gas
+            .try_into()
+            .ok()
+            .context("gas estimate should fit in a 128-bit int")?)
     }
 
     async fn simulate_handle_op_with_result(
         &self,
         op: Self::UO,
-        block_hash: H256,
+        block_id: BlockId,
         mut state_override: spoof::State,
     ) -> Result<(), GasEstimationError> {
         self.specialization
@@ -204,22 +220,21 @@ where
                 callless_op,
                 self.entry_point.address(),
                 target_call_data,
-                block_hash,
+                block_id,
                 self.settings.max_simulate_handle_ops_gas.into(),
                 &state_override,
             )
             .await?
             .map_err(GasEstimationError::RevertInValidation)?
             .target_result;
+        breakdown::record_simulation(None);
         if let Ok(result) = TestCallGasResult::decode(&target_revert_data) {
             if result.success {
                 Ok(())
             } else {
-                let error = if let Some(message) = eth::parse_revert_message(&result.revert_data) {
-                    GasEstimationError::RevertInCallWithMessage(message)
-                } else {
-                    GasEstimationError::RevertInCallWithBytes(result.revert_data)
-                };
+                let error =
+                    GasEstimationError::call_reverted(result.revert_data, &self.revert_decoder);
+                breakdown::record(|b| b.revert_reasons.push(error.to_string()));
                 Err(error)
             }
         } else {
@@ -240,6 +255,14 @@ where
             entry_point,
             settings,
             specialization,
+            revert_decoder: Arc::default(),
         }
     }
+
+    /// Sets the decoder used to decode custom errors the user operation's call
+    /// reverts with. By default, no custom errors are decoded.
+    pub fn with_revert_decoder(mut self, revert_decoder: Arc<RevertDecoder>) -> Self {
+        self.revert_decoder = revert_decoder;
+        self
+    }
 }
@@ -2,11 +2,14 @@ use std::sync::Arc;
 
 use anyhow::{anyhow, Context};
 use async_trait::async_trait;
-use ethers::types::{spoof, Address, Bytes, H256, U128, U256};
+use ethers::types::{spoof, Address, BlockId, Bytes, U128, U256};
 use rundler_provider::{EntryPoint, Provider, SimulateOpCallData, SimulationProvider};
 use rundler_types::{chain::ChainSpec, UserOperation};
 
-use super::Settings;
+use super::{
+    breakdown::{self, SearchBounds},
+    Settings,
+};
 use crate::GasEstimationError;
 
 /// Gas estimation will stop when the binary search bounds are within
@@ -17,6 +20,9 @@ const GAS_ESTIMATION_ERROR_MARGIN: f64 = 0.1;
 const OUT_OF_GAS_ERROR_CODES: &[&str] = &[
     "AA13", "AA23", "AA26", "AA33", "AA36", "AA40", "AA41", "AA51",
 ];
+/// The multiple of its verification gas the 0.6 entry point reserves for a user
+/// operation with a paymaster
+const PAYMASTER_VERIFICATION_GAS_MULTIPLIER: u64 = 3;
 
 /// Estimates a verification gas limit for a user operation. Can be used to
 /// estimate both verification gas and, in the v0.7 case, paymaster verification
@@ -37,7 +43,7 @@ pub trait VerificationGasEstimator: Send + Sync + 'static {
     >(
         &self,
         op: &Self::UO,
-        block_hash: H256,
+        block_id: BlockId,
         state_override: &spoof::State,
         max_guess: U128,
         get_op_with_limit: F,
@@ -71,13 +77,17 @@ where
     async fn estimate_verification_gas<F: Send + Sync + Fn(UO, GetOpWithLimitArgs) -> UO>(
         &self,
         op: &UO,
-        block_hash: H256,
+        block_id: BlockId,
         state_override: &spoof::State,
         max_guess: U128,
         get_op_with_limit: F,
     ) -> Result<U128, GasEstimationError> {
         let timer = std::time::Instant::now();
-        let paymaster_gas_fee = U128::from(self.settings.verification_estimation_gas_fee);
+        // The fee-payer holds `paymaster_fee_payer_multiplier` times the estimation fee, of
+        // which the entry point reserves 3x the verification gas cost
+        let paymaster_gas_fee = U128::from(self.settings.verification_estimation_gas_fee)
+            * U128::from(self.settings.paymaster_fee_payer_multiplier)
+            / U128::from(PAYMASTER_VERIFICATION_GAS_MULTIPLIER);
 
         // Fee logic for gas estimation:
         //
@@ -121,6 +131,7 @@ where
             )
             .await
             .context("failed to run initial guess")?;
+        breakdown::record_simulation(None);
 
         if gas_used.success {
             if self.entry_point.simulation_should_revert() {
@@ -133,6 +144,7 @@ where
             .decode_simulate_handle_ops_revert(gas_used.result)
             .err()
         {
+            breakdown::record(|b| b.revert_reasons.push(revert.to_string()));
             return Err(GasEstimationError::RevertInValidation(revert));
         }
 
@@ -144,12 +156,13 @@ where
                     op,
                     Address::zero(),
                     Bytes::new(),
-                    block_hash,
+                    block_id,
                     self.settings.max_simulate_handle_ops_gas.into(),
                     state_override,
                 )
                 .await?
                 .err();
+            breakdown::record_simulation(revert.as_ref().map(ToString::to_string));
 
             if let Some(revert) = revert {
                 if let Some(error_code) = revert.entry_point_error_code() {
@@ -166,6 +179,11 @@ where
             }
         };
 
+        breakdown::record(|b| {
+            b.simulate_handle_ops_gas_used =
+                b.simulate_handle_ops_gas_used.max(Some(gas_used.gas_used))
+        });
+
         let mut max_failure_gas = 1;
         let mut min_success_gas = self.settings.max_verification_gas;
 
@@ -178,6 +196,12 @@ where
             > (1.0 + GAS_ESTIMATION_ERROR_MARGIN)
         {
             num_rounds += 1;
+            breakdown::record(|b| {
+                b.verification_gas_search.push(SearchBounds {
+                    low: max_failure_gas.into(),
+                    high: min_success_gas.into(),
+                })
+            });
             if run_attempt_returning_error(guess).await? {
                 min_success_gas = guess;
             } else {
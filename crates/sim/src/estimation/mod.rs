@@ -11,19 +11,45 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use ethers::types::{Bytes, U128};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    future::Future,
+    sync::Arc,
+    time::Duration,
+};
+
+use ethers::{
+    abi::{self, Token},
+    types::{spoof, Address, BlockId, BlockNumber, Bytes, H256, U128, U256},
+    utils::keccak256,
+};
+use futures_util::{stream, StreamExt};
 #[cfg(feature = "test-utils")]
 use mockall::automock;
-use rundler_types::{GasEstimate, ValidationRevert};
+use rundler_provider::{EntryPoint, Provider};
+use rundler_types::{
+    chain::{ChainSpec, AVALANCHE_CHAIN_IDS},
+    rpc::{
+        ExecutionRevertedWithBytesData, ValidationRevertData, ENTRYPOINT_VALIDATION_REJECTED_CODE,
+        EXECUTION_REVERTED_CODE, INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE,
+    },
+    Eip7702Auth, GasEstimate, GasFees, UserOperation, ValidationRevert,
+};
+use rundler_utils::{eth, math};
 
 use crate::precheck::MIN_CALL_GAS_LIMIT;
 
+mod breakdown;
+pub use breakdown::{GasEstimateBreakdown, SearchBounds};
 mod estimate_verification_gas;
 pub use estimate_verification_gas::{VerificationGasEstimator, VerificationGasEstimatorImpl};
 mod estimate_call_gas;
 pub use estimate_call_gas::{
     CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization,
 };
+mod revert_decoder;
+pub use revert_decoder::RevertDecoder;
 
 /// Gas estimation module for Entry Point v0.6
 mod v0_6;
@@ -35,6 +61,9 @@ pub use v0_7::GasEstimator as GasEstimatorV0_7;
 const VERIFICATION_GAS_BUFFER_PERCENT: u64 = 10;
 /// Absolute value by which to increase the call gas limit after binary search
 const CALL_GAS_BUFFER_VALUE: U128 = U128([3000, 0]);
+/// Storage slot of the `nonceSequenceNumber` mapping in the entry point contract.
+/// The same for entry point v0.6 and v0.7.
+const NONCE_SEQUENCE_NUMBER_SLOT: u64 = 1;
 
 /// Error type for gas estimation
 #[derive(Debug, thiserror::Error)]
@@ -45,9 +74,16 @@ pub enum GasEstimationError {
     /// Call reverted with a string message
     #[error("user operation's call reverted: {0}")]
     RevertInCallWithMessage(String),
+    /// Call reverted with a Solidity panic code
+    #[error("user operation's call reverted with panic code: {0:#x}")]
+    RevertInCallWithPanic(u64),
     /// Call reverted with bytes
     #[error("user operation's call reverted: {0:#x}")]
     RevertInCallWithBytes(Bytes),
+    /// Call reverted with a custom error registered with the `RevertDecoder`,
+    /// formatted as `Name(arg, ...)`
+    #[error("user operation's call reverted: {0}")]
+    RevertInCallWithCustomError(String),
     /// Call used too much gas
     #[error("gas_used cannot be larger than a u64 integer")]
     GasUsedTooLarge,
@@ -57,11 +93,99 @@ pub enum GasEstimationError {
     /// The total amount of gas used by the UO is greater than allowed
     #[error("total gas used by the user operation {0} is greater than the allowed limit: {1}")]
     GasTotalTooLarge(u64, u64),
+    /// Supplied data field was too long
+    #[error("{0} cannot be longer than {1} bytes")]
+    FieldTooLarge(&'static str, usize),
+    /// Estimation did not complete within the configured timeout
+    #[error("gas estimation timed out after {0:?}, {1} simulations completed")]
+    Timeout(Duration, u64),
+    /// The operation's EIP-7702 authorization cannot be applied
+    #[error("invalid EIP-7702 authorization: {0}")]
+    InvalidEip7702Auth(String),
+    /// The entry point override is not one the estimator can estimate against
+    #[error("entry point {0:?} is not allowed")]
+    EntryPointNotAllowed(Address),
     /// Other error
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
+impl GasEstimationError {
+    /// Returns the error for a call reverting with `revert_data`, decoding standard
+    /// `Error(string)` and `Panic(uint256)` reverts, and custom errors registered
+    /// with `revert_decoder`
+    fn call_reverted(revert_data: Bytes, revert_decoder: &RevertDecoder) -> Self {
+        if let Some(message) = eth::parse_revert_message(&revert_data) {
+            Self::RevertInCallWithMessage(message)
+        } else if let Some(code) =
+            eth::parse_panic_code(&revert_data).and_then(|code| u64::try_from(code).ok())
+        {
+            Self::RevertInCallWithPanic(code)
+        } else if let Some(error) = revert_decoder.decode(&revert_data) {
+            Self::RevertInCallWithCustomError(error)
+        } else {
+            Self::RevertInCallWithBytes(revert_data)
+        }
+    }
+
+    /// Returns the JSON-RPC error code of the error, as defined by ERC-4337 for
+    /// validation and execution reverts
+    pub fn rpc_error_code(&self) -> i32 {
+        match self {
+            Self::RevertInValidation(_) | Self::GasUsedTooLarge => {
+                ENTRYPOINT_VALIDATION_REJECTED_CODE
+            }
+            Self::RevertInCallWithMessage(_)
+            | Self::RevertInCallWithPanic(_)
+            | Self::RevertInCallWithBytes(_)
+            | Self::RevertInCallWithCustomError(_) => EXECUTION_REVERTED_CODE,
+            Self::GasFieldTooLarge(_, _)
+            | Self::GasTotalTooLarge(_, _)
+            | Self::FieldTooLarge(_, _)
+            | Self::InvalidEip7702Auth(_)
+            | Self::EntryPointNotAllowed(_) => INVALID_PARAMS_CODE,
+            Self::Timeout(_, _) | Self::Other(_) => INTERNAL_ERROR_CODE,
+        }
+    }
+
+    /// Returns the data object of the JSON-RPC error, if the error has one.
+    ///
+    /// Validation reverts have the entry point's reason, its `AAxx` code if known,
+    /// the reason parsed from the reverting entity's revert data and that revert data. Call reverts that could
+    /// not be decoded have their revert data.
+    pub fn rpc_data(&self) -> Option<serde_json::Value> {
+        match self {
+            Self::RevertInValidation(revert) => {
+                serde_json::to_value(ValidationRevertData::from(revert.clone())).ok()
+            }
+            Self::RevertInCallWithBytes(revert_data) => {
+                serde_json::to_value(ExecutionRevertedWithBytesData {
+                    revert_data: revert_data.clone(),
+                })
+                .ok()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Options of a single `GasEstimator::estimate_op_gas` call, all unset by default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EstimationOptions {
+    /// If set, verification gas is estimated with the op's max fee and max priority
+    /// fee fixed at this price, instead of with fees derived from
+    /// `verification_estimation_gas_fee`, for accounts and paymasters whose validation
+    /// depends on the gas price. The fee payer must then hold the prefund of the op at
+    /// the maximum verification gas and this price, or validation reverts. State
+    /// overrides can set its balance or deposit.
+    pub validation_gas_price_override: Option<U256>,
+    /// If set, the op is estimated against the entry point deployed at this address
+    /// instead of the configured one. The address must have an estimator registered
+    /// with `with_entry_point_override`, else `GasEstimationError::EntryPointNotAllowed`
+    /// is returned.
+    pub entry_point_override: Option<Address>,
+}
+
 /// Gas estimator trait
 #[cfg_attr(feature = "test-utils", automock(type UserOperationOptionalGas = rundler_types::v0_6::UserOperationOptionalGas;))]
 #[async_trait::async_trait]
@@ -75,7 +199,416 @@ pub trait GasEstimator: Send + Sync + 'static {
         &self,
         op: Self::UserOperationOptionalGas,
         state_override: ethers::types::spoof::State,
+        options: EstimationOptions,
     ) -> Result<GasEstimate, GasEstimationError>;
+
+    /// Returns a gas estimate along with the fees recommended for the user
+    /// operation to be bundled promptly, using the current fee oracle, and the
+    /// additional prefund the op's fee payer needs at those limits and fees.
+    async fn estimate_op_gas_and_fees(
+        &self,
+        op: Self::UserOperationOptionalGas,
+        state_override: ethers::types::spoof::State,
+    ) -> Result<GasEstimateWithFees, GasEstimationError>;
+
+    /// Returns a gas estimate made against the maximum allowed gas limits, ignoring
+    /// any limits provided on the op, along with the margin left to each limit.
+    async fn estimate_op_gas_with_headroom(
+        &self,
+        op: Self::UserOperationOptionalGas,
+        state_override: ethers::types::spoof::State,
+    ) -> Result<GasEstimateWithHeadroom, GasEstimationError>;
+
+    /// Returns a gas estimate along with a breakdown of the work done to produce
+    /// it, for debugging estimates. Callers not needing the breakdown should use
+    /// `estimate_op_gas`.
+    async fn estimate_op_gas_with_breakdown(
+        &self,
+        op: Self::UserOperationOptionalGas,
+        state_override: ethers::types::spoof::State,
+    ) -> (
+        Result<GasEstimate, GasEstimationError>,
+        GasEstimateBreakdown,
+    );
+
+    /// Returns a gas estimate of each of `ops`, executed in order in a single
+    /// bundle, along with the gas limit of the bundle transaction.
+    ///
+    /// Each op is estimated as if the ops before it had already executed, advancing
+    /// their senders' nonces. Other state changes made by prior ops are not applied.
+    async fn estimate_bundle_gas(
+        &self,
+        ops: Vec<Self::UserOperationOptionalGas>,
+        state_override: ethers::types::spoof::State,
+    ) -> Result<BundleGasEstimate, GasEstimationError>;
+
+    /// Returns a gas estimate of each of `ops` with its state override, in the order
+    /// of `ops`. Unlike `estimate_bundle_gas`, each op is estimated independently.
+    ///
+    /// Estimations run concurrently, at most `Settings::max_concurrent_estimations`
+    /// at a time.
+    async fn estimate_op_gas_batch(
+        &self,
+        ops: Vec<(Self::UserOperationOptionalGas, ethers::types::spoof::State)>,
+    ) -> Vec<Result<GasEstimate, GasEstimationError>>;
+}
+
+/// Gas estimate with the headroom remaining to the maximum allowed gas limits
+#[derive(Debug, Clone)]
+pub struct GasEstimateWithHeadroom {
+    /// Gas limit estimates
+    pub gas_estimate: GasEstimate,
+    /// Unused margin between the estimates and the maximum allowed limits
+    pub headroom: GasHeadroom,
+}
+
+/// Unused margin between a gas estimate and the maximum allowed gas limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasHeadroom {
+    /// Margin to `Settings::max_verification_gas`
+    pub verification_gas_limit: U256,
+    /// Margin to `Settings::max_call_gas`
+    pub call_gas_limit: U256,
+    /// Margin to `Settings::max_paymaster_verification_gas`, if the estimate has a
+    /// paymaster verification gas limit
+    pub paymaster_verification_gas_limit: Option<U256>,
+}
+
+impl GasHeadroom {
+    /// Computes the headroom of `estimate` to the limits in `settings`
+    pub fn new(estimate: &GasEstimate, settings: &Settings) -> Self {
+        Self {
+            verification_gas_limit: U256::from(settings.max_verification_gas)
+                .saturating_sub(estimate.verification_gas_limit),
+            call_gas_limit: U256::from(settings.max_call_gas)
+                .saturating_sub(estimate.call_gas_limit),
+            paymaster_verification_gas_limit: estimate.paymaster_verification_gas_limit.map(
+                |limit| U256::from(settings.max_paymaster_verification_gas).saturating_sub(limit),
+            ),
+        }
+    }
+}
+
+/// Gas estimate of a bundle of user operations
+#[derive(Debug, Clone)]
+pub struct BundleGasEstimate {
+    /// Gas limit of a bundle transaction containing all of the ops, the sum of the
+    /// ops' gas limits and the transaction intrinsic gas
+    pub gas_limit: U256,
+    /// Gas limit estimates of each op, in bundle order
+    pub op_estimates: Vec<GasEstimate>,
+}
+
+/// Gas estimate with recommended fees
+#[derive(Debug, Clone)]
+pub struct GasEstimateWithFees {
+    /// Gas limit estimates
+    pub gas_estimate: GasEstimate,
+    /// Recommended `maxFeePerGas` and `maxPriorityFeePerGas`
+    pub recommended_fees: GasFees,
+    /// Additional funds the fee payer must deposit for the op to be valid with the
+    /// estimated gas limits, at the op's max fee if set, else the recommended one.
+    /// Zero if the fee payer is sufficiently funded.
+    pub prefund_shortfall: U256,
+}
+
+/// Gas estimators for deployments of an entry point other than the one an
+/// estimator is configured with, by entry point address. These addresses are the
+/// allowlist of entry point overrides.
+pub(crate) struct EntryPointOverrides<UO> {
+    estimators: HashMap<Address, Arc<dyn GasEstimator<UserOperationOptionalGas = UO>>>,
+}
+
+impl<UO> Default for EntryPointOverrides<UO> {
+    fn default() -> Self {
+        Self {
+            estimators: HashMap::new(),
+        }
+    }
+}
+
+impl<UO> Debug for EntryPointOverrides<UO> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.estimators.keys()).finish()
+    }
+}
+
+impl<UO> EntryPointOverrides<UO> {
+    pub(crate) fn insert(
+        &mut self,
+        entry_point: Address,
+        estimator: Arc<dyn GasEstimator<UserOperationOptionalGas = UO>>,
+    ) {
+        self.estimators.insert(entry_point, estimator);
+    }
+
+    /// Returns the estimator to use for `entry_point_override`, or `None` to use
+    /// the estimator configured with `entry_point`
+    pub(crate) fn get(
+        &self,
+        entry_point: Address,
+        entry_point_override: Option<Address>,
+    ) -> Result<Option<&dyn GasEstimator<UserOperationOptionalGas = UO>>, GasEstimationError> {
+        match entry_point_override {
+            Some(address) if address != entry_point => self
+                .estimators
+                .get(&address)
+                .map(|estimator| Some(estimator.as_ref()))
+                .ok_or(GasEstimationError::EntryPointNotAllowed(address)),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Runs `estimate` on each of `ops`, at most `max_concurrent` at a time, returning
+/// the results in the order of `ops`
+async fn estimate_batch<UO, F, Fut>(
+    ops: Vec<(UO, spoof::State)>,
+    max_concurrent: usize,
+    estimate: F,
+) -> Vec<Result<GasEstimate, GasEstimationError>>
+where
+    F: Fn(UO, spoof::State) -> Fut,
+    Fut: Future<Output = Result<GasEstimate, GasEstimationError>>,
+{
+    stream::iter(ops)
+        .map(|(op, state_override)| estimate(op, state_override))
+        .buffered(max_concurrent.max(1))
+        .collect()
+        .await
+}
+
+/// Returns how much more the fee payer of `op` needs to cover the op's maximum gas cost.
+///
+/// A paymaster pays from its entry point deposit, while a sender pays from its deposit
+/// and, for any missing funds, its balance.
+async fn prefund_shortfall<P, E, UO>(provider: &P, entry_point: &E, op: &UO) -> anyhow::Result<U256>
+where
+    P: Provider,
+    E: EntryPoint,
+    UO: UserOperation,
+{
+    let available = match op.paymaster() {
+        Some(paymaster) => entry_point.balance_of(paymaster, None).await?,
+        None => {
+            let deposit = entry_point.balance_of(op.sender(), None).await?;
+            let balance = provider.get_balance(op.sender(), None).await?;
+            deposit.saturating_add(balance)
+        }
+    };
+    Ok(op.max_gas_cost().saturating_sub(available))
+}
+
+/// Runs `estimation` with a breakdown of its work, failing with `GasEstimationError::Timeout`
+/// if it does not complete within `timeout`
+async fn estimate_with_timeout<T>(
+    timeout: Duration,
+    state_override: spoof::State,
+    estimation: impl Future<Output = Result<T, GasEstimationError>>,
+) -> (Result<T, GasEstimationError>, GasEstimateBreakdown) {
+    let (result, breakdown) =
+        breakdown::with_breakdown(state_override, tokio::time::timeout(timeout, estimation)).await;
+    let result = result.unwrap_or_else(|_| {
+        Err(GasEstimationError::Timeout(
+            timeout,
+            breakdown.simulate_handle_op_calls,
+        ))
+    });
+    (result, breakdown)
+}
+
+/// Runs `estimate`, running it once more if `settings.refresh_on_stale_revert` is set
+/// and it reverts in validation due to stale state. Each run fetches the block it
+/// simulates against, so the retry reads the fee payer's funds and the sender's nonce anew.
+async fn retry_on_stale_revert<T, F, Fut>(
+    settings: &Settings,
+    estimate: F,
+) -> Result<T, GasEstimationError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, GasEstimationError>>,
+{
+    match estimate().await {
+        Err(GasEstimationError::RevertInValidation(revert))
+            if settings.refresh_on_stale_revert
+                && revert.reason().is_some_and(|reason| reason.is_transient()) =>
+        {
+            tracing::debug!("retrying gas estimation against latest state after revert: {revert}");
+            estimate().await
+        }
+        result => result,
+    }
+}
+
+/// Returns the block to simulate an estimation against for `block_tag`.
+///
+/// The latest block is identified by its hash, so that all simulations of an
+/// estimation see the same state even if a new block arrives during it. If the
+/// provider does not serve the pending block, falls back to the latest block with
+/// a warning.
+async fn estimation_block<P: Provider>(
+    provider: &P,
+    block_tag: EstimationBlockTag,
+) -> Result<BlockId, GasEstimationError> {
+    if block_tag == EstimationBlockTag::Pending {
+        match provider.get_block(BlockNumber::Pending).await {
+            Ok(Some(_)) => return Ok(BlockNumber::Pending.into()),
+            Ok(None) => tracing::warn!(
+                "provider returned no pending block, estimating gas against the latest block"
+            ),
+            Err(error) => tracing::warn!(
+                "failed to get the pending block, estimating gas against the latest block: {error}"
+            ),
+        }
+    }
+
+    let (block_hash, _) = provider
+        .get_latest_block_hash_and_number()
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(block_hash.into())
+}
+
+/// Sets the `simulateHandleOp` gas used recorded in `breakdown` on a successful estimate
+fn with_recorded_gas_used(
+    result: Result<GasEstimate, GasEstimationError>,
+    breakdown: &GasEstimateBreakdown,
+) -> Result<GasEstimate, GasEstimationError> {
+    result.map(|estimate| GasEstimate {
+        simulate_handle_ops_gas_used: breakdown.simulate_handle_ops_gas_used,
+        ..estimate
+    })
+}
+
+/// Returns an error if one of the op's data fields is longer than allowed by `settings`
+fn check_field_sizes(
+    settings: &Settings,
+    op: &impl UserOperation,
+) -> Result<(), GasEstimationError> {
+    if let Some(max_size) = settings.max_op_field_size {
+        if let Some((field, _)) = op.oversized_field(max_size) {
+            return Err(GasEstimationError::FieldTooLarge(field, max_size));
+        }
+    }
+    Ok(())
+}
+
+/// Applies an op's EIP-7702 authorization, if any, to `state_override`, delegating
+/// the code of its `sender` as if the authorization had already been applied.
+///
+/// A delegated sender is an existing EOA, so an op with an authorization must not
+/// also deploy its sender with a factory.
+fn apply_eip7702_auth(
+    chain_spec: &ChainSpec,
+    sender: Address,
+    auth: Option<&Eip7702Auth>,
+    has_factory: bool,
+    state_override: &mut spoof::State,
+) -> Result<(), GasEstimationError> {
+    let Some(auth) = auth else {
+        return Ok(());
+    };
+    if has_factory {
+        return Err(GasEstimationError::InvalidEip7702Auth(
+            "an operation with an authorization cannot deploy its sender".to_string(),
+        ));
+    }
+    if !auth.is_valid_on_chain(chain_spec.id) {
+        return Err(GasEstimationError::InvalidEip7702Auth(format!(
+            "authorization chain ID {} does not match chain ID {}",
+            auth.chain_id, chain_spec.id
+        )));
+    }
+
+    auth.apply_to(sender, state_override);
+    breakdown::record(|breakdown| auth.apply_to(sender, &mut breakdown.state_override));
+    Ok(())
+}
+
+/// Adds overrides to `state_override` advancing the entry point nonce of each
+/// `(sender, nonce)` pair, as if operations using those nonces had already
+/// executed.
+///
+/// Only the nonces are advanced: balance, deposit, and storage changes that
+/// operations using those nonces would make are not applied. Each nonce key is
+/// advanced past the highest nonce given for it, in any order, and is never moved
+/// back below a sequence number already set in `state_override`.
+pub fn apply_prior_nonces_state_override(
+    state_override: &mut spoof::State,
+    entry_point: Address,
+    prior_nonces: impl IntoIterator<Item = (Address, U256)>,
+) {
+    let account = state_override.account(entry_point);
+    for (sender, nonce) in prior_nonces {
+        let slot = nonce_sequence_number_slot(sender, nonce >> 64);
+        let next_sequence = U256::from(nonce.low_u64()) + 1;
+        let current_sequence = account
+            .storage
+            .as_ref()
+            .and_then(|storage| storage.get(&slot))
+            .map(|value| U256::from_big_endian(value.as_bytes()));
+        if current_sequence.is_some_and(|current| current >= next_sequence) {
+            continue;
+        }
+
+        let mut value = [0_u8; 32];
+        next_sequence.to_big_endian(&mut value);
+        account.store(slot, H256(value));
+    }
+}
+
+/// Merges rundler's `internal` overrides for an estimation into the caller's
+/// `state_override`, account by account.
+///
+/// The caller's nonce, balance, and storage slot overrides take precedence, so an
+/// internal balance override is only applied if the caller did not set one, and
+/// internal storage overrides only fill the slots the caller left unset. Internal
+/// code overrides take precedence, as estimation relies on the code it injects.
+pub fn merge_state_overrides(
+    state_override: &mut spoof::State,
+    internal: impl IntoIterator<Item = (Address, spoof::Account)>,
+) {
+    for (address, internal) in internal {
+        let account = state_override.account(address);
+        account.nonce = account.nonce.or(internal.nonce);
+        account.balance = account.balance.or(internal.balance);
+        if internal.code.is_some() {
+            account.code = internal.code;
+        }
+        match (&mut account.storage, internal.storage) {
+            (Some(storage), Some(internal_storage)) => {
+                for (slot, value) in internal_storage.iter() {
+                    storage.entry(*slot).or_insert(*value);
+                }
+            }
+            (storage @ None, internal_storage) => *storage = internal_storage,
+            (Some(_), None) => {}
+        }
+    }
+}
+
+// Slot of `nonceSequenceNumber[sender][key]`
+fn nonce_sequence_number_slot(sender: Address, key: U256) -> H256 {
+    let sender_slot = keccak256(abi::encode(&[
+        Token::Address(sender),
+        Token::Uint(NONCE_SEQUENCE_NUMBER_SLOT.into()),
+    ]));
+    H256(keccak256(abi::encode(&[
+        Token::Uint(key),
+        Token::FixedBytes(sender_slot.to_vec()),
+    ])))
+}
+
+/// Block whose state gas is estimated against
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum EstimationBlockTag {
+    /// The latest block
+    #[default]
+    Latest,
+    /// The pending block, including the transactions the node expects in the next
+    /// block. Not supported by all providers.
+    Pending,
 }
 
 /// Settings for gas estimation
@@ -94,22 +627,905 @@ pub struct Settings {
     /// The maximum amount of gas that can be used in a call to `simulateHandleOps`
     pub max_simulate_handle_ops_gas: u64,
     /// The gas fee to use during verification gas estimation, required to be held by the fee-payer
-    /// during estimation. If using a paymaster, the fee-payer must have 3x this value.
+    /// during estimation. If using a paymaster, the fee-payer must have
+    /// `paymaster_fee_payer_multiplier` times this value.
     /// As the gas limit is varied during estimation, the fee is held constant by varying the
     /// gas price.
     /// Clients can use state overrides to set the balance of the fee-payer to at least this value.
+    /// Unused by estimations overriding the validation gas price.
     pub verification_estimation_gas_fee: u64,
+    /// The multiple of `verification_estimation_gas_fee` the fee-payer of a user operation with
+    /// a paymaster must hold during verification gas estimation. The 0.6 entry point reserves
+    /// 3x the verification gas of such operations, so the default of 3 holds the fee itself
+    /// constant. Operations without a paymaster are estimated with zero fees.
+    pub paymaster_fee_payer_multiplier: u64,
+    /// Call gas is estimated to within this many gas units, the estimate is rounded
+    /// up to the next multiple of it. Increasing this value reduces the number of
+    /// rounds needed in the binary search for call gas.
+    pub call_gas_estimation_tolerance: u64,
+    /// The maximum number of `simulateHandleOp` calls made during the binary search
+    /// for call gas. If reached, the upper bound of the search is used as the estimate.
+    pub max_estimation_attempts: u64,
+    /// The maximum size in bytes of a user operation's `paymasterAndData` and `signature`
+    /// fields. Unlimited if `None`.
+    pub max_op_field_size: Option<usize>,
+    /// The maximum time a single gas estimation may take, including all of its
+    /// simulations
+    pub estimation_timeout: Duration,
+    /// Whether to retry an estimation once, against the latest state, when it reverts
+    /// in validation with an error caused by state that may have changed since the op
+    /// was built, such as the fee payer's funds or the sender's nonce
+    pub refresh_on_stale_revert: bool,
+    /// Percentage added to the measured verification gas of an op before the fixed
+    /// buffers, for accounts whose validation cost varies between calls, e.g. with
+    /// the work of verifying a passkey signature
+    pub verification_gas_margin_percent: u64,
+    /// The maximum number of estimations of a batch run concurrently
+    pub max_concurrent_estimations: usize,
+    /// The block to simulate against. Estimating against the pending block accounts
+    /// for the transactions expected ahead of the op, but successive simulations of
+    /// an estimation may see different pending states.
+    pub block_tag: EstimationBlockTag,
 }
 
 impl Settings {
-    /// Check if the settings are valid
-    pub fn validate(&self) -> Option<String> {
+    /// Returns default settings for the chain with ID `chain_id`, to be overridden
+    /// as needed.
+    ///
+    /// These are the mainnet settings, except on chains whose block gas limit is
+    /// below the default gas limits. On the Avalanche C-Chain, blocks are limited
+    /// to 15M gas, so the gas limits are scaled down to fit an operation in a block.
+    ///
+    /// Arbitrum, Base and Polygon PoS use the mainnet settings. Their blocks fit the
+    /// default gas limits, and the L1 data cost of an operation on Arbitrum and Base
+    /// is priced in its preVerificationGas from the chain spec, not in the gas limits
+    /// estimated with these settings.
+    ///
+    /// The CLI defaults its gas limit flags to the preset of the configured chain.
+    pub fn preset_for_chain(chain_id: u64) -> Self {
+        let defaults = Self {
+            max_verification_gas: 5_000_000,
+            max_call_gas: 15_000_000,
+            max_paymaster_verification_gas: 5_000_000,
+            max_paymaster_post_op_gas: 15_000_000,
+            max_total_execution_gas: 25_000_000,
+            max_simulate_handle_ops_gas: 20_000_000,
+            verification_estimation_gas_fee: 1_000_000_000_000,
+            paymaster_fee_payer_multiplier: 3,
+            call_gas_estimation_tolerance: 4096,
+            max_estimation_attempts: 10,
+            max_op_field_size: None,
+            estimation_timeout: Duration::from_secs(10),
+            refresh_on_stale_revert: false,
+            verification_gas_margin_percent: 0,
+            max_concurrent_estimations: 4,
+            block_tag: EstimationBlockTag::Latest,
+        };
+
+        if AVALANCHE_CHAIN_IDS.contains(&chain_id) {
+            return Self {
+                max_verification_gas: 3_000_000,
+                max_call_gas: 9_000_000,
+                max_paymaster_verification_gas: 3_000_000,
+                max_paymaster_post_op_gas: 9_000_000,
+                max_total_execution_gas: 15_000_000,
+                max_simulate_handle_ops_gas: 12_000_000,
+                ..defaults
+            };
+        }
+        defaults
+    }
+
+    /// Returns the settings with the verification estimation gas fee scaled by the
+    /// chain's `verification_estimation_gas_fee_percent`
+    pub fn for_chain(self, chain_spec: &ChainSpec) -> Self {
+        Self {
+            verification_estimation_gas_fee: math::percent(
+                self.verification_estimation_gas_fee,
+                chain_spec.verification_estimation_gas_fee_percent,
+            ),
+            ..self
+        }
+    }
+
+    /// Check if the settings are valid, returning all of their problems
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
         if U128::from(self.max_call_gas)
             .cmp(&MIN_CALL_GAS_LIMIT)
             .is_lt()
         {
-            return Some("max_call_gas field cannot be lower than MIN_CALL_GAS_LIMIT".to_string());
+            errors.push("max_call_gas field cannot be lower than MIN_CALL_GAS_LIMIT".to_string());
+        }
+        if u128::from(self.max_simulate_handle_ops_gas)
+            < u128::from(self.max_call_gas) + u128::from(self.max_verification_gas)
+        {
+            errors.push(
+                "max_simulate_handle_ops_gas field cannot be lower than max_call_gas + max_verification_gas"
+                    .to_string(),
+            );
+        }
+        if self.verification_estimation_gas_fee == 0 {
+            // paymasters need a non-zero fee to trigger their fee transfers during estimation
+            errors.push(
+                "verification_estimation_gas_fee field must be greater than 0 to estimate operations with a paymaster"
+                    .to_string(),
+            );
+        }
+        if self.call_gas_estimation_tolerance == 0 {
+            errors.push("call_gas_estimation_tolerance field must be greater than 0".to_string());
         }
-        None
+        if self.max_estimation_attempts == 0 {
+            errors.push("max_estimation_attempts field must be greater than 0".to_string());
+        }
+        if self.max_concurrent_estimations == 0 {
+            errors.push("max_concurrent_estimations field must be greater than 0".to_string());
+        }
+        if self.estimation_timeout.is_zero() {
+            errors.push("estimation_timeout field must be greater than 0".to_string());
+        }
+        if self.paymaster_fee_payer_multiplier == 0 {
+            errors.push("paymaster_fee_payer_multiplier field must be greater than 0".to_string());
+        }
+        errors
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use ethers::{abi::AbiEncode, types::Block};
+    use rundler_provider::MockProvider;
+    use rundler_types::{v0_6, OpClassification};
+
+    use super::*;
+
+    struct FixedGasEstimator;
+
+    #[async_trait::async_trait]
+    impl GasEstimator for FixedGasEstimator {
+        type UserOperationOptionalGas = v0_6::UserOperationOptionalGas;
+
+        async fn estimate_op_gas(
+            &self,
+            _op: v0_6::UserOperationOptionalGas,
+            _state_override: spoof::State,
+            _options: EstimationOptions,
+        ) -> Result<GasEstimate, GasEstimationError> {
+            Ok(GasEstimate {
+                pre_verification_gas: U256::from(1),
+                call_gas_limit: U256::from(2),
+                verification_gas_limit: U256::from(3),
+                paymaster_verification_gas_limit: None,
+                paymaster_post_op_gas_limit: None,
+                classification: OpClassification::new(false, true, false),
+                simulate_handle_ops_gas_used: None,
+            })
+        }
+
+        async fn estimate_op_gas_and_fees(
+            &self,
+            op: v0_6::UserOperationOptionalGas,
+            state_override: spoof::State,
+        ) -> Result<GasEstimateWithFees, GasEstimationError> {
+            Ok(GasEstimateWithFees {
+                gas_estimate: self
+                    .estimate_op_gas(op, state_override, EstimationOptions::default())
+                    .await?,
+                recommended_fees: GasFees::default(),
+                prefund_shortfall: U256::zero(),
+            })
+        }
+
+        async fn estimate_op_gas_with_headroom(
+            &self,
+            op: v0_6::UserOperationOptionalGas,
+            state_override: spoof::State,
+        ) -> Result<GasEstimateWithHeadroom, GasEstimationError> {
+            let gas_estimate = self
+                .estimate_op_gas(op, state_override, EstimationOptions::default())
+                .await?;
+            Ok(GasEstimateWithHeadroom {
+                headroom: GasHeadroom::new(&gas_estimate, &settings()),
+                gas_estimate,
+            })
+        }
+
+        async fn estimate_op_gas_with_breakdown(
+            &self,
+            op: v0_6::UserOperationOptionalGas,
+            state_override: spoof::State,
+        ) -> (
+            Result<GasEstimate, GasEstimationError>,
+            GasEstimateBreakdown,
+        ) {
+            breakdown::with_breakdown(
+                state_override.clone(),
+                self.estimate_op_gas(op, state_override, EstimationOptions::default()),
+            )
+            .await
+        }
+
+        async fn estimate_bundle_gas(
+            &self,
+            ops: Vec<v0_6::UserOperationOptionalGas>,
+            state_override: spoof::State,
+        ) -> Result<BundleGasEstimate, GasEstimationError> {
+            let mut op_estimates = vec![];
+            for op in ops {
+                op_estimates.push(
+                    self.estimate_op_gas(op, state_override.clone(), EstimationOptions::default())
+                        .await?,
+                );
+            }
+            Ok(BundleGasEstimate {
+                gas_limit: U256::from(6 * op_estimates.len()),
+                op_estimates,
+            })
+        }
+
+        async fn estimate_op_gas_batch(
+            &self,
+            ops: Vec<(v0_6::UserOperationOptionalGas, spoof::State)>,
+        ) -> Vec<Result<GasEstimate, GasEstimationError>> {
+            estimate_batch(ops, 2, |op, state_override| {
+                self.estimate_op_gas(op, state_override, EstimationOptions::default())
+            })
+            .await
+        }
+    }
+
+    fn op_v0_6() -> v0_6::UserOperationOptionalGas {
+        v0_6::UserOperationOptionalGas {
+            sender: Address::zero(),
+            nonce: U256::zero(),
+            init_code: Bytes::new(),
+            call_data: Bytes::new(),
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            pre_verification_gas: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            paymaster_and_data: Bytes::new(),
+            signature: Bytes::new(),
+            eip7702_auth: None,
+        }
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            max_verification_gas: 5_000_000,
+            max_call_gas: 20_000_000,
+            max_paymaster_verification_gas: 5_000_000,
+            max_paymaster_post_op_gas: 20_000_000,
+            max_total_execution_gas: 25_000_000,
+            max_simulate_handle_ops_gas: 25_000_000,
+            verification_estimation_gas_fee: 1_000_000_000_000,
+            paymaster_fee_payer_multiplier: 3,
+            call_gas_estimation_tolerance: 4096,
+            max_estimation_attempts: 10,
+            max_op_field_size: None,
+            estimation_timeout: Duration::from_secs(10),
+            refresh_on_stale_revert: false,
+            verification_gas_margin_percent: 0,
+            max_concurrent_estimations: 4,
+            block_tag: EstimationBlockTag::Latest,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_estimation_block() {
+        let latest = H256::random();
+        let mut provider = MockProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || Ok((latest, 1.into())));
+        provider
+            .expect_get_block::<BlockNumber>()
+            .times(1)
+            .returning(|_| Ok(Some(Block::default())));
+
+        assert_eq!(
+            estimation_block(&provider, EstimationBlockTag::Latest)
+                .await
+                .unwrap(),
+            BlockId::Hash(latest)
+        );
+        assert_eq!(
+            estimation_block(&provider, EstimationBlockTag::Pending)
+                .await
+                .unwrap(),
+            BlockId::Number(BlockNumber::Pending)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_estimation_block_falls_back_to_latest() {
+        let latest = H256::random();
+        let mut provider = MockProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || Ok((latest, 1.into())));
+        provider
+            .expect_get_block::<BlockNumber>()
+            .returning(|_| Ok(None));
+
+        assert_eq!(
+            estimation_block(&provider, EstimationBlockTag::Pending)
+                .await
+                .unwrap(),
+            BlockId::Hash(latest)
+        );
+    }
+
+    #[test]
+    fn test_validate_paymaster_fee_payer_multiplier() {
+        assert!(settings().validate().is_empty());
+
+        let settings = Settings {
+            paymaster_fee_payer_multiplier: 0,
+            ..settings()
+        };
+        assert_eq!(settings.validate().len(), 1);
+    }
+
+    #[test]
+    fn test_validate_max_simulate_handle_ops_gas() {
+        // max_simulate_handle_ops_gas exactly covers call and verification gas
+        assert!(settings().validate().is_empty());
+
+        let settings = Settings {
+            max_simulate_handle_ops_gas: 24_999_999,
+            ..settings()
+        };
+        assert_eq!(
+            settings.validate(),
+            vec!["max_simulate_handle_ops_gas field cannot be lower than max_call_gas + max_verification_gas"]
+        );
+    }
+
+    #[test]
+    fn test_validate_zero_verification_estimation_gas_fee() {
+        let settings = Settings {
+            verification_estimation_gas_fee: 0,
+            ..settings()
+        };
+        assert_eq!(
+            settings.validate(),
+            vec!["verification_estimation_gas_fee field must be greater than 0 to estimate operations with a paymaster"]
+        );
+    }
+
+    #[test]
+    fn test_validate_returns_all_errors() {
+        let settings = Settings {
+            max_simulate_handle_ops_gas: 0,
+            verification_estimation_gas_fee: 0,
+            max_estimation_attempts: 0,
+            ..settings()
+        };
+        assert_eq!(settings.validate().len(), 3);
+    }
+
+    #[test]
+    fn test_settings_for_chain() {
+        let mainnet = ChainSpec::default();
+        let l2 = ChainSpec {
+            verification_estimation_gas_fee_percent: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            settings()
+                .for_chain(&mainnet)
+                .verification_estimation_gas_fee,
+            1_000_000_000_000
+        );
+        assert_eq!(
+            settings().for_chain(&l2).verification_estimation_gas_fee,
+            10_000_000_000
+        );
+        // other settings are unchanged
+        assert_eq!(
+            settings().for_chain(&l2).max_verification_gas,
+            settings().max_verification_gas
+        );
+    }
+
+    #[test]
+    fn test_preset_for_chain() {
+        let mainnet = Settings::preset_for_chain(1);
+        assert!(mainnet.validate().is_empty());
+        assert_eq!(mainnet.max_simulate_handle_ops_gas, 20_000_000);
+        assert_eq!(mainnet.max_total_execution_gas, 25_000_000);
+
+        // limited to the Avalanche block gas limit
+        let avax = Settings::preset_for_chain(43114);
+        assert_eq!(
+            Settings::preset_for_chain(43113).max_total_execution_gas,
+            15_000_000
+        );
+        assert!(avax.validate().is_empty());
+        assert_eq!(avax.max_total_execution_gas, 15_000_000);
+        assert!(avax.max_simulate_handle_ops_gas <= avax.max_total_execution_gas);
+        // non gas limit settings are unchanged
+        assert_eq!(
+            avax.max_estimation_attempts,
+            mainnet.max_estimation_attempts
+        );
+
+        // Arbitrum, Base and Polygon blocks fit the mainnet gas limits
+        for chain_id in [42161, 421614, 8453, 84532, 137, 80002] {
+            let preset = Settings::preset_for_chain(chain_id);
+            assert!(preset.validate().is_empty());
+            assert_eq!(preset.max_verification_gas, mainnet.max_verification_gas);
+            assert_eq!(preset.max_call_gas, mainnet.max_call_gas);
+            assert_eq!(
+                preset.max_total_execution_gas,
+                mainnet.max_total_execution_gas
+            );
+            assert_eq!(
+                preset.max_simulate_handle_ops_gas,
+                mainnet.max_simulate_handle_ops_gas
+            );
+        }
+    }
+
+    #[test]
+    fn test_gas_headroom() {
+        let estimate = GasEstimate {
+            pre_verification_gas: U256::from(50_000),
+            call_gas_limit: U256::from(100_000),
+            verification_gas_limit: U256::from(200_000),
+            paymaster_verification_gas_limit: Some(U256::from(300_000)),
+            paymaster_post_op_gas_limit: None,
+            classification: OpClassification::new(false, true, true),
+            simulate_handle_ops_gas_used: None,
+        };
+
+        assert_eq!(
+            GasHeadroom::new(&estimate, &settings()),
+            GasHeadroom {
+                verification_gas_limit: U256::from(4_800_000),
+                call_gas_limit: U256::from(19_900_000),
+                paymaster_verification_gas_limit: Some(U256::from(4_700_000)),
+            }
+        );
+
+        // estimates over a cap have no headroom
+        let estimate = GasEstimate {
+            call_gas_limit: U256::from(30_000_000),
+            paymaster_verification_gas_limit: None,
+            ..estimate
+        };
+        let headroom = GasHeadroom::new(&estimate, &settings());
+        assert_eq!(headroom.call_gas_limit, U256::zero());
+        assert_eq!(headroom.paymaster_verification_gas_limit, None);
+    }
+
+    #[tokio::test]
+    async fn test_entry_point_overrides() {
+        let entry_point = Address::random();
+        let allowed = Address::random();
+        let mut overrides = EntryPointOverrides::default();
+        overrides.insert(allowed, Arc::new(FixedGasEstimator));
+
+        // the configured entry point needs no override
+        assert!(overrides.get(entry_point, None).unwrap().is_none());
+        assert!(overrides
+            .get(entry_point, Some(entry_point))
+            .unwrap()
+            .is_none());
+
+        let op = op_v0_6();
+        let estimate = overrides
+            .get(entry_point, Some(allowed))
+            .unwrap()
+            .unwrap()
+            .estimate_op_gas(op, spoof::State::default(), EstimationOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(estimate.call_gas_limit, U256::from(2));
+
+        let other = Address::random();
+        assert!(matches!(
+            overrides.get(entry_point, Some(other)),
+            Err(GasEstimationError::EntryPointNotAllowed(address)) if address == other
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_batch_order_and_concurrency() {
+        let running = AtomicU64::new(0);
+        let max_running = AtomicU64::new(0);
+        let ops = (0..6_u64)
+            .map(|i| {
+                let op = op_v0_6();
+                let op = v0_6::UserOperationOptionalGas {
+                    nonce: U256::from(i),
+                    ..op
+                };
+                (op, spoof::State::default())
+            })
+            .collect();
+
+        let results = estimate_batch(ops, 2, |op, state_override| {
+            let (running, max_running) = (&running, &max_running);
+            async move {
+                let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now_running, Ordering::SeqCst);
+                // later ops finish first
+                let delay = 6 - op.nonce.as_u64();
+                tokio::time::sleep(Duration::from_millis(5 * delay)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+
+                let nonce = op.nonce;
+                let estimate = FixedGasEstimator
+                    .estimate_op_gas(op, state_override, EstimationOptions::default())
+                    .await?;
+                Ok(GasEstimate {
+                    call_gas_limit: nonce,
+                    ..estimate
+                })
+            }
+        })
+        .await;
+
+        let call_gas_limits: Vec<_> = results
+            .into_iter()
+            .map(|result| result.unwrap().call_gas_limit.as_u64())
+            .collect();
+        assert_eq!(call_gas_limits, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_rpc_error_code_and_data() {
+        let revert = GasEstimationError::RevertInValidation(ValidationRevert::Operation {
+            entry_point_reason: "AA23 reverted".to_string(),
+            inner_revert_data: Bytes::from(vec![0x01, 0x02]),
+            inner_revert_reason: Some("bad signature".to_string()),
+        });
+        assert_eq!(revert.rpc_error_code(), -32500);
+        assert_eq!(
+            revert.rpc_data().unwrap(),
+            serde_json::json!({
+                "reason": "AA23 reverted",
+                "code": "AA23",
+                "innerReason": "bad signature",
+                "revertData": "0x0102",
+            })
+        );
+
+        let revert =
+            GasEstimationError::RevertInValidation(ValidationRevert::EntryPoint("AA10".into()));
+        assert_eq!(
+            revert.rpc_data().unwrap(),
+            serde_json::json!({
+                "reason": "AA10",
+                "code": "AA10",
+                "innerReason": null,
+                "revertData": null,
+            })
+        );
+
+        let revert = GasEstimationError::RevertInCallWithBytes(Bytes::from(vec![0xab]));
+        assert_eq!(revert.rpc_error_code(), -32521);
+        assert_eq!(
+            revert.rpc_data().unwrap(),
+            serde_json::json!({ "revertData": "0xab" })
+        );
+
+        let revert = GasEstimationError::RevertInCallWithMessage("failed".to_string());
+        assert_eq!(revert.rpc_error_code(), -32521);
+        assert_eq!(revert.rpc_data(), None);
+
+        assert_eq!(
+            GasEstimationError::FieldTooLarge("callData", 10).rpc_error_code(),
+            -32602
+        );
+        assert_eq!(
+            GasEstimationError::Other(anyhow::anyhow!("oops")).rpc_error_code(),
+            -32603
+        );
+    }
+
+    #[test]
+    fn test_call_reverted_decodes_standard_reverts() {
+        let decoder = RevertDecoder::default();
+        let message = eth::ContractRevertError {
+            reason: "insufficient balance".to_string(),
+        };
+        assert!(matches!(
+            GasEstimationError::call_reverted(message.encode().into(), &decoder),
+            GasEstimationError::RevertInCallWithMessage(m) if m == "insufficient balance"
+        ));
+
+        let panic = eth::ContractPanicError {
+            code: U256::from(0x11),
+        };
+        assert!(matches!(
+            GasEstimationError::call_reverted(panic.encode().into(), &decoder),
+            GasEstimationError::RevertInCallWithPanic(0x11)
+        ));
+
+        let custom = Bytes::from(vec![0xde, 0xad, 0xbe, 0xef]);
+        assert!(matches!(
+            GasEstimationError::call_reverted(custom.clone(), &decoder),
+            GasEstimationError::RevertInCallWithBytes(b) if b == custom
+        ));
+    }
+
+    #[test]
+    fn test_call_reverted_decodes_registered_custom_errors() {
+        let decoder =
+            RevertDecoder::new().with_error("Unauthorized", vec![abi::ParamType::Address]);
+        let sender = Address::from_low_u64_be(1);
+        let revert_data: Bytes = [
+            &abi::short_signature("Unauthorized", &[abi::ParamType::Address])[..],
+            &abi::encode(&[Token::Address(sender)]),
+        ]
+        .concat()
+        .into();
+
+        assert!(matches!(
+            GasEstimationError::call_reverted(revert_data.clone(), &decoder),
+            GasEstimationError::RevertInCallWithCustomError(e) if e == format!("Unauthorized({sender:?})")
+        ));
+        // unregistered errors are not decoded
+        assert!(matches!(
+            GasEstimationError::call_reverted(revert_data.clone(), &RevertDecoder::default()),
+            GasEstimationError::RevertInCallWithBytes(b) if b == revert_data
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_with_timeout() {
+        let (result, breakdown) = estimate_with_timeout::<()>(
+            Duration::from_millis(10),
+            spoof::State::default(),
+            async {
+                breakdown::record_simulation(None);
+                breakdown::record_simulation(None);
+                std::future::pending().await
+            },
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(GasEstimationError::Timeout(timeout, 2)) if timeout == Duration::from_millis(10)
+        ));
+        assert_eq!(breakdown.simulate_handle_op_calls, 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_stale_revert() {
+        let refresh = Settings {
+            refresh_on_stale_revert: true,
+            ..settings()
+        };
+
+        // fails with `revert` on the first run only
+        let run = |revert: &'static str| {
+            let runs = AtomicU64::new(0);
+            move || {
+                let run = runs.fetch_add(1, Ordering::Relaxed);
+                async move {
+                    if run == 0 {
+                        Err(GasEstimationError::RevertInValidation(
+                            ValidationRevert::EntryPoint(revert.to_string()),
+                        ))
+                    } else {
+                        Ok(())
+                    }
+                }
+            }
+        };
+
+        // stale state reverts are retried
+        assert!(
+            retry_on_stale_revert(&refresh, run("AA25 invalid account nonce"))
+                .await
+                .is_ok()
+        );
+        assert!(
+            retry_on_stale_revert(&refresh, run("AA21 didn't pay prefund"))
+                .await
+                .is_ok()
+        );
+
+        // other reverts are not
+        assert!(matches!(
+            retry_on_stale_revert(&refresh, run("AA23 reverted")).await,
+            Err(GasEstimationError::RevertInValidation(_))
+        ));
+
+        // nor any revert unless enabled
+        assert!(matches!(
+            retry_on_stale_revert(&settings(), run("AA25 invalid account nonce")).await,
+            Err(GasEstimationError::RevertInValidation(_))
+        ));
+    }
+
+    #[test]
+    fn test_apply_prior_nonces_state_override() {
+        let entry_point = Address::from_low_u64_be(1);
+        let sender = Address::from_low_u64_be(2);
+        let key = U256::from(7);
+        let nonce = (key << 64) + 5;
+
+        let mut state_override = spoof::State::default();
+        apply_prior_nonces_state_override(&mut state_override, entry_point, [(sender, nonce)]);
+
+        // the sequence number of the nonce key is advanced past the prior nonce
+        let storage = state_override.account(entry_point).storage.clone().unwrap();
+        assert_eq!(storage.len(), 1);
+        assert_eq!(
+            storage.get(&nonce_sequence_number_slot(sender, key)),
+            Some(&H256::from_low_u64_be(6))
+        );
+    }
+
+    #[test]
+    fn test_apply_prior_nonces_state_override_max_per_key() {
+        let entry_point = Address::from_low_u64_be(1);
+        let sender = Address::from_low_u64_be(2);
+        let other_key = U256::one() << 64;
+
+        let mut state_override = spoof::State::default();
+        apply_prior_nonces_state_override(
+            &mut state_override,
+            entry_point,
+            [
+                (sender, U256::from(6)),
+                (sender, U256::from(5)),
+                (sender, other_key + 2),
+            ],
+        );
+        // an earlier nonce applied afterwards doesn't move the sequence back
+        apply_prior_nonces_state_override(&mut state_override, entry_point, [(sender, 4.into())]);
+
+        let storage = state_override.account(entry_point).storage.clone().unwrap();
+        assert_eq!(storage.len(), 2);
+        assert_eq!(
+            storage.get(&nonce_sequence_number_slot(sender, U256::zero())),
+            Some(&H256::from_low_u64_be(7))
+        );
+        assert_eq!(
+            storage.get(&nonce_sequence_number_slot(sender, U256::one())),
+            Some(&H256::from_low_u64_be(3))
+        );
+    }
+
+    fn account_with_slot(slot: u64, value: u64) -> spoof::Account {
+        let mut account = spoof::Account::default();
+        account.store(H256::from_low_u64_be(slot), H256::from_low_u64_be(value));
+        account
+    }
+
+    #[test]
+    fn test_merge_state_overrides_balance() {
+        let caller_set = Address::from_low_u64_be(1);
+        let caller_unset = Address::from_low_u64_be(2);
+        let mut state_override = spoof::State::default();
+        state_override.account(caller_set).balance(U256::from(5));
+
+        let mut internal = spoof::Account::default();
+        internal.balance(U256::from(100));
+        merge_state_overrides(
+            &mut state_override,
+            [(caller_set, internal.clone()), (caller_unset, internal)],
+        );
+
+        // the caller's balance is kept, and the internal one fills in otherwise
+        assert_eq!(
+            state_override.account(caller_set).balance,
+            Some(U256::from(5))
+        );
+        assert_eq!(
+            state_override.account(caller_unset).balance,
+            Some(U256::from(100))
+        );
+    }
+
+    #[test]
+    fn test_merge_state_overrides_code() {
+        let entry_point = Address::from_low_u64_be(1);
+        let mut state_override = spoof::State::default();
+        state_override
+            .account(entry_point)
+            .code(Bytes::from(vec![1]))
+            .nonce(7.into());
+
+        let mut internal = spoof::Account::default();
+        internal.code(Bytes::from(vec![2]));
+        merge_state_overrides(&mut state_override, [(entry_point, internal)]);
+
+        // the internal code is applied, without dropping the caller's other overrides
+        let account = state_override.account(entry_point);
+        assert_eq!(account.code, Some(Bytes::from(vec![2])));
+        assert_eq!(account.nonce, Some(7.into()));
+    }
+
+    #[test]
+    fn test_merge_state_overrides_storage_slots() {
+        let address = Address::from_low_u64_be(1);
+        let mut state_override = spoof::State::default();
+        *state_override.account(address) = account_with_slot(1, 10);
+        state_override
+            .account(address)
+            .store(H256::from_low_u64_be(2), H256::from_low_u64_be(20));
+
+        let mut internal = account_with_slot(2, 200);
+        internal.store(H256::from_low_u64_be(3), H256::from_low_u64_be(300));
+        merge_state_overrides(&mut state_override, [(address, internal)]);
+
+        // colliding slots keep the caller's value, others are merged in
+        let storage = state_override.account(address).storage.clone().unwrap();
+        assert_eq!(storage.len(), 3);
+        assert_eq!(
+            storage.get(&H256::from_low_u64_be(1)),
+            Some(&H256::from_low_u64_be(10))
+        );
+        assert_eq!(
+            storage.get(&H256::from_low_u64_be(2)),
+            Some(&H256::from_low_u64_be(20))
+        );
+        assert_eq!(
+            storage.get(&H256::from_low_u64_be(3)),
+            Some(&H256::from_low_u64_be(300))
+        );
+    }
+
+    #[test]
+    fn test_apply_eip7702_auth() {
+        let chain_spec = ChainSpec::default();
+        let sender = Address::random();
+        let auth = Eip7702Auth {
+            chain_id: chain_spec.id.into(),
+            address: Address::random(),
+            ..Default::default()
+        };
+
+        // no authorization, no override
+        let mut state_override = spoof::State::default();
+        apply_eip7702_auth(&chain_spec, sender, None, false, &mut state_override).unwrap();
+        assert_eq!(state_override.account(sender).code, None);
+
+        // the sender is delegated to the authorized address
+        apply_eip7702_auth(&chain_spec, sender, Some(&auth), false, &mut state_override).unwrap();
+        assert_eq!(
+            state_override.account(sender).code,
+            Some(auth.delegation_code())
+        );
+
+        // a delegated sender can't be deployed by a factory
+        assert!(matches!(
+            apply_eip7702_auth(
+                &chain_spec,
+                sender,
+                Some(&auth),
+                true,
+                &mut spoof::State::default()
+            ),
+            Err(GasEstimationError::InvalidEip7702Auth(_))
+        ));
+
+        // an authorization for another chain is rejected
+        let other_chain = Eip7702Auth {
+            chain_id: (chain_spec.id + 1).into(),
+            ..auth
+        };
+        assert!(matches!(
+            apply_eip7702_auth(
+                &chain_spec,
+                sender,
+                Some(&other_chain),
+                false,
+                &mut spoof::State::default()
+            ),
+            Err(GasEstimationError::InvalidEip7702Auth(_))
+        ));
     }
 }
@@ -0,0 +1,162 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::collections::HashMap;
+
+use ethers::{
+    abi::{self, Abi, ParamType, Token},
+    types::I256,
+    utils::hex,
+};
+
+/// Registry of custom Solidity errors, used to decode the revert data of a user
+/// operation's call into a human readable error.
+///
+/// Errors are keyed by their 4-byte selector. Revert data whose selector is not
+/// registered, or that does not decode as the registered parameters, is not decoded.
+#[derive(Clone, Debug, Default)]
+pub struct RevertDecoder {
+    errors: HashMap<[u8; 4], (String, Vec<ParamType>)>,
+}
+
+impl RevertDecoder {
+    /// Creates an empty decoder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a decoder with the custom errors declared in the ABI `abi`
+    pub fn from_abi(abi: &Abi) -> Self {
+        abi.errors().fold(Self::new(), |decoder, error| {
+            let params = error.inputs.iter().map(|p| p.kind.clone()).collect();
+            decoder.with_error(&error.name, params)
+        })
+    }
+
+    /// Registers the custom error `name` with parameters `params`, replacing any
+    /// error with the same selector
+    pub fn with_error(mut self, name: &str, params: Vec<ParamType>) -> Self {
+        let selector = abi::short_signature(name, &params);
+        self.errors.insert(selector, (name.to_string(), params));
+        self
+    }
+
+    /// Returns the number of registered errors
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Returns whether no errors are registered
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Decodes `revert_data` as a registered custom error, formatted as
+    /// `Name(arg, ...)`
+    pub fn decode(&self, revert_data: &[u8]) -> Option<String> {
+        let selector: [u8; 4] = revert_data.get(..4)?.try_into().ok()?;
+        let (name, params) = self.errors.get(&selector)?;
+        let tokens = abi::decode(params, &revert_data[4..]).ok()?;
+        Some(format!("{name}({})", format_tokens(&tokens)))
+    }
+}
+
+fn format_tokens(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(format_token)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_token(token: &Token) -> String {
+    match token {
+        Token::Address(address) => format!("{address:?}"),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => format!("0x{}", hex::encode(bytes)),
+        Token::Uint(value) => value.to_string(),
+        Token::Int(value) => I256::from_raw(*value).to_string(),
+        Token::Bool(value) => value.to_string(),
+        Token::String(value) => format!("{value:?}"),
+        Token::Array(tokens) | Token::FixedArray(tokens) => format!("[{}]", format_tokens(tokens)),
+        Token::Tuple(tokens) => format!("({})", format_tokens(tokens)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Address, U256};
+
+    use super::*;
+
+    fn encode_error(name: &str, params: &[ParamType], tokens: &[Token]) -> Vec<u8> {
+        [
+            &abi::short_signature(name, params)[..],
+            &abi::encode(tokens),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_decode_registered_error() {
+        let params = vec![ParamType::Address, ParamType::Uint(256)];
+        let decoder = RevertDecoder::new().with_error("InsufficientBalance", params.clone());
+        let address = Address::from_low_u64_be(1);
+        let data = encode_error(
+            "InsufficientBalance",
+            &params,
+            &[Token::Address(address), Token::Uint(U256::from(100))],
+        );
+
+        assert_eq!(
+            decoder.decode(&data).unwrap(),
+            format!("InsufficientBalance({address:?}, 100)")
+        );
+    }
+
+    #[test]
+    fn test_decode_unknown_or_malformed() {
+        let decoder = RevertDecoder::new().with_error("Unauthorized", vec![ParamType::Address]);
+
+        // unknown selector
+        let data = encode_error("Other", &[], &[]);
+        assert_eq!(decoder.decode(&data), None);
+        // too short for a selector
+        assert_eq!(decoder.decode(&[0x01, 0x02]), None);
+        // known selector, missing argument
+        let data = encode_error("Unauthorized", &[ParamType::Address], &[]);
+        assert_eq!(decoder.decode(&data), None);
+    }
+
+    #[test]
+    fn test_from_abi() {
+        let abi: Abi = serde_json::from_str(
+            r#"[
+                {"type": "error", "name": "Expired", "inputs": [{"name": "deadline", "type": "uint48"}]},
+                {"type": "error", "name": "Paused", "inputs": []},
+                {"type": "function", "name": "foo", "inputs": [], "outputs": [], "stateMutability": "view"}
+            ]"#,
+        )
+        .unwrap();
+        let decoder = RevertDecoder::from_abi(&abi);
+
+        assert_eq!(decoder.len(), 2);
+        let data = encode_error(
+            "Expired",
+            &[ParamType::Uint(48)],
+            &[Token::Uint(U256::from(42))],
+        );
+        assert_eq!(decoder.decode(&data).unwrap(), "Expired(42)");
+        let data = encode_error("Paused", &[], &[]);
+        assert_eq!(decoder.decode(&data).unwrap(), "Paused()");
+    }
+}
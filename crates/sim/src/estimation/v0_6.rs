@@ -16,7 +16,7 @@ use std::{cmp, ops::Add, sync::Arc};
 use ethers::{
     contract::EthCall,
     providers::spoof,
-    types::{Address, Bytes, H256, U256},
+    types::{Address, BlockId, Bytes, U256},
 };
 use rand::Rng;
 use rundler_provider::{EntryPoint, L1GasProvider, Provider, SimulationProvider};
@@ -36,8 +36,10 @@ use rundler_utils::{eth, math};
 use tokio::join;
 
 use super::{
-    CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization, GasEstimationError,
-    Settings, VerificationGasEstimator,
+    BundleGasEstimate, CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization,
+    EntryPointOverrides, EstimationOptions, GasEstimateBreakdown, GasEstimateWithFees,
+    GasEstimateWithHeadroom, GasEstimationError, GasHeadroom, RevertDecoder, Settings,
+    VerificationGasEstimator,
 };
 use crate::{
     estimation::estimate_verification_gas::GetOpWithLimitArgs, gas, precheck::MIN_CALL_GAS_LIMIT,
@@ -54,6 +56,7 @@ pub struct GasEstimator<P, E, VGE, CGE> {
     fee_estimator: FeeEstimator<P>,
     verification_gas_estimator: VGE,
     call_gas_estimator: CGE,
+    entry_point_overrides: EntryPointOverrides<UserOperationOptionalGas>,
 }
 
 #[async_trait::async_trait]
@@ -70,59 +73,147 @@ where
         &self,
         op: UserOperationOptionalGas,
         state_override: spoof::State,
+        options: EstimationOptions,
     ) -> Result<GasEstimate, GasEstimationError> {
-        self.check_provided_limits(&op)?;
-
-        let (block_hash, _) = self
-            .provider
-            .get_latest_block_hash_and_number()
-            .await
-            .map_err(anyhow::Error::from)?;
+        if let Some(estimator) = self
+            .entry_point_overrides
+            .get(self.entry_point.address(), options.entry_point_override)?
+        {
+            let options = EstimationOptions {
+                entry_point_override: None,
+                ..options
+            };
+            return estimator.estimate_op_gas(op, state_override, options).await;
+        }
 
-        let pre_verification_gas = self.estimate_pre_verification_gas(&op).await?;
+        self.estimate_op_gas_recording_breakdown(
+            op,
+            state_override,
+            options.validation_gas_price_override,
+        )
+        .await
+        .0
+    }
 
-        let full_op = UserOperation {
-            pre_verification_gas,
-            ..op.clone().into_user_operation(
+    async fn estimate_op_gas_and_fees(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: spoof::State,
+    ) -> Result<GasEstimateWithFees, GasEstimationError> {
+        let (gas_estimate, recommended_fees) = join!(
+            self.estimate_op_gas(op.clone(), state_override, EstimationOptions::default()),
+            self.fee_estimator.recommended_op_fees()
+        );
+        let gas_estimate = gas_estimate?;
+        let recommended_fees = recommended_fees?;
+
+        let op_with_gas = UserOperation {
+            pre_verification_gas: gas_estimate.pre_verification_gas,
+            call_gas_limit: gas_estimate.call_gas_limit,
+            verification_gas_limit: gas_estimate.verification_gas_limit,
+            max_fee_per_gas: op
+                .max_fee_per_gas
+                .unwrap_or(recommended_fees.max_fee_per_gas),
+            ..op.into_user_operation(
                 self.settings.max_call_gas.into(),
                 self.settings.max_verification_gas.into(),
             )
         };
+        let prefund_shortfall =
+            super::prefund_shortfall(&*self.provider, &self.entry_point, &op_with_gas).await?;
 
-        let verification_future =
-            self.estimate_verification_gas(&op, &full_op, block_hash, &state_override);
-        let call_future =
-            self.estimate_call_gas(&op, full_op.clone(), block_hash, state_override.clone());
+        Ok(GasEstimateWithFees {
+            gas_estimate,
+            recommended_fees,
+            prefund_shortfall,
+        })
+    }
 
-        // Not try_join! because then the output is nondeterministic if both
-        // verification and call estimation fail.
-        let timer = std::time::Instant::now();
-        let (verification_gas_limit, call_gas_limit) = join!(verification_future, call_future);
-        tracing::debug!("gas estimation took {}ms", timer.elapsed().as_millis());
+    async fn estimate_op_gas_with_headroom(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: spoof::State,
+    ) -> Result<GasEstimateWithHeadroom, GasEstimationError> {
+        // leave the limits unset so that the op is simulated with the maximum limits
+        let op = UserOperationOptionalGas {
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            ..op
+        };
+        let gas_estimate = self
+            .estimate_op_gas(op, state_override, EstimationOptions::default())
+            .await?;
 
-        let verification_gas_limit = verification_gas_limit?;
-        let call_gas_limit = call_gas_limit?;
+        Ok(GasEstimateWithHeadroom {
+            headroom: GasHeadroom::new(&gas_estimate, &self.settings),
+            gas_estimate,
+        })
+    }
 
-        // Verify total gas limit
-        let mut op_with_gas = full_op;
-        op_with_gas.verification_gas_limit = verification_gas_limit;
-        op_with_gas.call_gas_limit = call_gas_limit;
-        let gas_limit =
-            gas::user_operation_execution_gas_limit(&self.chain_spec, &op_with_gas, true);
-        if gas_limit > self.settings.max_total_execution_gas.into() {
-            return Err(GasEstimationError::GasTotalTooLarge(
-                gas_limit.as_u64(),
-                self.settings.max_total_execution_gas,
-            ));
+    async fn estimate_op_gas_with_breakdown(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: spoof::State,
+    ) -> (
+        Result<GasEstimate, GasEstimationError>,
+        GasEstimateBreakdown,
+    ) {
+        self.estimate_op_gas_recording_breakdown(op, state_override, None)
+            .await
+    }
+
+    async fn estimate_bundle_gas(
+        &self,
+        ops: Vec<UserOperationOptionalGas>,
+        mut state_override: spoof::State,
+    ) -> Result<BundleGasEstimate, GasEstimationError> {
+        let mut gas_limit = self.chain_spec.transaction_intrinsic_gas;
+        let mut op_estimates = Vec::with_capacity(ops.len());
+        for op in ops {
+            let estimate = self
+                .estimate_op_gas(
+                    op.clone(),
+                    state_override.clone(),
+                    EstimationOptions::default(),
+                )
+                .await?;
+            let op_with_gas = UserOperation {
+                pre_verification_gas: estimate.pre_verification_gas,
+                call_gas_limit: estimate.call_gas_limit,
+                verification_gas_limit: estimate.verification_gas_limit,
+                ..op.clone().into_user_operation(
+                    self.settings.max_call_gas.into(),
+                    self.settings.max_verification_gas.into(),
+                )
+            };
+            gas_limit += gas::user_operation_gas_limit(&self.chain_spec, &op_with_gas, false);
+            super::apply_prior_nonces_state_override(
+                &mut state_override,
+                self.entry_point.address(),
+                [(op.sender, op.nonce)],
+            );
+            op_estimates.push(estimate);
         }
 
-        Ok(GasEstimate {
-            pre_verification_gas,
-            verification_gas_limit,
-            call_gas_limit,
-            paymaster_verification_gas_limit: None,
+        Ok(BundleGasEstimate {
+            gas_limit,
+            op_estimates,
         })
     }
+
+    async fn estimate_op_gas_batch(
+        &self,
+        ops: Vec<(UserOperationOptionalGas, spoof::State)>,
+    ) -> Vec<Result<GasEstimate, GasEstimationError>> {
+        super::estimate_batch(
+            ops,
+            self.settings.max_concurrent_estimations,
+            |op, state_override| {
+                self.estimate_op_gas(op, state_override, EstimationOptions::default())
+            },
+        )
+        .await
+    }
 }
 
 impl<P, E>
@@ -147,8 +238,9 @@ where
         settings: Settings,
         fee_estimator: FeeEstimator<P>,
     ) -> Self {
-        if let Some(err) = settings.validate() {
-            panic!("Invalid gas estimator settings: {}", err);
+        let errors = settings.validate();
+        if !errors.is_empty() {
+            panic!("Invalid gas estimator settings: {}", errors.join(", "));
         }
 
         let verification_gas_estimator = VerificationGasEstimatorImpl::new(
@@ -170,8 +262,27 @@ where
             fee_estimator,
             verification_gas_estimator,
             call_gas_estimator,
+            entry_point_overrides: EntryPointOverrides::default(),
         }
     }
+
+    /// Sets the decoder used to decode custom errors the user operation's call
+    /// reverts with
+    pub fn with_revert_decoder(mut self, revert_decoder: Arc<RevertDecoder>) -> Self {
+        self.call_gas_estimator = self.call_gas_estimator.with_revert_decoder(revert_decoder);
+        self
+    }
+
+    /// Allows estimating against the entry point deployed at `entry_point` with
+    /// `estimator`, by passing that address as the entry point override
+    pub fn with_entry_point_override<G>(mut self, entry_point: Address, estimator: G) -> Self
+    where
+        G: GasEstimatorTrait<UserOperationOptionalGas = UserOperationOptionalGas>,
+    {
+        self.entry_point_overrides
+            .insert(entry_point, Arc::new(estimator));
+        self
+    }
 }
 
 impl<P, E, VGE, CGE> GasEstimator<P, E, VGE, CGE>
@@ -181,6 +292,108 @@ where
     VGE: VerificationGasEstimator<UO = UserOperation>,
     CGE: CallGasEstimator<UO = UserOperation>,
 {
+    /// Estimates the gas of `op` within the estimation timeout, recording a breakdown
+    /// of the estimation
+    async fn estimate_op_gas_recording_breakdown(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: spoof::State,
+        validation_gas_price_override: Option<U256>,
+    ) -> (
+        Result<GasEstimate, GasEstimationError>,
+        GasEstimateBreakdown,
+    ) {
+        let (result, breakdown) = super::estimate_with_timeout(
+            self.settings.estimation_timeout,
+            state_override.clone(),
+            super::retry_on_stale_revert(&self.settings, move || {
+                self.estimate_op_gas_inner(
+                    op.clone(),
+                    state_override.clone(),
+                    validation_gas_price_override,
+                )
+            }),
+        )
+        .await;
+        (super::with_recorded_gas_used(result, &breakdown), breakdown)
+    }
+
+    /// Estimates the gas of `op`, without the estimation timeout
+    async fn estimate_op_gas_inner(
+        &self,
+        op: UserOperationOptionalGas,
+        mut state_override: spoof::State,
+        validation_gas_price_override: Option<U256>,
+    ) -> Result<GasEstimate, GasEstimationError> {
+        self.check_provided_limits(&op)?;
+        super::apply_eip7702_auth(
+            &self.chain_spec,
+            op.sender,
+            op.eip7702_auth.as_ref(),
+            !op.init_code.is_empty(),
+            &mut state_override,
+        )?;
+
+        let block_id =
+            super::estimation_block(self.provider.as_ref(), self.settings.block_tag).await?;
+
+        let pre_verification_gas = self
+            .estimate_pre_verification_gas(&op, &state_override)
+            .await?;
+
+        let full_op = UserOperation {
+            pre_verification_gas,
+            ..op.clone().into_user_operation(
+                self.settings.max_call_gas.into(),
+                self.settings.max_verification_gas.into(),
+            )
+        };
+        super::check_field_sizes(&self.settings, &full_op)?;
+
+        let verification_future = self.estimate_verification_gas(
+            &op,
+            &full_op,
+            block_id,
+            &state_override,
+            validation_gas_price_override,
+        );
+        let call_future =
+            self.estimate_call_gas(&op, full_op.clone(), block_id, state_override.clone());
+
+        // Not try_join! because then the output is nondeterministic if both
+        // verification and call estimation fail.
+        let timer = std::time::Instant::now();
+        let (verification_gas_limit, call_gas_limit) = join!(verification_future, call_future);
+        tracing::debug!("gas estimation took {}ms", timer.elapsed().as_millis());
+
+        let verification_gas_limit = verification_gas_limit?;
+        let call_gas_limit = call_gas_limit?;
+
+        // Verify total gas limit
+        let mut op_with_gas = full_op;
+        op_with_gas.verification_gas_limit = verification_gas_limit;
+        op_with_gas.call_gas_limit = call_gas_limit;
+        let gas_limit =
+            gas::user_operation_execution_gas_limit(&self.chain_spec, &op_with_gas, true);
+        if gas_limit > self.settings.max_total_execution_gas.into() {
+            return Err(GasEstimationError::GasTotalTooLarge(
+                gas_limit.as_u64(),
+                self.settings.max_total_execution_gas,
+            ));
+        }
+
+        Ok(GasEstimate {
+            pre_verification_gas,
+            verification_gas_limit,
+            call_gas_limit,
+            paymaster_verification_gas_limit: None,
+            paymaster_post_op_gas_limit: None,
+            classification: op.classification(),
+            // recorded in the breakdown, set by `estimate_op_gas_with_breakdown`
+            simulate_handle_ops_gas_used: None,
+        })
+    }
+
     fn check_provided_limits(
         &self,
         optional_op: &UserOperationOptionalGas,
@@ -217,8 +430,9 @@ where
         &self,
         optional_op: &UserOperationOptionalGas,
         full_op: &UserOperation,
-        block_hash: H256,
+        block_id: BlockId,
         state_override: &spoof::State,
+        validation_gas_price_override: Option<U256>,
     ) -> Result<U256, GasEstimationError> {
         // if set and non-zero, don't estimate
         if let Some(vl) = optional_op.verification_gas_limit {
@@ -229,28 +443,33 @@ where
             }
         }
 
-        fn get_op_with_limit(op: UserOperation, args: GetOpWithLimitArgs) -> UserOperation {
+        let get_op_with_limit = |op: UserOperation, args: GetOpWithLimitArgs| {
             let GetOpWithLimitArgs { gas, fee } = args;
+            let fee = validation_gas_price_override.unwrap_or(fee.into());
             UserOperation {
                 verification_gas_limit: gas.into(),
-                max_fee_per_gas: fee.into(),
-                max_priority_fee_per_gas: fee.into(),
+                max_fee_per_gas: fee,
+                max_priority_fee_per_gas: fee,
                 call_gas_limit: U256::zero(),
                 ..op
             }
-        }
+        };
 
         let verification_gas_limit: U256 = self
             .verification_gas_estimator
             .estimate_verification_gas(
                 full_op,
-                block_hash,
+                block_id,
                 state_override,
                 self.settings.max_verification_gas.into(),
                 get_op_with_limit,
             )
             .await
             .map(|gas_u128| gas_u128.into())?;
+        let verification_gas_limit = math::increase_by_percent(
+            verification_gas_limit,
+            self.settings.verification_gas_margin_percent,
+        );
 
         // Add a buffer to the verification gas limit. Add 10% or 2000 gas, whichever is larger
         // to ensure we get at least a 2000 gas buffer. Cap at the max verification gas.
@@ -269,6 +488,7 @@ where
     async fn estimate_pre_verification_gas(
         &self,
         optional_op: &UserOperationOptionalGas,
+        state_override: &spoof::State,
     ) -> Result<U256, GasEstimationError> {
         if let Some(pvg) = optional_op.pre_verification_gas {
             if pvg != U256::zero() {
@@ -306,6 +526,7 @@ where
                 self.settings.max_verification_gas.into(),
             ),
             gas_price,
+            state_override,
         )
         .await?)
     }
@@ -314,7 +535,7 @@ where
         &self,
         optional_op: &UserOperationOptionalGas,
         full_op: UserOperation,
-        block_hash: H256,
+        block_id: BlockId,
         state_override: spoof::State,
     ) -> Result<U256, GasEstimationError> {
         // if set and non-zero, don't estimate
@@ -322,7 +543,7 @@ where
             if cl != U256::zero() {
                 // The user provided a non-zero value, simulate once
                 self.call_gas_estimator
-                    .simulate_handle_op_with_result(full_op, block_hash, state_override)
+                    .simulate_handle_op_with_result(full_op, block_id, state_override)
                     .await?;
                 return Ok(cl);
             }
@@ -330,7 +551,7 @@ where
 
         let call_gas_limit: U256 = self
             .call_gas_estimator
-            .estimate_call_gas(full_op, block_hash, state_override)
+            .estimate_call_gas(full_op, block_id, state_override)
             .await?
             .into();
 
@@ -360,12 +581,17 @@ impl CallGasEstimatorSpecialization for CallGasEstimatorSpecializationV06 {
         let moved_entry_point_address: Address = rand::thread_rng().gen();
         let estimation_proxy_bytecode =
             estimation_proxy_bytecode_with_target(moved_entry_point_address);
-        state_override
-            .account(moved_entry_point_address)
-            .code(ENTRY_POINT_V0_6_DEPLOYED_BYTECODE.clone());
-        state_override
-            .account(ep_to_override)
-            .code(estimation_proxy_bytecode);
+        let mut moved_entry_point = spoof::Account::default();
+        moved_entry_point.code(ENTRY_POINT_V0_6_DEPLOYED_BYTECODE.clone());
+        let mut proxy = spoof::Account::default();
+        proxy.code(estimation_proxy_bytecode);
+        super::merge_state_overrides(
+            state_override,
+            [
+                (moved_entry_point_address, moved_entry_point),
+                (ep_to_override, proxy),
+            ],
+        );
     }
 
     fn get_op_with_no_call_gas(&self, op: Self::UO) -> Self::UO {
@@ -422,11 +648,13 @@ fn estimation_proxy_bytecode_with_target(target: Address) -> Bytes {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use anyhow::anyhow;
     use ethers::{
-        abi::{AbiEncode, Address},
+        abi::{AbiDecode, AbiEncode, Address},
         contract::EthCall,
-        types::{U128, U64},
+        types::{H256, U128, U64},
         utils::hex,
     };
     use rundler_provider::{ExecutionResult, MockEntryPointV0_6, MockProvider, SimulateOpCallData};
@@ -443,18 +671,19 @@ mod tests {
             },
         },
         v0_6::{UserOperation, UserOperationOptionalGas},
-        UserOperation as UserOperationTrait, ValidationRevert,
+        GasFees, UserOperation as UserOperationTrait, ValidationRevert,
     };
     use rundler_utils::eth::{self, ContractRevertError};
 
     use super::*;
     use crate::{
         estimation::{
-            estimate_call_gas::PROXY_IMPLEMENTATION_ADDRESS_MARKER, CALL_GAS_BUFFER_VALUE,
-            VERIFICATION_GAS_BUFFER_PERCENT,
+            apply_prior_nonces_state_override, breakdown,
+            estimate_call_gas::PROXY_IMPLEMENTATION_ADDRESS_MARKER, EstimationBlockTag,
+            CALL_GAS_BUFFER_VALUE, VERIFICATION_GAS_BUFFER_PERCENT,
         },
         simulation::v0_6::REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER,
-        PriorityFeeMode, VerificationGasEstimatorImpl,
+        PriorityFeeMode, SearchBounds, VerificationGasEstimatorImpl,
     };
 
     // Gas overhead defaults
@@ -538,8 +767,17 @@ mod tests {
             max_paymaster_verification_gas: TEST_MAX_GAS_LIMITS,
             max_paymaster_post_op_gas: TEST_MAX_GAS_LIMITS,
             max_total_execution_gas: TEST_MAX_GAS_LIMITS,
-            max_simulate_handle_ops_gas: TEST_MAX_GAS_LIMITS,
+            max_simulate_handle_ops_gas: 2 * TEST_MAX_GAS_LIMITS,
             verification_estimation_gas_fee: 1_000_000_000_000,
+            paymaster_fee_payer_multiplier: 3,
+            call_gas_estimation_tolerance: 4096,
+            max_estimation_attempts: 10,
+            max_op_field_size: None,
+            estimation_timeout: Duration::from_secs(10),
+            refresh_on_stale_revert: false,
+            verification_gas_margin_percent: 0,
+            max_concurrent_estimations: 4,
+            block_tag: EstimationBlockTag::Latest,
         };
         let estimator = create_custom_estimator(ChainSpec::default(), provider, entry, settings);
         (estimator, settings)
@@ -558,6 +796,7 @@ mod tests {
             max_priority_fee_per_gas: None,
             paymaster_and_data: Bytes::new(),
             signature: Bytes::new(),
+            eip7702_auth: None,
         }
     }
 
@@ -588,7 +827,7 @@ mod tests {
         let (estimator, settings) = create_estimator(entry, provider);
         let user_op = demo_user_op_optional_gas(None);
         let estimation = estimator
-            .estimate_pre_verification_gas(&user_op)
+            .estimate_pre_verification_gas(&user_op, &spoof::State::default())
             .await
             .unwrap();
 
@@ -620,7 +859,7 @@ mod tests {
         let (mut entry, mut provider) = create_base_config();
         entry
             .expect_calc_l1_gas()
-            .returning(|_a, _b, _c| Ok(TEST_FEE));
+            .returning(|_a, _b, _c, _d| Ok(TEST_FEE));
         provider.expect_get_base_fee().returning(|| Ok(TEST_FEE));
         provider
             .expect_get_max_priority_fee()
@@ -632,8 +871,17 @@ mod tests {
             max_paymaster_verification_gas: 10000000000,
             max_paymaster_post_op_gas: 10000000000,
             max_total_execution_gas: 10000000000,
-            max_simulate_handle_ops_gas: 100000000,
+            max_simulate_handle_ops_gas: 20000000000,
             verification_estimation_gas_fee: 1_000_000_000_000,
+            paymaster_fee_payer_multiplier: 3,
+            call_gas_estimation_tolerance: 4096,
+            max_estimation_attempts: 10,
+            max_op_field_size: None,
+            estimation_timeout: Duration::from_secs(10),
+            refresh_on_stale_revert: false,
+            verification_gas_margin_percent: 0,
+            max_concurrent_estimations: 4,
+            block_tag: EstimationBlockTag::Latest,
         };
 
         // Chose arbitrum
@@ -654,7 +902,7 @@ mod tests {
 
         let user_op = demo_user_op_optional_gas(None);
         let estimation = estimator
-            .estimate_pre_verification_gas(&user_op)
+            .estimate_pre_verification_gas(&user_op, &spoof::State::default())
             .await
             .unwrap();
 
@@ -688,7 +936,7 @@ mod tests {
 
         entry
             .expect_calc_l1_gas()
-            .returning(|_a, _b, _c| Ok(TEST_FEE));
+            .returning(|_a, _b, _c, _d| Ok(TEST_FEE));
         provider.expect_get_base_fee().returning(|| Ok(TEST_FEE));
         provider
             .expect_get_max_priority_fee()
@@ -700,8 +948,17 @@ mod tests {
             max_paymaster_verification_gas: 10000000000,
             max_paymaster_post_op_gas: 10000000000,
             max_total_execution_gas: 10000000000,
-            max_simulate_handle_ops_gas: 100000000,
+            max_simulate_handle_ops_gas: 20000000000,
             verification_estimation_gas_fee: 1_000_000_000_000,
+            paymaster_fee_payer_multiplier: 3,
+            call_gas_estimation_tolerance: 4096,
+            max_estimation_attempts: 10,
+            max_op_field_size: None,
+            estimation_timeout: Duration::from_secs(10),
+            refresh_on_stale_revert: false,
+            verification_gas_margin_percent: 0,
+            max_concurrent_estimations: 4,
+            block_tag: EstimationBlockTag::Latest,
         };
 
         // Chose OP
@@ -715,7 +972,7 @@ mod tests {
 
         let user_op = demo_user_op_optional_gas(None);
         let estimation = estimator
-            .estimate_pre_verification_gas(&user_op)
+            .estimate_pre_verification_gas(&user_op, &spoof::State::default())
             .await
             .unwrap();
 
@@ -743,6 +1000,69 @@ mod tests {
         assert_eq!(result + dynamic_gas, estimation);
     }
 
+    #[tokio::test]
+    async fn test_calc_pre_verification_l1_oracle_override() {
+        let (mut entry, mut provider) = create_base_config();
+
+        // the L1 gas is higher once the oracle's L1 fee parameters are overridden
+        let oracle = Address::random();
+        entry
+            .expect_calc_l1_gas()
+            .returning(move |_a, _b, _c, state_override| {
+                let overridden = state_override.clone().account(oracle).storage.is_some();
+                Ok(if overridden { TEST_FEE * 5 } else { TEST_FEE })
+            });
+        provider.expect_get_base_fee().returning(|| Ok(TEST_FEE));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(TEST_FEE));
+
+        let cs = ChainSpec {
+            id: 10,
+            calldata_pre_verification_gas: true,
+            l1_gas_oracle_contract_type: L1GasOracleContractType::OptimismBedrock,
+            l1_gas_oracle_contract_address: oracle,
+            ..Default::default()
+        };
+        let settings = Settings {
+            max_verification_gas: TEST_MAX_GAS_LIMITS,
+            max_call_gas: TEST_MAX_GAS_LIMITS,
+            max_paymaster_verification_gas: TEST_MAX_GAS_LIMITS,
+            max_paymaster_post_op_gas: TEST_MAX_GAS_LIMITS,
+            max_total_execution_gas: TEST_MAX_GAS_LIMITS,
+            max_simulate_handle_ops_gas: 2 * TEST_MAX_GAS_LIMITS,
+            verification_estimation_gas_fee: 1_000_000_000_000,
+            paymaster_fee_payer_multiplier: 3,
+            call_gas_estimation_tolerance: 4096,
+            max_estimation_attempts: 10,
+            max_op_field_size: None,
+            estimation_timeout: Duration::from_secs(10),
+            refresh_on_stale_revert: false,
+            verification_gas_margin_percent: 0,
+            max_concurrent_estimations: 4,
+            block_tag: EstimationBlockTag::Latest,
+        };
+        let estimator = create_custom_estimator(cs, provider, entry, settings);
+
+        let user_op = demo_user_op_optional_gas(None);
+        let estimation = estimator
+            .estimate_pre_verification_gas(&user_op, &spoof::State::default())
+            .await
+            .unwrap();
+
+        let mut state_override = spoof::State::default();
+        state_override
+            .account(oracle)
+            .store(H256::zero(), H256::from_low_u64_be(100));
+        let overridden_estimation = estimator
+            .estimate_pre_verification_gas(&user_op, &state_override)
+            .await
+            .unwrap();
+
+        assert!(overridden_estimation > estimation);
+        assert_eq!(overridden_estimation - estimation, TEST_FEE * 4);
+    }
+
     #[tokio::test]
     async fn test_binary_search_verification_gas() {
         let (mut entry, mut provider) = create_base_config();
@@ -794,7 +1114,13 @@ mod tests {
         let optional_op = demo_user_op_optional_gas(Some(U256::from(10000)));
         let user_op = demo_user_op();
         let estimation = estimator
-            .estimate_verification_gas(&optional_op, &user_op, H256::zero(), &spoof::state())
+            .estimate_verification_gas(
+                &optional_op,
+                &user_op,
+                H256::zero().into(),
+                &spoof::state(),
+                None,
+            )
             .await
             .unwrap();
 
@@ -806,6 +1132,132 @@ mod tests {
         assert_eq!(expected_with_buffer, estimation);
     }
 
+    #[tokio::test]
+    async fn test_entry_point_override_not_allowed() {
+        let (entry, provider) = create_base_config();
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let entry_point = Address::random();
+        let options = EstimationOptions {
+            entry_point_override: Some(entry_point),
+            ..Default::default()
+        };
+        let err = estimator
+            .estimate_op_gas(demo_user_op_optional_gas(None), spoof::state(), options)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            GasEstimationError::EntryPointNotAllowed(address) if address == entry_point
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_binary_search_verification_gas_with_margin() {
+        let (mut entry, mut provider) = create_base_config();
+
+        let gas_usage = 10_000.into();
+
+        entry
+            .expect_call_spoofed_simulate_op()
+            .returning(move |op, _b, _c, _d, _e, _f| {
+                if op.total_verification_gas_limit() < gas_usage {
+                    return Ok(Err(ValidationRevert::EntryPoint("AA23".to_string())));
+                }
+
+                Ok(Ok(ExecutionResult {
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+
+        provider
+            .expect_get_gas_used()
+            .returning(move |_a, _b, _c, _d| {
+                Ok(GasUsedResult {
+                    gas_used: gas_usage * 2,
+                    success: false,
+                    result: Bytes::new(),
+                })
+            });
+
+        let (_, settings) = create_estimator(MockEntryPointV0_6::new(), MockProvider::new());
+        let settings = Settings {
+            verification_gas_margin_percent: 50,
+            ..settings
+        };
+        let estimator = create_custom_estimator(ChainSpec::default(), provider, entry, settings);
+        let optional_op = demo_user_op_optional_gas(Some(U256::from(10000)));
+        let user_op = demo_user_op();
+        let estimation = estimator
+            .estimate_verification_gas(
+                &optional_op,
+                &user_op,
+                H256::zero().into(),
+                &spoof::state(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        // the margin applies to the gas usage before the buffer
+        let expected = gas_usage + ChainSpec::default().deposit_transfer_overhead;
+        let expected_with_margin = math::increase_by_percent(expected, 50);
+        let expected_with_buffer =
+            math::increase_by_percent(expected_with_margin, VERIFICATION_GAS_BUFFER_PERCENT);
+
+        assert_eq!(expected_with_buffer, estimation);
+    }
+
+    #[tokio::test]
+    async fn test_binary_search_verification_gas_price_override() {
+        let (mut entry, mut provider) = create_base_config();
+
+        let gas_usage = 10_000.into();
+        let gas_price = U256::from(7_000_000_000_u64);
+
+        // every simulation is made at the overridden gas price, rather than the
+        // zero fees of an op without a paymaster
+        entry
+            .expect_call_spoofed_simulate_op()
+            .returning(move |op, _b, _c, _d, _e, _f| {
+                assert_eq!(op.max_fee_per_gas, gas_price);
+                assert_eq!(op.max_priority_fee_per_gas, gas_price);
+                if op.total_verification_gas_limit() < gas_usage {
+                    return Ok(Err(ValidationRevert::EntryPoint("AA23".to_string())));
+                }
+                Ok(Ok(ExecutionResult::default()))
+            });
+        provider
+            .expect_get_gas_used()
+            .returning(move |_a, _b, call_data, _d| {
+                let call = i_entry_point::SimulateHandleOpCall::decode(&call_data).unwrap();
+                assert_eq!(call.op.max_fee_per_gas, gas_price);
+                Ok(GasUsedResult {
+                    gas_used: gas_usage * 2,
+                    success: false,
+                    result: Bytes::new(),
+                })
+            });
+        entry
+            .expect_decode_simulate_handle_ops_revert()
+            .returning(|_a| Ok(ExecutionResult::default()));
+
+        let (estimator, _) = create_estimator(entry, provider);
+        let optional_op = demo_user_op_optional_gas(Some(U256::from(10000)));
+        let user_op = demo_user_op();
+        estimator
+            .estimate_verification_gas(
+                &optional_op,
+                &user_op,
+                H256::zero().into(),
+                &spoof::state(),
+                Some(gas_price),
+            )
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn test_binary_search_verification_gas_should_not_overflow() {
         let (mut entry, mut provider) = create_base_config();
@@ -853,7 +1305,13 @@ mod tests {
         let optional_op = demo_user_op_optional_gas(Some(U256::from(10000)));
         let user_op = demo_user_op();
         let estimation = estimator
-            .estimate_verification_gas(&optional_op, &user_op, H256::zero(), &spoof::state())
+            .estimate_verification_gas(
+                &optional_op,
+                &user_op,
+                H256::zero().into(),
+                &spoof::state(),
+                None,
+            )
             .await
             .err();
 
@@ -910,7 +1368,13 @@ mod tests {
         let optional_op = demo_user_op_optional_gas(Some(U256::from(10000)));
         let user_op = demo_user_op();
         let estimation = estimator
-            .estimate_verification_gas(&optional_op, &user_op, H256::zero(), &spoof::state())
+            .estimate_verification_gas(
+                &optional_op,
+                &user_op,
+                H256::zero().into(),
+                &spoof::state(),
+                None,
+            )
             .await;
 
         assert!(estimation.is_err());
@@ -957,7 +1421,13 @@ mod tests {
         let optional_op = demo_user_op_optional_gas(Some(U256::from(10000)));
         let user_op = demo_user_op();
         let estimation = estimator
-            .estimate_verification_gas(&optional_op, &user_op, H256::zero(), &spoof::state())
+            .estimate_verification_gas(
+                &optional_op,
+                &user_op,
+                H256::zero().into(),
+                &spoof::state(),
+                None,
+            )
             .await;
 
         assert!(estimation.is_err());
@@ -999,7 +1469,13 @@ mod tests {
         let optional_op = demo_user_op_optional_gas(Some(U256::from(10000)));
         let user_op = demo_user_op();
         let estimation = estimator
-            .estimate_verification_gas(&optional_op, &user_op, H256::zero(), &spoof::state())
+            .estimate_verification_gas(
+                &optional_op,
+                &user_op,
+                H256::zero().into(),
+                &spoof::state(),
+                None,
+            )
             .await;
 
         assert!(estimation.is_err());
@@ -1048,7 +1524,13 @@ mod tests {
         let optional_op = demo_user_op_optional_gas(Some(U256::from(10000)));
         let user_op = demo_user_op();
         let estimation = estimator
-            .estimate_verification_gas(&optional_op, &user_op, H256::zero(), &spoof::state())
+            .estimate_verification_gas(
+                &optional_op,
+                &user_op,
+                H256::zero().into(),
+                &spoof::state(),
+                None,
+            )
             .await;
 
         assert!(estimation.is_err());
@@ -1082,7 +1564,7 @@ mod tests {
         let optional_op = demo_user_op_optional_gas(None);
         let user_op = demo_user_op();
         let estimation = estimator
-            .estimate_call_gas(&optional_op, user_op, H256::zero(), spoof::state())
+            .estimate_call_gas(&optional_op, user_op, H256::zero().into(), spoof::state())
             .await
             .unwrap();
 
@@ -1091,6 +1573,70 @@ mod tests {
         assert_eq!(estimation, expected);
     }
 
+    #[tokio::test]
+    async fn test_estimate_call_gas_with_prior_op() {
+        let (mut entry, mut provider) = create_base_config();
+
+        // the call uses less gas once the sender's prior op has advanced its nonce
+        entry.expect_call_spoofed_simulate_op().returning(
+            move |_a, _b, _c, _d, _e, spoofed_state| {
+                let nonce_advanced = spoofed_state
+                    .clone()
+                    .account(Address::zero())
+                    .storage
+                    .is_some();
+                let gas_estimate = if nonce_advanced { 50_000 } else { 100_000 };
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasResult {
+                        gas_estimate: gas_estimate.into(),
+                        num_rounds: U256::from(10),
+                    }
+                    .encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            },
+        );
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+
+        let (estimator, _) = create_estimator(entry, provider);
+        let optional_op = UserOperationOptionalGas {
+            nonce: U256::one(),
+            ..demo_user_op_optional_gas(None)
+        };
+        let user_op = UserOperation {
+            nonce: U256::one(),
+            ..demo_user_op()
+        };
+
+        let standalone = estimator
+            .estimate_call_gas(
+                &optional_op,
+                user_op.clone(),
+                H256::zero().into(),
+                spoof::state(),
+            )
+            .await
+            .unwrap();
+
+        let mut state_override = spoof::state();
+        apply_prior_nonces_state_override(
+            &mut state_override,
+            Address::zero(),
+            [(Address::zero(), U256::zero())],
+        );
+        let sequential = estimator
+            .estimate_call_gas(&optional_op, user_op, H256::zero().into(), state_override)
+            .await
+            .unwrap();
+
+        assert_ne!(standalone, sequential);
+    }
+
     #[tokio::test]
     async fn test_estimate_call_gas_error() {
         let (mut entry, mut provider) = create_base_config();
@@ -1119,7 +1665,7 @@ mod tests {
         let user_op = demo_user_op();
         let estimation = estimator
             .call_gas_estimator
-            .estimate_call_gas(user_op, H256::zero(), spoof::state())
+            .estimate_call_gas(user_op, H256::zero().into(), spoof::state())
             .await
             .err()
             .unwrap();
@@ -1174,7 +1720,7 @@ mod tests {
         let user_op = demo_user_op();
         let estimation = estimator
             .call_gas_estimator
-            .estimate_call_gas(user_op, H256::zero(), spoof::state())
+            .estimate_call_gas(user_op, H256::zero().into(), spoof::state())
             .await
             .unwrap();
 
@@ -1184,6 +1730,121 @@ mod tests {
         assert_eq!(estimation, U128::from(200));
     }
 
+    #[tokio::test]
+    async fn test_estimate_call_gas_breakdown() {
+        let (mut entry, mut provider) = create_base_config();
+
+        entry
+            .expect_call_spoofed_simulate_op()
+            .returning(|_a, _b, _c, _d, _e, _f| {
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasContinuation {
+                        min_gas: U256::from(100),
+                        max_gas: U256::from(100000),
+                        num_rounds: U256::from(10),
+                    }
+                    .encode()
+                    .into(),
+                    target_success: false,
+                    ..Default::default()
+                }))
+            })
+            .times(1);
+        entry
+            .expect_call_spoofed_simulate_op()
+            .returning(|_a, _b, _c, _d, _e, _f| {
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasResult {
+                        gas_estimate: U256::from(200),
+                        num_rounds: U256::from(10),
+                    }
+                    .encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            })
+            .times(1);
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+
+        let (estimator, settings) = create_estimator(entry, provider);
+        let state_override = spoof::balance(Address::random(), U256::from(1));
+        let (estimation, breakdown) = breakdown::with_breakdown(
+            state_override.clone(),
+            estimator.call_gas_estimator.estimate_call_gas(
+                demo_user_op(),
+                H256::zero().into(),
+                state_override.clone(),
+            ),
+        )
+        .await;
+
+        assert_eq!(estimation.unwrap(), U128::from(200));
+        assert_eq!(breakdown.simulate_handle_op_calls, 2);
+        assert_eq!(
+            breakdown.call_gas_search,
+            vec![
+                SearchBounds {
+                    low: U256::zero(),
+                    high: settings.max_call_gas.into(),
+                },
+                SearchBounds {
+                    low: U256::from(100),
+                    high: U256::from(100000),
+                },
+            ]
+        );
+        assert!(breakdown.revert_reasons.is_empty());
+        assert_eq!(breakdown.state_override, state_override);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_call_gas_max_attempts() {
+        let (mut entry, mut provider) = create_base_config();
+
+        // every call narrows the search window without converging
+        let mut min_gas = U256::zero();
+        entry
+            .expect_call_spoofed_simulate_op()
+            .returning(move |_a, _b, _c, _d, _e, _f| {
+                min_gas += U256::from(100);
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasContinuation {
+                        min_gas,
+                        max_gas: U256::from(100000),
+                        num_rounds: U256::from(10),
+                    }
+                    .encode()
+                    .into(),
+                    target_success: false,
+                    ..Default::default()
+                }))
+            })
+            .times(3);
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+
+        let (_, settings) = create_estimator(MockEntryPointV0_6::new(), MockProvider::new());
+        let settings = Settings {
+            max_estimation_attempts: 3,
+            ..settings
+        };
+        let estimator = create_custom_estimator(ChainSpec::default(), provider, entry, settings);
+        let estimation = estimator
+            .call_gas_estimator
+            .estimate_call_gas(demo_user_op(), H256::zero().into(), spoof::state())
+            .await
+            .unwrap();
+
+        // the search stops after the maximum attempts, at its upper bound
+        assert_eq!(estimation, U128::from(100000));
+    }
+
     #[tokio::test]
     async fn test_estimation_optional_gas_used() {
         let (mut entry, mut provider) = create_base_config();
@@ -1246,7 +1907,7 @@ mod tests {
         let optional_op = demo_user_op_optional_gas(Some(U256::from(10000)));
 
         let estimation = estimator
-            .estimate_op_gas(optional_op, spoof::state())
+            .estimate_op_gas(optional_op, spoof::state(), EstimationOptions::default())
             .await
             .unwrap();
 
@@ -1268,6 +1929,9 @@ mod tests {
             estimation.call_gas_limit,
             U256::from(10000) + CALL_GAS_BUFFER_VALUE
         );
+
+        // the gas used by the simulation at max verification gas is surfaced
+        assert_eq!(estimation.simulate_handle_ops_gas_used, Some(gas_usage));
     }
 
     #[test]
@@ -1285,6 +1949,15 @@ mod tests {
             max_total_execution_gas: 10,
             max_simulate_handle_ops_gas: 10,
             verification_estimation_gas_fee: 1_000_000_000_000,
+            paymaster_fee_payer_multiplier: 3,
+            call_gas_estimation_tolerance: 4096,
+            max_estimation_attempts: 10,
+            max_op_field_size: None,
+            estimation_timeout: Duration::from_secs(10),
+            refresh_on_stale_revert: false,
+            verification_gas_margin_percent: 0,
+            max_concurrent_estimations: 4,
+            block_tag: EstimationBlockTag::Latest,
         };
 
         create_custom_estimator(ChainSpec::default(), provider, entry, settings);
@@ -1298,7 +1971,7 @@ mod tests {
         let optional_op = demo_user_op_optional_gas(Some(U256::from(TEST_MAX_GAS_LIMITS + 1)));
 
         let estimation = estimator
-            .estimate_op_gas(optional_op, spoof::state())
+            .estimate_op_gas(optional_op, spoof::state(), EstimationOptions::default())
             .await
             .err()
             .unwrap();
@@ -1318,7 +1991,7 @@ mod tests {
         optional_op.verification_gas_limit = Some(U256::from(TEST_MAX_GAS_LIMITS + 1));
 
         let estimation = estimator
-            .estimate_op_gas(optional_op, spoof::state())
+            .estimate_op_gas(optional_op, spoof::state(), EstimationOptions::default())
             .await
             .err()
             .unwrap();
@@ -1338,7 +2011,7 @@ mod tests {
         optional_op.call_gas_limit = Some(U256::from(TEST_MAX_GAS_LIMITS + 1));
 
         let estimation = estimator
-            .estimate_op_gas(optional_op, spoof::state())
+            .estimate_op_gas(optional_op, spoof::state(), EstimationOptions::default())
             .await
             .err()
             .unwrap();
@@ -1380,7 +2053,11 @@ mod tests {
         optional_op.verification_gas_limit = Some(U256::from(10000));
 
         let estimation = estimator
-            .estimate_op_gas(optional_op.clone(), spoof::state())
+            .estimate_op_gas(
+                optional_op.clone(),
+                spoof::state(),
+                EstimationOptions::default(),
+            )
             .await
             .unwrap();
 
@@ -1398,6 +2075,184 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_estimate_op_gas_and_fees() {
+        let (mut entry, mut provider) = create_base_config();
+
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((H256::zero(), U64::zero())));
+        provider
+            .expect_get_base_fee()
+            .returning(|| Ok(U256::from(100)));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::from(10)));
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::zero()));
+        entry.expect_balance_of().returning(|_, _| Ok(U256::zero()));
+
+        entry
+            .expect_call_spoofed_simulate_op()
+            .returning(move |_a, _b, _c, _d, _e, _f| {
+                Ok(Ok(ExecutionResult {
+                    target_result: TestCallGasResult {
+                        success: true,
+                        gas_used: 0.into(),
+                        revert_data: Bytes::new(),
+                    }
+                    .encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+
+        let (_, settings) = create_estimator(MockEntryPointV0_6::new(), MockProvider::new());
+        let provider = Arc::new(provider);
+        let estimator: GasEstimatorWithMocks = GasEstimator::new(
+            ChainSpec::default(),
+            Arc::clone(&provider),
+            Arc::new(entry),
+            settings,
+            FeeEstimator::new(
+                &ChainSpec::default(),
+                provider,
+                PriorityFeeMode::PriorityFeeIncreasePercent(10),
+                0,
+            ),
+        );
+
+        let mut optional_op = demo_user_op_optional_gas(Some(U256::from(10000)));
+        optional_op.call_gas_limit = Some(U256::from(10000));
+        optional_op.verification_gas_limit = Some(U256::from(10000));
+
+        let estimation = estimator
+            .estimate_op_gas_and_fees(optional_op.clone(), spoof::state())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            estimation.gas_estimate.call_gas_limit,
+            optional_op.call_gas_limit.unwrap()
+        );
+        // oracle priority fee of 10 increased by 10%, on top of the base fee of 100
+        assert_eq!(
+            estimation.recommended_fees,
+            GasFees {
+                max_fee_per_gas: U256::from(111),
+                max_priority_fee_per_gas: U256::from(11),
+            }
+        );
+    }
+
+    fn create_estimator_with_funds(deposit: U256, balance: U256) -> GasEstimatorWithMocks {
+        let (mut entry, mut provider) = create_base_config();
+
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((H256::zero(), U64::zero())));
+        provider
+            .expect_get_base_fee()
+            .returning(|| Ok(U256::from(100)));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::from(10)));
+        provider
+            .expect_get_balance()
+            .returning(move |_, _| Ok(balance));
+        entry.expect_balance_of().returning(move |_, _| Ok(deposit));
+
+        entry
+            .expect_call_spoofed_simulate_op()
+            .returning(move |_a, _b, _c, _d, _e, _f| {
+                Ok(Ok(ExecutionResult {
+                    target_result: TestCallGasResult {
+                        success: true,
+                        gas_used: 0.into(),
+                        revert_data: Bytes::new(),
+                    }
+                    .encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+
+        let (_, settings) = create_estimator(MockEntryPointV0_6::new(), MockProvider::new());
+        let provider = Arc::new(provider);
+        GasEstimator::new(
+            ChainSpec::default(),
+            Arc::clone(&provider),
+            Arc::new(entry),
+            settings,
+            FeeEstimator::new(
+                &ChainSpec::default(),
+                provider,
+                PriorityFeeMode::PriorityFeeIncreasePercent(10),
+                0,
+            ),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_oversized_field_rejected() {
+        let (entry, mut provider) = create_base_config();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((H256::zero(), U64::zero())));
+
+        let (_, settings) = create_estimator(MockEntryPointV0_6::new(), MockProvider::new());
+        let settings = Settings {
+            max_op_field_size: Some(100),
+            ..settings
+        };
+        let estimator = create_custom_estimator(ChainSpec::default(), provider, entry, settings);
+
+        let mut optional_op = demo_user_op_optional_gas(Some(U256::from(10000)));
+        optional_op.paymaster_and_data = vec![0; 101].into();
+
+        let err = estimator
+            .estimate_op_gas(optional_op, spoof::state(), EstimationOptions::default())
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            err,
+            GasEstimationError::FieldTooLarge("paymasterAndData", 100)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_op_gas_and_fees_prefund_shortfall() {
+        let mut optional_op = demo_user_op_optional_gas(Some(U256::from(10000)));
+        optional_op.call_gas_limit = Some(U256::from(10000));
+        optional_op.verification_gas_limit = Some(U256::from(10000));
+
+        // the sender's deposit and balance fall short of the op's max gas cost
+        let estimation = create_estimator_with_funds(U256::from(1000), U256::from(500))
+            .estimate_op_gas_and_fees(optional_op.clone(), spoof::state())
+            .await
+            .unwrap();
+        let gas = estimation.gas_estimate;
+        let max_gas_cost =
+            (gas.pre_verification_gas + gas.call_gas_limit + gas.verification_gas_limit)
+                * estimation.recommended_fees.max_fee_per_gas;
+        assert_eq!(
+            estimation.prefund_shortfall,
+            max_gas_cost - U256::from(1500)
+        );
+
+        // a funded sender has no shortfall
+        let estimation = create_estimator_with_funds(U256::zero(), U256::MAX)
+            .estimate_op_gas_and_fees(optional_op, spoof::state())
+            .await
+            .unwrap();
+        assert_eq!(estimation.prefund_shortfall, U256::zero());
+    }
+
     #[tokio::test]
     async fn test_provided_reverts() {
         let (mut entry, mut provider) = create_base_config();
@@ -1434,7 +2289,11 @@ mod tests {
         optional_op.verification_gas_limit = Some(U256::from(10000));
 
         let estimation_error = estimator
-            .estimate_op_gas(optional_op.clone(), spoof::state())
+            .estimate_op_gas(
+                optional_op.clone(),
+                spoof::state(),
+                EstimationOptions::default(),
+            )
             .await
             .err()
             .unwrap();
@@ -1476,7 +2335,11 @@ mod tests {
         optional_op.verification_gas_limit = Some(TEST_MAX_GAS_LIMITS.into());
 
         let err = estimator
-            .estimate_op_gas(optional_op.clone(), spoof::state())
+            .estimate_op_gas(
+                optional_op.clone(),
+                spoof::state(),
+                EstimationOptions::default(),
+            )
             .await
             .err()
             .unwrap();
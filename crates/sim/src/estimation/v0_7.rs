@@ -15,7 +15,7 @@ use std::{cmp, ops::Add, sync::Arc};
 
 use ethers::{
     contract::EthCall,
-    types::{spoof, Address, Bytes, H256, U128, U256},
+    types::{spoof, Address, BlockId, Bytes, U128, U256},
 };
 use rand::Rng;
 use rundler_provider::{EntryPoint, L1GasProvider, Provider, SimulationProvider};
@@ -34,7 +34,11 @@ use rundler_types::{
 use rundler_utils::{eth, math};
 use tokio::join;
 
-use super::{estimate_verification_gas::GetOpWithLimitArgs, GasEstimationError, Settings};
+use super::{
+    estimate_verification_gas::GetOpWithLimitArgs, BundleGasEstimate, EntryPointOverrides,
+    EstimationOptions, GasEstimateBreakdown, GasEstimateWithFees, GasEstimateWithHeadroom,
+    GasEstimationError, GasEstimator as GasEstimatorTrait, GasHeadroom, RevertDecoder, Settings,
+};
 use crate::{
     gas, CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization, FeeEstimator,
     VerificationGasEstimator, VerificationGasEstimatorImpl, MIN_CALL_GAS_LIMIT,
@@ -50,10 +54,11 @@ pub struct GasEstimator<P, E, VGE, CGE> {
     fee_estimator: FeeEstimator<P>,
     verification_gas_estimator: VGE,
     call_gas_estimator: CGE,
+    entry_point_overrides: EntryPointOverrides<UserOperationOptionalGas>,
 }
 
 #[async_trait::async_trait]
-impl<P, E, VGE, CGE> super::GasEstimator for GasEstimator<P, E, VGE, CGE>
+impl<P, E, VGE, CGE> GasEstimatorTrait for GasEstimator<P, E, VGE, CGE>
 where
     P: Provider,
     E: EntryPoint + SimulationProvider<UO = UserOperation> + L1GasProvider<UO = UserOperation>,
@@ -62,81 +67,163 @@ where
 {
     type UserOperationOptionalGas = UserOperationOptionalGas;
 
-    /// Returns a gas estimate or a revert message, or an anyhow error on any
-    /// other error.
     async fn estimate_op_gas(
         &self,
         op: UserOperationOptionalGas,
         state_override: spoof::State,
+        options: EstimationOptions,
     ) -> Result<GasEstimate, GasEstimationError> {
-        self.check_provided_limits(&op)?;
-
-        let Self {
-            provider, settings, ..
-        } = self;
+        if let Some(estimator) = self
+            .entry_point_overrides
+            .get(self.entry_point.address(), options.entry_point_override)?
+        {
+            let options = EstimationOptions {
+                entry_point_override: None,
+                ..options
+            };
+            return estimator.estimate_op_gas(op, state_override, options).await;
+        }
 
-        let (block_hash, _) = provider
-            .get_latest_block_hash_and_number()
-            .await
-            .map_err(anyhow::Error::from)?;
+        self.estimate_op_gas_recording_breakdown(
+            op,
+            state_override,
+            options.validation_gas_price_override,
+        )
+        .await
+        .0
+    }
 
-        let pre_verification_gas = self.estimate_pre_verification_gas(&op).await?;
+    async fn estimate_op_gas_and_fees(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: spoof::State,
+    ) -> Result<GasEstimateWithFees, GasEstimationError> {
+        let (gas_estimate, recommended_fees) = join!(
+            self.estimate_op_gas(op.clone(), state_override, EstimationOptions::default()),
+            self.fee_estimator.recommended_op_fees()
+        );
+        let gas_estimate = gas_estimate?;
+        let recommended_fees = recommended_fees?;
 
-        let full_op = op
-            .clone()
+        let max_fee_per_gas = op
+            .max_fee_per_gas
+            .unwrap_or(recommended_fees.max_fee_per_gas.as_u128().into());
+        let mut builder = op
             .into_user_operation_builder(
                 &self.chain_spec,
-                settings.max_call_gas.into(),
-                settings.max_verification_gas.into(),
-                settings.max_paymaster_verification_gas.into(),
+                self.settings.max_call_gas.into(),
+                self.settings.max_verification_gas.into(),
+                self.settings.max_paymaster_verification_gas.into(),
             )
-            .pre_verification_gas(pre_verification_gas)
-            .build();
+            .pre_verification_gas(gas_estimate.pre_verification_gas)
+            .call_gas_limit(gas_estimate.call_gas_limit.as_u128().into())
+            .verification_gas_limit(gas_estimate.verification_gas_limit.as_u128().into())
+            .max_fee_per_gas(max_fee_per_gas);
+        if let Some(limit) = gas_estimate.paymaster_verification_gas_limit {
+            builder = builder.paymaster_verification_gas_limit(limit.as_u128().into());
+        }
+        let prefund_shortfall =
+            super::prefund_shortfall(&*self.provider, &self.entry_point, &builder.build()).await?;
 
-        let verification_gas_future =
-            self.estimate_verification_gas(&op, &full_op, block_hash, &state_override);
-        let paymaster_verification_gas_future =
-            self.estimate_paymaster_verification_gas(&op, &full_op, block_hash, &state_override);
-        let call_gas_future =
-            self.estimate_call_gas(&op, full_op.clone(), block_hash, state_override.clone());
+        Ok(GasEstimateWithFees {
+            gas_estimate,
+            recommended_fees,
+            prefund_shortfall,
+        })
+    }
 
-        // Not try_join! because then the output is nondeterministic if multiple calls fail.
-        let timer = std::time::Instant::now();
-        let (verification_gas_limit, paymaster_verification_gas_limit, call_gas_limit) = join!(
-            verification_gas_future,
-            paymaster_verification_gas_future,
-            call_gas_future
-        );
-        tracing::debug!("gas estimation took {}ms", timer.elapsed().as_millis());
+    async fn estimate_op_gas_with_headroom(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: spoof::State,
+    ) -> Result<GasEstimateWithHeadroom, GasEstimationError> {
+        // leave the limits unset so that the op is simulated with the maximum limits
+        let op = UserOperationOptionalGas {
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            paymaster_verification_gas_limit: None,
+            ..op
+        };
+        let gas_estimate = self
+            .estimate_op_gas(op, state_override, EstimationOptions::default())
+            .await?;
 
-        let verification_gas_limit = verification_gas_limit?;
-        let paymaster_verification_gas_limit = paymaster_verification_gas_limit?;
-        let call_gas_limit = call_gas_limit?;
+        Ok(GasEstimateWithHeadroom {
+            headroom: GasHeadroom::new(&gas_estimate, &self.settings),
+            gas_estimate,
+        })
+    }
 
-        // check the total gas limit
-        let mut op_with_gas = full_op;
-        op_with_gas.pre_verification_gas = pre_verification_gas;
-        op_with_gas.call_gas_limit = call_gas_limit;
-        op_with_gas.verification_gas_limit = verification_gas_limit;
-        op_with_gas.paymaster_verification_gas_limit = paymaster_verification_gas_limit;
-        let gas_limit =
-            gas::user_operation_execution_gas_limit(&self.chain_spec, &op_with_gas, true);
-        if gas_limit > self.settings.max_total_execution_gas.into() {
-            return Err(GasEstimationError::GasTotalTooLarge(
-                gas_limit.as_u64(),
-                self.settings.max_total_execution_gas,
-            ));
+    async fn estimate_op_gas_with_breakdown(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: spoof::State,
+    ) -> (
+        Result<GasEstimate, GasEstimationError>,
+        GasEstimateBreakdown,
+    ) {
+        self.estimate_op_gas_recording_breakdown(op, state_override, None)
+            .await
+    }
+
+    async fn estimate_bundle_gas(
+        &self,
+        ops: Vec<UserOperationOptionalGas>,
+        mut state_override: spoof::State,
+    ) -> Result<BundleGasEstimate, GasEstimationError> {
+        let mut gas_limit = self.chain_spec.transaction_intrinsic_gas;
+        let mut op_estimates = Vec::with_capacity(ops.len());
+        for op in ops {
+            let estimate = self
+                .estimate_op_gas(
+                    op.clone(),
+                    state_override.clone(),
+                    EstimationOptions::default(),
+                )
+                .await?;
+            let mut builder = op
+                .clone()
+                .into_user_operation_builder(
+                    &self.chain_spec,
+                    self.settings.max_call_gas.into(),
+                    self.settings.max_verification_gas.into(),
+                    self.settings.max_paymaster_verification_gas.into(),
+                )
+                .pre_verification_gas(estimate.pre_verification_gas)
+                .call_gas_limit(estimate.call_gas_limit.as_u128().into())
+                .verification_gas_limit(estimate.verification_gas_limit.as_u128().into());
+            if let Some(limit) = estimate.paymaster_verification_gas_limit {
+                builder = builder.paymaster_verification_gas_limit(limit.as_u128().into());
+            }
+            let op_with_gas = builder.build();
+            gas_limit += gas::user_operation_gas_limit(&self.chain_spec, &op_with_gas, false);
+            super::apply_prior_nonces_state_override(
+                &mut state_override,
+                self.entry_point.address(),
+                [(op.sender, op.nonce)],
+            );
+            op_estimates.push(estimate);
         }
 
-        Ok(GasEstimate {
-            pre_verification_gas,
-            call_gas_limit: call_gas_limit.into(),
-            verification_gas_limit: verification_gas_limit.into(),
-            paymaster_verification_gas_limit: op
-                .paymaster
-                .map(|_| paymaster_verification_gas_limit.into()),
+        Ok(BundleGasEstimate {
+            gas_limit,
+            op_estimates,
         })
     }
+
+    async fn estimate_op_gas_batch(
+        &self,
+        ops: Vec<(UserOperationOptionalGas, spoof::State)>,
+    ) -> Vec<Result<GasEstimate, GasEstimationError>> {
+        super::estimate_batch(
+            ops,
+            self.settings.max_concurrent_estimations,
+            |op, state_override| {
+                self.estimate_op_gas(op, state_override, EstimationOptions::default())
+            },
+        )
+        .await
+    }
 }
 
 impl<P, E>
@@ -161,8 +248,9 @@ where
         settings: Settings,
         fee_estimator: FeeEstimator<P>,
     ) -> Self {
-        if let Some(err) = settings.validate() {
-            panic!("Invalid gas estimator settings: {}", err);
+        let errors = settings.validate();
+        if !errors.is_empty() {
+            panic!("Invalid gas estimator settings: {}", errors.join(", "));
         }
 
         let verification_gas_estimator = VerificationGasEstimatorImpl::new(
@@ -186,8 +274,27 @@ where
             fee_estimator,
             verification_gas_estimator,
             call_gas_estimator,
+            entry_point_overrides: EntryPointOverrides::default(),
         }
     }
+
+    /// Sets the decoder used to decode custom errors the user operation's call
+    /// reverts with
+    pub fn with_revert_decoder(mut self, revert_decoder: Arc<RevertDecoder>) -> Self {
+        self.call_gas_estimator = self.call_gas_estimator.with_revert_decoder(revert_decoder);
+        self
+    }
+
+    /// Allows estimating against the entry point deployed at `entry_point` with
+    /// `estimator`, by passing that address as the entry point override
+    pub fn with_entry_point_override<G>(mut self, entry_point: Address, estimator: G) -> Self
+    where
+        G: GasEstimatorTrait<UserOperationOptionalGas = UserOperationOptionalGas>,
+    {
+        self.entry_point_overrides
+            .insert(entry_point, Arc::new(estimator));
+        self
+    }
 }
 
 impl<P, E, VGE, CGE> GasEstimator<P, E, VGE, CGE>
@@ -197,6 +304,131 @@ where
     VGE: VerificationGasEstimator<UO = UserOperation>,
     CGE: CallGasEstimator<UO = UserOperation>,
 {
+    /// Estimates the gas of `op` within the estimation timeout, recording a breakdown
+    /// of the estimation
+    async fn estimate_op_gas_recording_breakdown(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: spoof::State,
+        validation_gas_price_override: Option<U256>,
+    ) -> (
+        Result<GasEstimate, GasEstimationError>,
+        GasEstimateBreakdown,
+    ) {
+        let (result, breakdown) = super::estimate_with_timeout(
+            self.settings.estimation_timeout,
+            state_override.clone(),
+            super::retry_on_stale_revert(&self.settings, move || {
+                self.estimate_op_gas_inner(
+                    op.clone(),
+                    state_override.clone(),
+                    validation_gas_price_override,
+                )
+            }),
+        )
+        .await;
+        (super::with_recorded_gas_used(result, &breakdown), breakdown)
+    }
+
+    /// Estimates the gas of `op`, without the estimation timeout
+    async fn estimate_op_gas_inner(
+        &self,
+        op: UserOperationOptionalGas,
+        mut state_override: spoof::State,
+        validation_gas_price_override: Option<U256>,
+    ) -> Result<GasEstimate, GasEstimationError> {
+        self.check_provided_limits(&op)?;
+        super::apply_eip7702_auth(
+            &self.chain_spec,
+            op.sender,
+            op.eip7702_auth.as_ref(),
+            op.factory.is_some(),
+            &mut state_override,
+        )?;
+
+        let Self {
+            provider, settings, ..
+        } = self;
+
+        let block_id = super::estimation_block(provider.as_ref(), settings.block_tag).await?;
+
+        let pre_verification_gas = self
+            .estimate_pre_verification_gas(&op, &state_override)
+            .await?;
+
+        let full_op = op
+            .clone()
+            .into_user_operation_builder(
+                &self.chain_spec,
+                settings.max_call_gas.into(),
+                settings.max_verification_gas.into(),
+                settings.max_paymaster_verification_gas.into(),
+            )
+            .pre_verification_gas(pre_verification_gas)
+            .build();
+        super::check_field_sizes(settings, &full_op)?;
+
+        let verification_gas_future = self.estimate_verification_gas(
+            &op,
+            &full_op,
+            block_id,
+            &state_override,
+            validation_gas_price_override,
+        );
+        let paymaster_verification_gas_future = self.estimate_paymaster_verification_gas(
+            &op,
+            &full_op,
+            block_id,
+            &state_override,
+            validation_gas_price_override,
+        );
+        let call_gas_future =
+            self.estimate_call_gas(&op, full_op.clone(), block_id, state_override.clone());
+
+        // Not try_join! because then the output is nondeterministic if multiple calls fail.
+        let timer = std::time::Instant::now();
+        let (verification_gas_limit, paymaster_verification_gas_limit, call_gas_limit) = join!(
+            verification_gas_future,
+            paymaster_verification_gas_future,
+            call_gas_future
+        );
+        tracing::debug!("gas estimation took {}ms", timer.elapsed().as_millis());
+
+        let verification_gas_limit = verification_gas_limit?;
+        let paymaster_verification_gas_limit = paymaster_verification_gas_limit?;
+        let call_gas_limit = call_gas_limit?;
+
+        // check the total gas limit
+        let mut op_with_gas = full_op;
+        op_with_gas.pre_verification_gas = pre_verification_gas;
+        op_with_gas.call_gas_limit = call_gas_limit;
+        op_with_gas.verification_gas_limit = verification_gas_limit;
+        op_with_gas.paymaster_verification_gas_limit = paymaster_verification_gas_limit;
+        let gas_limit =
+            gas::user_operation_execution_gas_limit(&self.chain_spec, &op_with_gas, true);
+        if gas_limit > self.settings.max_total_execution_gas.into() {
+            return Err(GasEstimationError::GasTotalTooLarge(
+                gas_limit.as_u64(),
+                self.settings.max_total_execution_gas,
+            ));
+        }
+
+        Ok(GasEstimate {
+            pre_verification_gas,
+            call_gas_limit: call_gas_limit.into(),
+            verification_gas_limit: verification_gas_limit.into(),
+            paymaster_verification_gas_limit: op
+                .paymaster
+                .map(|_| paymaster_verification_gas_limit.into()),
+            paymaster_post_op_gas_limit: op
+                .paymaster
+                .map(|_| op.paymaster_post_op_gas_limit.unwrap_or_default().into()),
+            classification: op.classification(),
+            // recorded in the breakdown, set by `estimate_op_gas_with_breakdown`
+            simulate_handle_ops_gas_used: None,
+        })
+    }
+
     fn check_provided_limits(
         &self,
         optional_op: &UserOperationOptionalGas,
@@ -249,8 +481,9 @@ where
         &self,
         optional_op: &UserOperationOptionalGas,
         full_op: &UserOperation,
-        block_hash: H256,
+        block_id: BlockId,
         state_override: &spoof::State,
+        validation_gas_price_override: Option<U256>,
     ) -> Result<U128, GasEstimationError> {
         // if set and non-zero, don't estimate
         if let Some(vl) = optional_op.verification_gas_limit {
@@ -263,6 +496,8 @@ where
 
         let get_op_with_limit = |op: UserOperation, args: GetOpWithLimitArgs| {
             let GetOpWithLimitArgs { gas, fee } = args;
+            let fee = validation_gas_price_override
+                .map_or(fee, |price| U128::try_from(price).unwrap_or(U128::MAX));
             UserOperationBuilder::from_uo(op, &self.chain_spec)
                 .verification_gas_limit(gas)
                 .max_fee_per_gas(fee)
@@ -276,15 +511,19 @@ where
             .verification_gas_estimator
             .estimate_verification_gas(
                 full_op,
-                block_hash,
+                block_id,
                 state_override,
                 self.settings.max_verification_gas.into(),
                 get_op_with_limit,
             )
             .await?;
 
+        // the margin applies before the buffer
         let verification_gas_limit = math::increase_by_percent(
-            verification_gas_limit,
+            math::increase_by_percent(
+                verification_gas_limit,
+                self.settings.verification_gas_margin_percent,
+            ),
             super::VERIFICATION_GAS_BUFFER_PERCENT,
         )
         .min(self.settings.max_verification_gas.into());
@@ -296,8 +535,9 @@ where
         &self,
         optional_op: &UserOperationOptionalGas,
         full_op: &UserOperation,
-        block_hash: H256,
+        block_id: BlockId,
         state_override: &spoof::State,
+        validation_gas_price_override: Option<U256>,
     ) -> Result<U128, GasEstimationError> {
         // If not using paymaster, return zero, else if set and non-zero, don't estimate and return value
         if let Some(pvl) = optional_op.verification_gas_limit {
@@ -308,6 +548,8 @@ where
 
         let get_op_with_limit = |op: UserOperation, args: GetOpWithLimitArgs| {
             let GetOpWithLimitArgs { gas, fee } = args;
+            let fee = validation_gas_price_override
+                .map_or(fee, |price| U128::try_from(price).unwrap_or(U128::MAX));
             UserOperationBuilder::from_uo(op, &self.chain_spec)
                 .max_fee_per_gas(fee)
                 .max_priority_fee_per_gas(fee)
@@ -321,7 +563,7 @@ where
             .verification_gas_estimator
             .estimate_verification_gas(
                 full_op,
-                block_hash,
+                block_id,
                 state_override,
                 self.settings.max_paymaster_verification_gas.into(),
                 get_op_with_limit,
@@ -340,6 +582,7 @@ where
     async fn estimate_pre_verification_gas(
         &self,
         optional_op: &UserOperationOptionalGas,
+        state_override: &spoof::State,
     ) -> Result<U256, GasEstimationError> {
         if let Some(pvg) = optional_op.pre_verification_gas {
             if pvg != U256::zero() {
@@ -371,6 +614,7 @@ where
             &optional_op.max_fill(&self.chain_spec),
             &optional_op.random_fill(&self.chain_spec),
             gas_price,
+            state_override,
         )
         .await?)
     }
@@ -379,7 +623,7 @@ where
         &self,
         optional_op: &UserOperationOptionalGas,
         full_op: UserOperation,
-        block_hash: H256,
+        block_id: BlockId,
         state_override: spoof::State,
     ) -> Result<U128, GasEstimationError> {
         // if set and non-zero, don't estimate
@@ -387,7 +631,7 @@ where
             if cl != U128::zero() {
                 // The user provided a non-zero value, simulate once
                 self.call_gas_estimator
-                    .simulate_handle_op_with_result(full_op, block_hash, state_override)
+                    .simulate_handle_op_with_result(full_op, block_id, state_override)
                     .await?;
                 return Ok(cl);
             }
@@ -395,7 +639,7 @@ where
 
         let call_gas_limit = self
             .call_gas_estimator
-            .estimate_call_gas(full_op, block_hash, state_override)
+            .estimate_call_gas(full_op, block_id, state_override)
             .await?;
 
         // Add a buffer to the call gas limit and clamp
@@ -426,12 +670,17 @@ impl CallGasEstimatorSpecialization for CallGasEstimatorSpecializationV07 {
         let moved_entry_point_address: Address = rand::thread_rng().gen();
         let estimation_proxy_bytecode =
             estimation_proxy_bytecode_with_target(moved_entry_point_address);
-        state_override
-            .account(moved_entry_point_address)
-            .code(ENTRYPOINTSIMULATIONS_DEPLOYED_BYTECODE.clone());
-        state_override
-            .account(ep_to_override)
-            .code(estimation_proxy_bytecode);
+        let mut moved_entry_point = spoof::Account::default();
+        moved_entry_point.code(ENTRYPOINTSIMULATIONS_DEPLOYED_BYTECODE.clone());
+        let mut proxy = spoof::Account::default();
+        proxy.code(estimation_proxy_bytecode);
+        super::merge_state_overrides(
+            state_override,
+            [
+                (moved_entry_point_address, moved_entry_point),
+                (ep_to_override, proxy),
+            ],
+        );
     }
 
     fn get_op_with_no_call_gas(&self, op: Self::UO) -> Self::UO {
@@ -486,10 +735,12 @@ fn estimation_proxy_bytecode_with_target(target: Address) -> Bytes {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use ethers::{
         abi::AbiEncode,
         contract::EthCall,
-        types::{Address, U64},
+        types::{Address, H256, U64},
         utils::hex,
     };
     use rundler_provider::{ExecutionResult, MockEntryPointV0_7, MockProvider, SimulateOpCallData};
@@ -504,8 +755,8 @@ mod tests {
 
     use super::*;
     use crate::{
-        estimation::estimate_call_gas::PROXY_IMPLEMENTATION_ADDRESS_MARKER, GasEstimator as _,
-        PriorityFeeMode,
+        estimation::estimate_call_gas::PROXY_IMPLEMENTATION_ADDRESS_MARKER, EstimationBlockTag,
+        GasEstimator as _, PriorityFeeMode,
     };
 
     // Alises for complex types (which also satisfy Clippy)
@@ -582,8 +833,17 @@ mod tests {
             max_paymaster_verification_gas: TEST_MAX_GAS_LIMITS,
             max_paymaster_post_op_gas: TEST_MAX_GAS_LIMITS,
             max_total_execution_gas: TEST_MAX_GAS_LIMITS,
-            max_simulate_handle_ops_gas: TEST_MAX_GAS_LIMITS,
+            max_simulate_handle_ops_gas: 2 * TEST_MAX_GAS_LIMITS,
             verification_estimation_gas_fee: 1_000_000_000_000,
+            paymaster_fee_payer_multiplier: 3,
+            call_gas_estimation_tolerance: 4096,
+            max_estimation_attempts: 10,
+            max_op_field_size: None,
+            estimation_timeout: Duration::from_secs(10),
+            refresh_on_stale_revert: false,
+            verification_gas_margin_percent: 0,
+            max_concurrent_estimations: 4,
+            block_tag: EstimationBlockTag::Latest,
         };
         let estimator = create_custom_estimator(ChainSpec::default(), provider, entry, settings);
         (estimator, settings)
@@ -608,6 +868,7 @@ mod tests {
 
             factory: None,
             factory_data: Bytes::new(),
+            eip7702_auth: None,
         }
     }
 
@@ -619,7 +880,7 @@ mod tests {
         let optional_op = demo_user_op_optional_gas(Some(U256::from(TEST_MAX_GAS_LIMITS + 1)));
 
         let estimation = estimator
-            .estimate_op_gas(optional_op, spoof::state())
+            .estimate_op_gas(optional_op, spoof::state(), EstimationOptions::default())
             .await
             .err()
             .unwrap();
@@ -639,7 +900,7 @@ mod tests {
         optional_op.verification_gas_limit = Some(U128::from(TEST_MAX_GAS_LIMITS + 1));
 
         let estimation = estimator
-            .estimate_op_gas(optional_op, spoof::state())
+            .estimate_op_gas(optional_op, spoof::state(), EstimationOptions::default())
             .await
             .err()
             .unwrap();
@@ -659,7 +920,7 @@ mod tests {
         optional_op.paymaster_verification_gas_limit = Some(U128::from(TEST_MAX_GAS_LIMITS + 1));
 
         let estimation = estimator
-            .estimate_op_gas(optional_op, spoof::state())
+            .estimate_op_gas(optional_op, spoof::state(), EstimationOptions::default())
             .await
             .err()
             .unwrap();
@@ -682,7 +943,7 @@ mod tests {
         optional_op.call_gas_limit = Some(U128::from(TEST_MAX_GAS_LIMITS + 1));
 
         let estimation = estimator
-            .estimate_op_gas(optional_op, spoof::state())
+            .estimate_op_gas(optional_op, spoof::state(), EstimationOptions::default())
             .await
             .err()
             .unwrap();
@@ -702,7 +963,7 @@ mod tests {
         optional_op.paymaster_post_op_gas_limit = Some(U128::from(TEST_MAX_GAS_LIMITS + 1));
 
         let estimation = estimator
-            .estimate_op_gas(optional_op, spoof::state())
+            .estimate_op_gas(optional_op, spoof::state(), EstimationOptions::default())
             .await
             .err()
             .unwrap();
@@ -747,7 +1008,11 @@ mod tests {
         optional_op.paymaster_post_op_gas_limit = Some(U128::from(10000));
 
         let estimation = estimator
-            .estimate_op_gas(optional_op.clone(), spoof::state())
+            .estimate_op_gas(
+                optional_op.clone(),
+                spoof::state(),
+                EstimationOptions::default(),
+            )
             .await
             .unwrap();
 
@@ -765,6 +1030,10 @@ mod tests {
                 .paymaster_verification_gas_limit
                 .map(|v| v.into())
         );
+        assert_eq!(
+            estimation.paymaster_post_op_gas_limit,
+            Some(U256::from(10000))
+        );
         assert_eq!(
             estimation.call_gas_limit,
             optional_op.call_gas_limit.unwrap().into()
@@ -807,7 +1076,11 @@ mod tests {
         optional_op.verification_gas_limit = Some(U128::from(10000));
 
         let estimation_error = estimator
-            .estimate_op_gas(optional_op.clone(), spoof::state())
+            .estimate_op_gas(
+                optional_op.clone(),
+                spoof::state(),
+                EstimationOptions::default(),
+            )
             .await
             .err()
             .unwrap();
@@ -861,10 +1134,11 @@ mod tests {
 
             factory: None,
             factory_data: Bytes::new(),
+            eip7702_auth: None,
         };
 
         let estimation = estimator
-            .estimate_op_gas(optional_op, spoof::state())
+            .estimate_op_gas(optional_op, spoof::state(), EstimationOptions::default())
             .await
             .err()
             .unwrap();
@@ -14,7 +14,7 @@
 use std::{cmp, fmt::Debug, sync::Arc};
 
 use anyhow::{bail, Context};
-use ethers::types::U256;
+use ethers::types::{spoof, U256};
 use rundler_provider::{EntryPoint, L1GasProvider, Provider};
 use rundler_types::{
     chain::{self, ChainSpec},
@@ -40,6 +40,9 @@ use super::oracle::{
 ///
 /// Networks that require dynamic pre_verification_gas are typically those that charge extra calldata fees
 /// that can scale based on dynamic gas prices.
+///
+/// `state_override` is applied when calling the network's L1 gas oracle, and can be used to
+/// override the L1 fee parameters stored by the oracle.
 pub async fn estimate_pre_verification_gas<
     UO: UserOperation,
     E: EntryPoint + L1GasProvider<UO = UO>,
@@ -49,6 +52,7 @@ pub async fn estimate_pre_verification_gas<
     full_op: &UO,
     random_op: &UO,
     gas_price: U256,
+    state_override: &spoof::State,
 ) -> anyhow::Result<U256> {
     let static_gas = full_op.calc_static_pre_verification_gas(chain_spec, true);
     if !chain_spec.calldata_pre_verification_gas {
@@ -56,7 +60,12 @@ pub async fn estimate_pre_verification_gas<
     }
 
     let dynamic_gas = entry_point
-        .calc_l1_gas(entry_point.address(), random_op.clone(), gas_price)
+        .calc_l1_gas(
+            entry_point.address(),
+            random_op.clone(),
+            gas_price,
+            state_override,
+        )
         .await?;
 
     Ok(static_gas.saturating_add(dynamic_gas))
@@ -89,7 +98,12 @@ pub async fn calc_required_pre_verification_gas<
     }
 
     let dynamic_gas = entry_point
-        .calc_l1_gas(entry_point.address(), op.clone(), gas_price)
+        .calc_l1_gas(
+            entry_point.address(),
+            op.clone(),
+            gas_price,
+            &spoof::State::default(),
+        )
         .await?;
 
     Ok(static_gas + dynamic_gas)
@@ -308,6 +322,15 @@ impl<P: Provider> FeeEstimator<P> {
         self.priority_fee_mode.required_fees(bundle_fees)
     }
 
+    /// Returns the recommended fees for a user operation to be included in the next bundle.
+    ///
+    /// These are the operation fees required by the current bundle fees, as reported by
+    /// the fee oracle and adjusted by the priority fee mode.
+    pub async fn recommended_op_fees(&self) -> anyhow::Result<GasFees> {
+        let (bundle_fees, _) = self.required_bundle_fees(None).await?;
+        Ok(self.required_op_fees(bundle_fees))
+    }
+
     async fn get_base_fee(&self) -> anyhow::Result<U256> {
         Ok(self.provider.get_base_fee().await?)
     }
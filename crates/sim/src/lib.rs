@@ -35,9 +35,11 @@ mod estimation;
 #[cfg(feature = "test-utils")]
 pub use estimation::MockGasEstimator;
 pub use estimation::{
-    CallGasEstimator, CallGasEstimatorImpl, CallGasEstimatorSpecialization, GasEstimationError,
-    GasEstimator, GasEstimatorV0_6, GasEstimatorV0_7, Settings as EstimationSettings,
-    VerificationGasEstimator, VerificationGasEstimatorImpl,
+    apply_prior_nonces_state_override, merge_state_overrides, BundleGasEstimate, CallGasEstimator,
+    CallGasEstimatorImpl, CallGasEstimatorSpecialization, EstimationBlockTag, EstimationOptions,
+    GasEstimateBreakdown, GasEstimateWithFees, GasEstimateWithHeadroom, GasEstimationError,
+    GasEstimator, GasEstimatorV0_6, GasEstimatorV0_7, GasHeadroom, RevertDecoder, SearchBounds,
+    Settings as EstimationSettings, VerificationGasEstimator, VerificationGasEstimatorImpl,
 };
 
 pub mod gas;
@@ -56,10 +58,10 @@ pub mod simulation;
 pub use simulation::MockSimulator;
 pub use simulation::{
     MempoolConfig, MempoolConfigs, Settings as SimulationSettings, SimulationError,
-    SimulationResult, Simulator,
+    SimulationResult, Simulator, StorageAllowlistEntry,
 };
 
 mod types;
-pub use types::{ExpectedStorage, ViolationError};
+pub use types::{AccessedSlots, AccessedStorage, ExpectedStorage, ViolationError};
 
 mod utils;
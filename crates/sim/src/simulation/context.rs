@@ -22,7 +22,7 @@ use rundler_types::{
 use serde::{Deserialize, Serialize};
 
 use super::Settings;
-use crate::{ExpectedStorage, ViolationError};
+use crate::{AccessedStorage, ExpectedStorage, ViolationError};
 
 #[derive(Clone, Debug)]
 pub struct ValidationContext<UO> {
@@ -47,6 +47,26 @@ pub(crate) struct TracerOutput {
     pub(crate) expected_storage: ExpectedStorage,
 }
 
+impl TracerOutput {
+    /// Returns the storage slots accessed across all validation phases. Reads keep
+    /// the value seen by the first phase reading the slot.
+    pub(crate) fn accessed_storage(&self) -> AccessedStorage {
+        let mut accessed_storage = AccessedStorage::default();
+        for phase in &self.phases {
+            for (&address, access_info) in &phase.storage_accesses {
+                let slots = accessed_storage.0.entry(address).or_default();
+                for (&slot, &value) in &access_info.reads {
+                    slots.reads.entry(slot).or_insert(value);
+                }
+                for (&slot, &count) in &access_info.writes {
+                    *slots.writes.entry(slot).or_default() += count;
+                }
+            }
+        }
+        accessed_storage
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct Phase {
@@ -11,16 +11,16 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::collections::HashSet;
+use std::{collections::HashSet, str::FromStr};
 
-use anyhow::Error;
+use anyhow::{bail, Error};
 use ethers::types::{Address, H256, U256};
 #[cfg(feature = "test-utils")]
 use mockall::automock;
 use rundler_provider::AggregatorSimOut;
 use rundler_types::{
     pool::{MempoolError, SimulationViolation},
-    EntityInfos, UserOperation, ValidTimeRange,
+    Entity, EntityInfos, StorageSlot, UserOperation, ValidTimeRange,
 };
 
 mod context;
@@ -147,6 +147,15 @@ pub trait Simulator: Send + Sync + 'static {
         block_hash: Option<H256>,
         expected_code_hash: Option<H256>,
     ) -> Result<SimulationResult, SimulationError>;
+
+    /// Simulate a user operation against the state at a historical block, for
+    /// analyzing past simulation results. Blocks older than the node's pruning
+    /// window require an archive node.
+    async fn simulate_validation_at_block(
+        &self,
+        op: Self::UO,
+        block_number: u64,
+    ) -> Result<SimulationResult, SimulationError>;
 }
 
 /// Simulation Settings
@@ -165,6 +174,22 @@ pub struct Settings {
     /// The max duration of the custom javascript tracer. Must be in a format parseable by the
     /// ParseDuration function on an ethereum node. See Docs: https://pkg.go.dev/time#ParseDuration
     pub tracer_timeout: String,
+    /// Storage accesses of trusted entities exempted from the storage access rules.
+    /// Violations of the rules by these accesses are logged instead of rejected.
+    pub storage_allowlist: Vec<StorageAllowlistEntry>,
+    /// Skip the storage access rules, enforcing only the opcode and other rules.
+    /// Only for deployments where the entities are trusted not to grief the mempool
+    /// through storage accesses.
+    pub skip_storage_rules: bool,
+    /// Aggregators that user operations may use. If `None`, any aggregator is
+    /// supported.
+    pub supported_aggregators: Option<HashSet<Address>>,
+    /// Buffer required between the verification gas limit and the verification gas
+    /// used during simulation. Only enforced for the 0.6 entry point.
+    pub verification_gas_limit_buffer: U256,
+    /// Maximum number of successful simulation results to cache. Results are cached
+    /// for the latest simulated block only. 0 disables caching.
+    pub cache_size: u32,
 }
 
 impl Settings {
@@ -175,6 +200,10 @@ impl Settings {
         max_simulate_handle_ops_gas: u64,
         max_verification_gas: u64,
         tracer_timeout: String,
+        storage_allowlist: Vec<StorageAllowlistEntry>,
+        skip_storage_rules: bool,
+        supported_aggregators: Option<HashSet<Address>>,
+        verification_gas_limit_buffer: Option<u64>,
     ) -> Self {
         Self {
             min_unstake_delay,
@@ -182,8 +211,22 @@ impl Settings {
             max_simulate_handle_ops_gas,
             max_verification_gas,
             tracer_timeout,
+            storage_allowlist,
+            skip_storage_rules,
+            supported_aggregators,
+            verification_gas_limit_buffer: verification_gas_limit_buffer
+                .map(U256::from)
+                .unwrap_or(v0_6::REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER),
+            cache_size: 0,
         }
     }
+
+    /// Returns true if user operations may use the aggregator at `address`
+    pub fn is_supported_aggregator(&self, address: Address) -> bool {
+        self.supported_aggregators
+            .as_ref()
+            .map_or(true, |aggregators| aggregators.contains(&address))
+    }
 }
 
 #[cfg(any(test, feature = "test-utils"))]
@@ -198,6 +241,53 @@ impl Default for Settings {
             max_simulate_handle_ops_gas: 550_000_000,
             max_verification_gas: 5_000_000,
             tracer_timeout: "10s".to_string(),
+            storage_allowlist: vec![],
+            skip_storage_rules: false,
+            supported_aggregators: None,
+            verification_gas_limit_buffer: v0_6::REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER,
+            cache_size: 0,
         }
     }
 }
+
+/// A storage access of an entity exempted from the storage access rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageAllowlistEntry {
+    /// The address of the entity accessing the storage
+    pub entity: Address,
+    /// The address of the contract owning the storage
+    pub contract: Address,
+    /// The storage slot. If `None`, accesses to any slot of the contract are exempted.
+    pub slot: Option<U256>,
+}
+
+impl StorageAllowlistEntry {
+    /// Returns true if the access of `slot` by `entity` is exempted by this entry
+    pub fn allows(&self, entity: &Entity, slot: &StorageSlot) -> bool {
+        self.entity == entity.address
+            && self.contract == slot.address
+            && self.slot.map_or(true, |s| s == slot.slot)
+    }
+}
+
+impl FromStr for StorageAllowlistEntry {
+    type Err = Error;
+
+    /// Parses an entry of the form `<entity>:<contract>` or `<entity>:<contract>:<slot>`,
+    /// with the slot in hex
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(':').collect::<Vec<_>>();
+        let (entity, contract, slot) = match parts[..] {
+            [entity, contract] => (entity, contract, None),
+            [entity, contract, slot] => (entity, contract, Some(slot)),
+            _ => bail!("storage allowlist entry should be <entity>:<contract>[:<slot>], got {s}"),
+        };
+        Ok(Self {
+            entity: entity.parse()?,
+            contract: contract.parse()?,
+            slot: slot
+                .map(|slot| U256::from_str_radix(slot.trim_start_matches("0x"), 16))
+                .transpose()?,
+        })
+    }
+}
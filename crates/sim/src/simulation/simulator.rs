@@ -13,9 +13,10 @@
 
 use std::{
     collections::{HashMap, HashSet},
+    fmt::{self, Debug, Formatter},
     marker::PhantomData,
     ops::Deref,
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
 use async_trait::async_trait;
@@ -30,6 +31,7 @@ use rundler_types::{
     Entity, EntityInfo, EntityInfos, EntityType, Opcode, StorageSlot, UserOperation,
     ValidTimeRange, ValidationOutput, ValidationReturnInfo, ViolationOpCode,
 };
+use rundler_utils::cache::LruMap;
 
 use super::context::{
     self, AccessInfo, AssociatedSlotsByAddress, ValidationContext, ValidationContextProvider,
@@ -102,6 +104,14 @@ where
 ///
 /// If no mempools are found, the simulator will return an error containing
 /// the violations.
+///
+/// Successful simulation results can be cached for the latest block, see the
+/// simulation settings' `cache_size`.
+///
+/// Storage access violations of entities on the storage allowlist of the
+/// simulation settings are logged and otherwise ignored. If the settings skip
+/// the storage rules, no storage access is checked, but the opcode and other
+/// rules are still enforced.
 #[derive(Debug)]
 pub struct SimulatorImpl<UO, P, E, V> {
     provider: Arc<P>,
@@ -110,9 +120,54 @@ pub struct SimulatorImpl<UO, P, E, V> {
     sim_settings: Settings,
     mempool_configs: HashMap<H256, MempoolConfig>,
     allow_unstaked_addresses: HashSet<Address>,
+    cache: Option<Mutex<SimulationCache>>,
     _uo_type: PhantomData<UO>,
 }
 
+/// Key of a cached simulation result: the hash of the user operation and the
+/// expected code hash it was simulated with
+type SimulationCacheKey = (H256, Option<H256>);
+
+/// Successful simulation results at a single block
+struct SimulationCache {
+    block_hash: H256,
+    results: LruMap<SimulationCacheKey, SimulationResult>,
+}
+
+impl SimulationCache {
+    fn new(size: u32) -> Self {
+        Self {
+            block_hash: H256::zero(),
+            results: LruMap::new(size),
+        }
+    }
+
+    fn get(&mut self, block_hash: H256, key: &SimulationCacheKey) -> Option<SimulationResult> {
+        if block_hash != self.block_hash {
+            return None;
+        }
+        self.results.get(key).cloned()
+    }
+
+    // Results of a different block than the cached ones replace them
+    fn insert(&mut self, block_hash: H256, key: SimulationCacheKey, result: SimulationResult) {
+        if block_hash != self.block_hash {
+            self.results.clear();
+            self.block_hash = block_hash;
+        }
+        self.results.insert(key, result);
+    }
+}
+
+impl Debug for SimulationCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimulationCache")
+            .field("block_hash", &self.block_hash)
+            .field("len", &self.results.len())
+            .finish()
+    }
+}
+
 impl<UO, P, E, V> SimulatorImpl<UO, P, E, V>
 where
     UO: UserOperation,
@@ -144,6 +199,9 @@ where
             }
         }
 
+        let cache = (sim_settings.cache_size > 0)
+            .then(|| Mutex::new(SimulationCache::new(sim_settings.cache_size)));
+
         Self {
             provider,
             entry_point,
@@ -151,6 +209,7 @@ where
             sim_settings,
             mempool_configs,
             allow_unstaked_addresses,
+            cache,
             _uo_type: PhantomData,
         }
     }
@@ -171,6 +230,27 @@ where
             .await
     }
 
+    // Returns true if `violation` is a storage access exempted by the storage allowlist,
+    // logging a warning in that case
+    fn is_allowlisted_storage_access(&self, violation: &SimulationViolation) -> bool {
+        let (entity, slot) = match violation {
+            SimulationViolation::InvalidStorageAccess(entity, slot)
+            | SimulationViolation::AssociatedStorageDuringDeploy(Some(entity), slot) => {
+                (entity, slot)
+            }
+            _ => return false,
+        };
+        let allowed = self
+            .sim_settings
+            .storage_allowlist
+            .iter()
+            .any(|entry| entry.allows(entity, slot));
+        if allowed {
+            tracing::warn!("Allowing storage access of allowlisted entity: {violation:?}");
+        }
+        allowed
+    }
+
     // Parse the output from tracing and return a list of violations.
     // Most violations found during this stage are allowlistable and can be added
     // to the list of allowlisted violations on a given mempool.
@@ -234,6 +314,9 @@ where
             for (addr, access_info) in &phase.storage_accesses {
                 let address = *addr;
                 accessed_addresses.insert(address);
+                if self.sim_settings.skip_storage_rules {
+                    continue;
+                }
 
                 let restrictions = parse_storage_accesses(ParseStorageAccess {
                     access_info,
@@ -414,6 +497,21 @@ where
         let mut violations = vec![];
 
         let aggregator_address = entry_point_out.aggregator_info.map(|info| info.address);
+        if let Some(aggregator_address) = aggregator_address {
+            if !self
+                .sim_settings
+                .is_supported_aggregator(aggregator_address)
+            {
+                return Err(SimulationError {
+                    violation_error: vec![SimulationViolation::UnsupportedAggregator(
+                        aggregator_address,
+                    )]
+                    .into(),
+                    entity_infos: None,
+                });
+            }
+        }
+
         let code_hash_future = utils::get_code_hash(
             self.provider.deref(),
             tracer_out.accessed_contracts.keys().cloned().collect(),
@@ -464,6 +562,7 @@ where
 {
     type UO = UO;
 
+    #[tracing::instrument(skip_all, fields(sender = ?op.sender()))]
     async fn simulate_validation(
         &self,
         op: UO,
@@ -482,6 +581,21 @@ where
                 (hash_and_num.0, Some(hash_and_num.1.as_u64()))
             }
         };
+
+        // The chain ID doesn't matter for a key of a cache of a single entry point
+        let cache_key = self
+            .cache
+            .is_some()
+            .then(|| (op.hash(self.entry_point.address(), 0), expected_code_hash));
+        if let (Some(cache), Some(cache_key)) = (&self.cache, &cache_key) {
+            let cached = cache.lock().unwrap().get(block_hash, cache_key);
+            if let Some(mut result) = cached {
+                metrics::counter!("sim_cache_hits").increment(1);
+                result.block_number = block_number.or(result.block_number);
+                return Ok(result);
+            }
+        }
+
         let block_id = block_hash.into();
         let mut context = match self
             .validation_context_provider
@@ -494,6 +608,8 @@ where
 
         // Gather all violations from the tracer
         let mut overridable_violations = self.gather_context_violations(&mut context)?;
+        // Exempt the storage accesses of allowlisted entities
+        overridable_violations.retain(|violation| !self.is_allowlisted_storage_access(violation));
         // Sort violations so that the final error message is deterministic
         overridable_violations.sort();
         // Check violations against mempool rules, find supporting mempools, error if none found
@@ -540,7 +656,7 @@ where
         // Conduct any stake overrides before assigning entity_infos
         override_infos_staked(&mut context.entity_infos, &self.allow_unstaked_addresses);
 
-        Ok(SimulationResult {
+        let result = SimulationResult {
             mempools,
             block_hash,
             block_number,
@@ -554,7 +670,25 @@ where
             expected_storage: tracer_out.expected_storage,
             requires_post_op: !paymaster_context.is_empty(),
             entity_infos: context.entity_infos,
-        })
+        };
+        if let (Some(cache), Some(cache_key)) = (&self.cache, cache_key) {
+            cache
+                .lock()
+                .unwrap()
+                .insert(block_hash, cache_key, result.clone());
+        }
+        Ok(result)
+    }
+
+    async fn simulate_validation_at_block(
+        &self,
+        op: UO,
+        block_number: u64,
+    ) -> Result<SimulationResult, SimulationError> {
+        let block_hash = utils::get_block_hash(self.provider.deref(), block_number).await?;
+        let mut result = self.simulate_validation(op, Some(block_hash), None).await?;
+        result.block_number = Some(block_number);
+        Ok(result)
     }
 }
 
@@ -680,17 +814,22 @@ fn override_infos_staked(eis: &mut EntityInfos, allow_unstaked_addresses: &HashS
 
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
+    use std::{
+        str::FromStr,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
 
     use context::ContractInfo;
-    use ethers::types::{Address, BlockId, BlockNumber, Bytes, U256, U64};
+    use ethers::types::{Address, Block, BlockId, BlockNumber, Bytes, U256, U64};
     use rundler_provider::{AggregatorOut, MockEntryPointV0_6, MockProvider};
     use rundler_types::{
-        contracts::utils::get_code_hashes::CodeHashesResult, v0_6::UserOperation, Opcode, StakeInfo,
+        contracts::utils::get_code_hashes::CodeHashesResult, v0_6::UserOperation, AggregatorInfo,
+        Opcode, StakeInfo,
     };
 
     use self::context::{Phase, TracerOutput};
     use super::*;
+    use crate::simulation::StorageAllowlistEntry;
 
     mockall::mock! {
         ValidationContextProviderV0_6 {}
@@ -855,6 +994,22 @@ mod tests {
         }
     }
 
+    fn test_user_operation() -> UserOperation {
+        UserOperation {
+            sender: Address::from_str("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4").unwrap(),
+            nonce: U256::from(264),
+            init_code: Bytes::from_str("0x").unwrap(),
+            call_data: Bytes::from_str("0xb61d27f6000000000000000000000000b856dbd4fa1a79a46d426f537455e7d3e79ab7c4000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000004d087d28800000000000000000000000000000000000000000000000000000000").unwrap(),
+            call_gas_limit: U256::from(9100),
+            verification_gas_limit: U256::from(64805),
+            pre_verification_gas: U256::from(46128),
+            max_fee_per_gas: U256::from(105000100),
+            max_priority_fee_per_gas: U256::from(105000000),
+            paymaster_and_data: Bytes::from_str("0x").unwrap(),
+            signature: Bytes::from_str("0x98f89993ce573172635b44ef3b0741bd0c19dd06909d3539159f6d66bef8c0945550cc858b1cf5921dfce0986605097ba34c2cf3fc279154dd25e161ea7b3d0f1c").unwrap(),
+        }
+    }
+
     fn create_simulator(
         provider: MockProvider,
         entry_point: MockEntryPointV0_6,
@@ -921,19 +1076,7 @@ mod tests {
             .expect_validate_user_op_signature()
             .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
 
-        let user_operation = UserOperation {
-            sender: Address::from_str("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4").unwrap(),
-            nonce: U256::from(264),
-            init_code: Bytes::from_str("0x").unwrap(),
-            call_data: Bytes::from_str("0xb61d27f6000000000000000000000000b856dbd4fa1a79a46d426f537455e7d3e79ab7c4000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000004d087d28800000000000000000000000000000000000000000000000000000000").unwrap(),
-            call_gas_limit: U256::from(9100),
-            verification_gas_limit: U256::from(64805),
-            pre_verification_gas: U256::from(46128),
-            max_fee_per_gas: U256::from(105000100),
-            max_priority_fee_per_gas: U256::from(105000000),
-            paymaster_and_data: Bytes::from_str("0x").unwrap(),
-            signature: Bytes::from_str("0x98f89993ce573172635b44ef3b0741bd0c19dd06909d3539159f6d66bef8c0945550cc858b1cf5921dfce0986605097ba34c2cf3fc279154dd25e161ea7b3d0f1c").unwrap(),
-        };
+        let user_operation = test_user_operation();
 
         let simulator = create_simulator(provider, entry_point, context);
         let res = simulator
@@ -1215,4 +1358,210 @@ mod tests {
             )]
         );
     }
+
+    #[tokio::test]
+    async fn test_storage_allowlist() {
+        let (provider, entry_point, context_provider) = create_base_config();
+        let paymaster = Address::random();
+        let contract = Address::random();
+        let mut simulator = create_simulator(provider, entry_point, context_provider);
+        simulator.sim_settings.storage_allowlist = vec![
+            format!("{paymaster:?}:{contract:?}:0x1").parse().unwrap(),
+            StorageAllowlistEntry {
+                entity: paymaster,
+                contract: Address::zero(),
+                slot: None,
+            },
+        ];
+
+        let access = |entity: Address, address: Address, slot: u64| {
+            SimulationViolation::InvalidStorageAccess(
+                Entity::paymaster(entity),
+                StorageSlot {
+                    address,
+                    slot: U256::from(slot),
+                },
+            )
+        };
+
+        // the allowlisted slot, and any slot of the allowlisted contract
+        assert!(simulator.is_allowlisted_storage_access(&access(paymaster, contract, 1)));
+        assert!(simulator.is_allowlisted_storage_access(&access(paymaster, Address::zero(), 7)));
+        // other slots, contracts and entities are still rejected
+        assert!(!simulator.is_allowlisted_storage_access(&access(paymaster, contract, 2)));
+        assert!(!simulator.is_allowlisted_storage_access(&access(Address::random(), contract, 1)));
+        assert!(!simulator.is_allowlisted_storage_access(
+            &SimulationViolation::UsedForbiddenOpcode(
+                Entity::paymaster(paymaster),
+                contract,
+                ViolationOpCode(Opcode::GASPRICE),
+            )
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_skip_storage_rules() {
+        let (provider, mut entry_point, mut context_provider) = create_base_config();
+        entry_point
+            .expect_address()
+            .returning(|| Address::from_str("0x5ff137d4b0fdcd49dca30c7cf57e578a026d2789").unwrap());
+        context_provider
+            .expect_get_specific_violations()
+            .return_const(vec![]);
+
+        let sender_address =
+            Address::from_str("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4").unwrap();
+        let mut context = get_test_context();
+        context.tracer_out.phases[1].forbidden_opcodes_used =
+            vec![format!("{sender_address:?}:GASPRICE")];
+        // a storage access for a random unrelated address
+        let external_access_address = Address::random();
+        context.tracer_out.phases[1].storage_accesses.insert(
+            external_access_address,
+            AccessInfo {
+                reads: HashMap::new(),
+                writes: HashMap::from([(U256::from(1), 1)]),
+            },
+        );
+
+        let mut simulator = create_simulator(provider, entry_point, context_provider);
+        simulator.sim_settings.skip_storage_rules = true;
+        let res = simulator.gather_context_violations(&mut context);
+
+        // the opcode rules are still enforced
+        assert_eq!(
+            res.unwrap(),
+            vec![SimulationViolation::UsedForbiddenOpcode(
+                Entity::account(sender_address),
+                sender_address,
+                ViolationOpCode(Opcode::GASPRICE),
+            )]
+        );
+        // and the accessed contract's code is still checked
+        assert!(context
+            .accessed_addresses
+            .contains(&external_access_address));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_aggregator() {
+        let (provider, entry_point, context_provider) = create_base_config();
+        let aggregator = Address::random();
+        let mut context = get_test_context();
+        context.entry_point_out.aggregator_info = Some(AggregatorInfo {
+            address: aggregator,
+            stake_info: StakeInfo::from((U256::default(), U256::default())),
+        });
+
+        let mut simulator = create_simulator(provider, entry_point, context_provider);
+        simulator.sim_settings.supported_aggregators = Some(HashSet::from([Address::random()]));
+        let res = simulator
+            .check_contracts(UserOperation::default(), &mut context, None)
+            .await;
+
+        assert!(matches!(
+            res,
+            Err(SimulationError {
+                violation_error: ViolationError::Violations(violations),
+                ..
+            }) if violations == vec![SimulationViolation::UnsupportedAggregator(aggregator)]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_at_block() {
+        let (mut provider, entry_point, mut context) = create_base_config();
+        let block_hash = H256::random();
+        provider
+            .expect_get_block::<u64>()
+            .withf(|&block_number| block_number == 100)
+            .returning(move |_| {
+                Ok(Some(Block {
+                    hash: Some(block_hash),
+                    ..Default::default()
+                }))
+            });
+        // validation is traced at the requested block
+        context
+            .expect_get_context()
+            .withf(move |_, &block_id| block_id == BlockId::Hash(block_hash))
+            .times(1)
+            .returning(|_, _| Err(ViolationError::Other(anyhow::anyhow!("trace failed"))));
+
+        let simulator = create_simulator(provider, entry_point, context);
+        let res = simulator
+            .simulate_validation_at_block(UserOperation::default(), 100)
+            .await;
+
+        assert!(matches!(
+            res,
+            Err(SimulationError {
+                violation_error: ViolationError::Other(_),
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_simulation_cache() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+
+        // the first two simulations are at the same block, the third at a new block
+        let block_hashes = [H256::random(), H256::random()];
+        let calls = AtomicUsize::new(0);
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || {
+                let block = calls.fetch_add(1, Ordering::SeqCst) / 2;
+                Ok((block_hashes[block], U64::from(block)))
+            });
+
+        // the second simulation is cached, the third misses the cache
+        context
+            .expect_get_context()
+            .times(2)
+            .returning(move |_, _| Ok(get_test_context()));
+        context
+            .expect_get_specific_violations()
+            .return_const(vec![]);
+
+        // The underlying call constructor when getting the code hash in check_contracts
+        provider
+            .expect_call_constructor()
+            .returning(|_, _: Vec<Address>, _, _| {
+                Ok(CodeHashesResult {
+                    hash: H256::from_str(
+                        "0x091cd005abf68e7b82c951a8619f065986132f67a0945153533cfcdd93b6895f",
+                    )
+                    .unwrap()
+                    .into(),
+                })
+            });
+
+        entry_point
+            .expect_address()
+            .returning(|| Address::from_str("0x5ff137d4b0fdcd49dca30c7cf57e578a026d2789").unwrap());
+        entry_point
+            .expect_validate_user_op_signature()
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+        let mut simulator = create_simulator(provider, entry_point, context);
+        simulator.cache = Some(Mutex::new(SimulationCache::new(10)));
+        let first = simulator
+            .simulate_validation(test_user_operation(), None, None)
+            .await
+            .unwrap();
+        let second = simulator
+            .simulate_validation(test_user_operation(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(first.code_hash, second.code_hash);
+        assert_eq!(second.block_number, Some(0));
+
+        let third = simulator
+            .simulate_validation(test_user_operation(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(third.block_number, Some(1));
+    }
 }
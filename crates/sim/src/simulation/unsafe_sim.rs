@@ -11,18 +11,17 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{marker::PhantomData, sync::Arc};
+use std::{marker::PhantomData, ops::Deref, sync::Arc};
 
 use ethers::types::H256;
-use rundler_provider::{
-    AggregatorOut, EntryPoint, Provider, SignatureAggregator, SimulationProvider,
-};
+use rundler_provider::{AggregatorSimOut, EntryPoint, Provider, SimulationProvider};
 use rundler_types::{
     pool::SimulationViolation, EntityInfos, UserOperation, ValidTimeRange, ValidationError,
 };
 
 use crate::{
-    SimulationError, SimulationResult, SimulationSettings as Settings, Simulator, ViolationError,
+    utils, SimulationError, SimulationResult, SimulationSettings as Settings, Simulator,
+    ViolationError,
 };
 
 /// An unsafe simulator that can be used in place of a regular simulator
@@ -31,6 +30,11 @@ use crate::{
 ///
 /// WARNING: This is "unsafe" for a reason. None of the ERC-7562 checks are
 /// performed.
+///
+/// The aggregator of an op is the one its account declares during validation,
+/// and is trusted: the op's signature is not validated by the aggregator. Ops
+/// are still grouped by that aggregator when bundled, and bundles fail if the
+/// aggregator rejects their signatures.
 pub struct UnsafeSimulator<UO, P, E> {
     provider: Arc<P>,
     entry_point: E,
@@ -55,13 +59,15 @@ impl<UO, P, E> Simulator for UnsafeSimulator<UO, P, E>
 where
     UO: UserOperation,
     P: Provider,
-    E: EntryPoint + SimulationProvider<UO = UO> + SignatureAggregator<UO = UO> + Clone,
+    E: EntryPoint + SimulationProvider<UO = UO> + Clone,
 {
     type UO = UO;
 
     // Run an unsafe simulation
     //
-    // The only validation checks that are performed are signature checks
+    // The only validation checks that are performed are the account and paymaster
+    // signature checks
+    #[tracing::instrument(skip_all, fields(sender = ?op.sender()))]
     async fn simulate_validation(
         &self,
         op: UO,
@@ -137,23 +143,26 @@ where
         let mut violations = vec![];
 
         let aggregator = if let Some(aggregator_info) = validation_result.aggregator_info {
-            let agg_out = self
-                .entry_point
-                .validate_user_op_signature(
-                    aggregator_info.address,
-                    op,
-                    self.sim_settings.max_verification_gas,
-                )
-                .await?;
-
-            match agg_out {
-                AggregatorOut::NotNeeded => None,
-                AggregatorOut::SuccessWithInfo(info) => Some(info),
-                AggregatorOut::ValidationReverted => {
-                    violations.push(SimulationViolation::AggregatorValidationFailed);
-                    None
-                }
+            if !self
+                .sim_settings
+                .is_supported_aggregator(aggregator_info.address)
+            {
+                Err(SimulationError {
+                    violation_error: vec![SimulationViolation::UnsupportedAggregator(
+                        aggregator_info.address,
+                    )]
+                    .into(),
+                    entity_infos: Some(entity_infos),
+                })?
             }
+
+            // Trust the aggregator declared by the account without validating the
+            // op's signature with it. Only the address is used to group the op in
+            // bundles, the bundle's signature is aggregated when it is built.
+            Some(AggregatorSimOut {
+                address: aggregator_info.address,
+                signature: op.signature().clone(),
+            })
         } else {
             None
         };
@@ -183,4 +192,15 @@ where
             })
         }
     }
+
+    async fn simulate_validation_at_block(
+        &self,
+        op: UO,
+        block_number: u64,
+    ) -> Result<SimulationResult, SimulationError> {
+        let block_hash = utils::get_block_hash(self.provider.deref(), block_number).await?;
+        let mut result = self.simulate_validation(op, Some(block_hash), None).await?;
+        result.block_number = Some(block_number);
+        Ok(result)
+    }
 }
@@ -20,10 +20,7 @@ use rundler_types::{
     EntityType, UserOperation as UserOperationTrait, ValidationOutput,
 };
 
-use super::{
-    tracer::{SimulateValidationTracer, SimulateValidationTracerImpl},
-    REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER,
-};
+use super::tracer::{SimulateValidationTracer, SimulateValidationTracerImpl};
 use crate::{
     simulation::context::{
         self as sim_context, ValidationContext,
@@ -162,10 +159,11 @@ where
         let verification_buffer = op
             .total_verification_gas_limit()
             .saturating_sub(verification_gas_used);
-        if verification_buffer < REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER {
+        let required_buffer = self.sim_settings.verification_gas_limit_buffer;
+        if verification_buffer < required_buffer {
             violations.push(SimulationViolation::VerificationGasLimitBufferTooLow(
                 op.total_verification_gas_limit(),
-                verification_gas_used + REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER,
+                verification_gas_used + required_buffer,
             ));
         }
 
@@ -205,7 +203,10 @@ mod tests {
     use sim_context::ContractInfo;
 
     use super::*;
-    use crate::simulation::context::{Phase, TracerOutput};
+    use crate::{
+        simulation::context::{AccessInfo, Phase, TracerOutput},
+        AccessedSlots, AccessedStorage,
+    };
 
     fn get_test_tracer_output() -> TracerOutput {
         TracerOutput {
@@ -357,4 +358,96 @@ mod tests {
             )
         ));
     }
+
+    #[tokio::test]
+    async fn test_verification_gas_limit_buffer() {
+        let mut tracer = MockTracer::new();
+        tracer
+            .expect_trace_simulate_validation()
+            .returning(|_, _| Ok(get_test_tracer_output()));
+        let mut context_provider = ValidationContextProvider {
+            simulate_validation_tracer: tracer,
+            sim_settings: Default::default(),
+        };
+        let is_buffer_too_low =
+            |context_provider: &ValidationContextProvider<MockTracer>,
+             context: &ValidationContext<UserOperation>| {
+                context_provider
+                    .get_specific_violations(context)
+                    .iter()
+                    .any(|v| matches!(v, SimulationViolation::VerificationGasLimitBufferTooLow(..)))
+            };
+
+        let op = UserOperation {
+            verification_gas_limit: U256::from(1_000_000),
+            ..Default::default()
+        };
+        let context = context_provider
+            .get_context(op, BlockId::Number(0.into()))
+            .await
+            .unwrap();
+
+        context_provider.sim_settings.verification_gas_limit_buffer = U256::zero();
+        assert!(!is_buffer_too_low(&context_provider, &context));
+        context_provider.sim_settings.verification_gas_limit_buffer = U256::from(1_000_000);
+        assert!(is_buffer_too_low(&context_provider, &context));
+    }
+
+    #[tokio::test]
+    async fn test_trace_accessed_storage() {
+        let contract = Address::random();
+        let mut tracer = MockTracer::new();
+        tracer
+            .expect_trace_simulate_validation()
+            .returning(move |_, _| {
+                let mut tracer_output = get_test_tracer_output();
+                tracer_output.phases[1].storage_accesses.insert(
+                    contract,
+                    AccessInfo {
+                        reads: HashMap::from([(U256::from(1), U256::from(10))]),
+                        writes: HashMap::from([(U256::from(2), 1)]),
+                    },
+                );
+                tracer_output.phases[2].storage_accesses.insert(
+                    contract,
+                    AccessInfo {
+                        reads: HashMap::from([
+                            (U256::from(1), U256::from(11)),
+                            (U256::from(3), U256::from(30)),
+                        ]),
+                        writes: HashMap::from([(U256::from(2), 2)]),
+                    },
+                );
+                Ok(tracer_output)
+            });
+
+        let accessed_storage = tracer
+            .trace_accessed_storage(UserOperation::default(), BlockId::Number(0.into()))
+            .await
+            .unwrap();
+
+        // reads keep the value seen by the first phase, writes are counted across phases
+        assert_eq!(
+            accessed_storage,
+            AccessedStorage(
+                [(
+                    contract,
+                    AccessedSlots {
+                        reads: [
+                            (U256::from(1), U256::from(10)),
+                            (U256::from(3), U256::from(30))
+                        ]
+                        .into(),
+                        writes: [(U256::from(2), 3)].into(),
+                    }
+                )]
+                .into()
+            )
+        );
+        assert_eq!(
+            accessed_storage.slots(contract).collect::<Vec<_>>(),
+            vec![U256::from(1), U256::from(3), U256::from(2)]
+        );
+        assert_eq!(accessed_storage.expected_storage().0[&contract].len(), 2);
+    }
 }
@@ -18,5 +18,5 @@ pub(crate) use context::ValidationContextProvider;
 
 mod tracer;
 
-/// Required buffer for verification gas limit when targeting the 0.6 entrypoint contract
+/// Default required buffer for verification gas limit when targeting the 0.6 entrypoint contract
 pub(crate) const REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER: U256 = U256([2000, 0, 0, 0]);
@@ -21,7 +21,7 @@ use rundler_provider::{Provider, SimulationProvider};
 use rundler_types::v0_6::UserOperation;
 use serde::Deserialize;
 
-use crate::simulation::context::TracerOutput;
+use crate::{simulation::context::TracerOutput, AccessedStorage};
 
 impl TryFrom<GethTrace> for TracerOutput {
     type Error = anyhow::Error;
@@ -44,6 +44,19 @@ pub(super) trait SimulateValidationTracer: Send + Sync + 'static {
         op: UserOperation,
         block_id: BlockId,
     ) -> anyhow::Result<TracerOutput>;
+
+    /// Traces the simulation of a user operation, returning all the storage slots
+    /// accessed during validation.
+    async fn trace_accessed_storage(
+        &self,
+        op: UserOperation,
+        block_id: BlockId,
+    ) -> anyhow::Result<AccessedStorage> {
+        Ok(self
+            .trace_simulate_validation(op, block_id)
+            .await?
+            .accessed_storage())
+    }
 }
 
 /// Tracer implementation for the bundler's custom tracer.
@@ -59,6 +59,45 @@ impl ExpectedStorage {
     }
 }
 
+/// The storage slots accessed by a user operation during validation, by the
+/// address of the contract owning them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AccessedStorage(pub BTreeMap<Address, AccessedSlots>);
+
+/// The storage slots of a contract accessed during validation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AccessedSlots {
+    /// Slots read before being written, with their value prior to validation
+    pub reads: BTreeMap<U256, U256>,
+    /// Slots written, with their number of writes
+    pub writes: BTreeMap<U256, u64>,
+}
+
+impl AccessedStorage {
+    /// Returns the addresses of the slots accessed in `address`, read or written
+    pub fn slots(&self, address: Address) -> impl Iterator<Item = U256> + '_ {
+        self.0.get(&address).into_iter().flat_map(|slots| {
+            slots
+                .reads
+                .keys()
+                .chain(slots.writes.keys().filter(|s| !slots.reads.contains_key(s)))
+                .copied()
+        })
+    }
+
+    /// Returns the values of the read slots prior to validation, which validation
+    /// depends on
+    pub fn expected_storage(&self) -> ExpectedStorage {
+        let mut expected_storage = ExpectedStorage::default();
+        for (&address, slots) in &self.0 {
+            for (&slot, &value) in &slots.reads {
+                expected_storage.insert(address, slot, value);
+            }
+        }
+        expected_storage
+    }
+}
+
 use std::fmt::{Display, Formatter};
 
 /// An error that occurs when a user operation violates a spec rule.
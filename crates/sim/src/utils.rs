@@ -16,6 +16,18 @@ use ethers::types::{spoof, Address, BlockId, H256};
 use rundler_provider::Provider;
 use rundler_types::contracts::utils::get_code_hashes::{CodeHashesResult, GETCODEHASHES_BYTECODE};
 
+/// Returns the hash of the block with number `block_number`
+pub(crate) async fn get_block_hash<P: Provider>(
+    provider: &P,
+    block_number: u64,
+) -> anyhow::Result<H256> {
+    provider
+        .get_block(block_number)
+        .await?
+        .and_then(|block| block.hash)
+        .with_context(|| format!("block {block_number} should exist"))
+}
+
 /// Hashes together the code from all the provided addresses. The order of the input addresses does
 /// not matter.
 pub(crate) async fn get_code_hash<P: Provider>(
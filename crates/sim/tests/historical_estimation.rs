@@ -0,0 +1,180 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Regression tests estimating historical mainnet user operations against the
+//! state of the block they were estimated at, and comparing the estimates to
+//! recorded values.
+//!
+//! Requires an archive node, so is ignored by default. Run with:
+//!
+//! ```text
+//! ARCHIVE_RPC_URL=<url> cargo test -p rundler-sim --test historical_estimation -- --ignored
+//! ```
+//!
+//! Set `RECORD_HISTORICAL_ESTIMATES=1` to overwrite the recorded estimates with the
+//! current ones instead of comparing, after verifying that a change in the estimates
+//! is expected.
+//!
+//! Cases are added to the fixture by hand with a name, block number and operation,
+//! and their estimates recorded with `RECORD_HISTORICAL_ESTIMATES=1`. The test fails
+//! if the fixture has no cases.
+
+use std::{env, fs, path::PathBuf, sync::Arc, time::Duration};
+
+use ethers::types::U256;
+use rundler_provider::{new_provider, EthersEntryPointV0_6, PinnedProvider};
+use rundler_sim::{
+    EstimationOptions, EstimationSettings, FeeEstimator, GasEstimator, GasEstimatorV0_6,
+    PriorityFeeMode,
+};
+use rundler_types::{chain::ChainSpec, v0_6::UserOperationOptionalGas, GasEstimate};
+use serde::{Deserialize, Serialize};
+
+const FIXTURE: &str = "tests/data/historical_estimates.json";
+
+/// A historical user operation and the estimate recorded for it
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Case {
+    name: String,
+    /// Block whose state the operation is estimated against
+    block_number: u64,
+    op: UserOperationOptionalGas,
+    /// Recorded estimate, unset until first recorded
+    expected: Option<RecordedEstimate>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordedEstimate {
+    pre_verification_gas: U256,
+    call_gas_limit: U256,
+    verification_gas_limit: U256,
+}
+
+impl From<GasEstimate> for RecordedEstimate {
+    fn from(estimate: GasEstimate) -> Self {
+        Self {
+            pre_verification_gas: estimate.pre_verification_gas,
+            call_gas_limit: estimate.call_gas_limit,
+            verification_gas_limit: estimate.verification_gas_limit,
+        }
+    }
+}
+
+/// Mainnet defaults, with a longer timeout for slow archive nodes
+fn settings() -> EstimationSettings {
+    EstimationSettings {
+        estimation_timeout: Duration::from_secs(60),
+        ..EstimationSettings::preset_for_chain(1)
+    }
+}
+
+/// Returns the differences between the recorded and the actual estimate
+fn compare(expected: RecordedEstimate, actual: RecordedEstimate) -> Vec<String> {
+    [
+        (
+            "preVerificationGas",
+            expected.pre_verification_gas,
+            actual.pre_verification_gas,
+        ),
+        (
+            "callGasLimit",
+            expected.call_gas_limit,
+            actual.call_gas_limit,
+        ),
+        (
+            "verificationGasLimit",
+            expected.verification_gas_limit,
+            actual.verification_gas_limit,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, expected, actual)| expected != actual)
+    .map(|(field, expected, actual)| format!("{field}: expected {expected}, got {actual}"))
+    .collect()
+}
+
+async fn estimate(rpc_url: &str, case: &Case) -> anyhow::Result<RecordedEstimate> {
+    let chain_spec = ChainSpec::default();
+    let settings = settings();
+    let provider = new_provider(rpc_url, None)?;
+    let pinned = Arc::new(PinnedProvider::new(Arc::clone(&provider), case.block_number).await?);
+    let entry_point = EthersEntryPointV0_6::new(
+        chain_spec.entry_point_address_v0_6,
+        &chain_spec,
+        settings.max_simulate_handle_ops_gas,
+        provider,
+    );
+    let fee_estimator = FeeEstimator::new(
+        &chain_spec,
+        Arc::clone(&pinned),
+        PriorityFeeMode::PriorityFeeIncreasePercent(0),
+        0,
+    );
+    let estimator = GasEstimatorV0_6::new(chain_spec, pinned, entry_point, settings, fee_estimator);
+
+    let estimate = estimator
+        .estimate_op_gas(
+            case.op.clone(),
+            Default::default(),
+            EstimationOptions::default(),
+        )
+        .await?;
+    Ok(estimate.into())
+}
+
+#[tokio::test]
+#[ignore = "requires an archive node at ARCHIVE_RPC_URL"]
+async fn test_historical_estimates() {
+    let rpc_url = env::var("ARCHIVE_RPC_URL").expect("ARCHIVE_RPC_URL should be set");
+    let record = env::var("RECORD_HISTORICAL_ESTIMATES").is_ok_and(|v| v == "1");
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(FIXTURE);
+    let mut cases: Vec<Case> = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert!(
+        !cases.is_empty(),
+        "{FIXTURE} has no cases, add historical operations to it and record their estimates with RECORD_HISTORICAL_ESTIMATES=1"
+    );
+
+    let mut failures = vec![];
+    for case in &mut cases {
+        let actual = match estimate(&rpc_url, case).await {
+            Ok(actual) => actual,
+            Err(error) => {
+                failures.push(format!("{}: estimation failed: {error:#}", case.name));
+                continue;
+            }
+        };
+        if record {
+            case.expected = Some(actual);
+            continue;
+        }
+        match case.expected {
+            Some(expected) => failures.extend(
+                compare(expected, actual)
+                    .into_iter()
+                    .map(|diff| format!("{}: {diff}", case.name)),
+            ),
+            None => failures.push(format!("{}: no recorded estimate", case.name)),
+        }
+    }
+
+    if record {
+        fs::write(&path, serde_json::to_string_pretty(&cases).unwrap() + "\n").unwrap();
+    }
+    assert!(
+        failures.is_empty(),
+        "historical estimates differ:\n{}",
+        failures.join("\n")
+    );
+}
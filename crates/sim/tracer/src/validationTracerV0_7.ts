@@ -362,8 +362,9 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
       }
 
       // [OP-012]
-      if (this.lastOp === 'GAS' && !opcode.includes('CALL')) {
-        // count "GAS" opcode only if not followed by "CALL"
+      // MODIFICATION: match the call opcodes exactly, as CALLER and CALLDATA* also include 'CALL'
+      if (this.lastOp === 'GAS' && opcode.match(/^(CALL|CALLCODE|DELEGATECALL|STATICCALL)$/) == null) {
+        // count "GAS" opcode only if not followed by a call
         this.countSlot(this.currentLevel.opcodes, 'GAS')
       }
       if (opcode !== 'GAS') {
@@ -0,0 +1,90 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use ethers::types::{spoof, Address, Bytes, U256};
+use serde::{Deserialize, Serialize};
+
+/// Prefix of the code of an EOA delegated with EIP-7702, followed by the address
+/// of the delegate
+const DELEGATION_DESIGNATOR_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+
+/// An EIP-7702 authorization, by which an EOA delegates its code to a contract
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Eip7702Auth {
+    /// Chain ID the authorization is valid on, zero for any chain
+    pub chain_id: U256,
+    /// Address of the contract the EOA delegates to
+    pub address: Address,
+    /// Nonce of the EOA the authorization is valid at
+    pub nonce: U256,
+    /// Signature y parity
+    pub y_parity: U256,
+    /// Signature r value
+    pub r: U256,
+    /// Signature s value
+    pub s: U256,
+}
+
+impl Eip7702Auth {
+    /// Returns whether the authorization is valid on the chain with ID `chain_id`
+    pub fn is_valid_on_chain(&self, chain_id: u64) -> bool {
+        self.chain_id.is_zero() || self.chain_id == U256::from(chain_id)
+    }
+
+    /// Returns the code of an EOA once this authorization is applied, the
+    /// delegation designator pointing to the delegate
+    pub fn delegation_code(&self) -> Bytes {
+        [&DELEGATION_DESIGNATOR_PREFIX[..], self.address.as_bytes()]
+            .concat()
+            .into()
+    }
+
+    /// Overrides the code of `sender` in `state_override` with the delegation
+    /// designator, as if this authorization had been applied
+    pub fn apply_to(&self, sender: Address, state_override: &mut spoof::State) {
+        state_override.account(sender).code(self.delegation_code());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delegation_code() {
+        let auth = Eip7702Auth {
+            address: Address::random(),
+            ..Default::default()
+        };
+
+        let code = auth.delegation_code();
+        assert_eq!(code.len(), 23);
+        assert_eq!(code[..3], [0xef, 0x01, 0x00]);
+        assert_eq!(&code[3..], auth.address.as_bytes());
+    }
+
+    #[test]
+    fn test_valid_on_chain() {
+        let any_chain = Eip7702Auth::default();
+        assert!(any_chain.is_valid_on_chain(1));
+        assert!(any_chain.is_valid_on_chain(10));
+
+        let mainnet = Eip7702Auth {
+            chain_id: U256::one(),
+            ..Default::default()
+        };
+        assert!(mainnet.is_valid_on_chain(1));
+        assert!(!mainnet.is_valid_on_chain(10));
+    }
+}
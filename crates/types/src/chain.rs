@@ -21,6 +21,15 @@ use serde::{Deserialize, Serialize};
 const ENTRY_POINT_ADDRESS_V6_0: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
 const ENTRY_POINT_ADDRESS_V7_0: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
 
+/// Chain IDs of Arbitrum One and Arbitrum Sepolia
+pub const ARBITRUM_CHAIN_IDS: [u64; 2] = [42161, 421614];
+/// Chain IDs of the Avalanche C-Chain and its Fuji testnet
+pub const AVALANCHE_CHAIN_IDS: [u64; 2] = [43114, 43113];
+/// Chain IDs of Base and Base Sepolia
+pub const BASE_CHAIN_IDS: [u64; 2] = [8453, 84532];
+/// Chain IDs of Polygon PoS and its Amoy testnet
+pub const POLYGON_CHAIN_IDS: [u64; 2] = [137, 80002];
+
 /// Chain specification for Rundler
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ChainSpec {
@@ -72,6 +81,10 @@ pub struct ChainSpec {
     /// true if L1 calldata gas should be included in the gas limit
     /// only applies when calldata_pre_verification_gas is true
     pub include_l1_gas_in_gas_limit: bool,
+    /// percentage of the configured verification estimation gas fee to use on this chain.
+    /// Chains with cheap gas can lower this to reduce the balance the fee-payer must hold
+    /// during verification gas estimation.
+    pub verification_estimation_gas_fee_percent: u64,
 
     /*
      * Fee estimation
@@ -162,6 +175,7 @@ impl Default for ChainSpec {
             l1_gas_oracle_contract_type: L1GasOracleContractType::default(),
             l1_gas_oracle_contract_address: Address::zero(),
             include_l1_gas_in_gas_limit: true,
+            verification_estimation_gas_fee_percent: 100,
             priority_fee_oracle_type: PriorityFeeOracleType::default(),
             min_max_priority_fee_per_gas: U256::zero(),
             max_max_priority_fee_per_gas: U256::MAX,
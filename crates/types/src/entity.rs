@@ -150,6 +150,22 @@ impl TryFrom<i32> for EntityUpdateType {
     }
 }
 
+/// Why an entity was rejected from a bundle
+#[derive(Display, Debug, Clone, Ord, Copy, Eq, PartialEq, EnumIter, PartialOrd, Deserialize)]
+#[display(style = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub enum EntityUpdateReason {
+    /// An op of the entity violated the validation rules, or reverted in validation,
+    /// during simulation
+    SimulationFailed,
+    /// An op of the entity failed validation during the bundle's gas estimation
+    GasEstimationFailed,
+    /// The aggregator failed to validate the bundle's aggregated signature
+    SignatureValidationFailed,
+    /// The paymaster's deposit could not cover its ops in the bundle
+    InsufficientDeposit,
+}
+
 /// A update that needs to be applied to an entity
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct EntityUpdate {
@@ -157,6 +173,8 @@ pub struct EntityUpdate {
     pub entity: Entity,
     /// The kind of update to perform for the entity
     pub update_type: EntityUpdateType,
+    /// Why the entity was rejected from a bundle
+    pub reason: EntityUpdateReason,
 }
 
 /// additional context about an entity
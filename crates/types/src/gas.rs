@@ -30,7 +30,14 @@ impl From<&TypedTransaction> for GasFees {
                 max_fee_per_gas: tx.max_fee_per_gas.unwrap_or_default(),
                 max_priority_fee_per_gas: tx.max_priority_fee_per_gas.unwrap_or_default(),
             },
-            _ => Self::default(),
+            // a legacy gas price is both the max fee and the priority fee
+            TypedTransaction::Legacy(_) | TypedTransaction::Eip2930(_) => {
+                let gas_price = tx.gas_price().unwrap_or_default();
+                Self {
+                    max_fee_per_gas: gas_price,
+                    max_priority_fee_per_gas: gas_price,
+                }
+            }
         }
     }
 }
@@ -46,4 +53,71 @@ impl GasFees {
             ),
         }
     }
+
+    /// Increase the gas fees by a percentage, to at least the given minimum fees.
+    ///
+    /// The max fee is never lower than the priority fee, so that raising the
+    /// priority fee to its minimum keeps the fees valid.
+    pub fn increase_by_percent_with_floor(
+        self,
+        percent: u64,
+        min_priority_fee: U256,
+        min_max_fee: U256,
+    ) -> Self {
+        let increased = self.increase_by_percent(percent);
+        let max_priority_fee_per_gas = increased.max_priority_fee_per_gas.max(min_priority_fee);
+        Self {
+            max_fee_per_gas: increased
+                .max_fee_per_gas
+                .max(min_max_fee)
+                .max(max_priority_fee_per_gas),
+            max_priority_fee_per_gas,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_increase_by_percent_with_floor_below_floor() {
+        let fees = GasFees {
+            max_fee_per_gas: U256::from(100),
+            max_priority_fee_per_gas: U256::from(1),
+        };
+
+        // a 10% increase of a priority fee of 1 wei stays under the floor
+        let increased = fees.increase_by_percent_with_floor(10, U256::from(50), U256::from(150));
+        assert_eq!(
+            increased,
+            GasFees {
+                max_fee_per_gas: U256::from(150),
+                max_priority_fee_per_gas: U256::from(50),
+            }
+        );
+    }
+
+    #[test]
+    fn test_increase_by_percent_with_floor_above_floor() {
+        let fees = GasFees {
+            max_fee_per_gas: U256::from(100),
+            max_priority_fee_per_gas: U256::from(10),
+        };
+
+        let increased = fees.increase_by_percent_with_floor(10, U256::from(5), U256::from(50));
+        assert_eq!(increased, fees.increase_by_percent(10));
+    }
+
+    #[test]
+    fn test_increase_by_percent_with_floor_max_fee_covers_priority_fee() {
+        let fees = GasFees {
+            max_fee_per_gas: U256::from(10),
+            max_priority_fee_per_gas: U256::from(1),
+        };
+
+        let increased = fees.increase_by_percent_with_floor(10, U256::from(100), U256::zero());
+        assert_eq!(increased.max_priority_fee_per_gas, U256::from(100));
+        assert_eq!(increased.max_fee_per_gas, U256::from(100));
+    }
 }
@@ -20,6 +20,9 @@
 
 //! Rundler common types
 
+mod authorization;
+pub use authorization::Eip7702Auth;
+
 pub mod builder;
 
 pub mod chain;
@@ -28,7 +31,9 @@ pub mod chain;
 pub mod contracts;
 
 mod entity;
-pub use entity::{Entity, EntityInfo, EntityInfos, EntityType, EntityUpdate, EntityUpdateType};
+pub use entity::{
+    Entity, EntityInfo, EntityInfos, EntityType, EntityUpdate, EntityUpdateReason, EntityUpdateType,
+};
 
 mod opcode;
 pub use opcode::{Opcode, ViolationOpCode};
@@ -38,6 +43,8 @@ pub use gas::GasFees;
 
 pub mod pool;
 
+pub mod rpc;
+
 mod timestamp;
 pub use timestamp::{Timestamp, ValidTimeRange};
 
@@ -50,5 +57,5 @@ pub use storage::StorageSlot;
 mod validation_results;
 pub use validation_results::{
     parse_validation_data, AggregatorInfo, StakeInfo, ValidationError, ValidationOutput,
-    ValidationReturnInfo, ValidationRevert,
+    ValidationReturnInfo, ValidationRevert, ValidationRevertReason,
 };
@@ -175,8 +175,9 @@ pub enum SimulationViolation {
     /// The user operation factory entity called CREATE2 more than once during initialization
     #[display("factory may only call CREATE2 once during initialization")]
     FactoryCalledCreate2Twice(Address),
-    /// The user operation accessed a storage slot that is not allowed
-    #[display("{0.kind} accessed forbidden storage at address {1:?} during validation")]
+    /// The user operation accessed a storage slot that is not allowed, with the
+    /// entity that accessed it and the slot accessed
+    #[display("{0.kind} {0.address:?} accessed forbidden storage at address {1.address:?} slot {1.slot:#x} during validation")]
     InvalidStorageAccess(Entity, StorageSlot),
     /// The user operation accessed a storage slot on the sender while being deployed
     /// and the accessing entity or the factory is not staked
@@ -228,6 +229,9 @@ pub enum SimulationViolation {
     /// Unsupported contract type
     #[display("accessed unsupported contract type: {0:?} at {1:?}. Address must be whitelisted")]
     AccessedUnsupportedContractType(String, Address),
+    /// The user operation uses an aggregator that is not supported
+    #[display("aggregator {0:?} is not supported")]
+    UnsupportedAggregator(Address),
 }
 
 /// Information about a storage violation based on stake status
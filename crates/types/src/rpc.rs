@@ -0,0 +1,111 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! JSON-RPC error codes and error data shared by the crates that produce RPC errors
+
+use std::fmt::{self, Display};
+
+use ethers::types::Bytes;
+use serde::Serialize;
+
+use crate::ValidationRevert;
+
+/// Invalid method parameters, defined by JSON-RPC
+pub const INVALID_PARAMS_CODE: i32 = -32602;
+/// Internal JSON-RPC error, defined by JSON-RPC
+pub const INTERNAL_ERROR_CODE: i32 = -32603;
+
+/// Validation rejected by the entry point or an entity, defined by ERC-4337
+pub const ENTRYPOINT_VALIDATION_REJECTED_CODE: i32 = -32500;
+/// Validation rejected by the paymaster, defined by ERC-4337
+pub const PAYMASTER_VALIDATION_REJECTED_CODE: i32 = -32501;
+/// Banned opcode or storage access used during validation, defined by ERC-4337
+pub const OPCODE_VIOLATION_CODE: i32 = -32502;
+/// Operation out of its valid time range, defined by ERC-4337
+pub const OUT_OF_TIME_RANGE_CODE: i32 = -32503;
+/// Entity throttled or banned, defined by ERC-4337
+pub const THROTTLED_OR_BANNED_CODE: i32 = -32504;
+/// Entity stake or unstake delay too low, defined by ERC-4337
+pub const STAKE_TOO_LOW_CODE: i32 = -32505;
+/// Unsupported signature aggregator, defined by ERC-4337
+pub const UNSUPPORTED_AGGREGATOR_CODE: i32 = -32506;
+/// Invalid signature, defined by ERC-4337
+pub const SIGNATURE_CHECK_FAILED_CODE: i32 = -32507;
+/// Paymaster deposit too low, defined by ERC-4337
+pub const PAYMASTER_DEPOSIT_TOO_LOW_CODE: i32 = -32508;
+/// Operation's call reverted, defined by ERC-4337
+pub const EXECUTION_REVERTED_CODE: i32 = -32521;
+
+/// Data of a validation revert error
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationRevertData {
+    /// Reason returned by the entry point
+    pub reason: Option<String>,
+    /// The entry point's `AAxx` code, if known
+    pub code: Option<String>,
+    /// Reason parsed from the reverting entity's revert data
+    pub inner_reason: Option<String>,
+    /// Revert data of the reverting entity
+    pub revert_data: Option<Bytes>,
+}
+
+impl Display for ValidationRevertData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(reason) = &self.reason {
+            write!(f, "[reason]: {}", reason)?;
+        }
+        if let Some(inner_reason) = &self.inner_reason {
+            write!(f, " | [inner reason]: {}", inner_reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<ValidationRevert> for ValidationRevertData {
+    fn from(value: ValidationRevert) -> Self {
+        let code = value.reason().map(|reason| reason.to_string());
+        match value {
+            ValidationRevert::EntryPoint(reason) => Self {
+                reason: Some(reason),
+                code,
+                inner_reason: None,
+                revert_data: None,
+            },
+            ValidationRevert::Operation {
+                entry_point_reason,
+                inner_revert_data,
+                inner_revert_reason,
+            } => Self {
+                reason: Some(entry_point_reason),
+                code,
+                inner_reason: inner_revert_reason,
+                revert_data: Some(inner_revert_data),
+            },
+            ValidationRevert::Unknown(data) => Self {
+                reason: None,
+                code,
+                inner_reason: None,
+                revert_data: Some(data),
+            },
+        }
+    }
+}
+
+/// Data of an execution revert error whose revert data could not be decoded
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionRevertedWithBytesData {
+    /// Revert data of the operation's call
+    pub revert_data: Bytes,
+}
@@ -90,6 +90,12 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     /// Get the user operation calldata
     fn call_data(&self) -> &Bytes;
 
+    /// Get the user operation paymaster and data, packed as sent to the entry point
+    fn paymaster_and_data(&self) -> &Bytes;
+
+    /// Get the user operation signature
+    fn signature(&self) -> &Bytes;
+
     /// Returns the call gas limit
     fn call_gas_limit(&self) -> U256;
 
@@ -156,6 +162,17 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     fn single_uo_bundle_size_bytes(&self) -> usize {
         self.abi_encoded_size() + BUNDLE_BYTE_OVERHEAD + USER_OP_OFFSET_WORD_SIZE
     }
+
+    /// Returns the name and size of the first of the `paymasterAndData` and
+    /// `signature` fields that is longer than `max_size` bytes, if any
+    fn oversized_field(&self, max_size: usize) -> Option<(&'static str, usize)> {
+        [
+            ("paymasterAndData", self.paymaster_and_data().len()),
+            ("signature", self.signature().len()),
+        ]
+        .into_iter()
+        .find(|(_, size)| *size > max_size)
+    }
 }
 
 /// User operation enum
@@ -223,6 +240,20 @@ impl UserOperation for UserOperationVariant {
         }
     }
 
+    fn paymaster_and_data(&self) -> &Bytes {
+        match self {
+            UserOperationVariant::V0_6(op) => op.paymaster_and_data(),
+            UserOperationVariant::V0_7(op) => op.paymaster_and_data(),
+        }
+    }
+
+    fn signature(&self) -> &Bytes {
+        match self {
+            UserOperationVariant::V0_6(op) => op.signature(),
+            UserOperationVariant::V0_7(op) => op.signature(),
+        }
+    }
+
     fn max_gas_cost(&self) -> U256 {
         match self {
             UserOperationVariant::V0_6(op) => op.max_gas_cost(),
@@ -365,6 +396,52 @@ impl UserOperationOptionalGas {
         };
         abi_size + BUNDLE_BYTE_OVERHEAD + USER_OP_OFFSET_WORD_SIZE
     }
+
+    /// Returns the classification of the user operation
+    pub fn classification(&self) -> OpClassification {
+        match self {
+            UserOperationOptionalGas::V0_6(op) => op.classification(),
+            UserOperationOptionalGas::V0_7(op) => op.classification(),
+        }
+    }
+}
+
+/// What a user operation does, derived from which of its fields are set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpClassification {
+    /// Whether the operation deploys its sender, makes a call, or both
+    pub kind: OpKind,
+    /// Whether the operation's gas is paid for by a paymaster
+    pub paymaster_sponsored: bool,
+}
+
+impl OpClassification {
+    /// Classifies an operation by whether it has init code, call data, and a paymaster
+    pub fn new(has_init_code: bool, has_call_data: bool, has_paymaster: bool) -> Self {
+        let kind = match (has_init_code, has_call_data) {
+            (true, true) => OpKind::DeploymentAndCall,
+            (true, false) => OpKind::Deployment,
+            (false, true) => OpKind::Call,
+            (false, false) => OpKind::Empty,
+        };
+        Self {
+            kind,
+            paymaster_sponsored: has_paymaster,
+        }
+    }
+}
+
+/// Whether a user operation deploys its sender, makes a call, or both
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpKind {
+    /// Only deploys the sender, with empty call data
+    Deployment,
+    /// Only calls an already deployed sender
+    Call,
+    /// Deploys the sender and then calls it
+    DeploymentAndCall,
+    /// Neither deploys the sender nor calls it, only validating it
+    Empty,
 }
 
 /// Gas estimate
@@ -382,6 +459,23 @@ pub struct GasEstimate {
     ///
     /// v0.7: populated only if the user operation has a paymaster
     pub paymaster_verification_gas_limit: Option<U256>,
+    /// Paymaster post op gas limit
+    ///
+    /// v0.6: unused
+    ///
+    /// v0.7: populated only if the user operation has a paymaster. Not estimated,
+    /// this is the limit the estimate was made with: the one provided, else zero.
+    pub paymaster_post_op_gas_limit: Option<U256>,
+    /// Classification of the estimated user operation
+    pub classification: OpClassification,
+    /// Gas used by the `simulateHandleOp` call made at the maximum verification gas,
+    /// the gas the whole op consumed in simulation. Compare to the maximum gas of
+    /// a `simulateHandleOps` call to find ops that barely fit under it.
+    ///
+    /// Unset if the verification gas was not estimated, having been provided on the
+    /// op. For v0.7 ops with a paymaster, the larger of the account and paymaster
+    /// verification estimations.
+    pub simulate_handle_ops_gas_used: Option<U256>,
 }
 
 /// User operations per aggregator
@@ -436,6 +530,27 @@ pub(crate) fn default_if_none_or_equal<V: Copy + PartialEq>(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_op_classification() {
+        let cases = [
+            (true, true, OpKind::DeploymentAndCall),
+            (true, false, OpKind::Deployment),
+            (false, true, OpKind::Call),
+            (false, false, OpKind::Empty),
+        ];
+        for (init_code, call_data, kind) in cases {
+            for paymaster in [false, true] {
+                assert_eq!(
+                    OpClassification::new(init_code, call_data, paymaster),
+                    OpClassification {
+                        kind,
+                        paymaster_sponsored: paymaster,
+                    }
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_byte_array_abi_len() {
         let b = Bytes::from(vec![0u8; 32]);
@@ -20,12 +20,14 @@ use rand::{self, RngCore};
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 
-use super::{UserOperation as UserOperationTrait, UserOperationId, UserOperationVariant};
+use super::{
+    OpClassification, UserOperation as UserOperationTrait, UserOperationId, UserOperationVariant,
+};
 pub use crate::contracts::v0_6::i_entry_point::{UserOperation, UserOpsPerAggregator};
 use crate::{
     chain::ChainSpec,
     entity::{Entity, EntityType},
-    EntryPointVersion,
+    Eip7702Auth, EntryPointVersion,
 };
 
 /// Gas overhead required by the entry point contract for the inner call
@@ -90,6 +92,14 @@ impl UserOperationTrait for UserOperation {
         &self.call_data
     }
 
+    fn paymaster_and_data(&self) -> &Bytes {
+        &self.paymaster_and_data
+    }
+
+    fn signature(&self) -> &Bytes {
+        &self.signature
+    }
+
     fn max_gas_cost(&self) -> U256 {
         let mul = if self.paymaster().is_some() { 3 } else { 1 };
         self.max_fee_per_gas
@@ -286,9 +296,21 @@ pub struct UserOperationOptionalGas {
     pub paymaster_and_data: Bytes,
     /// Signature (required, dummy value for gas estimation)
     pub signature: Bytes,
+    /// EIP-7702 authorization delegating the code of an EOA sender, applied to the
+    /// sender for gas estimation (optional)
+    pub eip7702_auth: Option<Eip7702Auth>,
 }
 
 impl UserOperationOptionalGas {
+    /// Classifies the user operation by which of its fields are set
+    pub fn classification(&self) -> OpClassification {
+        OpClassification::new(
+            !self.init_code.is_empty(),
+            !self.call_data.is_empty(),
+            !self.paymaster_and_data.is_empty(),
+        )
+    }
+
     /// Fill in the optional and dummy fields of the user operation with values
     /// that will cause the maximum possible calldata gas cost.
     pub fn max_fill(&self, max_call_gas: U256, max_verification_gas: U256) -> UserOperation {
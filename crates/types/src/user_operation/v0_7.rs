@@ -18,9 +18,12 @@ use ethers::{
 };
 use rand::RngCore;
 
-use super::{UserOperation as UserOperationTrait, UserOperationId, UserOperationVariant};
+use super::{
+    OpClassification, UserOperation as UserOperationTrait, UserOperationId, UserOperationVariant,
+};
 use crate::{
-    chain::ChainSpec, contracts::v0_7::shared_types::PackedUserOperation, Entity, EntryPointVersion,
+    chain::ChainSpec, contracts::v0_7::shared_types::PackedUserOperation, Eip7702Auth, Entity,
+    EntryPointVersion,
 };
 
 /// Gas overhead required by the entry point contract for the inner call
@@ -137,6 +140,14 @@ impl UserOperationTrait for UserOperation {
         &self.call_data
     }
 
+    fn paymaster_and_data(&self) -> &Bytes {
+        &self.packed.paymaster_and_data
+    }
+
+    fn signature(&self) -> &Bytes {
+        &self.signature
+    }
+
     fn max_gas_cost(&self) -> U256 {
         U256::from(self.max_fee_per_gas)
             * (self.pre_verification_gas
@@ -335,9 +346,21 @@ pub struct UserOperationOptionalGas {
     pub paymaster_post_op_gas_limit: Option<U128>,
     /// Paymaster data
     pub paymaster_data: Bytes,
+    /// EIP-7702 authorization delegating the code of an EOA sender, applied to the
+    /// sender for gas estimation
+    pub eip7702_auth: Option<Eip7702Auth>,
 }
 
 impl UserOperationOptionalGas {
+    /// Classifies the user operation by which of its fields are set
+    pub fn classification(&self) -> OpClassification {
+        OpClassification::new(
+            self.factory.is_some(),
+            !self.call_data.is_empty(),
+            self.paymaster.is_some(),
+        )
+    }
+
     /// Fill in the optional and dummy fields of the user operation with values
     /// that will cause the maximum possible calldata gas cost.
     pub fn max_fill(&self, chain_spec: &ChainSpec) -> UserOperation {
@@ -866,6 +889,7 @@ mod tests {
     use ethers::utils::hex::{self, FromHex};
 
     use super::*;
+    use crate::OpKind;
 
     #[test]
     fn test_pack_unpack() {
@@ -987,4 +1011,53 @@ mod tests {
         assert_eq!(uo.paymaster_verification_gas_limit, 10.into());
         assert_eq!(uo.paymaster_post_op_gas_limit, 20.into());
     }
+
+    #[test]
+    fn test_optional_gas_classification() {
+        let op = UserOperationOptionalGas {
+            sender: Address::zero(),
+            nonce: U256::zero(),
+            call_data: Bytes::new(),
+            signature: Bytes::new(),
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            pre_verification_gas: None,
+            max_priority_fee_per_gas: None,
+            max_fee_per_gas: None,
+            factory: None,
+            factory_data: Bytes::new(),
+            paymaster: None,
+            paymaster_verification_gas_limit: None,
+            paymaster_post_op_gas_limit: None,
+            paymaster_data: Bytes::new(),
+            eip7702_auth: None,
+        };
+        assert_eq!(op.classification().kind, OpKind::Empty);
+
+        let deployment = UserOperationOptionalGas {
+            factory: Some(Address::random()),
+            ..op.clone()
+        };
+        assert_eq!(deployment.classification().kind, OpKind::Deployment);
+
+        let call = UserOperationOptionalGas {
+            call_data: Bytes::from(vec![1]),
+            ..op.clone()
+        };
+        assert_eq!(call.classification().kind, OpKind::Call);
+
+        let sponsored_deployment_and_call = UserOperationOptionalGas {
+            factory: Some(Address::random()),
+            call_data: Bytes::from(vec![1]),
+            paymaster: Some(Address::random()),
+            ..op
+        };
+        assert_eq!(
+            sponsored_deployment_and_call.classification(),
+            OpClassification {
+                kind: OpKind::DeploymentAndCall,
+                paymaster_sponsored: true,
+            }
+        );
+    }
 }
@@ -11,12 +11,16 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::ops::Add;
+use std::{
+    fmt::{self, Display},
+    ops::Add,
+};
 
 use ethers::{
     abi::{self, AbiDecode, AbiError},
     types::{Address, Bytes, H160, U256},
 };
+use num_enum::TryFromPrimitive;
 use rundler_utils::eth::ContractRevertError;
 
 use crate::{
@@ -78,6 +82,13 @@ impl ValidationRevert {
             .map(|m| &m[..4])
     }
 
+    /// Decodes the entry point's error code into a typed reason, if it is a known
+    /// `AAxx` code
+    pub fn reason(&self) -> Option<ValidationRevertReason> {
+        let code = self.entry_point_error_code()?.strip_prefix("AA")?;
+        ValidationRevertReason::try_from(code.parse::<u8>().ok()?).ok()
+    }
+
     fn display_operation_error(
         entry_point_message: &str,
         inner_message: &Option<String>,
@@ -89,6 +100,92 @@ impl ValidationRevert {
     }
 }
 
+/// Reason an operation failed validation in the entry point, decoded from the
+/// `AAxx` code prefixing the reason of its `FailedOp` revert. The discriminant is
+/// the number of the code.
+///
+/// The first digit of the code identifies the entity at fault: 1 for the factory,
+/// 2 for the account, 3 for the paymaster, 4 and 5 for the gas limits and 9 for the
+/// bundle itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[repr(u8)]
+pub enum ValidationRevertReason {
+    /// AA10: the op has init code but its sender is already deployed
+    SenderAlreadyConstructed = 10,
+    /// AA13: the factory call reverted or ran out of gas
+    InitCodeFailed = 13,
+    /// AA14: the factory returned a different address than the sender
+    InitCodeWrongSender = 14,
+    /// AA15: the factory did not deploy code at the sender
+    InitCodeDidNotDeploySender = 15,
+    /// AA20: the sender is not deployed and the op has no init code
+    AccountNotDeployed = 20,
+    /// AA21: the account did not pay its prefund
+    AccountPrefundNotPaid = 21,
+    /// AA22: the account's signature expired or is not yet valid
+    AccountExpiredOrNotDue = 22,
+    /// AA23: the account's validation reverted or ran out of gas
+    AccountReverted = 23,
+    /// AA24: the account's signature is invalid
+    AccountSignatureError = 24,
+    /// AA25: the op's nonce is invalid for the account
+    InvalidAccountNonce = 25,
+    /// AA26: the account's validation used more than the verification gas limit
+    OverVerificationGasLimit = 26,
+    /// AA30: the paymaster is not deployed
+    PaymasterNotDeployed = 30,
+    /// AA31: the paymaster's deposit does not cover the op's prefund
+    PaymasterDepositTooLow = 31,
+    /// AA32: the paymaster's signature expired or is not yet valid
+    PaymasterExpiredOrNotDue = 32,
+    /// AA33: the paymaster's validation reverted or ran out of gas
+    PaymasterReverted = 33,
+    /// AA34: the paymaster's signature is invalid
+    PaymasterSignatureError = 34,
+    /// AA36: the paymaster's validation used more than its verification gas limit
+    OverPaymasterVerificationGasLimit = 36,
+    /// AA40: validation used more than the verification gas limit
+    OverVerificationGas = 40,
+    /// AA41: too little verification gas was left for the paymaster's validation
+    TooLittleVerificationGas = 41,
+    /// AA50: the paymaster's `postOp` reverted
+    PostOpReverted = 50,
+    /// AA51: the prefund is below the actual gas cost of the op
+    PrefundBelowActualGasCost = 51,
+    /// AA90: the bundle's beneficiary is the zero address
+    InvalidBeneficiary = 90,
+    /// AA91: the entry point failed to pay the beneficiary
+    FailedSendToBeneficiary = 91,
+    /// AA92: an internal entry point function was called externally
+    InternalCallOnly = 92,
+    /// AA93: the op's paymaster and data are malformed
+    InvalidPaymasterAndData = 93,
+    /// AA94: the op's gas values overflow
+    GasValuesOverflow = 94,
+    /// AA95: the bundle ran out of gas
+    OutOfGas = 95,
+    /// AA96: the op's aggregator is invalid
+    InvalidAggregator = 96,
+}
+
+impl ValidationRevertReason {
+    /// Returns whether the failure depends on state that may change before the op
+    /// is validated again, the fee payer's funds or the sender's nonce, rather than
+    /// on the op itself
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::AccountPrefundNotPaid | Self::InvalidAccountNonce | Self::PaymasterDepositTooLow
+        )
+    }
+}
+
+impl Display for ValidationRevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AA{}", *self as u8)
+    }
+}
+
 impl From<ContractRevertError> for ValidationRevert {
     fn from(value: ContractRevertError) -> Self {
         ValidationRevert::EntryPoint(value.reason)
@@ -434,7 +531,7 @@ impl From<AggregatorStakeInfoV0_7> for AggregatorInfo {
 
 #[cfg(test)]
 mod tests {
-    use super::parse_validation_data;
+    use super::{parse_validation_data, ValidationRevert, ValidationRevertReason};
 
     #[test]
     fn test_parse_validation_data() {
@@ -450,4 +547,37 @@ mod tests {
         assert_eq!(parsed.valid_until, 0x66778899aabb);
         assert_eq!(parsed.valid_after, 0x001122334455);
     }
+
+    #[test]
+    fn test_validation_revert_reason() {
+        let revert = ValidationRevert::EntryPoint("AA21 didn't pay prefund".to_string());
+        let reason = revert.reason().unwrap();
+        assert_eq!(reason, ValidationRevertReason::AccountPrefundNotPaid);
+        assert_eq!(reason.to_string(), "AA21");
+        assert!(reason.is_transient());
+        // the human readable reason is kept
+        assert_eq!(revert.to_string(), "AA21 didn't pay prefund");
+
+        let revert = ValidationRevert::Operation {
+            entry_point_reason: "AA13 initCode failed or OOG".to_string(),
+            inner_revert_data: Default::default(),
+            inner_revert_reason: None,
+        };
+        assert_eq!(
+            revert.reason(),
+            Some(ValidationRevertReason::InitCodeFailed)
+        );
+        assert!(!ValidationRevertReason::InitCodeFailed.is_transient());
+
+        // unknown codes and reasons without a code are not decoded
+        assert_eq!(
+            ValidationRevert::EntryPoint("AA99 unknown".to_string()).reason(),
+            None
+        );
+        assert_eq!(
+            ValidationRevert::EntryPoint("reverted".to_string()).reason(),
+            None
+        );
+        assert_eq!(ValidationRevert::Unknown(Default::default()).reason(), None);
+    }
 }
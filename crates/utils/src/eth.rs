@@ -17,7 +17,7 @@ use ethers::{
     abi::{AbiDecode, AbiEncode, RawLog},
     contract::ContractError,
     providers::Middleware,
-    types::{Address, Bytes, Log, Selector},
+    types::{Address, Bytes, Log, Selector, U256},
 };
 
 /// Creates call data from a method and its arguments. The arguments should be
@@ -56,6 +56,22 @@ pub fn parse_revert_message(revert_data: &[u8]) -> Option<String> {
         .map(|err| err.reason)
 }
 
+/// The abi for the panics raised by the Solidity compiler's checks, such as
+/// failed asserts and arithmetic overflows
+#[derive(Clone, Debug, Default, Eq, PartialEq, ethers::contract::EthError)]
+#[etherror(name = "Panic", abi = "Panic(uint256)")]
+pub struct ContractPanicError {
+    /// Panic code
+    pub code: U256,
+}
+
+/// Parses the panic code from the revert data
+pub fn parse_panic_code(revert_data: &[u8]) -> Option<U256> {
+    ContractPanicError::decode(revert_data)
+        .ok()
+        .map(|err| err.code)
+}
+
 /// Converts an ethers `Log` into an ethabi `RawLog`.
 pub fn log_to_raw_log(log: Log) -> RawLog {
     let Log { topics, data, .. } = log;